@@ -0,0 +1,203 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use enro::{platform, FileAnalysis};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use crate::{dedup, display_path};
+
+// Appended to on every run, so one directory can collect several passes
+const MANIFEST: &str = "enro-manifest.jsonl";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ActionKind {
+    /// Copy the file, leaving the original in place
+    Copy,
+    /// Move the file
+    Move,
+    /// Move the file and make the moved copy read-only
+    Quarantine,
+}
+
+impl ActionKind {
+    fn key(self) -> &'static str {
+        match self {
+            ActionKind::Copy => "copy",
+            ActionKind::Move => "move",
+            ActionKind::Quarantine => "quarantine",
+        }
+    }
+
+    fn past_tense(self) -> &'static str {
+        match self {
+            ActionKind::Copy => "Copied",
+            ActionKind::Move => "Moved",
+            ActionKind::Quarantine => "Quarantined",
+        }
+    }
+}
+
+/// What --action does with each reported file: `quarantine:DIR`, `move:DIR` or `copy:DIR`
+#[derive(Clone, Debug)]
+pub struct FileAction {
+    pub kind: ActionKind,
+    pub dir: PathBuf,
+}
+
+impl FromStr for FileAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, dir) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected ACTION:DIR such as `quarantine:/srv/quarantine`, got `{}`", s))?;
+        let kind = match kind.to_ascii_lowercase().as_str() {
+            "copy" => ActionKind::Copy,
+            "move" => ActionKind::Move,
+            "quarantine" => ActionKind::Quarantine,
+            _ => return Err(format!("unknown action `{}`; expected quarantine, move or copy", kind)),
+        };
+        if dir.is_empty() {
+            return Err("the action needs a destination directory".to_string());
+        }
+        Ok(FileAction {
+            kind,
+            dir: PathBuf::from(dir),
+        })
+    }
+}
+
+impl FileAction {
+    /// Apply the action to every reported file that is a regular file on disk, recording each
+    /// one in the manifest; archive members, URLs and stdin have nothing to move
+    pub fn apply(&self, results: &[FileAnalysis], machine_readable: bool) -> Result<()> {
+        let files: Vec<&FileAnalysis> = results
+            .iter()
+            .filter(|analysis| fs::symlink_metadata(&analysis.path).is_ok_and(|m| m.is_file()))
+            .collect();
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.dir).with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let manifest_path = self.dir.join(MANIFEST);
+        let mut manifest = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&manifest_path)
+            .with_context(|| format!("Failed to open manifest {}", manifest_path.display()))?;
+
+        let mut done = 0;
+        for analysis in files {
+            match self.apply_one(analysis) {
+                Ok(entry) => {
+                    writeln!(manifest, "{}", entry)
+                        .with_context(|| format!("Failed to write manifest {}", manifest_path.display()))?;
+                    done += 1;
+                }
+                Err(e) => eprintln!("Warning: {} {}: {:#}", self.kind.key(), display_path(&analysis.path), e),
+            }
+        }
+
+        let message = format!(
+            "{} {} file(s) to {} (manifest: {})",
+            self.kind.past_tense(),
+            done,
+            self.dir.display(),
+            manifest_path.display()
+        );
+        if machine_readable {
+            eprintln!("{}", message);
+        } else {
+            println!("\n  {} {}", "•".cyan(), message.bold());
+        }
+        Ok(())
+    }
+
+    fn apply_one(&self, analysis: &FileAnalysis) -> Result<serde_json::Value> {
+        let source = &analysis.path;
+        // Hashed before anything moves, so the manifest can prove what was isolated
+        let hash = dedup::hash_file(source)?;
+        let destination = self.destination(source)?;
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        match self.kind {
+            ActionKind::Copy => {
+                fs::copy(platform::long_path(source), platform::long_path(&destination))?;
+            }
+            ActionKind::Move | ActionKind::Quarantine => move_file(source, &destination)?,
+        }
+        if self.kind == ActionKind::Quarantine {
+            let mut permissions = fs::metadata(&destination)?.permissions();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                // Owner read only: nothing can write to or execute it by accident
+                permissions.set_mode(0o400);
+            }
+            #[cfg(not(unix))]
+            permissions.set_readonly(true);
+            fs::set_permissions(&destination, permissions)?;
+        }
+
+        Ok(serde_json::json!({
+            "time": humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            "action": self.kind.key(),
+            "source": std::path::absolute(source).unwrap_or_else(|_| source.clone()),
+            "destination": destination,
+            "type": analysis.file_type.display_compact(),
+            "entropy": (analysis.entropy * 10000.0).round() / 10000.0,
+            "size": analysis.size,
+            "blake3": hash.to_hex().as_str(),
+        }))
+    }
+
+    /// The source's absolute path recreated under the directory, so files with the same name
+    /// from different places do not collide; a numeric suffix covers earlier runs
+    fn destination(&self, source: &Path) -> Result<PathBuf> {
+        let absolute = std::path::absolute(source)?;
+        let mut destination = self.dir.clone();
+        for component in absolute.components() {
+            match component {
+                // `C:` becomes a directory named `C`
+                Component::Prefix(prefix) => {
+                    destination.push(prefix.as_os_str().to_string_lossy().replace([':', '\\', '?'], ""))
+                }
+                Component::Normal(name) => destination.push(name),
+                _ => {}
+            }
+        }
+
+        if !destination.exists() {
+            return Ok(destination);
+        }
+        let name = destination.file_name().unwrap_or_default().to_os_string();
+        for n in 1.. {
+            let mut numbered = name.clone();
+            numbered.push(format!(".{}", n));
+            let candidate = destination.with_file_name(numbered);
+            if !candidate.exists() {
+                return Ok(candidate);
+            }
+        }
+        unreachable!()
+    }
+}
+
+// A rename cannot cross filesystems; fall back to copying and removing the original
+fn move_file(source: &Path, destination: &Path) -> io::Result<()> {
+    let (source, destination) = (platform::long_path(source), platform::long_path(destination));
+    match fs::rename(&source, &destination) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            fs::copy(&source, &destination)?;
+            fs::remove_file(&source)
+        }
+        Err(e) => Err(e),
+    }
+}
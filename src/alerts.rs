@@ -0,0 +1,174 @@
+use enro::{FileAnalysis, FileType};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::display_path;
+
+/// Extensions appended by common ransomware families to the files they encrypted
+const RANSOMWARE_EXTENSIONS: &[&str] = &[
+    "8base", "akira", "basta", "blackcat", "cerber", "conti", "crab", "crypt", "crypted", "cryptolocker", "crypz",
+    "crysis", "djvu", "encrypted", "hive", "karma", "lockbit", "locked", "locky", "odin", "phobos", "royal",
+    "ryk", "ryuk", "sage", "thor", "wcry", "wncry", "wnry", "zepto", "zzzzz",
+];
+
+// A directory needs this many analyzed files before its encryption ratio means anything
+const MIN_DIRECTORY_FILES: usize = 5;
+
+enum Alert {
+    EncryptedFile { path: PathBuf, entropy: f64 },
+    RansomwareExtension { path: PathBuf, extension: String },
+    EncryptionRatio { directory: PathBuf, encrypted: usize, files: usize },
+}
+
+impl Alert {
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            Alert::EncryptedFile { path, entropy } => serde_json::json!({
+                "kind": "encrypted_file",
+                "path": display_path(path),
+                "entropy": (entropy * 10000.0).round() / 10000.0,
+            }),
+            Alert::RansomwareExtension { path, extension } => serde_json::json!({
+                "kind": "ransomware_extension",
+                "path": display_path(path),
+                "extension": extension,
+            }),
+            Alert::EncryptionRatio { directory, encrypted, files } => serde_json::json!({
+                "kind": "encryption_ratio",
+                "directory": display_path(directory),
+                "encrypted": encrypted,
+                "files": files,
+            }),
+        }
+    }
+
+    fn summary(&self) -> String {
+        match self {
+            Alert::EncryptedFile { path, entropy } => {
+                format!("Encrypted file {} (entropy {:.2})", display_path(path), entropy)
+            }
+            Alert::RansomwareExtension { path, extension } => {
+                format!("Ransomware extension .{} on {}", extension, display_path(path))
+            }
+            Alert::EncryptionRatio { directory, encrypted, files } => {
+                format!("{} of {} files encrypted in {}", encrypted, files, display_path(directory))
+            }
+        }
+    }
+}
+
+/// Posts --alert-webhook notifications. It remembers what it has seen, so a long-running watch
+/// alerts on each encrypted file and each directory crossing the ratio only once.
+pub struct Alerter {
+    url: String,
+    ratio: f64,
+    // Whether each analyzed file was encrypted, grouped by directory
+    directories: HashMap<PathBuf, HashMap<PathBuf, bool>>,
+    alerted_files: HashSet<PathBuf>,
+    alerted_directories: HashSet<PathBuf>,
+}
+
+impl Alerter {
+    pub fn new(url: &str, ratio: f64) -> Self {
+        Alerter {
+            url: url.to_string(),
+            ratio,
+            directories: HashMap::new(),
+            alerted_files: HashSet::new(),
+            alerted_directories: HashSet::new(),
+        }
+    }
+
+    /// Check newly analyzed files against the alert conditions and post whatever fired
+    pub fn notify(&mut self, results: &[FileAnalysis]) {
+        let alerts = self.check(results);
+        if alerts.is_empty() {
+            return;
+        }
+        if let Err(e) = self.post(&alerts) {
+            eprintln!("Warning: failed to send alert webhook: {:#}", e);
+        }
+    }
+
+    fn check(&mut self, results: &[FileAnalysis]) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        let mut touched = HashSet::new();
+        for analysis in results {
+            let encrypted = analysis.file_type == FileType::Encrypted;
+            if let Some(directory) = analysis.path.parent() {
+                self.directories
+                    .entry(directory.to_path_buf())
+                    .or_default()
+                    .insert(analysis.path.clone(), encrypted);
+                touched.insert(directory.to_path_buf());
+            }
+
+            if self.alerted_files.contains(&analysis.path) {
+                continue;
+            }
+            if let Some(extension) = ransomware_extension(&analysis.path) {
+                alerts.push(Alert::RansomwareExtension {
+                    path: analysis.path.clone(),
+                    extension,
+                });
+                self.alerted_files.insert(analysis.path.clone());
+            } else if encrypted {
+                alerts.push(Alert::EncryptedFile {
+                    path: analysis.path.clone(),
+                    entropy: analysis.entropy,
+                });
+                self.alerted_files.insert(analysis.path.clone());
+            }
+        }
+
+        let mut touched: Vec<PathBuf> = touched.into_iter().collect();
+        touched.sort();
+        for directory in touched {
+            let files = &self.directories[&directory];
+            let encrypted = files.values().filter(|&&e| e).count();
+            let over = files.len() >= MIN_DIRECTORY_FILES && encrypted as f64 / files.len() as f64 > self.ratio;
+            // Once a directory recovers, crossing the ratio again alerts again
+            if !over {
+                self.alerted_directories.remove(&directory);
+            } else if self.alerted_directories.insert(directory.clone()) {
+                alerts.push(Alert::EncryptionRatio {
+                    directory,
+                    encrypted,
+                    files: files.len(),
+                });
+            }
+        }
+        alerts
+    }
+
+    fn post(&self, alerts: &[Alert]) -> anyhow::Result<()> {
+        let mut text = format!("enro: {} alert(s)", alerts.len());
+        for alert in alerts.iter().take(10) {
+            text.push_str("\n• ");
+            text.push_str(&alert.summary());
+        }
+        if alerts.len() > 10 {
+            text.push_str(&format!("\n… and {} more", alerts.len() - 10));
+        }
+
+        // `text` is what Slack and Teams incoming webhooks display; the rest is for automation
+        let payload = serde_json::json!({
+            "text": text,
+            "source": "enro",
+            "host": sysinfo::System::host_name(),
+            "time": humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            "alerts": alerts.iter().map(Alert::to_json).collect::<Vec<_>>(),
+        });
+        crate::remote::agent()
+            .post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(&payload.to_string())?;
+        Ok(())
+    }
+}
+
+fn ransomware_extension(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    RANSOMWARE_EXTENSIONS.contains(&extension.as_str()).then_some(extension)
+}
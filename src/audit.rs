@@ -0,0 +1,172 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::{Args as ClapArgs, ValueEnum};
+use enro::{FileAnalysis, FileType};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::walk::{SkipReason, Skipped};
+use crate::{category, display_path, hashset};
+
+// `prev` of the first entry, which has no predecessor
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Check that an --audit-log file is intact: every entry names the SHA-256 of the one before it
+#[derive(ClapArgs, Debug)]
+pub struct VerifyAuditLogArgs {
+    /// Audit log written with --audit-log
+    #[arg(value_name = "FILE")]
+    pub file: PathBuf,
+}
+
+/// Appends one JSON line per run to --audit-log. Each line carries the SHA-256 of the previous
+/// one, so editing, removing or reordering entries breaks the chain `enro verify-audit-log` checks.
+pub struct AuditLog {
+    path: PathBuf,
+    started: DateTime<Utc>,
+}
+
+impl AuditLog {
+    /// Checks the log can be appended to before the scan starts, and notes the start time
+    pub fn open(path: &Path) -> Result<Self> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+        Ok(AuditLog {
+            path: path.to_path_buf(),
+            started: Utc::now(),
+        })
+    }
+
+    /// Append the entry for this run: its parameters, timing, counts by category and skip
+    /// reason, and the findings (encrypted, random and known-bad files)
+    pub fn record(
+        &self,
+        paths: &[PathBuf],
+        results: &[FileAnalysis],
+        skipped: &Skipped,
+        errors: usize,
+        failed: bool,
+    ) -> Result<()> {
+        let mut categories = Map::new();
+        for analysis in results {
+            let name = category(&analysis.file_type)
+                .to_possible_value()
+                .map_or_else(String::new, |v| v.get_name().to_string());
+            let count = categories.entry(name).or_insert(Value::from(0u64));
+            *count = Value::from(count.as_u64().unwrap_or(0) + 1);
+        }
+        let skipped: Map<String, Value> = SkipReason::ALL
+            .iter()
+            .filter(|&&reason| skipped.count(reason) > 0)
+            .map(|&reason| (reason.key().to_string(), skipped.count(reason).into()))
+            .collect();
+        let findings: Vec<Value> = results
+            .iter()
+            .filter(|r| matches!(r.file_type, FileType::Encrypted | FileType::Random) || hashset::is_known_bad(r))
+            .map(finding)
+            .collect();
+
+        let mut entry = json!({
+            "seq": 0,
+            "prev": GENESIS,
+            "event": "scan",
+            "version": env!("CARGO_PKG_VERSION"),
+            "started": self.started.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "finished": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            "host": sysinfo::System::host_name(),
+            "user": std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok(),
+            "cwd": std::env::current_dir().ok().map(|dir| dir.display().to_string()),
+            "argv": std::env::args().collect::<Vec<_>>(),
+            "paths": paths.iter().map(|p| display_path(p)).collect::<Vec<_>>(),
+            "files": results.len(),
+            "errors": errors,
+            "categories": categories,
+            "skipped": skipped,
+            "findings": findings,
+            "exit_code": i32::from(failed),
+        });
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open audit log {}", self.path.display()))?;
+        // Held while the chain is extended, so concurrent runs cannot both link to the same entry
+        file.lock()
+            .with_context(|| format!("Failed to lock audit log {}", self.path.display()))?;
+        let mut existing = String::new();
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_string(&mut existing)
+            .with_context(|| format!("Failed to read audit log {}", self.path.display()))?;
+        let previous: Vec<&str> = existing.lines().filter(|line| !line.is_empty()).collect();
+        entry["seq"] = previous.len().into();
+        if let Some(last) = previous.last() {
+            entry["prev"] = line_hash(last).into();
+        }
+        // An earlier run that died mid-write must not glue this entry onto its partial line
+        let separator = if existing.is_empty() || existing.ends_with('\n') { "" } else { "\n" };
+        writeln!(file, "{}{}", separator, entry)
+            .with_context(|| format!("Failed to write audit log {}", self.path.display()))?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+fn finding(analysis: &FileAnalysis) -> Value {
+    let mut finding = json!({
+        "path": display_path(&analysis.path),
+        "type": analysis.file_type.display_compact(),
+        "entropy": (analysis.entropy * 10000.0).round() / 10000.0,
+        "size": analysis.size,
+    });
+    if !analysis.hashes.is_empty() {
+        let hashes: Map<String, Value> = analysis
+            .hashes
+            .iter()
+            .map(|(algorithm, hex)| (algorithm.key().to_string(), hex.as_str().into()))
+            .collect();
+        finding["hashes"] = hashes.into();
+    }
+    if !analysis.notes.is_empty() {
+        finding["notes"] = analysis.notes.clone().into();
+    }
+    finding
+}
+
+fn line_hash(line: &str) -> String {
+    Sha256::digest(line.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn verify(args: &VerifyAuditLogArgs) -> Result<()> {
+    let content =
+        fs::read_to_string(&args.file).with_context(|| format!("Failed to read audit log {}", args.file.display()))?;
+    let mut prev = GENESIS.to_string();
+    let mut entries = 0;
+    for (i, line) in content.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let entry: Value = serde_json::from_str(line).with_context(|| format!("line {}: not a JSON entry", i + 1))?;
+        if entry["seq"].as_u64() != Some(entries) {
+            bail!(
+                "line {}: expected entry {}, found {}; entries were removed or reordered",
+                i + 1,
+                entries,
+                entry["seq"]
+            );
+        }
+        if entry["prev"].as_str() != Some(prev.as_str()) {
+            bail!("line {}: the chain is broken; the entry before it was modified", i + 1);
+        }
+        prev = line_hash(line);
+        entries += 1;
+    }
+    println!("{}: {} entries, chain intact (last entry {})", args.file.display(), entries, prev);
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+use colored::Colorize;
+use enro::FileAnalysis;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::{category, diff, display_path, Category};
+
+/// Findings recorded in a committed `enro --json` report, so only new ones fail a run
+pub struct Baseline {
+    categories: HashMap<String, Category>,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> Result<Self> {
+        let categories = diff::load_report(path)?
+            .into_iter()
+            .map(|record| {
+                let category = category(&record.file_type);
+                (record.path, category)
+            })
+            .collect();
+        Ok(Baseline { categories })
+    }
+
+    /// Files in one of `categories` that the baseline did not already list in one of them
+    pub fn new_findings(&self, results: &[FileAnalysis], categories: &[Category]) -> Vec<FileAnalysis> {
+        let mut findings: Vec<FileAnalysis> = results
+            .iter()
+            .filter(|analysis| categories.contains(&category(&analysis.file_type)))
+            .filter(|analysis| {
+                self.categories
+                    .get(&display_path(&analysis.path))
+                    .is_none_or(|known| !categories.contains(known))
+            })
+            .cloned()
+            .collect();
+        findings.sort_by(|a, b| a.path.cmp(&b.path));
+        findings
+    }
+}
+
+/// List new findings; machine-readable runs get them on stderr so stdout stays parseable
+pub fn report(findings: &[FileAnalysis], baseline: &Path, machine_readable: bool) {
+    if machine_readable {
+        for analysis in findings {
+            eprintln!(
+                "New finding: {} ({}, entropy {:.2})",
+                display_path(&analysis.path),
+                analysis.file_type.display_compact(),
+                analysis.entropy
+            );
+        }
+        if !findings.is_empty() {
+            eprintln!("{} new finding(s) not in baseline {}", findings.len(), baseline.display());
+        }
+        return;
+    }
+
+    if findings.is_empty() {
+        println!("\n  {} {}", "•".cyan(), format!("No new findings since baseline {}", baseline.display()).green());
+        return;
+    }
+    println!("\n{}", "NEW FINDINGS (not in baseline)".red().bold());
+    for analysis in findings {
+        println!(
+            "  {}  {}  {:.2}/8.0",
+            display_path(&analysis.path),
+            analysis.file_type.display_plain(),
+            analysis.entropy
+        );
+    }
+    println!(
+        "\n  {} {}",
+        "•".cyan(),
+        format!("{} new finding(s) not in baseline {}", findings.len(), baseline.display()).red().bold()
+    );
+}
@@ -0,0 +1,161 @@
+use anyhow::{Context, Result};
+use enro::FileAnalysis;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::walk::Skipped;
+use crate::FileError;
+
+// How often what was recorded is flushed to disk while files are being analyzed
+const INTERVAL: Duration = Duration::from_secs(30);
+
+/// A scan in progress: the file list it set out to analyze and everything finished so far. Saved as
+/// one JSON line with the list and what was finished before, then a line per file finished since.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub files: Vec<PathBuf>,
+    pub skipped: Skipped,
+    pub results: Vec<FileAnalysis>,
+    pub errors: Vec<FileError>,
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Failed to open checkpoint {}", path.display()))?;
+        let mut lines = BufReader::new(file).lines();
+        let header = lines
+            .next()
+            .transpose()
+            .with_context(|| format!("Failed to read checkpoint {}", path.display()))?
+            .unwrap_or_default();
+        let mut checkpoint: Checkpoint =
+            serde_json::from_str(&header).with_context(|| format!("{} is not an enro checkpoint", path.display()))?;
+        for line in lines {
+            let line = line.with_context(|| format!("Failed to read checkpoint {}", path.display()))?;
+            // The interruption may have cut the last line short
+            let Ok(entry) = serde_json::from_str::<Entry>(&line) else {
+                break;
+            };
+            match entry {
+                Entry::Result(analysis) => checkpoint.results.push(*analysis),
+                Entry::Error(error) => checkpoint.errors.push(error),
+            }
+        }
+        Ok(checkpoint)
+    }
+
+    /// Files of the list that have neither a result nor an error yet
+    pub fn remaining(&self) -> Vec<PathBuf> {
+        let done: HashSet<&Path> = self
+            .results
+            .iter()
+            .map(|r| r.path.as_path())
+            .chain(self.errors.iter().map(|e| e.path.as_path()))
+            .collect();
+        self.files
+            .iter()
+            .filter(|file| !done.contains(file.as_path()))
+            .cloned()
+            .collect()
+    }
+}
+
+/// A line recorded after the checkpoint's first
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Entry {
+    Result(Box<FileAnalysis>),
+    Error(FileError),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum EntryRef<'a> {
+    Result(&'a FileAnalysis),
+    Error(&'a FileError),
+}
+
+/// Appends a line to the checkpoint for every file the analysis threads report finished, flushed
+/// every so often
+pub struct Checkpointer {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+struct State {
+    checkpoint: Checkpoint,
+    writer: BufWriter<File>,
+    flushed: Instant,
+}
+
+impl Checkpointer {
+    pub fn new(path: &Path, checkpoint: Checkpoint) -> Result<Self> {
+        // Written once up front, so even an early interruption skips the walk on resume. Next to
+        // the checkpoint and renamed over it, so an interruption mid-write leaves the previous
+        // one intact.
+        let mut temp = path.to_path_buf().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+        let file = File::create(&temp).with_context(|| format!("Failed to write checkpoint {}", temp.display()))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, &checkpoint)?;
+        writeln!(writer)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&temp, path).with_context(|| format!("Failed to write checkpoint {}", path.display()))?;
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to write checkpoint {}", path.display()))?;
+        Ok(Checkpointer {
+            path: path.to_path_buf(),
+            state: Mutex::new(State {
+                checkpoint,
+                writer: BufWriter::new(file),
+                flushed: Instant::now(),
+            }),
+        })
+    }
+
+    pub fn record(&self, result: &Result<FileAnalysis, FileError>) {
+        let (entry, path) = match result {
+            Ok(analysis) => (EntryRef::Result(analysis), &analysis.path),
+            Err(error) => (EntryRef::Error(error), &error.path),
+        };
+        let line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Warning: Failed to record {} in the checkpoint: {}", path.display(), e);
+                return;
+            }
+        };
+        let result = result.clone();
+        let mut state = self.state.lock().unwrap();
+        match result {
+            Ok(analysis) => state.checkpoint.results.push(analysis),
+            Err(error) => state.checkpoint.errors.push(error),
+        }
+        let mut written = writeln!(state.writer, "{}", line);
+        if written.is_ok() && state.flushed.elapsed() >= INTERVAL {
+            written = state.writer.flush();
+            state.flushed = Instant::now();
+        }
+        if let Err(e) = written {
+            eprintln!("Warning: Failed to write checkpoint {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// The scan completed, so there is nothing left to resume; returns everything it recorded
+    pub fn finish(self) -> Checkpoint {
+        let state = self.state.into_inner().unwrap();
+        drop(state.writer);
+        let _ = fs::remove_file(&self.path);
+        state.checkpoint
+    }
+}
@@ -0,0 +1,56 @@
+use enro::FileAnalysis;
+
+use crate::policy::FailOn;
+use crate::{display_path, Args, Category};
+
+/// Settings implied by --ci: plain CSV (unless --json was asked for), no colors, and a failing
+/// exit status for encrypted/random files unless --fail-on or --baseline already decide it
+pub fn apply(args: &mut Args) {
+    colored::control::set_override(false);
+    if !args.json {
+        args.simple = true;
+    }
+    if args.fail_on.is_empty() && args.baseline.is_none() {
+        args.fail_on = vec![FailOn::Category(Category::Encrypted), FailOn::Category(Category::Random)];
+    }
+}
+
+/// GitHub Actions error annotations on stderr, one per file that fails the run; other CI systems
+/// show them as plain log lines
+pub fn annotate(violations: &[(FileAnalysis, &FailOn)], new_findings: Option<&[FileAnalysis]>) {
+    for (analysis, condition) in violations {
+        annotation(analysis, &format!("matched --fail-on {}", condition));
+    }
+    for analysis in new_findings.unwrap_or_default() {
+        annotation(analysis, "not in baseline");
+    }
+    let failed = violations.len() + new_findings.map_or(0, <[_]>::len);
+    if failed > 0 {
+        eprintln!("enro: {} finding(s) fail this run", failed);
+    }
+}
+
+fn annotation(analysis: &FileAnalysis, reason: &str) {
+    let path = display_path(&analysis.path);
+    eprintln!(
+        "::error file={},title={}::{}",
+        escape_property(&path),
+        escape_property(&format!("enro: {}", analysis.file_type.display_plain())),
+        escape_data(&format!(
+            "{} is {} (entropy {:.2}), {}",
+            path,
+            analysis.file_type.display_plain(),
+            analysis.entropy,
+            reason
+        ))
+    );
+}
+
+// Workflow command escaping: messages must not break the line, properties also not the list
+fn escape_data(value: &str) -> String {
+    value.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
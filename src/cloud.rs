@@ -0,0 +1,141 @@
+use anyhow::{bail, Context, Result};
+use enro::{analyze_reader, AnalyzeOptions, FileAnalysis};
+use futures::stream::{BoxStream, StreamExt, TryStreamExt};
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectMeta, ObjectStore};
+use rayon::prelude::*;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tokio::runtime::Runtime;
+use url::Url;
+
+// object_store is async; rayon workers block on this shared runtime
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to start async runtime")
+    })
+}
+
+/// Store for a bucket URL, with each provider's standard credential discovery:
+/// - S3: `AWS_*` variables, web identity/container credentials, EC2 instance metadata
+/// - Azure: `AZURE_STORAGE_*`/`AZURE_*` variables (account key, SAS, service principal), managed identity
+/// - GCS: `GOOGLE_*` variables, application default credentials, GCE metadata server
+fn open_store(url: &Url) -> Result<Box<dyn ObjectStore>> {
+    match url.scheme() {
+        #[cfg(feature = "s3")]
+        "s3" => Ok(Box::new(
+            object_store::aws::AmazonS3Builder::from_env()
+                .with_url(url.as_str())
+                .build()?,
+        )),
+        #[cfg(feature = "azure")]
+        "az" => Ok(Box::new(
+            object_store::azure::MicrosoftAzureBuilder::from_env()
+                .with_url(url.as_str())
+                .build()?,
+        )),
+        #[cfg(feature = "gcs")]
+        "gs" => Ok(Box::new(
+            object_store::gcp::GoogleCloudStorageBuilder::from_env()
+                .with_url(url.as_str())
+                .build()?,
+        )),
+        other => bail!("Unsupported object storage scheme: {}://", other),
+    }
+}
+
+/// Analyze every object under a bucket prefix (or the single object it names), fetching
+/// only the first --max-bytes of each with ranged GETs
+pub fn analyze_objects(location: &str, options: &AnalyzeOptions, min_size: u64) -> Result<Vec<FileAnalysis>> {
+    let url = Url::parse(location).with_context(|| format!("Invalid URL: {}", location))?;
+    let store = open_store(&url).with_context(|| format!("Failed to open {}", location))?;
+    let prefix = ObjectPath::from_url_path(url.path())?;
+
+    let mut objects: Vec<ObjectMeta> = runtime()
+        .block_on(store.list(Some(&prefix)).try_collect())
+        .with_context(|| format!("Failed to list {}", location))?;
+    if objects.is_empty() {
+        if let Ok(object) = runtime().block_on(store.head(&prefix)) {
+            objects.push(object);
+        }
+    }
+    objects.retain(|o| o.size >= min_size);
+
+    let bucket = &url[..url::Position::BeforePath];
+    Ok(objects
+        .par_iter()
+        .filter_map(|object| {
+            let name = format!("{}/{}", bucket, object.location);
+            match analyze_object(store.as_ref(), object, name.clone(), options) {
+                Ok(analysis) => Some(analysis),
+                Err(e) => {
+                    eprintln!("Warning: {}: {:#}", name, e);
+                    None
+                }
+            }
+        })
+        .collect())
+}
+
+fn analyze_object(store: &dyn ObjectStore, object: &ObjectMeta, name: String, options: &AnalyzeOptions) -> Result<FileAnalysis> {
+    // Without digests to compute, only the prefix classification counts is fetched
+    let mut analysis = match options.max_bytes.filter(|_| options.hashes.is_empty()) {
+        Some(max) => {
+            let end = (max as u64).min(object.size);
+            let data = if end > 0 {
+                runtime().block_on(store.get_range(&object.location, 0..end))?.to_vec()
+            } else {
+                Vec::new()
+            };
+            analyze_reader(PathBuf::from(name), &mut data.as_slice(), options)?
+        }
+        None => {
+            let stream = runtime().block_on(store.get(&object.location))?.into_stream();
+            let reader = StreamReader {
+                stream: stream.map_ok(|bytes| bytes.to_vec()).boxed(),
+                chunk: Vec::new(),
+                pos: 0,
+            };
+            crate::remote::analyze_body(name, reader, Some(object.size), options)?
+        }
+    };
+    analysis.size = object.size;
+    crate::remote::inspect_zip_ranges(&mut analysis, options, |range| {
+        let bytes = runtime().block_on(store.get_range(&object.location, range)).map_err(io::Error::other)?;
+        Ok(bytes.to_vec())
+    });
+
+    Ok(analysis)
+}
+
+/// Blocking `Read` over an object's body stream
+struct StreamReader {
+    stream: BoxStream<'static, object_store::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for StreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            match runtime().block_on(self.stream.next()) {
+                Some(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                Some(Err(e)) => return Err(io::Error::other(e)),
+                None => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
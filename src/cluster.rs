@@ -0,0 +1,92 @@
+use enro::fuzzy::tlsh_distance;
+use enro::hash::HashAlgorithm;
+use enro::FileAnalysis;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// Near-identical files have near-identical entropy; pairs further apart are not compared
+const ENTROPY_TOLERANCE: f64 = 0.5;
+
+/// Files whose TLSH digests are within --cluster-distance of one another, directly or through
+/// other members
+pub struct Cluster {
+    /// In report order
+    pub paths: Vec<PathBuf>,
+    pub min_entropy: f64,
+    pub max_entropy: f64,
+    /// Largest distance between two members that put them in the same cluster
+    pub max_distance: u32,
+}
+
+/// Group analyzed files by TLSH similarity. Files TLSH cannot characterize (under 50 bytes, or too
+/// uniform) are never clustered.
+pub fn find_clusters(results: &[FileAnalysis], max_distance: u32) -> Vec<Cluster> {
+    // (index into results, TLSH length byte, digest)
+    let mut digests: Vec<(usize, u8, &str)> = results
+        .iter()
+        .enumerate()
+        .filter_map(|(i, analysis)| {
+            let (_, digest) = analysis.hashes.iter().find(|(a, _)| *a == HashAlgorithm::Tlsh)?;
+            let length = u8::from_str_radix(digest.get(4..6)?, 16).ok()?.rotate_left(4);
+            digest.starts_with("T1").then_some((i, length, digest.as_str()))
+        })
+        .collect();
+    digests.sort_by_key(|&(_, length, _)| length);
+
+    // Each length step beyond the first adds 12, so files further apart in size cannot be close
+    let max_length_gap = max_distance / 12 + 1;
+    let mut parent: Vec<usize> = (0..results.len()).collect();
+    let mut link_distance: HashMap<usize, u32> = HashMap::new();
+    for (n, &(i, length, digest)) in digests.iter().enumerate() {
+        for &(j, other_length, other) in &digests[n + 1..] {
+            if (other_length - length) as u32 > max_length_gap {
+                break;
+            }
+            if (results[i].entropy - results[j].entropy).abs() > ENTROPY_TOLERANCE {
+                continue;
+            }
+            let Some(distance) = tlsh_distance(digest, other).filter(|&d| d <= max_distance) else {
+                continue;
+            };
+            let (root_i, root_j) = (find(&mut parent, i), find(&mut parent, j));
+            let linked = distance
+                .max(link_distance.remove(&root_i).unwrap_or(0))
+                .max(link_distance.remove(&root_j).unwrap_or(0));
+            // The earlier file becomes the root, so clusters come out in report order
+            let root = root_i.min(root_j);
+            parent[root_i.max(root_j)] = root;
+            link_distance.insert(root, linked);
+        }
+    }
+
+    let mut clusters: Vec<Cluster> = Vec::new();
+    let mut by_root: HashMap<usize, usize> = HashMap::new();
+    for (i, analysis) in results.iter().enumerate() {
+        let root = find(&mut parent, i);
+        let Some(&max_distance) = link_distance.get(&root) else {
+            continue;
+        };
+        let index = *by_root.entry(root).or_insert_with(|| {
+            clusters.push(Cluster {
+                paths: Vec::new(),
+                min_entropy: f64::MAX,
+                max_entropy: f64::MIN,
+                max_distance,
+            });
+            clusters.len() - 1
+        });
+        let cluster = &mut clusters[index];
+        cluster.paths.push(analysis.path.clone());
+        cluster.min_entropy = cluster.min_entropy.min(analysis.entropy);
+        cluster.max_entropy = cluster.max_entropy.max(analysis.entropy);
+    }
+    clusters
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
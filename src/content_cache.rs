@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use enro::hash::HashAlgorithm;
+use enro::{analyze_file, platform, AnalyzeOptions, FileAnalysis, ResultStore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    // Same rule as the --incremental cache: results only carry over between identical options
+    options: serde_json::Value,
+    /// Keyed by hex BLAKE3 digest of the whole content
+    entries: HashMap<String, FileAnalysis>,
+}
+
+/// Results of earlier --content-cache scans keyed by what files hold rather than where they are,
+/// so copies under other paths, on other hosts or in other backup sets are not analyzed again
+pub struct ContentCache {
+    path: PathBuf,
+    options: serde_json::Value,
+    // Also filled as this run goes, so copies within one scan are analyzed once
+    entries: Mutex<HashMap<String, FileAnalysis>>,
+    hits: AtomicUsize,
+}
+
+impl ContentCache {
+    /// An empty cache when the file does not exist yet or was written with other options
+    pub fn load(path: &Path, options: &AnalyzeOptions) -> Result<Self> {
+        let options = serde_json::to_value(options)?;
+        let entries = match File::open(path) {
+            Ok(file) => {
+                let cache: CacheFile = serde_json::from_reader(BufReader::new(file))
+                    .with_context(|| format!("{} is not an enro content cache", path.display()))?;
+                if cache.options == options {
+                    cache.entries
+                } else {
+                    HashMap::new()
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to open content cache {}", path.display())),
+        };
+        Ok(ContentCache {
+            path: path.to_path_buf(),
+            options,
+            entries: Mutex::new(entries),
+            hits: AtomicUsize::new(0),
+        })
+    }
+
+    /// Analyze `path`, or copy the result of a file with the same content. The BLAKE3 digest is
+    /// computed in the same pass as the analysis, and a result found for it saves inspecting the
+    /// file further. Files whose result depends on their path, Git objects and named pipes, are
+    /// always analyzed and not stored.
+    pub fn analyze(&self, path: &Path, options: &AnalyzeOptions) -> Result<FileAnalysis> {
+        let before = match stamp(path) {
+            Ok(before) if path.is_file() && !enro::git::in_objects_dir(path) => before,
+            _ => return analyze_file(path, options),
+        };
+        let lookup = Lookup {
+            cache: self,
+            path,
+            before,
+        };
+        enro::analyze_file_with_store(path, options, Some(&lookup))
+    }
+
+    /// Files whose result was copied rather than analyzed on this run
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Write the cache, with the results of this run added
+    pub fn save(self) -> Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+        let file = File::create(&temp).with_context(|| format!("Failed to write content cache {}", temp.display()))?;
+        let mut writer = BufWriter::new(file);
+        let cache = CacheFile {
+            options: self.options,
+            entries: self.entries.into_inner().unwrap(),
+        };
+        serde_json::to_writer(&mut writer, &cache)?;
+        writer.flush()?;
+        fs::rename(&temp, &self.path).with_context(|| format!("Failed to write content cache {}", self.path.display()))
+    }
+}
+
+/// Looks up and stores the result of one file's analysis
+struct Lookup<'a> {
+    cache: &'a ContentCache,
+    path: &'a Path,
+    before: (u64, SystemTime),
+}
+
+impl ResultStore for Lookup<'_> {
+    fn algorithm(&self) -> HashAlgorithm {
+        HashAlgorithm::Blake3
+    }
+
+    fn get(&self, digest: &str) -> Option<FileAnalysis> {
+        let cached = self.cache.entries.lock().unwrap().get(digest)?.clone();
+        self.cache.hits.fetch_add(1, Ordering::Relaxed);
+        Some(cached)
+    }
+
+    fn put(&self, digest: &str, analysis: &FileAnalysis) {
+        // A file written to while it was read may not hold what the digest says
+        if stamp(self.path).is_ok_and(|after| after == self.before) {
+            self.cache.entries.lock().unwrap().insert(digest.to_string(), analysis.clone());
+        }
+    }
+}
+
+fn stamp(path: &Path) -> std::io::Result<(u64, SystemTime)> {
+    fs::metadata(platform::long_path(path)).and_then(|m| Ok((m.len(), m.modified()?)))
+}
+
+#[cfg(windows)]
+pub fn default_path() -> PathBuf {
+    std::env::var_os("LOCALAPPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("enro")
+        .join("content.json")
+}
+
+#[cfg(not(windows))]
+pub fn default_path() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("enro")
+        .join("content.json")
+}
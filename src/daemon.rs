@@ -0,0 +1,397 @@
+use anyhow::{bail, Context, Result};
+use clap::builder::BoolishValueParser;
+use clap::Args as ClapArgs;
+use enro::{analyze_file, AnalyzeOptions, FileAnalysis};
+use interprocess::local_socket::prelude::*;
+use interprocess::local_socket::{GenericFilePath, ListenerOptions, Stream};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::SocketAddr;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::SystemTime;
+
+use crate::history::History;
+use crate::metrics::Metrics;
+use crate::schedule::Schedule;
+use crate::{category, display_path, watch, Category};
+
+/// Serve analysis requests from local tools over a Unix domain socket or named pipe
+#[derive(ClapArgs, Debug)]
+pub struct DaemonArgs {
+    /// Socket to listen on: a Unix domain socket path, or `\\.\pipe\NAME` on Windows
+    /// [default: $XDG_RUNTIME_DIR/enro.sock or <temp dir>/enro.sock; \\.\pipe\enro on Windows]
+    #[arg(long, env = "ENRO_SOCKET", value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+
+    /// Maximum number of bytes to read per file unless a request sets its own (omit to scan
+    /// entire files)
+    #[arg(short = 'b', long, env = "ENRO_MAX_BYTES")]
+    pub max_bytes: Option<usize>,
+
+    /// List ZIP entries and their encryption in responses
+    #[arg(long, env = "ENRO_ZIP_ENTRIES", value_parser = BoolishValueParser::new())]
+    pub zip_entries: bool,
+
+    /// List OLE/CFB streams in responses
+    #[arg(long, env = "ENRO_OLE_STREAMS", value_parser = BoolishValueParser::new())]
+    pub ole_streams: bool,
+
+    /// Results kept for files that have not changed since they were analyzed, the oldest dropped
+    /// first once there are N (0 disables the cache)
+    #[arg(long, env = "ENRO_CACHE_ENTRIES", value_name = "N", default_value_t = 100_000)]
+    pub cache_entries: usize,
+
+    /// Clients served at once; further connections wait until one closes
+    #[arg(
+        long,
+        env = "ENRO_MAX_CONNECTIONS",
+        value_name = "N",
+        default_value_t = 64,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub max_connections: u32,
+
+    /// Serve Prometheus metrics (files scanned, bytes read, counts per classification) at
+    /// http://ADDR/metrics, e.g. `127.0.0.1:9184`
+    #[arg(long, env = "ENRO_METRICS_ADDR", value_name = "ADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Also scan the --scan-path directories on this cron schedule, in local time (`0 2 * * *`
+    /// for 2am daily, `@weekly`); the results warm the cache and the metrics
+    #[arg(long, env = "ENRO_SCHEDULE", value_name = "CRON", value_parser = clap::value_parser!(Schedule), requires = "scan_path")]
+    pub schedule: Option<Schedule>,
+
+    /// Directory the scheduled scans walk recursively (repeatable)
+    #[arg(long, env = "ENRO_SCAN_PATH", value_name = "PATH", requires = "schedule")]
+    pub scan_path: Vec<PathBuf>,
+
+    /// Append each scheduled scan's results to this SQLite history database, queried with
+    /// `enro history` and `enro report`
+    #[arg(long, env = "ENRO_HISTORY", value_name = "DB", requires = "schedule")]
+    pub history: Option<PathBuf>,
+}
+
+/// One request line: either a bare path, or a JSON object
+/// `{"path": "...", "max_bytes": N}` with `max_bytes` optional
+struct Request {
+    path: PathBuf,
+    max_bytes: Option<usize>,
+}
+
+/// A cached result stays valid while the file keeps its size and modification time
+struct CacheEntry {
+    size: u64,
+    modified: Option<SystemTime>,
+    max_bytes: Option<usize>,
+    analysis: FileAnalysis,
+}
+
+/// Results by path, the oldest dropped first once the cache is full
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<PathBuf, CacheEntry>,
+    // Paths in the order they were first cached
+    order: VecDeque<PathBuf>,
+}
+
+impl Cache {
+    fn insert(&mut self, path: PathBuf, entry: CacheEntry, capacity: usize) {
+        if !self.entries.contains_key(&path) {
+            while self.entries.len() >= capacity {
+                let Some(oldest) = self.order.pop_front() else {
+                    break;
+                };
+                self.entries.remove(&oldest);
+            }
+            self.order.push_back(path.clone());
+        }
+        self.entries.insert(path, entry);
+    }
+}
+
+struct Daemon {
+    options: AnalyzeOptions,
+    cache_entries: usize,
+    cache: Mutex<Cache>,
+    metrics: Arc<Metrics>,
+    max_connections: usize,
+    // Connections being served
+    connections: Mutex<usize>,
+    closed: Condvar,
+}
+
+/// A connection's place among the `max_connections` served at once, given back on drop
+struct Slot {
+    daemon: Arc<Daemon>,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        *self.daemon.connections.lock().unwrap() -= 1;
+        self.daemon.closed.notify_one();
+    }
+}
+
+pub fn run(args: &DaemonArgs) -> Result<()> {
+    let socket = args.socket.clone().unwrap_or_else(default_socket);
+    let listener = listen(&socket)?;
+    let metrics = Metrics::new();
+    if let Some(addr) = args.metrics_addr {
+        crate::metrics::serve(addr, metrics.clone())?;
+    }
+
+    // Sized from the system's memory on first use, which is the slow part of a cold start
+    enro::get_optimal_chunk_size();
+    let daemon = Arc::new(Daemon {
+        options: AnalyzeOptions {
+            max_bytes: args.max_bytes,
+            zip_entries: args.zip_entries,
+            ole_streams: args.ole_streams,
+            ..AnalyzeOptions::default()
+        },
+        cache_entries: args.cache_entries,
+        cache: Mutex::new(Cache::default()),
+        metrics,
+        max_connections: args.max_connections as usize,
+        connections: Mutex::new(0),
+        closed: Condvar::new(),
+    });
+    eprintln!("Listening on {}", socket.display());
+    if let Some(schedule) = args.schedule.clone() {
+        if let Some(db) = &args.history {
+            // Fail at startup rather than at the first scheduled scan
+            History::open(db)?;
+        }
+        let daemon = Arc::clone(&daemon);
+        let (paths, history) = (args.scan_path.clone(), args.history.clone());
+        std::thread::spawn(move || daemon.run_schedule(&schedule, &paths, history.as_deref()));
+    }
+
+    loop {
+        // Not accepting while every slot is taken leaves further clients waiting to connect
+        let slot = Daemon::slot(&daemon);
+        match listener.accept() {
+            Ok(stream) => {
+                std::thread::spawn(move || {
+                    if let Err(e) = slot.daemon.serve(stream) {
+                        eprintln!("Warning: connection closed: {}", e);
+                    }
+                });
+            }
+            Err(e) => eprintln!("Warning: failed to accept a connection: {}", e),
+        }
+    }
+}
+
+#[cfg(windows)]
+fn default_socket() -> PathBuf {
+    PathBuf::from(r"\\.\pipe\enro")
+}
+
+#[cfg(not(windows))]
+fn default_socket() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("enro.sock")
+}
+
+#[cfg(unix)]
+fn listener_options(name: interprocess::local_socket::Name<'_>) -> ListenerOptions<'_> {
+    use interprocess::os::unix::local_socket::ListenerOptionsExt;
+    // Requests run with the daemon's permissions, so only its owner may send them
+    ListenerOptions::new().name(name).mode(0o600)
+}
+
+#[cfg(not(unix))]
+fn listener_options(name: interprocess::local_socket::Name<'_>) -> ListenerOptions<'_> {
+    ListenerOptions::new().name(name)
+}
+
+fn listen(socket: &Path) -> Result<interprocess::local_socket::Listener> {
+    let name = || socket.to_fs_name::<GenericFilePath>();
+    let name_error = || format!("Invalid socket name: {}", socket.display());
+    match listener_options(name().with_context(name_error)?).create_sync() {
+        Ok(listener) => Ok(listener),
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+            if Stream::connect(name().with_context(name_error)?).is_ok() {
+                bail!("Another daemon is already listening on {}", socket.display());
+            }
+            // Left behind by a daemon that did not shut down cleanly
+            listener_options(name().with_context(name_error)?)
+                .try_overwrite(true)
+                .create_sync()
+                .with_context(|| format!("Failed to listen on {}", socket.display()))
+        }
+        Err(e) => Err(e).with_context(|| format!("Failed to listen on {}", socket.display())),
+    }
+}
+
+impl Daemon {
+    /// Wait until fewer than `max_connections` clients are being served, and take a place
+    fn slot(daemon: &Arc<Daemon>) -> Slot {
+        let mut connections = daemon.connections.lock().unwrap();
+        while *connections >= daemon.max_connections {
+            connections = daemon.closed.wait(connections).unwrap();
+        }
+        *connections += 1;
+        Slot {
+            daemon: Arc::clone(daemon),
+        }
+    }
+
+    /// Scan `paths` each time the schedule fires, for as long as the daemon runs
+    fn run_schedule(&self, schedule: &Schedule, paths: &[PathBuf], history: Option<&Path>) {
+        while let Some(next) = schedule.next() {
+            eprintln!("Next scheduled scan at {}", humantime::format_rfc3339_seconds(next));
+            if let Ok(wait) = next.duration_since(SystemTime::now()) {
+                std::thread::sleep(wait);
+            }
+            if let Err(e) = self.scheduled_scan(paths, history) {
+                eprintln!("Warning: scheduled scan failed: {:#}", e);
+            }
+        }
+        eprintln!("Warning: schedule {} never fires again, scheduled scans stopped", schedule);
+    }
+
+    fn scheduled_scan(&self, paths: &[PathBuf], history: Option<&Path>) -> Result<()> {
+        let files = watch::existing_files(paths, true);
+        let (results, errors): (Vec<_>, Vec<_>) = files
+            .par_iter()
+            .map(|path| {
+                self.analyze(&Request {
+                    path: path.clone(),
+                    max_bytes: None,
+                })
+            })
+            .partition(Result::is_ok);
+        let results: Vec<FileAnalysis> = results.into_iter().flatten().map(|(analysis, _)| analysis).collect();
+        let count = |target: Category| results.iter().filter(|a| category(&a.file_type) == target).count();
+        let mut summary = format!(
+            "Scheduled scan analyzed {} file(s): {} encrypted, {} random, {} error(s)",
+            results.len(),
+            count(Category::Encrypted),
+            count(Category::Random),
+            errors.len()
+        );
+        if let Some(db) = history {
+            let id = History::open(db)?.record(paths, &results)?;
+            summary.push_str(&format!(", recorded as scan {}", id));
+        }
+        eprintln!("{}", summary);
+        Ok(())
+    }
+
+    /// Answer each request line with one JSON line until the client disconnects
+    fn serve(&self, stream: Stream) -> io::Result<()> {
+        let mut reader = BufReader::new(&stream);
+        let mut writer = &stream;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let response = match parse_request(line) {
+                Ok(request) => self.respond(&request),
+                Err(message) => serde_json::json!({ "error": message }),
+            };
+            writeln!(writer, "{}", response)?;
+        }
+    }
+
+    fn respond(&self, request: &Request) -> serde_json::Value {
+        match self.analyze(request) {
+            Ok((analysis, cached)) => serde_json::json!({
+                "path": display_path(&analysis.path),
+                "type": analysis.file_type.display_compact(),
+                "entropy": (analysis.entropy * 10000.0).round() / 10000.0,
+                "size": analysis.size,
+                "notes": analysis.notes,
+                "entries": analysis.entries.iter().map(|e| serde_json::json!({
+                    "name": e.name,
+                    "size": e.size,
+                    "encryption": e.encryption.map(|scheme| scheme.label()),
+                })).collect::<Vec<_>>(),
+                "streams": analysis.streams.iter().map(|s| serde_json::json!({
+                    "name": s.name,
+                    "size": s.size,
+                    "entropy": s.entropy,
+                })).collect::<Vec<_>>(),
+                "cached": cached,
+            }),
+            Err(e) => serde_json::json!({
+                "path": display_path(&request.path),
+                "error": format!("{:#}", e),
+            }),
+        }
+    }
+
+    /// The analysis of a file and whether it came from the cache
+    fn analyze(&self, request: &Request) -> Result<(FileAnalysis, bool)> {
+        let max_bytes = request.max_bytes.or(self.options.max_bytes);
+        let metadata = std::fs::metadata(enro::platform::long_path(&request.path))
+            .with_context(|| format!("Failed to read metadata of {}", request.path.display()))?;
+        let (size, modified) = (metadata.len(), metadata.modified().ok());
+
+        if let Some(entry) = self.cache.lock().unwrap().entries.get(&request.path) {
+            // Without a modification time there is no telling whether the file changed
+            if entry.size == size && entry.modified.is_some() && entry.modified == modified && entry.max_bytes == max_bytes {
+                return Ok((entry.analysis.clone(), true));
+            }
+        }
+
+        let options = AnalyzeOptions {
+            max_bytes,
+            ..self.options.clone()
+        };
+        let analysis = analyze_file(&request.path, &options)
+            .inspect_err(|_| self.metrics.record_error())
+            .with_context(|| format!("Failed to analyze {}", request.path.display()))?;
+        self.metrics.record(&analysis);
+
+        if self.cache_entries > 0 {
+            let entry = CacheEntry {
+                size,
+                modified,
+                max_bytes,
+                analysis: analysis.clone(),
+            };
+            self.cache.lock().unwrap().insert(request.path.clone(), entry, self.cache_entries);
+        }
+        Ok((analysis, false))
+    }
+}
+
+fn parse_request(line: &str) -> Result<Request, String> {
+    if !line.starts_with('{') {
+        return Ok(Request {
+            path: PathBuf::from(line),
+            max_bytes: None,
+        });
+    }
+
+    let value: serde_json::Value = serde_json::from_str(line).map_err(|e| format!("Invalid request: {}", e))?;
+    let path = value["path"]
+        .as_str()
+        .ok_or_else(|| "Invalid request: \"path\" must be a string".to_string())?;
+    let max_bytes = match &value["max_bytes"] {
+        serde_json::Value::Null => None,
+        n => Some(
+            n.as_u64()
+                .ok_or_else(|| "Invalid request: \"max_bytes\" must be a non-negative integer".to_string())?
+                as usize,
+        ),
+    };
+    Ok(Request {
+        path: PathBuf::from(path),
+        max_bytes,
+    })
+}
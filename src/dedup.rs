@@ -0,0 +1,86 @@
+use enro::{pagecache, platform, FileAnalysis};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Files with byte-for-byte identical content
+pub struct DuplicateGroup {
+    pub hash: blake3::Hash,
+    pub size: u64,
+    /// In report order; the first one is kept when collapsing
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// Space taken by all copies but one
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+pub fn hash_file(path: &Path) -> io::Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut pagecache::open(&platform::long_path(path))?, &mut hasher)?;
+    Ok(hasher.finalize())
+}
+
+/// Group analyzed files by BLAKE3 content hash. Only files sharing a size with another
+/// file are hashed; empty, unreadable and special files are never reported.
+pub fn find_duplicates(results: &[FileAnalysis]) -> Vec<DuplicateGroup> {
+    let mut by_size: HashMap<u64, Vec<&Path>> = HashMap::new();
+    // Named pipes were consumed by analysis and cannot be read again
+    for analysis in results.iter().filter(|a| a.size > 0 && a.path.is_file()) {
+        by_size.entry(analysis.size).or_default().push(&analysis.path);
+    }
+
+    let candidates: Vec<(u64, &Path)> = by_size
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .flat_map(|(size, paths)| paths.into_iter().map(move |p| (size, p)))
+        .collect();
+
+    let hashed: Vec<(blake3::Hash, u64, &Path)> = candidates
+        .into_par_iter()
+        .filter_map(|(size, path)| hash_file(path).ok().map(|hash| (hash, size, path)))
+        .collect();
+
+    let mut groups: HashMap<blake3::Hash, DuplicateGroup> = HashMap::new();
+    for (hash, size, path) in hashed {
+        groups
+            .entry(hash)
+            .or_insert_with(|| DuplicateGroup { hash, size, paths: Vec::new() })
+            .paths
+            .push(path.to_path_buf());
+    }
+
+    // Keep the order files were analyzed in, both within and across groups
+    let order: HashMap<&Path, usize> = results.iter().enumerate().map(|(i, a)| (a.path.as_path(), i)).collect();
+    let mut groups: Vec<DuplicateGroup> = groups.into_values().filter(|g| g.paths.len() > 1).collect();
+    for group in &mut groups {
+        group.paths.sort_by_key(|p| order[p.as_path()]);
+    }
+    groups.sort_by_key(|g| order[g.paths[0].as_path()]);
+    groups
+}
+
+/// Drop all but the first copy of each duplicate group, noting how many copies it stands for
+pub fn collapse(results: Vec<FileAnalysis>, groups: &[DuplicateGroup]) -> Vec<FileAnalysis> {
+    let mut copies: HashMap<&Path, usize> = HashMap::new();
+    let mut dropped: HashSet<&Path> = HashSet::new();
+    for group in groups {
+        copies.insert(&group.paths[0], group.paths.len() - 1);
+        dropped.extend(group.paths[1..].iter().map(PathBuf::as_path));
+    }
+
+    results
+        .into_iter()
+        .filter(|a| !dropped.contains(a.path.as_path()))
+        .map(|mut a| {
+            if let Some(&n) = copies.get(a.path.as_path()) {
+                a.notes.push(format!("{} identical {}", n, if n == 1 { "copy" } else { "copies" }));
+            }
+            a
+        })
+        .collect()
+}
@@ -0,0 +1,128 @@
+//! Third-party format recognition: a [`Detector`] sees each file's leading bytes and statistics
+//! after enro's own detection and may replace the classification.
+//!
+//! ```no_run
+//! use enro::detector::{Detection, Detector, DetectorInput};
+//! use enro::FileType;
+//!
+//! struct AcmeCad;
+//!
+//! impl Detector for AcmeCad {
+//!     fn name(&self) -> &str {
+//!         "acme-cad/1"
+//!     }
+//!
+//!     fn detect(&self, input: &DetectorInput) -> Option<Detection> {
+//!         input.header.starts_with(b"ACME\x01").then(|| Detection {
+//!             file_type: FileType::Document("ACMECAD".to_string()),
+//!             confidence: 95,
+//!             label: None,
+//!         })
+//!     }
+//! }
+//!
+//! let analysis = enro::Analyzer::new().detector(AcmeCad).analyze_file("drawing.acd")?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::FileType;
+
+/// What a detector gets to look at
+pub struct DetectorInput<'a> {
+    pub path: &'a Path,
+    /// The first chunk read, which starts with the file's leading bytes
+    pub header: &'a [u8],
+    pub size: u64,
+    /// Entropy over every byte read
+    pub entropy: f64,
+    /// Bytes read, less than `size` when `max_bytes` cut the read short
+    pub bytes_read: usize,
+    /// How often each byte value occurred in what was read
+    pub byte_counts: &'a [u64; 256],
+    /// enro's own classification
+    pub file_type: &'a FileType,
+}
+
+/// A detector's classification and how sure it is (0-100)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub file_type: FileType,
+    pub confidence: u8,
+    /// Noted in place of the detector's name, for verdicts no [`FileType`] expresses, such as
+    /// `SuspectedRansomware`
+    pub label: Option<String>,
+}
+
+/// Recognizes formats enro does not know
+pub trait Detector: Send + Sync {
+    /// Shown next to the classifications it makes; result caches compare names, so include a
+    /// version that changes with the detector's logic
+    fn name(&self) -> &str;
+
+    /// A classification for the file, or `None` to leave it alone
+    fn detect(&self, input: &DetectorInput) -> Option<Detection>;
+}
+
+/// The detectors consulted for every file; the most confident detection wins, the first
+/// registered on a tie
+#[derive(Clone, Default)]
+pub struct Detectors(Vec<Arc<dyn Detector>>);
+
+impl Detectors {
+    pub fn push(&mut self, detector: Arc<dyn Detector>) {
+        self.0.push(detector);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|detector| detector.name())
+    }
+
+    /// The winning detection and the name of the detector that made it
+    pub fn detect(&self, input: &DetectorInput) -> Option<(&str, Detection)> {
+        let mut best: Option<(&str, Detection)> = None;
+        for detector in &self.0 {
+            let Some(mut detection) = detector.detect(input) else {
+                continue;
+            };
+            detection.confidence = detection.confidence.min(100);
+            if best.as_ref().is_none_or(|(_, b)| detection.confidence > b.confidence) {
+                best = Some((detector.name(), detection));
+            }
+        }
+        best
+    }
+}
+
+impl FromIterator<Arc<dyn Detector>> for Detectors {
+    fn from_iter<I: IntoIterator<Item = Arc<dyn Detector>>>(iter: I) -> Self {
+        Detectors(iter.into_iter().collect())
+    }
+}
+
+impl fmt::Debug for Detectors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+// Detectors are code, so two sets are the same when their names are
+impl PartialEq for Detectors {
+    fn eq(&self, other: &Self) -> bool {
+        self.names().eq(other.names())
+    }
+}
+
+impl Serialize for Detectors {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.names())
+    }
+}
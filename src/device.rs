@@ -0,0 +1,414 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use enro::platform::Special;
+use enro::recovery::TolerantReader;
+use enro::{calculate_entropy, format_size};
+use indicatif::{ProgressBar, ProgressStyle};
+use prettytable::{Cell, Row, Table};
+use std::fs::OpenOptions;
+use std::io::{BufRead, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::Args;
+
+const SECTOR: usize = 512;
+// Blocks at or above this entropy are indistinguishable from ciphertext
+const HIGH_ENTROPY: f64 = 7.9;
+// Blocks below this are a single repeated byte (wiped or never written)
+const EMPTY_ENTROPY: f64 = 0.01;
+
+/// On-disk magic of a filesystem, volume manager or encrypted container,
+/// relative to the start of the volume
+struct Signature {
+    name: &'static str,
+    offset: usize,
+    magic: &'static [u8],
+    /// Extra sanity check for short magics that random data would often match
+    validate: Option<fn(&[u8]) -> bool>,
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature { name: "LUKS encrypted volume", offset: 0, magic: b"LUKS\xba\xbe", validate: None },
+    Signature { name: "BitLocker encrypted volume", offset: 3, magic: b"-FVE-FS-", validate: None },
+    Signature { name: "NTFS", offset: 3, magic: b"NTFS    ", validate: None },
+    Signature { name: "exFAT", offset: 3, magic: b"EXFAT   ", validate: None },
+    Signature { name: "FAT32", offset: 82, magic: b"FAT32   ", validate: None },
+    Signature { name: "FAT12/16", offset: 54, magic: b"FAT1", validate: Some(valid_fat16) },
+    Signature { name: "ext2/3/4", offset: 1080, magic: b"\x53\xef", validate: Some(valid_ext) },
+    Signature { name: "XFS", offset: 0, magic: b"XFSB", validate: None },
+    Signature { name: "Btrfs", offset: 0x10040, magic: b"_BHRfS_M", validate: None },
+    Signature { name: "APFS container", offset: 32, magic: b"NXSB", validate: None },
+    Signature { name: "HFS+", offset: 1024, magic: b"H+\x00\x04", validate: None },
+    Signature { name: "ISO 9660", offset: 0x8001, magic: b"CD001", validate: None },
+    Signature { name: "LVM2 physical volume", offset: 512, magic: b"LABELONE", validate: None },
+    Signature { name: "Linux swap", offset: 4086, magic: b"SWAPSPACE2", validate: None },
+    Signature { name: "GPT partition table", offset: 512, magic: b"EFI PART", validate: None },
+];
+
+// Bytes past a candidate volume start that must be buffered to check every signature
+const LOOKAHEAD: usize = 0x10048;
+
+fn valid_fat16(volume: &[u8]) -> bool {
+    matches!(volume.get(54..62), Some(b"FAT12   " | b"FAT16   ")) && volume.get(510..512) == Some(&[0x55, 0xAA])
+}
+
+fn valid_ext(volume: &[u8]) -> bool {
+    let Some(sb) = volume.get(1024..1104) else {
+        return false;
+    };
+    let log_block_size = u32::from_le_bytes([sb[24], sb[25], sb[26], sb[27]]);
+    let rev_level = u32::from_le_bytes([sb[76], sb[77], sb[78], sb[79]]);
+    let inodes = u32::from_le_bytes([sb[0], sb[1], sb[2], sb[3]]);
+    log_block_size <= 6 && rev_level <= 1 && inodes > 0
+}
+
+fn signature_matches(signature: &Signature, volume: &[u8]) -> bool {
+    let end = signature.offset + signature.magic.len();
+    volume.len() >= end
+        && &volume[signature.offset..end] == signature.magic
+        && signature.validate.is_none_or(|check| check(volume))
+}
+
+/// Parse a byte size such as `4096`, `64K`, `1MiB`, `10GiB` or `2GB`
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: u64 = number.parse().map_err(|_| format!("invalid size: {}", s))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        _ => return Err(format!("unknown size unit: {}", unit)),
+    };
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size too large: {}", s))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum BlockClass {
+    Empty,
+    Data,
+    HighEntropy,
+    /// Skipped after read errors (--keep-going-io)
+    Unreadable,
+}
+
+impl BlockClass {
+    fn of(entropy: f64) -> Self {
+        if entropy < EMPTY_ENTROPY {
+            BlockClass::Empty
+        } else if entropy >= HIGH_ENTROPY {
+            BlockClass::HighEntropy
+        } else {
+            BlockClass::Data
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            BlockClass::Empty => "empty",
+            BlockClass::Data => "data",
+            BlockClass::HighEntropy => "high entropy",
+            BlockClass::Unreadable => "unreadable",
+        }
+    }
+}
+
+/// Consecutive blocks of the same class
+struct Region {
+    start: u64,
+    end: u64,
+    class: BlockClass,
+    entropy_sum: f64,
+    blocks: u64,
+}
+
+impl Region {
+    fn entropy(&self) -> f64 {
+        self.entropy_sum / self.blocks as f64
+    }
+}
+
+struct Hit {
+    offset: u64,
+    name: &'static str,
+}
+
+/// Whether `path` names a block device (not a regular file, image or character device)
+pub fn is_block_device(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|m| Special::of(m.file_type()) == Some(Special::BlockDevice))
+}
+
+fn is_device(path: &Path) -> Result<bool> {
+    let metadata = std::fs::metadata(path).with_context(|| format!("Cannot access {}", path.display()))?;
+    Ok(!metadata.is_file())
+}
+
+// Raw device reads need an explicit yes, either interactively or via --yes/--allow-device
+fn confirm(path: &Path, offset: u64, length: u64) -> Result<()> {
+    if !std::io::stdin().is_terminal() {
+        bail!("Refusing to read raw device {} without --yes", path.display());
+    }
+    eprint!(
+        "Read {} of raw device {} starting at offset {}? [y/N] ",
+        format_size(length),
+        path.display(),
+        offset
+    );
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    if !matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+        bail!("Aborted");
+    }
+    Ok(())
+}
+
+/// Profile a block device or disk image: block entropy map plus volume signatures
+pub fn run(path: &Path, args: &Args) -> Result<()> {
+    // Read-only, whatever the caller's permissions would allow
+    let mut file = OpenOptions::new()
+        .read(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    // Block devices report a zero length in metadata; seeking finds the real size
+    let total = file.seek(SeekFrom::End(0)).context("Failed to determine device size")?;
+    if args.offset >= total {
+        bail!("Offset {} is beyond the end of {} ({} bytes)", args.offset, path.display(), total);
+    }
+    let length = args.length.unwrap_or(u64::MAX).min(total - args.offset);
+    let block_size = usize::try_from(args.block_size)
+        .ok()
+        .filter(|&b| b >= SECTOR && b % SECTOR == 0)
+        .context("Block size must be a non-zero multiple of 512 bytes")?;
+
+    if is_device(path)? && !args.yes && !args.allow_device {
+        confirm(path, args.offset, length)?;
+    }
+
+    file.seek(SeekFrom::Start(args.offset))?;
+    let end = args.offset + length;
+    let mut reader = TolerantReader::new(enro::pagecache::DropBehind::new(file), end, args.keep_going_io)?;
+
+    let pb = if args.machine_readable() {
+        ProgressBar::hidden()
+    } else {
+        println!("Profiling {} of {}...\n", format_size(length), path.display());
+        let pb = ProgressBar::new(length);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+                .unwrap()
+                .progress_chars("#>-"),
+        );
+        pb
+    };
+
+    let mut regions: Vec<Region> = Vec::new();
+    let mut hits: Vec<Hit> = Vec::new();
+    // Tail of the previous block, so signatures straddling a block boundary are still seen
+    let mut window: Vec<u8> = Vec::new();
+    let mut window_start = args.offset;
+    let mut block = vec![0u8; block_size];
+    let mut position = args.offset;
+    let mut unreadable = 0;
+
+    while position < end {
+        let n = read_full(&mut reader, &mut block)
+            .context("Failed to read device (--keep-going-io skips unreadable regions)")?;
+        let block_end = reader.position();
+        if block_end == position {
+            break;
+        }
+
+        let entropy = calculate_entropy(&block[..n]);
+        let class = if n == 0 { BlockClass::Unreadable } else { BlockClass::of(entropy) };
+        match regions.last_mut() {
+            Some(region) if region.class == class => {
+                region.end = block_end;
+                region.entropy_sum += entropy;
+                region.blocks += 1;
+            }
+            _ => regions.push(Region {
+                start: position,
+                end: block_end,
+                class,
+                entropy_sum: entropy,
+                blocks: 1,
+            }),
+        }
+
+        window.extend_from_slice(&block[..n]);
+        position = block_end;
+        pb.set_position(position - args.offset);
+
+        let eof = position >= end;
+        if reader.unreadable > unreadable {
+            // Bytes after a skipped region are no longer contiguous with the buffer; start over
+            unreadable = reader.unreadable;
+            scan_signatures(&window, window.len(), window_start, args.offset, &mut hits);
+            window.clear();
+            window_start = position;
+            continue;
+        }
+
+        // Check every sector whose lookahead is fully buffered, keep the rest for the next block
+        let checkable = if eof { window.len() } else { window.len().saturating_sub(LOOKAHEAD) };
+        let checkable = checkable - checkable % SECTOR;
+        scan_signatures(&window, checkable, window_start, args.offset, &mut hits);
+        window.drain(..checkable);
+        window_start += checkable as u64;
+    }
+    scan_signatures(&window, window.len(), window_start, args.offset, &mut hits);
+    pb.finish_and_clear();
+
+    if args.json {
+        let json = serde_json::json!({
+            "device": path.display().to_string(),
+            "offset": args.offset,
+            "length": position - args.offset,
+            "block_size": block_size,
+            "unreadable": unreadable,
+            "signatures": hits
+                .iter()
+                .map(|h| serde_json::json!({ "offset": h.offset, "name": h.name }))
+                .collect::<Vec<_>>(),
+            "regions": regions
+                .iter()
+                .map(|r| serde_json::json!({
+                    "start": r.start,
+                    "end": r.end,
+                    "class": r.class.label(),
+                    "entropy": (r.entropy() * 10000.0).round() / 10000.0,
+                }))
+                .collect::<Vec<_>>(),
+        });
+        println!("{}", serde_json::to_string_pretty(&json)?);
+    } else if args.simple {
+        println!("Kind,Start,End,Detail,Entropy");
+        for h in &hits {
+            println!("signature,{},,{},", h.offset, h.name);
+        }
+        for r in &regions {
+            println!("region,{},{},{},{:.2}", r.start, r.end, r.class.label(), r.entropy());
+        }
+    } else {
+        display_profile(&hits, &regions, position - args.offset, unreadable);
+    }
+
+    Ok(())
+}
+
+fn scan_signatures(window: &[u8], checkable: usize, window_start: u64, range_start: u64, hits: &mut Vec<Hit>) {
+    for pos in (0..checkable).step_by(SECTOR) {
+        let offset = window_start + pos as u64;
+        let volume = &window[pos..];
+        for signature in SIGNATURES {
+            if signature_matches(signature, volume) {
+                hits.push(Hit { offset, name: signature.name });
+            }
+        }
+        // A boot-sector marker alone is too common in random data to report anywhere but the start
+        if offset == range_start && volume.len() >= SECTOR && volume[510..512] == [0x55, 0xAA] {
+            hits.push(Hit { offset, name: "MBR/boot sector" });
+        }
+    }
+}
+
+// Fill the buffer unless EOF is reached; short device reads are common
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn display_profile(hits: &[Hit], regions: &[Region], length: u64, unreadable: u64) {
+    let format = prettytable::format::FormatBuilder::new()
+        .column_separator(' ')
+        .borders(' ')
+        .padding(1, 1)
+        .build();
+
+    println!("{}", "SIGNATURES".bold().cyan());
+    if hits.is_empty() {
+        println!("  {}", "No filesystem or encrypted volume signatures found".dimmed());
+    } else {
+        let mut table = Table::new();
+        table.set_format(format);
+        table.add_row(Row::new(vec![
+            Cell::new("Offset").style_spec("Fb"),
+            Cell::new("Signature").style_spec("Fb"),
+        ]));
+        for hit in hits {
+            let name = if hit.name.contains("encrypted") {
+                hit.name.red().bold().to_string()
+            } else {
+                hit.name.to_string()
+            };
+            table.add_row(Row::new(vec![
+                Cell::new(&format!("{:#x}", hit.offset)),
+                Cell::new(&name),
+            ]));
+        }
+        table.printstd();
+    }
+
+    println!("\n{}", "REGIONS".bold().cyan());
+    let mut table = Table::new();
+    table.set_format(format);
+    table.add_row(Row::new(vec![
+        Cell::new("Start").style_spec("Fb"),
+        Cell::new("End").style_spec("Fb"),
+        Cell::new("Size").style_spec("Fb"),
+        Cell::new("Class").style_spec("Fb"),
+        Cell::new("Entropy").style_spec("Fb"),
+    ]));
+    for region in regions {
+        let class = match region.class {
+            BlockClass::HighEntropy => region.class.label().red().to_string(),
+            BlockClass::Empty => region.class.label().dimmed().to_string(),
+            BlockClass::Unreadable => region.class.label().yellow().to_string(),
+            BlockClass::Data => region.class.label().to_string(),
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(&format!("{:#x}", region.start)),
+            Cell::new(&format!("{:#x}", region.end)),
+            Cell::new(&format_size(region.end - region.start)),
+            Cell::new(&class),
+            Cell::new(&format!("{:.2}/8.0", region.entropy())),
+        ]));
+    }
+    table.printstd();
+
+    let high: u64 = regions
+        .iter()
+        .filter(|r| r.class == BlockClass::HighEntropy)
+        .map(|r| r.end - r.start)
+        .sum();
+    let percent = if length > 0 { high as f64 * 100.0 / length as f64 } else { 0.0 };
+    println!(
+        "\n  {} {}",
+        "•".cyan(),
+        format!("{:.1}% of {} is high-entropy (encrypted or random)", percent, format_size(length)).bold()
+    );
+    if unreadable > 0 {
+        println!(
+            "  {} {}",
+            "⚠️".yellow(),
+            format!("{} could not be read and was skipped", format_size(unreadable)).yellow().bold()
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+use colored::Colorize;
+use enro::{format_size, FileType};
+use prettytable::{Cell, Row};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::escape_csv;
+use crate::history::{entropy_colored, new_table, History, Record};
+
+/// Compare two scans: JSON reports written by `enro --json`, or scan IDs from a history database
+#[derive(ClapArgs, Debug)]
+pub struct DiffArgs {
+    /// Earlier scan: a JSON report, or a scan ID with --history
+    #[arg(value_name = "OLD")]
+    pub old: String,
+
+    /// Later scan: a JSON report, or a scan ID with --history
+    #[arg(value_name = "NEW")]
+    pub new: String,
+
+    /// Read OLD and NEW as scan IDs from this history database (see `enro scan --history`)
+    #[arg(long, value_name = "DB")]
+    pub history: Option<PathBuf>,
+
+    /// Only report files that became Encrypted/Random
+    #[arg(long)]
+    pub suspicious_only: bool,
+
+    /// Simple output format (CSV, no colors)
+    #[arg(short, long, conflicts_with = "json")]
+    pub simple: bool,
+
+    /// JSON output format (for scripting)
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Change {
+    Added,
+    Removed,
+    Reclassified,
+}
+
+impl Change {
+    fn key(self) -> &'static str {
+        match self {
+            Change::Added => "added",
+            Change::Removed => "removed",
+            Change::Reclassified => "reclassified",
+        }
+    }
+}
+
+struct Difference<'a> {
+    change: Change,
+    old: Option<&'a Record>,
+    new: Option<&'a Record>,
+}
+
+impl Difference<'_> {
+    fn path(&self) -> &str {
+        &self.old.or(self.new).expect("a difference has at least one side").path
+    }
+
+    /// Whether the file turned into encrypted or random data, the signature of ransomware
+    /// and of data being staged for exfiltration
+    fn suspicious(&self) -> bool {
+        let encrypted = |r: &Record| matches!(r.file_type, FileType::Encrypted | FileType::Random);
+        self.new.is_some_and(encrypted) && !self.old.is_some_and(encrypted)
+    }
+}
+
+pub fn run(args: &DiffArgs) -> Result<()> {
+    let (old, new) = match &args.history {
+        Some(db) => {
+            let history = History::open(db)?;
+            (history.load(parse_scan_id(&args.old)?)?, history.load(parse_scan_id(&args.new)?)?)
+        }
+        None => (load_report(Path::new(&args.old))?, load_report(Path::new(&args.new))?),
+    };
+
+    let old: BTreeMap<&str, &Record> = old.iter().map(|r| (r.path.as_str(), r)).collect();
+    let new: BTreeMap<&str, &Record> = new.iter().map(|r| (r.path.as_str(), r)).collect();
+    let mut differences: Vec<Difference> = Vec::new();
+    for (path, &before) in &old {
+        match new.get(path) {
+            None => differences.push(Difference {
+                change: Change::Removed,
+                old: Some(before),
+                new: None,
+            }),
+            Some(&after) if after.file_type != before.file_type => differences.push(Difference {
+                change: Change::Reclassified,
+                old: Some(before),
+                new: Some(after),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (path, &after) in &new {
+        if !old.contains_key(path) {
+            differences.push(Difference {
+                change: Change::Added,
+                old: None,
+                new: Some(after),
+            });
+        }
+    }
+    differences.sort_by(|a, b| a.path().cmp(b.path()));
+    if args.suspicious_only {
+        differences.retain(Difference::suspicious);
+    }
+
+    if args.json {
+        print_json(&differences)
+    } else if args.simple {
+        print_csv(&differences);
+        Ok(())
+    } else {
+        print_tables(&differences);
+        Ok(())
+    }
+}
+
+fn parse_scan_id(id: &str) -> Result<i64> {
+    id.parse().with_context(|| format!("With --history, OLD and NEW are scan IDs, not `{}`", id))
+}
+
+/// The files of a `enro --json` report; entries for files that failed to analyze are skipped
+pub fn load_report(path: &Path) -> Result<Vec<Record>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let report: serde_json::Value =
+        serde_json::from_str(&text).with_context(|| format!("{} is not a JSON report", path.display()))?;
+    let files = report["files"]
+        .as_array()
+        .with_context(|| format!("{} has no \"files\" array; write it with --json", path.display()))?;
+
+    let mut records = Vec::new();
+    for file in files {
+        let (Some(file_path), Some(file_type)) = (file["path"].as_str(), file["type"].as_str()) else {
+            continue;
+        };
+        records.push(Record {
+            path: file_path.to_string(),
+            file_type: file_type
+                .parse()
+                .map_err(anyhow::Error::msg)
+                .with_context(|| format!("{}: {}", path.display(), file_path))?,
+            entropy: file["entropy"].as_f64().unwrap_or(0.0),
+            size: file["size"].as_u64().unwrap_or(0),
+        });
+    }
+    Ok(records)
+}
+
+fn print_json(differences: &[Difference]) -> Result<()> {
+    let side = |r: Option<&Record>| {
+        r.map(|r| {
+            serde_json::json!({
+                "type": r.file_type.display_compact(),
+                "entropy": (r.entropy * 10000.0).round() / 10000.0,
+                "size": r.size,
+            })
+        })
+    };
+    let changes: Vec<_> = differences
+        .iter()
+        .map(|d| {
+            serde_json::json!({
+                "path": d.path(),
+                "change": d.change.key(),
+                "old": side(d.old),
+                "new": side(d.new),
+                "suspicious": d.suspicious(),
+            })
+        })
+        .collect();
+    let count = |change: Change| differences.iter().filter(|d| d.change == change).count();
+    let output = serde_json::json!({
+        "changes": changes,
+        "summary": {
+            "added": count(Change::Added),
+            "removed": count(Change::Removed),
+            "reclassified": count(Change::Reclassified),
+            "suspicious": differences.iter().filter(|d| d.suspicious()).count(),
+        },
+    });
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+fn print_csv(differences: &[Difference]) {
+    println!("Change,Path,Old Type,New Type,Old Entropy,New Entropy,Size,Suspicious");
+    for d in differences {
+        println!(
+            "{},{},{},{},{},{},{},{}",
+            d.change.key(),
+            escape_csv(d.path()),
+            d.old.map(|r| r.file_type.display_compact()).unwrap_or_default(),
+            d.new.map(|r| r.file_type.display_compact()).unwrap_or_default(),
+            d.old.map(|r| format!("{:.2}", r.entropy)).unwrap_or_default(),
+            d.new.map(|r| format!("{:.2}", r.entropy)).unwrap_or_default(),
+            d.new.or(d.old).map_or(0, |r| r.size),
+            d.suspicious()
+        );
+    }
+}
+
+fn print_tables(differences: &[Difference]) {
+    if differences.is_empty() {
+        println!("{}", "No differences between the two scans.".green());
+        return;
+    }
+
+    // Transitions into Encrypted/Random come first: they are what an incident responder asks about
+    let suspicious: Vec<&Difference> = differences.iter().filter(|d| d.suspicious()).collect();
+    if !suspicious.is_empty() {
+        println!("{}", "BECAME ENCRYPTED/RANDOM".red().bold());
+        print_table(&suspicious);
+        println!();
+    }
+    for (change, title) in [
+        (Change::Reclassified, "RECLASSIFIED"),
+        (Change::Added, "ADDED"),
+        (Change::Removed, "REMOVED"),
+    ] {
+        let rows: Vec<&Difference> = differences
+            .iter()
+            .filter(|d| d.change == change && !d.suspicious())
+            .collect();
+        if !rows.is_empty() {
+            println!("{}", title.bold());
+            print_table(&rows);
+            println!();
+        }
+    }
+
+    let count = |change: Change| differences.iter().filter(|d| d.change == change).count();
+    println!(
+        "  {} {}",
+        "•".cyan(),
+        format!(
+            "{} added, {} removed, {} reclassified",
+            count(Change::Added),
+            count(Change::Removed),
+            count(Change::Reclassified)
+        )
+        .bold()
+    );
+    if !suspicious.is_empty() {
+        println!(
+            "  {} {}",
+            "•".cyan(),
+            format!("{} file(s) became Encrypted/Random", suspicious.len()).red().bold()
+        );
+    }
+}
+
+fn print_table(differences: &[&Difference]) {
+    let mut table = new_table(&["Path", "Before", "After", "Entropy", "Size"]);
+    for d in differences {
+        let entropy = match (d.old, d.new) {
+            (Some(old), Some(new)) => format!("{} → {}", entropy_colored(old.entropy), entropy_colored(new.entropy)),
+            (old, new) => entropy_colored(old.or(new).map_or(0.0, |r| r.entropy)),
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(d.path()),
+            Cell::new(&d.old.map_or_else(|| "-".to_string(), |r| r.file_type.display_plain())),
+            Cell::new(&d.new.map_or_else(|| "-".to_string(), |r| r.file_type.display_plain())),
+            Cell::new(&entropy),
+            Cell::new(&format_size(d.new.or(d.old).map_or(0, |r| r.size))),
+        ]));
+    }
+    table.printstd();
+}
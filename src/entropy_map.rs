@@ -0,0 +1,117 @@
+use anyhow::{Context, Result};
+use enro::{calculate_entropy, platform};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// The map aims for about this many blocks, whatever the file size
+const MAX_BLOCKS: u64 = 2048;
+// Smaller blocks would make entropy meaningless (at most 8 bits over 256 distinct bytes)
+const MIN_BLOCK_SIZE: u64 = 256;
+// Short magics turn up by chance in large random files; the list stops here
+const MAX_SIGNATURES: usize = 200;
+
+/// File magics worth pointing out wherever they occur, e.g. an archive appended to an image
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"PK\x03\x04", "ZIP local file header"),
+    (b"PK\x05\x06", "ZIP end of central directory"),
+    (b"%PDF-", "PDF"),
+    (b"\x89PNG\r\n\x1a\n", "PNG"),
+    (b"\xff\xd8\xff", "JPEG"),
+    (b"GIF87a", "GIF"),
+    (b"GIF89a", "GIF"),
+    (b"\x1f\x8b\x08", "GZIP"),
+    (b"7z\xbc\xaf\x27\x1c", "7-Zip"),
+    (b"Rar!\x1a\x07", "RAR"),
+    (b"\xfd7zXZ\x00", "XZ"),
+    (b"\x28\xb5\x2f\xfd", "Zstandard"),
+    (b"\x7fELF", "ELF executable"),
+    (b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1", "OLE compound file"),
+    (b"SQLite format 3\x00", "SQLite database"),
+    (b"-----BEGIN ", "PEM block"),
+    (b"LUKS\xba\xbe", "LUKS header"),
+    (b"OggS", "Ogg"),
+];
+
+const LONGEST_SIGNATURE: usize = 16;
+
+/// Entropy of consecutive fixed-size blocks of a file, with the offsets of known signatures
+pub struct EntropyMap {
+    pub size: u64,
+    pub block_size: u64,
+    pub blocks: Vec<f64>,
+    pub signatures: Vec<(u64, &'static str)>,
+    /// More signatures were found than [`MAX_SIGNATURES`]
+    pub truncated: bool,
+}
+
+impl EntropyMap {
+    /// Read the whole file; `None` when `cancel` was set before it finished
+    pub fn compute(path: &Path, cancel: &AtomicBool) -> Result<Option<Self>> {
+        let mut file = File::open(platform::long_path(path)).with_context(|| format!("Failed to open {}", path.display()))?;
+        let size = file.metadata()?.len();
+        let block_size = size.div_ceil(MAX_BLOCKS).max(MIN_BLOCK_SIZE);
+
+        let mut map = EntropyMap {
+            size,
+            block_size,
+            blocks: Vec::new(),
+            signatures: Vec::new(),
+            truncated: false,
+        };
+        let mut block = Vec::with_capacity(block_size as usize);
+        // Bytes not yet checked for signatures, kept until a whole signature fits after them
+        let mut pending: Vec<u8> = Vec::new();
+        let mut pending_start = 0u64;
+        loop {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(None);
+            }
+            block.clear();
+            let n = (&mut file).take(block_size).read_to_end(&mut block)?;
+            let eof = n < block_size as usize;
+            if n > 0 {
+                map.blocks.push(calculate_entropy(&block));
+            }
+
+            pending.extend_from_slice(&block);
+            let checkable = if eof {
+                pending.len()
+            } else {
+                pending.len().saturating_sub(LONGEST_SIGNATURE - 1)
+            };
+            map.find_signatures(&pending, checkable, pending_start);
+            pending.drain(..checkable);
+            pending_start += checkable as u64;
+            if eof {
+                return Ok(Some(map));
+            }
+        }
+    }
+
+    fn find_signatures(&mut self, data: &[u8], checkable: usize, start: u64) {
+        if self.truncated {
+            return;
+        }
+        for pos in 0..checkable {
+            for &(magic, name) in SIGNATURES {
+                if data[pos..].starts_with(magic) {
+                    if self.signatures.len() == MAX_SIGNATURES {
+                        self.truncated = true;
+                        return;
+                    }
+                    self.signatures.push((start + pos as u64, name));
+                }
+            }
+        }
+    }
+
+    /// Whether a signature starts inside the block
+    pub fn has_signature(&self, block: usize) -> bool {
+        let start = block as u64 * self.block_size;
+        self.signatures
+            .iter()
+            .any(|&(offset, _)| offset >= start && offset < start + self.block_size)
+    }
+}
@@ -0,0 +1,224 @@
+//! --sample-files: analyzing a random subset of the files found and extrapolating to all of them
+
+use clap::ValueEnum;
+use enro::FileAnalysis;
+use std::path::PathBuf;
+
+use crate::{category, Category};
+
+// Two-sided 95% confidence
+pub const CONFIDENCE: f64 = 0.95;
+const Z: f64 = 1.96;
+
+/// How many of the files found to analyze
+#[derive(Clone, Copy, Debug)]
+pub enum SampleSize {
+    /// A share of them, between 0 and 1
+    Fraction(f64),
+    Count(usize),
+}
+
+impl SampleSize {
+    fn of(&self, population: usize) -> usize {
+        match *self {
+            SampleSize::Fraction(fraction) => ((population as f64 * fraction).ceil() as usize).clamp(1, population.max(1)),
+            SampleSize::Count(count) => count,
+        }
+        .min(population)
+    }
+}
+
+/// Parse --sample-files: a percentage such as `5%` or a number of files such as `1000`
+pub fn parse_sample_size(s: &str) -> Result<SampleSize, String> {
+    let s = s.trim();
+    match s.strip_suffix('%') {
+        Some(percent) => match percent.trim().parse::<f64>() {
+            Ok(percent) if percent > 0.0 && percent <= 100.0 => Ok(SampleSize::Fraction(percent / 100.0)),
+            _ => Err(format!("'{}' is not a percentage above 0 and at most 100", s)),
+        },
+        None => match s.parse::<usize>() {
+            Ok(count) if count > 0 => Ok(SampleSize::Count(count)),
+            _ => Err(format!("'{}' is neither a percentage like 5% nor a number of files", s)),
+        },
+    }
+}
+
+/// Pick `size` of `files` at random, each equally likely, keeping them in the order found
+pub fn draw(files: Vec<PathBuf>, size: SampleSize, seed: Option<u64>) -> Vec<PathBuf> {
+    let mut rng = seed.map_or_else(fastrand::Rng::new, fastrand::Rng::with_seed);
+    let n = size.of(files.len());
+    // Partial Fisher-Yates: the first n slots end up a uniform sample
+    let mut picked: Vec<usize> = (0..files.len()).collect();
+    for i in 0..n {
+        let j = rng.usize(i..picked.len());
+        picked.swap(i, j);
+    }
+    picked.truncate(n);
+    picked.sort_unstable();
+    let mut picked = picked.into_iter().peekable();
+    files
+        .into_iter()
+        .enumerate()
+        .filter(|&(i, _)| picked.next_if_eq(&i).is_some())
+        .map(|(_, file)| file)
+        .collect()
+}
+
+/// A point estimate and the confidence interval around it
+#[derive(Clone, Copy, Debug)]
+pub struct Interval {
+    pub low: f64,
+    pub estimate: f64,
+    pub high: f64,
+}
+
+impl Interval {
+    /// `estimate` give or take `Z` standard errors, never below zero
+    fn around(estimate: f64, standard_error: f64) -> Self {
+        Interval {
+            low: (estimate - Z * standard_error).max(0.0),
+            estimate,
+            high: estimate + Z * standard_error,
+        }
+    }
+
+    fn scaled(self, factor: f64) -> Self {
+        Interval {
+            low: self.low * factor,
+            estimate: self.estimate * factor,
+            high: self.high * factor,
+        }
+    }
+}
+
+/// What the sample says about one category across all files found
+pub struct CategoryEstimate {
+    pub category: Category,
+    /// Files of this category in the sample
+    pub sampled: usize,
+    /// Share of all files, between 0 and 1
+    pub file_share: Interval,
+    pub files: Interval,
+    /// Share of all bytes, between 0 and 1
+    pub byte_share: Interval,
+    pub bytes: Interval,
+}
+
+/// Extrapolation from a simple random sample of `sampled` out of `population` files
+pub struct Estimate {
+    pub population: usize,
+    pub sampled: usize,
+    pub total_bytes: Interval,
+    /// Categories met in the sample, plus Encrypted and Random, which are always reported
+    pub categories: Vec<CategoryEstimate>,
+}
+
+impl Estimate {
+    /// Extrapolate from the results of the sampled files; those that could not be analyzed are
+    /// left out, as if they had not been drawn
+    pub fn new(results: &[FileAnalysis], population: usize) -> Self {
+        let n = results.len();
+        let sizes: Vec<f64> = results.iter().map(|r| r.size as f64).collect();
+        let total_bytes = Interval::around(population as f64 * mean(&sizes), total_error(&sizes, population));
+
+        let categories = Category::value_variants()
+            .iter()
+            .filter_map(|&c| {
+                let sampled = results.iter().filter(|r| category(&r.file_type) == c).count();
+                if sampled == 0 && !matches!(c, Category::Encrypted | Category::Random) {
+                    return None;
+                }
+                let bytes: Vec<f64> = results
+                    .iter()
+                    .map(|r| if category(&r.file_type) == c { r.size as f64 } else { 0.0 })
+                    .collect();
+                let file_share = wilson(sampled, n, population);
+                Some(CategoryEstimate {
+                    category: c,
+                    sampled,
+                    file_share,
+                    files: file_share.scaled(population as f64),
+                    byte_share: ratio(&bytes, &sizes, population),
+                    bytes: Interval::around(population as f64 * mean(&bytes), total_error(&bytes, population)),
+                })
+            })
+            .collect();
+
+        Estimate {
+            population,
+            sampled: n,
+            total_bytes,
+            categories,
+        }
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn variance(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = mean(values);
+    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64
+}
+
+/// Share of the population left out of the sample; the finite population correction
+fn unsampled(n: usize, population: usize) -> f64 {
+    if population == 0 {
+        return 0.0;
+    }
+    1.0 - n as f64 / population as f64
+}
+
+/// Standard error of `population` times the sample mean of `values`
+fn total_error(values: &[f64], population: usize) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    population as f64 * (unsampled(values.len(), population) * variance(values) / values.len() as f64).sqrt()
+}
+
+/// Wilson score interval of a share, which unlike the plain one stays informative when few or
+/// none of the sampled files have the property; the sample counts for more the larger a part of
+/// the population it is
+fn wilson(hits: usize, n: usize, population: usize) -> Interval {
+    if n == 0 {
+        return Interval { low: 0.0, estimate: 0.0, high: 1.0 };
+    }
+    let share = hits as f64 / n as f64;
+    let left_out = unsampled(n, population);
+    if left_out == 0.0 {
+        return Interval { low: share, estimate: share, high: share };
+    }
+    let n = n as f64 / left_out;
+    let z2 = Z * Z;
+    let center = (share + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let half = Z / (1.0 + z2 / n) * (share * (1.0 - share) / n + z2 / (4.0 * n * n)).sqrt();
+    Interval {
+        low: (center - half).max(0.0),
+        estimate: share,
+        high: (center + half).min(1.0),
+    }
+}
+
+/// Ratio estimate of `sum(part) / sum(whole)` with its linearized standard error
+fn ratio(part: &[f64], whole: &[f64], population: usize) -> Interval {
+    let whole_mean = mean(whole);
+    if whole_mean == 0.0 {
+        return Interval { low: 0.0, estimate: 0.0, high: 0.0 };
+    }
+    let r = mean(part) / whole_mean;
+    let residuals: Vec<f64> = part.iter().zip(whole).map(|(y, x)| y - r * x).collect();
+    let error = (unsampled(part.len(), population) * variance(&residuals) / part.len() as f64).sqrt() / whole_mean;
+    let interval = Interval::around(r, error);
+    Interval {
+        high: interval.high.min(1.0),
+        ..interval
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::{Context, Result};
+use enro::{FileAnalysis, FileType};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::SystemTime;
+
+use crate::display_path;
+use crate::walk::{SkipReason, Skipped};
+
+// Structured data ID under the documentation enterprise number (RFC 5612)
+const SD_ID: &str = "enro@32473";
+// Facility `user`
+const FACILITY: u8 = 1;
+const WARNING: u8 = 4;
+const INFO: u8 = 6;
+
+enum Sink {
+    Syslog(UdpSocket),
+    #[cfg(unix)]
+    LocalSyslog(std::os::unix::net::UnixDatagram),
+    #[cfg(unix)]
+    Journald(std::os::unix::net::UnixDatagram),
+}
+
+/// One event: a severity, a human-readable message and structured fields
+struct Event {
+    severity: u8,
+    kind: &'static str,
+    message: String,
+    fields: Vec<(&'static str, String)>,
+}
+
+/// Sends findings and summaries to syslog (RFC 5424) and/or the systemd journal
+pub struct EventLog {
+    sinks: Vec<Sink>,
+    hostname: String,
+}
+
+impl EventLog {
+    /// None when no event output was asked for
+    pub fn open(syslog: bool, syslog_server: Option<&str>, journald: bool) -> Result<Option<Self>> {
+        let mut sinks = Vec::new();
+        if syslog {
+            sinks.push(local_syslog()?);
+        }
+        if let Some(server) = syslog_server {
+            let addr = server
+                .to_socket_addrs()
+                .ok()
+                .and_then(|mut addrs| addrs.next())
+                .with_context(|| format!("Failed to resolve syslog server {}", server))?;
+            let local: SocketAddr = if addr.is_ipv4() {
+                (Ipv4Addr::UNSPECIFIED, 0).into()
+            } else {
+                (Ipv6Addr::UNSPECIFIED, 0).into()
+            };
+            let socket = UdpSocket::bind(local).context("Failed to open a UDP socket for syslog")?;
+            socket.connect(addr)?;
+            sinks.push(Sink::Syslog(socket));
+        }
+        if journald {
+            sinks.push(journal()?);
+        }
+        if sinks.is_empty() {
+            return Ok(None);
+        }
+
+        let hostname = sysinfo::System::host_name().unwrap_or_else(|| "-".to_string());
+        Ok(Some(EventLog { sinks, hostname }))
+    }
+
+    /// One warning per file classified as encrypted or random
+    pub fn findings(&self, results: &[FileAnalysis]) {
+        for analysis in results {
+            if !matches!(analysis.file_type, FileType::Encrypted | FileType::Random) {
+                continue;
+            }
+            let path = display_path(&analysis.path);
+            self.send(&Event {
+                severity: WARNING,
+                kind: "finding",
+                message: format!(
+                    "{} classified as {} (entropy {:.2})",
+                    path,
+                    analysis.file_type.display_plain(),
+                    analysis.entropy
+                ),
+                fields: vec![
+                    ("path", path),
+                    ("type", analysis.file_type.display_compact()),
+                    ("entropy", format!("{:.4}", analysis.entropy)),
+                    ("size", analysis.size.to_string()),
+                ],
+            });
+        }
+    }
+
+    /// Totals of a finished scan
+    pub fn summary(&self, results: &[FileAnalysis], skipped: &Skipped, errors: usize) {
+        let count = |file_type: FileType| results.iter().filter(|r| r.file_type == file_type).count();
+        let (encrypted, random) = (count(FileType::Encrypted), count(FileType::Random));
+        let skipped: usize = SkipReason::ALL.iter().map(|&reason| skipped.count(reason)).sum();
+        self.send(&Event {
+            severity: INFO,
+            kind: "summary",
+            message: format!(
+                "Scan finished: {} files, {} encrypted, {} random, {} skipped, {} errors",
+                results.len(),
+                encrypted,
+                random,
+                skipped,
+                errors
+            ),
+            fields: vec![
+                ("files", results.len().to_string()),
+                ("encrypted", encrypted.to_string()),
+                ("random", random.to_string()),
+                ("skipped", skipped.to_string()),
+                ("errors", errors.to_string()),
+            ],
+        });
+    }
+
+    fn send(&self, event: &Event) {
+        for sink in &self.sinks {
+            let sent = match sink {
+                Sink::Syslog(socket) => socket.send(self.rfc5424(event).as_bytes()).map(drop),
+                #[cfg(unix)]
+                Sink::LocalSyslog(socket) => socket.send(self.rfc5424(event).as_bytes()).map(drop),
+                #[cfg(unix)]
+                Sink::Journald(socket) => socket.send(&journal_entry(event)).map(drop),
+            };
+            if let Err(e) = sent {
+                eprintln!("Warning: failed to log event: {}", e);
+            }
+        }
+    }
+
+    fn rfc5424(&self, event: &Event) -> String {
+        let mut data = format!("[{}", SD_ID);
+        data.push_str(&format!(" event=\"{}\"", event.kind));
+        for (name, value) in &event.fields {
+            data.push_str(&format!(" {}=\"{}\"", name, escape_param(value)));
+        }
+        data.push(']');
+        format!(
+            "<{}>1 {} {} enro {} {} {} {}",
+            FACILITY * 8 + event.severity,
+            humantime::format_rfc3339_micros(SystemTime::now()),
+            self.hostname,
+            std::process::id(),
+            event.kind.to_ascii_uppercase(),
+            data,
+            event.message
+        )
+    }
+}
+
+// `"`, `\` and `]` must be escaped inside SD-PARAM values
+fn escape_param(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(unix)]
+fn local_syslog() -> Result<Sink> {
+    let socket = std::os::unix::net::UnixDatagram::unbound()?;
+    // /dev/log on Linux, /var/run/syslog on macOS
+    ["/dev/log", "/var/run/syslog"]
+        .iter()
+        .find(|path| socket.connect(path).is_ok())
+        .context("No local syslog socket (/dev/log) to send to; use --syslog-server")?;
+    Ok(Sink::LocalSyslog(socket))
+}
+
+#[cfg(not(unix))]
+fn local_syslog() -> Result<Sink> {
+    anyhow::bail!("--syslog needs a local syslog socket, which this platform lacks; use --syslog-server")
+}
+
+#[cfg(unix)]
+fn journal() -> Result<Sink> {
+    let socket = std::os::unix::net::UnixDatagram::unbound()?;
+    socket
+        .connect("/run/systemd/journal/socket")
+        .context("Failed to connect to the systemd journal")?;
+    Ok(Sink::Journald(socket))
+}
+
+#[cfg(not(unix))]
+fn journal() -> Result<Sink> {
+    anyhow::bail!("--journald is only available on Linux with systemd")
+}
+
+/// An entry in the journal's native protocol; fields become `ENRO_<NAME>`
+#[cfg(unix)]
+fn journal_entry(event: &Event) -> Vec<u8> {
+    let mut entry = Vec::new();
+    let mut field = |name: &str, value: &str| {
+        entry.extend_from_slice(name.as_bytes());
+        // Values containing newlines are sent length-prefixed
+        if value.contains('\n') {
+            entry.push(b'\n');
+            entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            entry.push(b'=');
+        }
+        entry.extend_from_slice(value.as_bytes());
+        entry.push(b'\n');
+    };
+    field("MESSAGE", &event.message);
+    field("PRIORITY", &event.severity.to_string());
+    field("SYSLOG_IDENTIFIER", "enro");
+    field("ENRO_EVENT", event.kind);
+    for (name, value) in &event.fields {
+        field(&format!("ENRO_{}", name.to_ascii_uppercase()), value);
+    }
+    entry
+}
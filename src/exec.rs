@@ -0,0 +1,140 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use enro::FileAnalysis;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+// Paths passed to one --exec-batch invocation, well under any platform's argument length limit
+const BATCH_SIZE: usize = 1000;
+
+/// A command for --exec/--exec-batch, split into words the way a POSIX shell would but run
+/// without one, so file names are never interpreted
+#[derive(Clone, Debug)]
+pub struct CommandTemplate {
+    words: Vec<String>,
+}
+
+impl FromStr for CommandTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = shlex::split(s).ok_or_else(|| format!("unbalanced quotes in `{}`", s))?;
+        if words.is_empty() {
+            return Err("the command is empty".to_string());
+        }
+        Ok(CommandTemplate { words })
+    }
+}
+
+impl CommandTemplate {
+    /// Run once per file: `{}` becomes the path (appended when no word has it), `{type}` and
+    /// `{entropy}` its classification
+    pub fn run_each(&self, results: &[FileAnalysis], machine_readable: bool) -> Result<()> {
+        let has_path = self.words.iter().any(|word| word.contains("{}"));
+        let mut failed = 0;
+        for analysis in results {
+            let path = argument_path(&analysis.path);
+            let entropy = format!("{:.2}", analysis.entropy);
+            let mut args: Vec<OsString> = self.words[1..]
+                .iter()
+                .map(|word| {
+                    let word = word
+                        .replace("{type}", &analysis.file_type.display_compact())
+                        .replace("{entropy}", &entropy);
+                    substitute_path(&word, &path)
+                })
+                .collect();
+            if !has_path {
+                args.push(path);
+            }
+            if !self.run(&args, machine_readable)? {
+                failed += 1;
+            }
+        }
+        self.summary(results.len(), failed, machine_readable);
+        Ok(())
+    }
+
+    /// Run with every path at once: in place of a word that is exactly `{}`, or appended, in
+    /// batches of up to 1000 paths
+    pub fn run_batch(&self, results: &[FileAnalysis], machine_readable: bool) -> Result<()> {
+        let paths: Vec<OsString> = results.iter().map(|analysis| argument_path(&analysis.path)).collect();
+        let mut failed = 0;
+        for batch in paths.chunks(BATCH_SIZE) {
+            let mut args = Vec::new();
+            let mut placed = false;
+            for word in &self.words[1..] {
+                if word == "{}" {
+                    args.extend(batch.iter().cloned());
+                    placed = true;
+                } else {
+                    args.push(OsString::from(word));
+                }
+            }
+            if !placed {
+                args.extend(batch.iter().cloned());
+            }
+            if !self.run(&args, machine_readable)? {
+                failed += batch.len();
+            }
+        }
+        self.summary(results.len(), failed, machine_readable);
+        Ok(())
+    }
+
+    /// Whether the command exited successfully; not finding the program at all is an error. Under
+    /// --json/--simple its output goes to stderr, so it does not mix with the report.
+    fn run(&self, args: &[OsString], machine_readable: bool) -> Result<bool> {
+        let program = &self.words[0];
+        let mut command = Command::new(program);
+        command.args(args);
+        if machine_readable {
+            command.stdout(Stdio::from(io::stderr()));
+        }
+        let status = command.status().with_context(|| format!("Failed to run {}", program))?;
+        if !status.success() {
+            let args: Vec<_> = args.iter().map(|arg| arg.to_string_lossy()).collect();
+            eprintln!("Warning: {} {} exited with {}", program, args.join(" "), status);
+        }
+        Ok(status.success())
+    }
+
+    fn summary(&self, files: usize, failed: usize, machine_readable: bool) {
+        if files == 0 {
+            return;
+        }
+        let message = format!("Ran {} for {} file(s), {} failed", self.words[0], files, failed);
+        if machine_readable {
+            eprintln!("{}", message);
+        } else if failed > 0 {
+            println!("\n  {} {}", "•".cyan(), message.red().bold());
+        } else {
+            println!("\n  {} {}", "•".cyan(), message.bold());
+        }
+    }
+}
+
+/// The path as the command gets it: as found, byte for byte, with `./` before a relative one so a
+/// name like `-rf` cannot pass for an option
+fn argument_path(path: &Path) -> OsString {
+    if path.is_absolute() || path.starts_with(".") || path.starts_with("..") {
+        path.as_os_str().to_os_string()
+    } else {
+        Path::new(".").join(path).into_os_string()
+    }
+}
+
+/// `word` with every `{}` replaced by `path`
+fn substitute_path(word: &str, path: &OsStr) -> OsString {
+    let mut arg = OsString::new();
+    for (i, part) in word.split("{}").enumerate() {
+        if i > 0 {
+            arg.push(path);
+        }
+        arg.push(part);
+    }
+    arg
+}
@@ -0,0 +1,184 @@
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use clap::ValueEnum;
+use enro::hash::HashAlgorithm;
+use enro::FileAnalysis;
+use serde_json::{json, Map, Value};
+use sha1::{Digest, Sha1};
+use std::io::Write;
+use uuid::Uuid;
+
+use crate::{category, display_path, hashset};
+
+// STIX 2.1 namespace for the deterministic IDs of cyber-observable objects
+const SCO_NAMESPACE: Uuid = Uuid::from_u128(0x00abedb4_aa42_4ed7_a5ed_bff3ff4e4100);
+
+/// Formats for --export
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    /// STIX 2.1 bundle of file objects and indicators, for MISP and other threat intel platforms
+    Stix,
+}
+
+impl ExportFormat {
+    /// Digests the export needs, computed during analysis
+    pub fn hashes(self) -> &'static [HashAlgorithm] {
+        match self {
+            ExportFormat::Stix => &[HashAlgorithm::Sha256],
+        }
+    }
+}
+
+pub fn write(format: ExportFormat, results: &[FileAnalysis], out: &mut impl Write) -> Result<()> {
+    let document = match format {
+        ExportFormat::Stix => stix_bundle(results),
+    };
+    serde_json::to_writer_pretty(&mut *out, &document)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+pub fn write_file(format: ExportFormat, results: &[FileAnalysis], path: &std::path::Path) -> Result<()> {
+    let mut file = std::io::BufWriter::new(
+        std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?,
+    );
+    write(format, results, &mut file)?;
+    file.flush().with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Hash names from STIX's hashing-algorithm vocabulary; BLAKE3 is not in it, but the vocabulary is open
+fn stix_hash_name(algorithm: HashAlgorithm) -> &'static str {
+    match algorithm {
+        HashAlgorithm::Md5 => "MD5",
+        HashAlgorithm::Sha1 => "SHA-1",
+        HashAlgorithm::Sha256 => "SHA-256",
+        HashAlgorithm::Blake3 => "BLAKE3",
+        HashAlgorithm::Tlsh => "TLSH",
+        HashAlgorithm::Ssdeep => "SSDEEP",
+    }
+}
+
+/// A file object for every result and an indicator for every one with an exact digest
+fn stix_bundle(results: &[FileAnalysis]) -> Value {
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    // Derived like an observable's ID, so every export names the same producer
+    let identity_id = format!("identity--{}", sco_uuid(&json!({ "name": "enro" })));
+    let mut objects = vec![json!({
+        "type": "identity",
+        "spec_version": "2.1",
+        "id": identity_id,
+        "created": now,
+        "modified": now,
+        "name": "enro",
+        "identity_class": "system",
+    })];
+
+    for analysis in results {
+        let name = analysis.path.file_name().unwrap_or(analysis.path.as_os_str()).to_string_lossy();
+        let hashes: Map<String, Value> = analysis
+            .hashes
+            .iter()
+            .filter(|(_, hex)| hex != "TNULL")
+            .map(|(algorithm, hex)| (stix_hash_name(*algorithm).to_string(), hex.as_str().into()))
+            .collect();
+        let category = category(&analysis.file_type)
+            .to_possible_value()
+            .map_or_else(String::new, |v| v.get_name().to_string());
+        let entropy = (analysis.entropy * 10000.0).round() / 10000.0;
+
+        let file_id = format!("file--{}", file_uuid(&name, &hashes));
+        let mut file = json!({
+            "type": "file",
+            "spec_version": "2.1",
+            "id": file_id,
+            "name": name,
+            "size": analysis.size,
+            "x_enro_path": display_path(&analysis.path),
+            "x_enro_type": analysis.file_type.display_compact(),
+            "x_enro_category": category,
+            "x_enro_entropy": entropy,
+        });
+        if !hashes.is_empty() {
+            file["hashes"] = hashes.clone().into();
+        }
+        if !analysis.notes.is_empty() {
+            file["x_enro_notes"] = analysis.notes.clone().into();
+        }
+        objects.push(file);
+
+        let Some(pattern) = indicator_pattern(&hashes) else {
+            continue;
+        };
+        let known_bad = hashset::is_known_bad(analysis);
+        let indicator_id = format!("indicator--{}", Uuid::new_v4());
+        objects.push(json!({
+            "type": "indicator",
+            "spec_version": "2.1",
+            "id": indicator_id,
+            "created_by_ref": identity_id,
+            "created": now,
+            "modified": now,
+            "name": format!("{} file {}", analysis.file_type.display_compact(), name),
+            "description": format!(
+                "enro classified {} as {} (entropy {:.2}/8.0)",
+                display_path(&analysis.path),
+                analysis.type_label(analysis.file_type.display_plain()),
+                analysis.entropy
+            ),
+            "indicator_types": [if known_bad { "malicious-activity" } else { "anomalous-activity" }],
+            "pattern": pattern,
+            "pattern_type": "stix",
+            "valid_from": now,
+            "labels": [category],
+        }));
+        objects.push(json!({
+            "type": "relationship",
+            "spec_version": "2.1",
+            "id": format!("relationship--{}", Uuid::new_v4()),
+            "created_by_ref": identity_id,
+            "created": now,
+            "modified": now,
+            "relationship_type": "related-to",
+            "source_ref": indicator_id,
+            "target_ref": file_id,
+        }));
+    }
+
+    json!({
+        "type": "bundle",
+        "id": format!("bundle--{}", Uuid::new_v4()),
+        "objects": objects,
+    })
+}
+
+/// `[file:hashes.'SHA-256' = '...']` on the strongest exact digest
+fn indicator_pattern(hashes: &Map<String, Value>) -> Option<String> {
+    ["SHA-256", "SHA-1", "MD5"].into_iter().find_map(|name| {
+        let hex = hashes.get(name)?.as_str()?;
+        Some(format!("[file:hashes.'{}' = '{}']", name, hex))
+    })
+}
+
+/// The deterministic ID STIX gives a file: its name and one hash, preferring MD5, SHA-1, SHA-256
+fn file_uuid(name: &str, hashes: &Map<String, Value>) -> Uuid {
+    let mut contributing = Map::new();
+    if let Some((key, value)) = ["MD5", "SHA-1", "SHA-256"]
+        .into_iter()
+        .find_map(|key| Some((key, hashes.get(key)?)))
+        .or_else(|| hashes.iter().next().map(|(key, value)| (key.as_str(), value)))
+    {
+        contributing.insert("hashes".to_string(), json!({ key: value }));
+    }
+    contributing.insert("name".to_string(), name.into());
+    sco_uuid(&contributing.into())
+}
+
+/// UUIDv5 over the canonical JSON of the ID contributing properties
+fn sco_uuid(contributing: &Value) -> Uuid {
+    // Keys are inserted in sorted order, and serde_json writes no whitespace
+    let digest = Sha1::new()
+        .chain_update(SCO_NAMESPACE.as_bytes())
+        .chain_update(contributing.to_string().as_bytes())
+        .finalize();
+    uuid::Builder::from_sha1_bytes(digest[..16].try_into().expect("SHA-1 is 20 bytes")).into_uuid()
+}
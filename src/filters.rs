@@ -0,0 +1,196 @@
+use anyhow::{Context, Result};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Glob patterns: ones without a `/` match the file name anywhere in the tree,
+/// the rest match the path relative to the scan root
+struct Patterns {
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl Patterns {
+    fn new(patterns: &[String]) -> Result<Option<Self>> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in patterns {
+            if pattern.contains('/') {
+                let glob = GlobBuilder::new(pattern.trim_start_matches('/'))
+                    .literal_separator(true)
+                    .build()
+                    .with_context(|| format!("Invalid glob pattern: {}", pattern))?;
+                paths.add(glob);
+            } else {
+                names.add(Glob::new(pattern).with_context(|| format!("Invalid glob pattern: {}", pattern))?);
+            }
+        }
+
+        Ok(Some(Patterns {
+            names: names.build()?,
+            paths: paths.build()?,
+        }))
+    }
+
+    fn is_match(&self, relative: &Path) -> bool {
+        relative.file_name().is_some_and(|name| self.names.is_match(name)) || self.paths.is_match(relative)
+    }
+}
+
+/// Name/path filters applied while walking directories
+pub struct PathFilter {
+    include: Option<Patterns>,
+    exclude: Option<Patterns>,
+    extensions: Option<HashSet<String>>,
+    excluded_extensions: HashSet<String>,
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(PathFilter {
+            include: Patterns::new(include)?,
+            exclude: Patterns::new(exclude)?,
+            extensions: None,
+            excluded_extensions: HashSet::new(),
+        })
+    }
+
+    /// Restrict files to the `only` extensions (if any) and drop the `not` ones;
+    /// matching ignores case and a leading dot
+    pub fn with_extensions(mut self, only: &[String], not: &[String]) -> Self {
+        if !only.is_empty() {
+            self.extensions = Some(only.iter().map(|e| normalize_extension(e)).collect());
+        }
+        self.excluded_extensions = not.iter().map(|e| normalize_extension(e)).collect();
+        self
+    }
+
+    /// Whether a file found under `root` should be analyzed
+    pub fn includes_file(&self, root: &Path, path: &Path) -> bool {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        self.includes_extension(path)
+            && self.include.as_ref().is_none_or(|p| p.is_match(relative))
+            && !self.is_excluded(relative)
+    }
+
+    fn includes_extension(&self, path: &Path) -> bool {
+        if self.extensions.is_none() && self.excluded_extensions.is_empty() {
+            return true;
+        }
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        self.extensions.as_ref().is_none_or(|only| only.contains(&extension))
+            && !self.excluded_extensions.contains(&extension)
+    }
+
+    /// Whether to descend into a directory found under `root`
+    pub fn includes_dir(&self, root: &Path, path: &Path) -> bool {
+        !self.is_excluded(path.strip_prefix(root).unwrap_or(path))
+    }
+
+    fn is_excluded(&self, relative: &Path) -> bool {
+        self.exclude.as_ref().is_some_and(|p| p.is_match(relative))
+    }
+}
+
+/// Pseudo-filesystems and automount points left out of whole-system scans
+#[cfg(target_os = "linux")]
+const SYSTEM_EXCLUDES: &[&str] = &["/proc", "/sys", "/dev", "/run", "/net"];
+#[cfg(target_os = "macos")]
+const SYSTEM_EXCLUDES: &[&str] = &["/dev", "/System/Volumes", "/Volumes", "/Network", "/net", "/home"];
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+const SYSTEM_EXCLUDES: &[&str] = &[];
+
+/// Directory trees left out by --exclude-path, matched on absolute paths
+pub struct ExcludedPaths {
+    paths: Vec<PathBuf>,
+}
+
+impl ExcludedPaths {
+    /// Each path is matched as given and, through symlinks, as it resolves
+    pub fn new(paths: &[PathBuf]) -> Self {
+        let mut excluded = ExcludedPaths { paths: Vec::new() };
+        for path in paths {
+            excluded.add(path);
+        }
+        excluded
+    }
+
+    /// Add the platform's pseudo-filesystems and automount points when `root` is the filesystem root
+    pub fn with_system_defaults(mut self, root: &Path) -> Self {
+        if std::fs::canonicalize(root).is_ok_and(|r| r.parent().is_none()) {
+            for path in SYSTEM_EXCLUDES {
+                self.add(Path::new(path));
+            }
+        }
+        self
+    }
+
+    fn add(&mut self, path: &Path) {
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        if let Ok(canonical) = std::fs::canonicalize(path) {
+            if canonical != absolute {
+                self.paths.push(canonical);
+            }
+        }
+        self.paths.push(absolute);
+    }
+
+    /// Whether `path` is one of the excluded paths or lies below one
+    pub fn contains(&self, path: &Path) -> bool {
+        if self.paths.is_empty() {
+            return false;
+        }
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        self.paths.iter().any(|excluded| absolute.starts_with(excluded))
+    }
+}
+
+fn normalize_extension(extension: &str) -> String {
+    extension.trim().trim_start_matches('.').to_lowercase()
+}
+
+/// Parse a --newer-than/--older-than value: a duration back from now (`24h`, `7d`, `2w 3d`)
+/// or a UTC timestamp (`2024-05-01`, `2024-05-01 13:30:00`, RFC 3339)
+pub fn parse_time(value: &str) -> Result<SystemTime, String> {
+    let value = value.trim();
+    if let Ok(ago) = humantime::parse_duration(value) {
+        return Ok(SystemTime::now().checked_sub(ago).unwrap_or(SystemTime::UNIX_EPOCH));
+    }
+
+    let timestamp = if value.len() == 10 {
+        format!("{} 00:00:00", value)
+    } else {
+        value.to_string()
+    };
+    humantime::parse_rfc3339_weak(&timestamp)
+        .map_err(|_| format!("invalid time `{}` (expected a duration like 24h or a date like 2024-05-01)", value))
+}
+
+/// Modification-time window from --newer-than/--older-than
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeWindow {
+    pub newer_than: Option<SystemTime>,
+    pub older_than: Option<SystemTime>,
+}
+
+impl TimeWindow {
+    /// Whether a file modified at `modified` falls inside the window; files without a
+    /// usable modification time only pass an unrestricted window
+    pub fn contains(&self, modified: std::io::Result<SystemTime>) -> bool {
+        if self.newer_than.is_none() && self.older_than.is_none() {
+            return true;
+        }
+        let Ok(modified) = modified else {
+            return false;
+        };
+        self.newer_than.is_none_or(|t| modified >= t) && self.older_than.is_none_or(|t| modified < t)
+    }
+}
@@ -0,0 +1,343 @@
+//! Fuzzy hashes: TLSH and ssdeep digests are close for files that differ only slightly, and are
+//! computed as compatible with the reference implementations so they can be matched against
+//! threat-intel feeds
+
+// TLSH's Pearson table
+const V_TABLE: [u8; 256] = [
+    1, 87, 49, 12, 176, 178, 102, 166, 121, 193, 6, 84, 249, 230, 44, 163, 14, 197, 213, 181, 161, 85, 218, 80, 64,
+    239, 24, 226, 236, 142, 38, 200, 110, 177, 104, 103, 141, 253, 255, 50, 77, 101, 81, 18, 45, 96, 31, 222, 25, 107,
+    190, 70, 86, 237, 240, 34, 72, 242, 20, 214, 244, 227, 149, 235, 97, 234, 57, 22, 60, 250, 82, 175, 208, 5, 127,
+    199, 111, 62, 135, 248, 174, 169, 211, 58, 66, 154, 106, 195, 245, 171, 17, 187, 182, 179, 0, 243, 132, 56, 148,
+    75, 128, 133, 158, 100, 130, 126, 91, 13, 153, 246, 216, 219, 119, 68, 223, 78, 83, 88, 201, 99, 122, 11, 92, 32,
+    136, 114, 52, 10, 138, 30, 48, 183, 156, 35, 61, 26, 143, 74, 251, 94, 129, 162, 63, 152, 170, 7, 115, 167, 241,
+    206, 3, 150, 55, 59, 151, 220, 90, 53, 23, 131, 125, 173, 15, 238, 79, 95, 89, 16, 105, 137, 225, 224, 217, 160,
+    37, 123, 118, 73, 2, 157, 46, 116, 9, 145, 134, 228, 207, 212, 202, 215, 69, 229, 27, 188, 67, 124, 168, 252, 42,
+    4, 29, 108, 21, 247, 19, 205, 39, 203, 233, 40, 186, 147, 198, 192, 155, 33, 164, 191, 98, 204, 165, 180, 117, 76,
+    140, 36, 210, 172, 41, 54, 159, 8, 185, 232, 113, 196, 231, 47, 146, 120, 51, 65, 28, 144, 254, 221, 93, 189, 194,
+    139, 112, 43, 71, 109, 184, 209,
+];
+
+const TLSH_BUCKETS: usize = 128;
+const TLSH_WINDOW: usize = 5;
+// Shorter inputs, or ones with too little variety, get no digest
+const TLSH_MIN_LENGTH: u64 = 50;
+// The length byte is only defined up to 4 GiB
+const TLSH_MAX_LENGTH: u64 = u32::MAX as u64;
+
+fn pearson(salt: u8, i: u8, j: u8, k: u8) -> u8 {
+    let mut h = V_TABLE[salt as usize];
+    h = V_TABLE[(h ^ i) as usize];
+    h = V_TABLE[(h ^ j) as usize];
+    V_TABLE[(h ^ k) as usize]
+}
+
+/// TLSH (128 buckets, 1-byte checksum), printed in version 4 form: `T1` and 70 hex digits
+#[derive(Clone)]
+pub struct Tlsh {
+    buckets: [u32; 256],
+    checksum: u8,
+    window: [u8; TLSH_WINDOW],
+    len: u64,
+}
+
+impl Default for Tlsh {
+    fn default() -> Self {
+        Tlsh {
+            buckets: [0; 256],
+            checksum: 0,
+            window: [0; TLSH_WINDOW],
+            len: 0,
+        }
+    }
+}
+
+impl Tlsh {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            let j = (self.len % TLSH_WINDOW as u64) as usize;
+            self.window[j] = byte;
+            if self.len >= TLSH_WINDOW as u64 - 1 {
+                let back = |n: usize| self.window[(j + TLSH_WINDOW - n) % TLSH_WINDOW];
+                let (b1, b2, b3, b4) = (back(1), back(2), back(3), back(4));
+                self.checksum = pearson(0, byte, b1, self.checksum);
+                for (salt, x, y) in [(2, b1, b2), (3, b1, b3), (5, b2, b3), (7, b2, b4), (11, b1, b4), (13, b3, b4)] {
+                    self.buckets[pearson(salt, byte, x, y) as usize] += 1;
+                }
+            }
+            self.len += 1;
+        }
+    }
+
+    /// `None` for inputs too short or too uniform to characterize
+    pub fn finish(&self) -> Option<String> {
+        if self.len < TLSH_MIN_LENGTH || self.len > TLSH_MAX_LENGTH {
+            return None;
+        }
+        let buckets = &self.buckets[..TLSH_BUCKETS];
+        let mut sorted = buckets.to_vec();
+        sorted.sort_unstable();
+        let (q1, q2, q3) = (sorted[TLSH_BUCKETS / 4 - 1], sorted[TLSH_BUCKETS / 2 - 1], sorted[TLSH_BUCKETS * 3 / 4 - 1]);
+        if q3 == 0 || buckets.iter().filter(|&&count| count > 0).count() <= TLSH_BUCKETS / 2 {
+            return None;
+        }
+
+        let swap = |b: u8| b.rotate_left(4);
+        let q1_ratio = ((q1.wrapping_mul(100) as f32 / q3 as f32) as u32 % 16) as u8;
+        let q2_ratio = ((q2.wrapping_mul(100) as f32 / q3 as f32) as u32 % 16) as u8;
+        let mut digest = vec![swap(self.checksum), swap(length_capture(self.len)), swap(q1_ratio | (q2_ratio << 4))];
+        // The body runs from the last group of four buckets to the first
+        digest.extend(buckets.chunks(4).rev().map(|group| {
+            group.iter().enumerate().fold(0u8, |code, (j, &count)| {
+                let quartile = if count > q3 {
+                    3
+                } else if count > q2 {
+                    2
+                } else if count > q1 {
+                    1
+                } else {
+                    0
+                };
+                code | (quartile << (j * 2))
+            })
+        }));
+        Some(format!("T1{}", digest.iter().map(|b| format!("{:02X}", b)).collect::<String>()))
+    }
+}
+
+/// TLSH's difference score between two digests as [`Tlsh::finish`] prints them: 0 for the same
+/// features, rising with dissimilarity; below about 30 the files are near-identical
+pub fn tlsh_distance(a: &str, b: &str) -> Option<u32> {
+    let (a, b) = (parse_tlsh(a)?, parse_tlsh(b)?);
+    // Header bytes are printed nibble-swapped: checksum, length, then Q1 and Q2 ratios
+    let (length_a, length_b) = (a[1].rotate_left(4), b[1].rotate_left(4));
+    let mut distance = match mod_diff(length_a as u32, length_b as u32, 256) {
+        diff @ (0 | 1) => diff,
+        diff => diff * 12,
+    };
+    for (x, y) in [(a[2] >> 4, b[2] >> 4), (a[2] & 0xf, b[2] & 0xf)] {
+        distance += match mod_diff(x as u32, y as u32, 16) {
+            diff @ (0 | 1) => diff,
+            diff => (diff - 1) * 12,
+        };
+    }
+    if a[0] != b[0] {
+        distance += 1;
+    }
+    // Each bucket's quartile differs by 0-3, and opposite quartiles count double
+    for (&x, &y) in a[3..].iter().zip(&b[3..]) {
+        for shift in (0..8).step_by(2) {
+            distance += match ((x >> shift) & 3).abs_diff((y >> shift) & 3) {
+                3 => 6,
+                diff => diff as u32,
+            };
+        }
+    }
+    Some(distance)
+}
+
+fn parse_tlsh(digest: &str) -> Option<Vec<u8>> {
+    let hex = digest.strip_prefix("T1")?;
+    if hex.len() != 70 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn mod_diff(x: u32, y: u32, range: u32) -> u32 {
+    let direct = x.abs_diff(y);
+    direct.min(range - direct)
+}
+
+/// TLSH's logarithmic length byte
+fn length_capture(len: u64) -> u8 {
+    // As the reference computes it: the length as a float, logarithms as doubles
+    let log = (len as f32 as f64).ln();
+    let i = if len <= 656 {
+        (log / 0.405_465_1).floor()
+    } else if len <= 3199 {
+        (log / 0.262_364_26 - 8.727_77).floor()
+    } else {
+        (log / 0.095_310_180 - 62.547_2).floor()
+    };
+    (i as i64 & 0xff) as u8
+}
+
+const SSDEEP_WINDOW: usize = 7;
+const SSDEEP_MIN_BLOCK_SIZE: u64 = 3;
+const SSDEEP_LENGTH: usize = 64;
+const SSDEEP_BLOCK_HASHES: usize = 31;
+const FNV_PRIME: u32 = 0x0100_0193;
+const FNV_INIT: u32 = 0x2802_1967;
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn block_size(index: usize) -> u64 {
+    SSDEEP_MIN_BLOCK_SIZE << index
+}
+
+#[derive(Clone, Copy)]
+struct BlockHash {
+    h: u32,
+    half_h: u32,
+    digest: [u8; SSDEEP_LENGTH],
+    /// Characters completed; `digest[len]` holds the pending one once the digest is full
+    len: usize,
+    half_digest: u8,
+}
+
+impl BlockHash {
+    const EMPTY: BlockHash = BlockHash {
+        h: FNV_INIT,
+        half_h: FNV_INIT,
+        digest: [0; SSDEEP_LENGTH],
+        len: 0,
+        half_digest: 0,
+    };
+}
+
+/// ssdeep (context-triggered piecewise hashing), printed as `BLOCKSIZE:DIGEST:DIGEST`
+#[derive(Clone)]
+pub struct Ssdeep {
+    window: [u8; SSDEEP_WINDOW],
+    h1: u32,
+    h2: u32,
+    h3: u32,
+    // 32 bits, as in the reference, so the window position wraps the same way
+    n: u32,
+    blocks: [BlockHash; SSDEEP_BLOCK_HASHES],
+    start: usize,
+    end: usize,
+    len: u64,
+}
+
+impl Default for Ssdeep {
+    fn default() -> Self {
+        Ssdeep {
+            window: [0; SSDEEP_WINDOW],
+            h1: 0,
+            h2: 0,
+            h3: 0,
+            n: 0,
+            blocks: [BlockHash::EMPTY; SSDEEP_BLOCK_HASHES],
+            start: 0,
+            end: 1,
+            len: 0,
+        }
+    }
+}
+
+impl Ssdeep {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn rolling_sum(&self) -> u32 {
+        self.h1.wrapping_add(self.h2).wrapping_add(self.h3)
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.len += data.len() as u64;
+        for &byte in data {
+            self.step(byte);
+        }
+    }
+
+    fn step(&mut self, byte: u8) {
+        let c = byte as u32;
+        let slot = self.n as usize % SSDEEP_WINDOW;
+        self.h2 = self.h2.wrapping_sub(self.h1).wrapping_add(SSDEEP_WINDOW as u32 * c);
+        self.h1 = self.h1.wrapping_add(c).wrapping_sub(self.window[slot] as u32);
+        self.window[slot] = byte;
+        self.n = self.n.wrapping_add(1);
+        self.h3 = (self.h3 << 5) ^ c;
+        let sum = self.rolling_sum();
+
+        for block in &mut self.blocks[self.start..self.end] {
+            block.h = block.h.wrapping_mul(FNV_PRIME) ^ c;
+            block.half_h = block.half_h.wrapping_mul(FNV_PRIME) ^ c;
+        }
+
+        for i in self.start..self.end {
+            // A trigger point for a block size is one for all smaller ones too
+            if sum as u64 % block_size(i) != block_size(i) - 1 {
+                break;
+            }
+            if self.blocks[i].len == 0 && self.end < SSDEEP_BLOCK_HASHES {
+                // The next block size starts out from this one's state
+                let previous = self.blocks[self.end - 1];
+                self.blocks[self.end] = BlockHash {
+                    h: previous.h,
+                    half_h: previous.half_h,
+                    ..BlockHash::EMPTY
+                };
+                self.end += 1;
+            }
+            let block = &mut self.blocks[i];
+            block.digest[block.len] = BASE64[(block.h % 64) as usize];
+            block.half_digest = BASE64[(block.half_h % 64) as usize];
+            if block.len < SSDEEP_LENGTH - 1 {
+                block.len += 1;
+                block.digest[block.len] = 0;
+                block.h = FNV_INIT;
+                if block.len < SSDEEP_LENGTH / 2 {
+                    block.half_h = FNV_INIT;
+                    block.half_digest = 0;
+                }
+            } else {
+                self.reduce();
+            }
+        }
+    }
+
+    // Stop tracking the smallest block size once the input has outgrown it
+    fn reduce(&mut self) {
+        if self.end - self.start < 2
+            || block_size(self.start) * SSDEEP_LENGTH as u64 >= self.len
+            || self.blocks[self.start + 1].len < SSDEEP_LENGTH / 2
+        {
+            return;
+        }
+        self.start += 1;
+    }
+
+    pub fn finish(&self) -> String {
+        let sum = self.rolling_sum();
+        let mut index = self.start;
+        while block_size(index) * (SSDEEP_LENGTH as u64) < self.len && index < SSDEEP_BLOCK_HASHES - 1 {
+            index += 1;
+        }
+        index = index.min(self.end - 1);
+        while index > self.start && self.blocks[index].len < SSDEEP_LENGTH / 2 {
+            index -= 1;
+        }
+
+        let block = &self.blocks[index];
+        let mut result = format!("{}:", block_size(index)).into_bytes();
+        result.extend_from_slice(&block.digest[..block.len]);
+        if sum != 0 {
+            result.push(BASE64[(block.h % 64) as usize]);
+        } else if block.digest[block.len] != 0 {
+            result.push(block.digest[block.len]);
+        }
+        result.push(b':');
+        if index < self.end - 1 {
+            let next = &self.blocks[index + 1];
+            let len = next.len.min(SSDEEP_LENGTH / 2 - 1);
+            result.extend_from_slice(&next.digest[..len]);
+            if sum != 0 {
+                result.push(BASE64[(next.half_h % 64) as usize]);
+            } else if next.half_digest != 0 {
+                result.push(next.half_digest);
+            }
+        } else if sum != 0 {
+            result.push(BASE64[(block.h % 64) as usize]);
+        }
+        String::from_utf8(result).expect("digests are base64")
+    }
+}
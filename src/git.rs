@@ -0,0 +1,111 @@
+use anyhow::{bail, Context, Result};
+use flate2::read::ZlibDecoder;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path};
+
+use crate::{calculate_entropy_from_counts, count_bytes, detect_file_type, platform, FileAnalysis, FileType};
+
+const PACK_MAGIC: &[u8] = b"PACK";
+const PACK_INDEX_MAGIC: &[u8] = b"\xfftOc";
+// Decompressed bytes kept for classifying what a loose object holds
+const CONTENT_SAMPLE: usize = 64 * 1024;
+
+/// Paths of the form `.git/objects/...`, whose files are classified by where they are as well as
+/// what they hold
+pub fn in_objects_dir(path: &Path) -> bool {
+    let parts: Vec<Component> = path.components().collect();
+    parts.windows(2).any(|w| {
+        w[0].as_os_str() == ".git" && w[1].as_os_str() == "objects"
+    })
+}
+
+/// Loose objects are stored as `objects/ab/cdef...` (2 + 38 hex digits)
+fn is_loose_object_path(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let dir = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+    let is_hex = |s: &str| s.bytes().all(|b| b.is_ascii_hexdigit());
+
+    dir.len() == 2 && is_hex(dir) && (name.len() == 38 || name.len() == 62) && is_hex(name)
+}
+
+/// Reclassify Git object storage so it does not show up as Compressed/Random noise
+pub fn inspect(analysis: &mut FileAnalysis, head: &[u8], max_bytes: Option<usize>) {
+    if !in_objects_dir(&analysis.path) {
+        return;
+    }
+
+    if head.starts_with(PACK_MAGIC) && head.len() >= 12 {
+        let count = u32::from_be_bytes([head[8], head[9], head[10], head[11]]);
+        analysis.file_type = FileType::Git("pack".to_string());
+        analysis.notes.push(format!("{} objects", count));
+    } else if head.starts_with(PACK_INDEX_MAGIC) {
+        analysis.file_type = FileType::Git("pack index".to_string());
+    } else if is_loose_object_path(&analysis.path) {
+        if let Ok((kind, content_type, content_entropy)) = decode_loose_object(&analysis.path, max_bytes) {
+            analysis.file_type = FileType::Git(kind);
+            analysis.notes.push(format!(
+                "content: {} {:.2}",
+                content_type.display_plain(),
+                content_entropy
+            ));
+        }
+    }
+}
+
+/// Inflate a loose object, returning its kind plus the classification and entropy of its payload
+pub fn decode_loose_object(path: &Path, max_bytes: Option<usize>) -> Result<(String, FileType, f64)> {
+    let file = File::open(platform::long_path(path)).context("Failed to open Git object")?;
+    let decoder = ZlibDecoder::new(BufReader::new(file));
+    let limit = max_bytes.map_or(u64::MAX, |m| m as u64);
+    let mut reader = decoder.take(limit);
+
+    // Header: "<kind> <size>\0"
+    let mut header = Vec::new();
+    let mut byte = [0u8; 1];
+    loop {
+        reader.read_exact(&mut byte).context("Truncated Git object header")?;
+        if byte[0] == 0 {
+            break;
+        }
+        header.push(byte[0]);
+        if header.len() > 32 {
+            bail!("Invalid Git object header");
+        }
+    }
+    let header = String::from_utf8_lossy(&header);
+    let kind = header.split(' ').next().unwrap_or_default().to_string();
+    if !matches!(kind.as_str(), "blob" | "tree" | "commit" | "tag") {
+        bail!("Unknown Git object kind: {}", kind);
+    }
+
+    let (content_type, entropy) = classify_stream(&mut reader)?;
+    Ok((kind, content_type, entropy))
+}
+
+/// Classify decompressed content: type from the first bytes, entropy over everything
+pub fn classify_stream<R: Read>(reader: &mut R) -> Result<(FileType, f64)> {
+    let mut counts = [0u64; 256];
+    let mut total = 0usize;
+    let mut sample = Vec::new();
+    let mut chunk = vec![0u8; 64 * 1024];
+
+    loop {
+        let n = reader.read(&mut chunk).context("Failed to inflate Git object")?;
+        if n == 0 {
+            break;
+        }
+        if sample.len() < CONTENT_SAMPLE {
+            let take = n.min(CONTENT_SAMPLE - sample.len());
+            sample.extend_from_slice(&chunk[..take]);
+        }
+        count_bytes(&chunk[..n], &mut counts);
+        total += n;
+    }
+
+    Ok((detect_file_type(&sample), calculate_entropy_from_counts(&counts, total)))
+}
@@ -0,0 +1,409 @@
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+use colored::Colorize;
+use enro::{calculate_entropy, detect_file_type, format_size, secrets, FileType};
+use git2::{Delta, FileMode, ObjectType, Oid, Repository, Sort, TreeWalkMode, TreeWalkResult};
+use globset::{Glob, GlobSetBuilder};
+use indicatif::ProgressBar;
+use prettytable::{Cell, Row, Table};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use crate::escape_csv;
+use crate::history::{entropy_colored, new_table};
+
+/// Classify every blob reachable from any ref, including history
+#[derive(ClapArgs, Debug)]
+pub struct GitScanArgs {
+    /// Repository to scan
+    #[arg(value_name = "REPO", default_value = ".")]
+    pub repo: PathBuf,
+
+    /// Report every blob, not only encrypted/random blobs and secrets
+    #[arg(long)]
+    pub all: bool,
+
+    /// Minimum blob size to analyze (in bytes)
+    #[arg(short, long, default_value = "0")]
+    pub min_size: u64,
+
+    /// Maximum number of bytes of each blob to analyze (omit to scan entire blob)
+    #[arg(short = 'b', long)]
+    pub max_bytes: Option<usize>,
+
+    /// Simple output format (CSV, no colors)
+    #[arg(short, long)]
+    pub simple: bool,
+
+    /// JSON output format (for scripting)
+    #[arg(long, conflicts_with = "simple")]
+    pub json: bool,
+}
+
+/// Check the files staged for commit, failing when any holds encrypted/random content or key
+/// material; meant to run as a pre-commit hook
+#[derive(ClapArgs, Debug)]
+pub struct GitStagedArgs {
+    /// Repository whose index to check
+    #[arg(value_name = "REPO", default_value = ".")]
+    pub repo: PathBuf,
+
+    /// Staged paths that may hold encrypted/random content or secrets, e.g. `tests/fixtures/**`
+    /// (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub allow: Vec<String>,
+
+    /// Maximum number of bytes of each blob to analyze (omit to scan entire blob)
+    #[arg(short = 'b', long)]
+    pub max_bytes: Option<usize>,
+
+    /// Simple output format (CSV, no colors)
+    #[arg(short, long)]
+    pub simple: bool,
+
+    /// JSON output format (for scripting)
+    #[arg(long, conflicts_with = "simple")]
+    pub json: bool,
+}
+
+struct BlobReport {
+    oid: Oid,
+    path: String,
+    commit: Oid,
+    file_type: FileType,
+    entropy: f64,
+    size: u64,
+    secrets: Vec<&'static str>,
+    in_head: bool,
+}
+
+impl BlobReport {
+    fn is_finding(&self) -> bool {
+        !self.secrets.is_empty() || matches!(self.file_type, FileType::Encrypted | FileType::Random)
+    }
+
+    fn notes(&self) -> Vec<String> {
+        let mut notes: Vec<String> = self.secrets.iter().map(|s| s.to_string()).collect();
+        if !self.in_head {
+            notes.push("not in HEAD".to_string());
+        }
+        notes
+    }
+}
+
+// Every blob in a tree, skipping subtrees already visited through other commits
+fn collect_tree_blobs(
+    tree: &git2::Tree,
+    seen_trees: &mut HashSet<Oid>,
+    mut on_blob: impl FnMut(Oid, String),
+) -> Result<()> {
+    tree.walk(TreeWalkMode::PreOrder, |root, entry| match entry.kind() {
+        Some(ObjectType::Tree) => {
+            if seen_trees.insert(entry.id()) {
+                TreeWalkResult::Ok
+            } else {
+                TreeWalkResult::Skip
+            }
+        }
+        Some(ObjectType::Blob) => {
+            on_blob(entry.id(), format!("{}{}", root, entry.name().unwrap_or("?")));
+            TreeWalkResult::Ok
+        }
+        _ => TreeWalkResult::Ok,
+    })?;
+    Ok(())
+}
+
+pub fn run_scan(args: &GitScanArgs) -> Result<()> {
+    let repo = Repository::discover(&args.repo)
+        .with_context(|| format!("Not a Git repository: {}", args.repo.display()))?;
+
+    // Blobs present in the current checkout, to tell history-only content apart
+    let mut head_blobs = HashSet::new();
+    if let Ok(tree) = repo.head().and_then(|h| h.peel_to_tree()) {
+        collect_tree_blobs(&tree, &mut HashSet::new(), |oid, _| {
+            head_blobs.insert(oid);
+        })?;
+    }
+
+    // Oldest commits first, so each blob is attributed to the commit that introduced it
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    revwalk.push_glob("*")?;
+    if repo.head().is_ok() {
+        revwalk.push_head()?;
+    }
+
+    let mut seen_trees = HashSet::new();
+    let mut seen_blobs = HashSet::new();
+    let mut blobs: Vec<(Oid, String, Oid)> = Vec::new();
+    for commit_id in revwalk {
+        let commit_id = commit_id?;
+        let tree = repo.find_commit(commit_id)?.tree()?;
+        if !seen_trees.insert(tree.id()) {
+            continue;
+        }
+        collect_tree_blobs(&tree, &mut seen_trees, |oid, path| {
+            if seen_blobs.insert(oid) {
+                blobs.push((oid, path, commit_id));
+            }
+        })?;
+    }
+
+    let machine_readable = args.simple || args.json;
+    let pb = if machine_readable {
+        ProgressBar::hidden()
+    } else {
+        println!("Scanning {} blob(s) from history...\n", blobs.len());
+        ProgressBar::new(blobs.len() as u64)
+    };
+
+    let mut reports = Vec::new();
+    for (oid, path, commit) in blobs {
+        pb.inc(1);
+        let Ok(blob) = repo.find_blob(oid) else {
+            continue;
+        };
+        let content = blob.content();
+        if (content.len() as u64) < args.min_size {
+            continue;
+        }
+        let data = &content[..args.max_bytes.unwrap_or(content.len()).min(content.len())];
+
+        let report = BlobReport {
+            oid,
+            path,
+            commit,
+            file_type: detect_file_type(data),
+            entropy: calculate_entropy(data),
+            size: content.len() as u64,
+            secrets: secrets::find_secrets(data),
+            in_head: head_blobs.contains(&oid),
+        };
+        if args.all || report.is_finding() {
+            reports.push(report);
+        }
+    }
+    pb.finish_and_clear();
+
+    if args.json {
+        let blobs: Vec<serde_json::Value> = reports
+            .iter()
+            .map(|r| {
+                serde_json::json!({
+                    "blob": r.oid.to_string(),
+                    "path": r.path,
+                    "commit": r.commit.to_string(),
+                    "type": r.file_type.display_compact(),
+                    "entropy": (r.entropy * 10000.0).round() / 10000.0,
+                    "size": r.size,
+                    "secrets": r.secrets,
+                    "in_head": r.in_head,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "blobs": blobs }))?);
+    } else if args.simple {
+        println!("Blob,Path,Commit,Type,Entropy,Size,Notes");
+        for r in &reports {
+            println!(
+                "{},{},{},{},{:.2},{},{}",
+                r.oid,
+                escape_csv(&r.path),
+                r.commit,
+                r.file_type.display_compact(),
+                r.entropy,
+                r.size,
+                escape_csv(&r.notes().join("; "))
+            );
+        }
+    } else {
+        display_blob_table(&reports);
+    }
+
+    Ok(())
+}
+
+fn short_id(oid: &Oid) -> String {
+    oid.to_string()[..10].to_string()
+}
+
+fn display_blob_table(reports: &[BlobReport]) {
+    if reports.is_empty() {
+        println!("{}", "No findings in repository history.".green());
+        return;
+    }
+
+    let mut table = Table::new();
+    let format = prettytable::format::FormatBuilder::new()
+        .column_separator(' ')
+        .borders(' ')
+        .padding(1, 1)
+        .build();
+    table.set_format(format);
+    table.add_row(Row::new(vec![
+        Cell::new("Blob").style_spec("Fb"),
+        Cell::new("Commit").style_spec("Fb"),
+        Cell::new("Path").style_spec("Fb"),
+        Cell::new("Type").style_spec("Fb"),
+        Cell::new("Entropy").style_spec("Fb"),
+        Cell::new("Size").style_spec("Fb"),
+        Cell::new("Notes").style_spec("Fb"),
+    ]));
+
+    for r in reports {
+        let entropy_str = format!("{:.2}/8.0", r.entropy);
+        let entropy_colored = if r.entropy > 7.5 {
+            entropy_str.red().to_string()
+        } else if r.entropy > 6.0 {
+            entropy_str.yellow().to_string()
+        } else {
+            entropy_str.green().to_string()
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(&short_id(&r.oid)),
+            Cell::new(&short_id(&r.commit)),
+            Cell::new(&r.path),
+            Cell::new(&r.file_type.display_plain()),
+            Cell::new(&entropy_colored),
+            Cell::new(&format_size(r.size)),
+            Cell::new(&r.notes().join(", ").yellow().to_string()),
+        ]));
+    }
+
+    table.printstd();
+
+    let secrets = reports.iter().filter(|r| !r.secrets.is_empty()).count();
+    let history_only = reports.iter().filter(|r| !r.in_head).count();
+    println!(
+        "\n  {} {}",
+        "•".cyan(),
+        format!("{} blob(s) reported, {} with secrets, {} only in history", reports.len(), secrets, history_only).bold()
+    );
+}
+
+/// A staged file that would bring encrypted/random content or secrets into the next commit
+struct StagedFinding {
+    oid: Oid,
+    path: String,
+    file_type: FileType,
+    entropy: f64,
+    size: u64,
+    secrets: Vec<&'static str>,
+}
+
+pub fn run_staged(args: &GitStagedArgs) -> Result<ExitCode> {
+    let repo = Repository::discover(&args.repo)
+        .with_context(|| format!("Not a Git repository: {}", args.repo.display()))?;
+    let mut allow = GlobSetBuilder::new();
+    for pattern in &args.allow {
+        allow.add(Glob::new(pattern).with_context(|| format!("Invalid --allow pattern: {}", pattern))?);
+    }
+    let allow = allow.build()?;
+
+    // The index against HEAD is what `git diff --cached` shows; before the first commit
+    // everything staged is new. Blobs come from the index, so unstaged edits do not count.
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let index = repo.index()?;
+    let diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None)?;
+
+    let mut findings = Vec::new();
+    for delta in diff.deltas() {
+        let file = delta.new_file();
+        // Deletions add nothing, and submodules are commits rather than blobs
+        if delta.status() == Delta::Deleted || file.mode() == FileMode::Commit {
+            continue;
+        }
+        let Some(path) = file.path() else {
+            continue;
+        };
+        if allow.is_match(path) {
+            continue;
+        }
+        let blob = repo.find_blob(file.id())?;
+        let content = blob.content();
+        let data = &content[..args.max_bytes.unwrap_or(content.len()).min(content.len())];
+
+        let finding = StagedFinding {
+            oid: file.id(),
+            path: path.to_string_lossy().replace('\\', "/"),
+            file_type: detect_file_type(data),
+            entropy: calculate_entropy(data),
+            size: content.len() as u64,
+            secrets: secrets::find_secrets(data),
+        };
+        if !finding.secrets.is_empty() || matches!(finding.file_type, FileType::Encrypted | FileType::Random) {
+            findings.push(finding);
+        }
+    }
+
+    if args.json {
+        let files: Vec<serde_json::Value> = findings
+            .iter()
+            .map(|f| {
+                serde_json::json!({
+                    "path": f.path,
+                    "blob": f.oid.to_string(),
+                    "type": f.file_type.display_compact(),
+                    "entropy": (f.entropy * 10000.0).round() / 10000.0,
+                    "size": f.size,
+                    "secrets": f.secrets,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "files": files }))?);
+    } else if args.simple {
+        println!("Path,Blob,Type,Entropy,Size,Secrets");
+        for f in &findings {
+            println!(
+                "{},{},{},{:.2},{},{}",
+                escape_csv(&f.path),
+                f.oid,
+                f.file_type.display_compact(),
+                f.entropy,
+                f.size,
+                escape_csv(&f.secrets.join("; "))
+            );
+        }
+    } else {
+        display_staged_findings(&findings);
+    }
+
+    Ok(if findings.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn display_staged_findings(findings: &[StagedFinding]) {
+    if findings.is_empty() {
+        println!("{}", "No encrypted/random content or secrets staged.".green());
+        return;
+    }
+
+    let mut table = new_table(&["Path", "Type", "Entropy", "Size", "Secrets"]);
+    for f in findings {
+        table.add_row(Row::new(vec![
+            Cell::new(&f.path),
+            Cell::new(&f.file_type.display_plain()),
+            Cell::new(&entropy_colored(f.entropy)),
+            Cell::new(&format_size(f.size)),
+            Cell::new(&f.secrets.join(", ").yellow().to_string()),
+        ]));
+    }
+    table.printstd();
+
+    println!(
+        "\n  {} {}",
+        "•".cyan(),
+        format!("Commit blocked: {} staged file(s) with encrypted/random content or secrets", findings.len())
+            .red()
+            .bold()
+    );
+    println!(
+        "  {} Unstage them, allow them with --allow GLOB, or bypass the hook with `git commit --no-verify`",
+        "•".cyan()
+    );
+}
@@ -0,0 +1,154 @@
+//! Digests computed while a file is read for classification: cryptographic hashes, and the
+//! fuzzy hashes of [`crate::fuzzy`]
+
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::fuzzy;
+
+/// A digest --hash can compute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Blake3,
+    Tlsh,
+    Ssdeep,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 6] = [
+        HashAlgorithm::Md5,
+        HashAlgorithm::Sha1,
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Blake3,
+        HashAlgorithm::Tlsh,
+        HashAlgorithm::Ssdeep,
+    ];
+
+    /// Lowercase name, as accepted by --hash and used for output fields
+    pub fn key(self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::Tlsh => "tlsh",
+            HashAlgorithm::Ssdeep => "ssdeep",
+        }
+    }
+
+    /// Similar files get similar digests, so equal ones are not the same file
+    pub fn is_fuzzy(self) -> bool {
+        matches!(self, HashAlgorithm::Tlsh | HashAlgorithm::Ssdeep)
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase().replace('-', "");
+        HashAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.key() == s)
+            .ok_or_else(|| format!("unknown hash `{}`; expected md5, sha1, sha256, blake3, tlsh or ssdeep", s))
+    }
+}
+
+enum State {
+    Md5(Md5),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+    Tlsh(Box<fuzzy::Tlsh>),
+    Ssdeep(Box<fuzzy::Ssdeep>),
+}
+
+/// Running digests of one file; bytes go in through [`Hashers::update`] or as a [`Write`]
+pub struct Hashers(Vec<(HashAlgorithm, State)>);
+
+impl Hashers {
+    /// `None` when no digest was asked for
+    pub fn new(algorithms: &[HashAlgorithm]) -> Option<Self> {
+        if algorithms.is_empty() {
+            return None;
+        }
+        let states = algorithms
+            .iter()
+            .map(|&algorithm| {
+                let state = match algorithm {
+                    HashAlgorithm::Md5 => State::Md5(Md5::new()),
+                    HashAlgorithm::Sha1 => State::Sha1(Sha1::new()),
+                    HashAlgorithm::Sha256 => State::Sha256(Sha256::new()),
+                    HashAlgorithm::Blake3 => State::Blake3(Box::new(blake3::Hasher::new())),
+                    HashAlgorithm::Tlsh => State::Tlsh(Box::default()),
+                    HashAlgorithm::Ssdeep => State::Ssdeep(Box::default()),
+                };
+                (algorithm, state)
+            })
+            .collect();
+        Some(Hashers(states))
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for (_, state) in &mut self.0 {
+            match state {
+                State::Md5(hasher) => hasher.update(data),
+                State::Sha1(hasher) => hasher.update(data),
+                State::Sha256(hasher) => hasher.update(data),
+                State::Blake3(hasher) => {
+                    hasher.update(data);
+                }
+                State::Tlsh(hasher) => hasher.update(data),
+                State::Ssdeep(hasher) => hasher.update(data),
+            }
+        }
+    }
+
+    /// Lowercase hex digests, in the order the algorithms were given
+    pub fn finish(self) -> Vec<(HashAlgorithm, String)> {
+        self.0
+            .into_iter()
+            .map(|(algorithm, state)| {
+                let hex = match state {
+                    State::Md5(hasher) => to_hex(&hasher.finalize()),
+                    State::Sha1(hasher) => to_hex(&hasher.finalize()),
+                    State::Sha256(hasher) => to_hex(&hasher.finalize()),
+                    State::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+                    // As py-tlsh reports inputs TLSH cannot characterize
+                    State::Tlsh(hasher) => hasher.finish().unwrap_or_else(|| "TNULL".to_string()),
+                    State::Ssdeep(hasher) => hasher.finish(),
+                };
+                (algorithm, hex)
+            })
+            .collect()
+    }
+}
+
+impl Write for Hashers {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
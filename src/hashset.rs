@@ -0,0 +1,302 @@
+use anyhow::{bail, Context, Result};
+use clap::Args as ClapArgs;
+use enro::hash::HashAlgorithm;
+use enro::FileAnalysis;
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::walk::{SkipReason, Skipped};
+
+// Compact format: this magic, a version byte, the algorithm's index in HashAlgorithm::ALL, then the
+// raw digests, sorted and without duplicates, so a set loads with one read and is searched in place
+const MAGIC: &[u8; 8] = b"ENROHSET";
+const VERSION: u8 = 1;
+const NOTE_PREFIX: &str = "known bad: ";
+
+/// Convert hash lists to the compact binary format read by `--hashset`
+#[derive(ClapArgs, Debug)]
+pub struct HashsetArgs {
+    /// NSRL `NSRLFile.txt`, `sha256sum`-style output or one hex digest per line
+    #[arg(value_name = "LIST", required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    /// Where to write the compact hash set
+    #[arg(short, long, value_name = "FILE")]
+    pub output: PathBuf,
+
+    /// Digest the lists hold; 64-digit lists are read as SHA-256 unless this says blake3
+    #[arg(long, value_name = "ALG", value_parser = clap::value_parser!(HashAlgorithm))]
+    pub algorithm: Option<HashAlgorithm>,
+}
+
+/// Whether files in a set belong in the report
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// Left out of the report, e.g. NSRL's operating system and application files
+    Good,
+    /// Flagged in the report
+    Bad,
+}
+
+/// A hash set given with `--hashset [good:|bad:]FILE`: text lists (NSRL, `sha256sum` output, one
+/// digest per line) or the compact format written by `enro hashset`
+#[derive(Clone)]
+pub struct KnownHashes {
+    pub kind: Kind,
+    pub name: String,
+    pub algorithm: HashAlgorithm,
+    digests: Arc<Digests>,
+}
+
+impl fmt::Debug for KnownHashes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} {} ({}, {} digests)", self.kind, self.name, self.algorithm, self.digests.len())
+    }
+}
+
+impl FromStr for KnownHashes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, path) = match s.split_once(':') {
+            Some(("good", path)) => (Kind::Good, path),
+            Some(("bad", path)) => (Kind::Bad, path),
+            _ => (Kind::Good, s),
+        };
+        let path = Path::new(path);
+        let digests = Digests::load(path, None).map_err(|e| format!("{:#}", e))?;
+        Ok(KnownHashes {
+            kind,
+            name: path.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+            algorithm: digests.algorithm,
+            digests: Arc::new(digests),
+        })
+    }
+}
+
+impl KnownHashes {
+    fn contains(&self, analysis: &FileAnalysis) -> bool {
+        analysis
+            .hashes
+            .iter()
+            .find(|(algorithm, _)| *algorithm == self.algorithm)
+            .and_then(|(_, hex)| decode_hex(hex))
+            .is_some_and(|digest| self.digests.contains(&digest))
+    }
+}
+
+/// Sorted raw digests of one algorithm
+struct Digests {
+    algorithm: HashAlgorithm,
+    width: usize,
+    data: Vec<u8>,
+}
+
+impl Digests {
+    fn load(path: &Path, algorithm: Option<HashAlgorithm>) -> Result<Self> {
+        let mut file = BufReader::new(fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?);
+        if file.fill_buf()?.starts_with(MAGIC) {
+            let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            return Self::from_compact(data).with_context(|| format!("{} is not a valid hash set", path.display()));
+        }
+        Self::from_text(file, algorithm).with_context(|| format!("{}: not a hash list", path.display()))
+    }
+
+    fn from_compact(mut data: Vec<u8>) -> Result<Self> {
+        let (&version, &index) = match data.get(MAGIC.len()..MAGIC.len() + 2) {
+            Some([version, index]) => (version, index),
+            _ => bail!("truncated header"),
+        };
+        if version != VERSION {
+            bail!("unsupported version {}", version);
+        }
+        let algorithm = *HashAlgorithm::ALL
+            .get(index as usize)
+            .filter(|algorithm| !algorithm.is_fuzzy())
+            .context("unknown algorithm")?;
+        let width = digest_width(algorithm);
+        data.drain(..MAGIC.len() + 2);
+        if !data.len().is_multiple_of(width) {
+            bail!("truncated digest");
+        }
+        Ok(Digests { algorithm, width, data })
+    }
+
+    fn from_text(reader: impl BufRead, algorithm: Option<HashAlgorithm>) -> Result<Self> {
+        if let Some(fuzzy) = algorithm.filter(|algorithm| algorithm.is_fuzzy()) {
+            bail!("{} digests of similar files differ, so they cannot be matched exactly", fuzzy);
+        }
+        let mut digests: Vec<Vec<u8>> = Vec::new();
+        let mut algorithm = algorithm;
+        // Column of the digest in NSRL's quoted CSV, found from its header line
+        let mut column = None;
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if i == 0 && line.starts_with('"') {
+                let (index, found) = nsrl_column(line).context("NSRL header has no SHA-256, SHA-1 or MD5 column")?;
+                column = Some(index);
+                algorithm = algorithm.or(Some(found));
+                continue;
+            }
+            let field = match column {
+                Some(index) => line.split(',').nth(index).map(|f| f.trim_matches('"')),
+                // `sha256sum` output has the path after the digest
+                None => line.split_whitespace().next(),
+            };
+            let digest = field
+                .and_then(decode_hex)
+                .with_context(|| format!("line {}: expected a hex digest", i + 1))?;
+            let expected = match algorithm {
+                Some(algorithm) => algorithm,
+                None => *algorithm.insert(match digest.len() {
+                    16 => HashAlgorithm::Md5,
+                    20 => HashAlgorithm::Sha1,
+                    32 => HashAlgorithm::Sha256,
+                    n => bail!("line {}: no supported digest is {} bytes long", i + 1, n),
+                }),
+            };
+            if digest.len() != digest_width(expected) {
+                bail!("line {}: not a {} digest", i + 1, expected);
+            }
+            digests.push(digest);
+        }
+        let algorithm = algorithm.context("no digests")?;
+        digests.sort_unstable();
+        digests.dedup();
+        Ok(Digests {
+            algorithm,
+            width: digest_width(algorithm),
+            data: digests.concat(),
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.data.len() / self.width
+    }
+
+    fn get(&self, index: usize) -> &[u8] {
+        &self.data[index * self.width..(index + 1) * self.width]
+    }
+
+    fn contains(&self, digest: &[u8]) -> bool {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.get(mid).cmp(digest) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return true,
+            }
+        }
+        false
+    }
+}
+
+/// The digest column NSRL's header names, preferring the strongest
+fn nsrl_column(header: &str) -> Option<(usize, HashAlgorithm)> {
+    let columns: Vec<String> = header
+        .split(',')
+        .map(|c| c.trim_matches('"').replace('-', "").to_ascii_lowercase())
+        .collect();
+    [HashAlgorithm::Sha256, HashAlgorithm::Sha1, HashAlgorithm::Md5]
+        .into_iter()
+        .find_map(|algorithm| Some((columns.iter().position(|c| c == algorithm.key())?, algorithm)))
+}
+
+fn digest_width(algorithm: HashAlgorithm) -> usize {
+    match algorithm {
+        HashAlgorithm::Md5 => 16,
+        HashAlgorithm::Sha1 => 20,
+        HashAlgorithm::Sha256 | HashAlgorithm::Blake3 => 32,
+        HashAlgorithm::Tlsh | HashAlgorithm::Ssdeep => unreachable!("fuzzy hashes are not matched exactly"),
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.is_empty() || !hex.is_ascii() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Digests the sets need, to be computed during analysis
+pub fn algorithms(sets: &[KnownHashes]) -> impl Iterator<Item = HashAlgorithm> + '_ {
+    sets.iter().map(|set| set.algorithm)
+}
+
+/// Leave files in known-good sets out of `results`, counted as skipped, and note the ones in
+/// known-bad sets
+pub fn apply(sets: &[KnownHashes], results: Vec<FileAnalysis>, skipped: &mut Skipped) -> Vec<FileAnalysis> {
+    if sets.is_empty() {
+        return results;
+    }
+    let mut kept = Vec::with_capacity(results.len());
+    for mut analysis in results {
+        if annotate(sets, &mut analysis) {
+            kept.push(analysis);
+        } else {
+            skipped.add(SkipReason::KnownGood, &analysis.path);
+        }
+    }
+    kept
+}
+
+/// Note the known-bad sets holding one file; false when a known-good set holds it and it is
+/// left out instead
+pub fn annotate(sets: &[KnownHashes], analysis: &mut FileAnalysis) -> bool {
+    if sets.iter().any(|set| set.kind == Kind::Good && set.contains(analysis)) {
+        return false;
+    }
+    let bad: Vec<String> = sets
+        .iter()
+        .filter(|set| set.kind == Kind::Bad && set.contains(analysis))
+        .map(|set| format!("{}{}", NOTE_PREFIX, set.name))
+        .collect();
+    analysis.notes.extend(bad);
+    true
+}
+
+pub fn is_known_bad(analysis: &FileAnalysis) -> bool {
+    analysis.notes.iter().any(|note| note.starts_with(NOTE_PREFIX))
+}
+
+pub fn run(args: &HashsetArgs) -> Result<()> {
+    let sets = args
+        .inputs
+        .iter()
+        .map(|path| Digests::load(path, args.algorithm))
+        .collect::<Result<Vec<_>>>()?;
+    let algorithm = sets[0].algorithm;
+    if let Some(other) = sets.iter().find(|set| set.algorithm != algorithm) {
+        bail!("The lists mix {} and {} digests; a hash set holds one kind", algorithm, other.algorithm);
+    }
+    let width = digest_width(algorithm);
+    let mut digests: Vec<&[u8]> = sets.iter().flat_map(|set| set.data.chunks_exact(width)).collect();
+    digests.sort_unstable();
+    digests.dedup();
+
+    let index = HashAlgorithm::ALL.iter().position(|&a| a == algorithm).expect("listed in ALL") as u8;
+    let mut output = std::io::BufWriter::new(
+        fs::File::create(&args.output).with_context(|| format!("Failed to create {}", args.output.display()))?,
+    );
+    output.write_all(MAGIC)?;
+    output.write_all(&[VERSION, index])?;
+    for digest in &digests {
+        output.write_all(digest)?;
+    }
+    output.flush()?;
+
+    println!("Wrote {} {} digest(s) to {}", digests.len(), algorithm, args.output.display());
+    Ok(())
+}
@@ -0,0 +1,472 @@
+use anyhow::{bail, Context, Result};
+use clap::Args as ClapArgs;
+use colored::Colorize;
+use enro::{format_size, FileAnalysis, FileType};
+use prettytable::{Cell, Row, Table};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{category, display_path, escape_csv, Category};
+
+/// Query the scan history recorded with --history
+#[derive(ClapArgs, Debug)]
+pub struct HistoryArgs {
+    /// History database written by `enro scan --history`
+    #[arg(value_name = "DB")]
+    pub db: PathBuf,
+
+    /// List the files recorded by one scan
+    #[arg(long, value_name = "ID", group = "query")]
+    pub scan: Option<i64>,
+
+    /// Show how one file was classified in each scan that saw it
+    #[arg(long, value_name = "PATH", group = "query")]
+    pub file: Option<String>,
+
+    /// Files now in this category that were not in it before --since, e.g. `encrypted`
+    #[arg(long, value_enum, value_name = "CATEGORY", group = "query")]
+    pub became: Option<Category>,
+
+    /// How far back --became looks (`7d`, `1week`, `12h`)
+    #[arg(long, value_name = "DURATION", default_value = "7d", value_parser = humantime::parse_duration, requires = "became")]
+    pub since: Duration,
+
+    /// Simple output format (CSV, no colors)
+    #[arg(short, long, conflicts_with = "json")]
+    pub simple: bool,
+
+    /// JSON output format (for scripting)
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// One recorded scan
+pub struct ScanInfo {
+    pub id: i64,
+    pub time: SystemTime,
+    pub roots: String,
+    pub files: u64,
+    pub encrypted: u64,
+    pub random: u64,
+}
+
+/// One file as a scan classified it
+pub struct Record {
+    pub path: String,
+    pub file_type: FileType,
+    pub entropy: f64,
+    pub size: u64,
+}
+
+/// The SQLite database behind --history: one row per scan, one row per file per scan
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path).with_context(|| format!("Failed to open history database {}", path.display()))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS scans (
+                id INTEGER PRIMARY KEY,
+                time INTEGER NOT NULL,
+                roots TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS results (
+                scan_id INTEGER NOT NULL REFERENCES scans(id),
+                path TEXT NOT NULL,
+                type TEXT NOT NULL,
+                entropy REAL NOT NULL,
+                size INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS results_scan ON results(scan_id);
+            CREATE INDEX IF NOT EXISTS results_path ON results(path);",
+        )
+        .with_context(|| format!("{} is not an enro history database", path.display()))?;
+        Ok(History { conn })
+    }
+
+    /// Store a finished scan and return its ID
+    pub fn record(&mut self, roots: &[PathBuf], results: &[FileAnalysis]) -> Result<i64> {
+        let roots: Vec<String> = roots.iter().map(|root| display_path(root)).collect();
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO scans (time, roots) VALUES (?1, ?2)",
+            params![unix_time(SystemTime::now()), roots.join("\n")],
+        )?;
+        let id = tx.last_insert_rowid();
+        {
+            let mut insert =
+                tx.prepare("INSERT INTO results (scan_id, path, type, entropy, size) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+            for analysis in results {
+                insert.execute(params![
+                    id,
+                    display_path(&analysis.path),
+                    analysis.file_type.display_compact(),
+                    analysis.entropy,
+                    analysis.size as i64
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(id)
+    }
+
+    pub fn scans(&self) -> Result<Vec<ScanInfo>> {
+        let mut query = self.conn.prepare(
+            "SELECT s.id, s.time, s.roots, COUNT(r.path),
+                    COALESCE(SUM(r.type = 'Encrypted'), 0), COALESCE(SUM(r.type = 'Random'), 0)
+             FROM scans s LEFT JOIN results r ON r.scan_id = s.id
+             GROUP BY s.id ORDER BY s.id",
+        )?;
+        let scans = query.query_map([], |row| {
+            Ok(ScanInfo {
+                id: row.get(0)?,
+                time: from_unix_time(row.get(1)?),
+                roots: row.get(2)?,
+                files: row.get(3)?,
+                encrypted: row.get(4)?,
+                random: row.get(5)?,
+            })
+        })?;
+        Ok(scans.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Every file recorded by a scan, by path
+    pub fn load(&self, id: i64) -> Result<Vec<Record>> {
+        let exists = self
+            .conn
+            .query_row("SELECT 1 FROM scans WHERE id = ?1", [id], |_| Ok(()))
+            .optional()?;
+        if exists.is_none() {
+            bail!("No scan {} in the history database", id);
+        }
+
+        let mut query =
+            self.conn.prepare("SELECT path, type, entropy, size FROM results WHERE scan_id = ?1 ORDER BY path")?;
+        let rows = query.query_map([id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get(2)?, row.get(3)?))
+        })?;
+        rows.map(|row| {
+            let (path, file_type, entropy, size) = row?;
+            Ok(Record {
+                file_type: parse_type(&file_type)?,
+                path,
+                entropy,
+                size,
+            })
+        })
+        .collect()
+    }
+
+    /// How many files of each type a scan recorded
+    pub fn type_counts(&self, id: i64) -> Result<Vec<(FileType, u64)>> {
+        let mut query = self.conn.prepare("SELECT type, COUNT(*) FROM results WHERE scan_id = ?1 GROUP BY type")?;
+        let rows = query.query_map([id], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?;
+        rows.map(|row| {
+            let (file_type, count) = row?;
+            Ok((parse_type(&file_type)?, count))
+        })
+        .collect()
+    }
+
+    /// A file's classification in each scan that saw it, oldest first
+    fn timeline(&self, path: &str) -> Result<Vec<(i64, SystemTime, Record)>> {
+        let mut query = self.conn.prepare(
+            "SELECT s.id, s.time, r.type, r.entropy, r.size
+             FROM results r JOIN scans s ON s.id = r.scan_id
+             WHERE r.path = ?1 ORDER BY s.id",
+        )?;
+        let rows = query.query_map([path], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?, row.get(3)?, row.get(4)?))
+        })?;
+        rows.map(|row| {
+            let (id, time, file_type, entropy, size) = row?;
+            let record = Record {
+                path: path.to_string(),
+                file_type: parse_type(&file_type)?,
+                entropy,
+                size,
+            };
+            Ok((id, from_unix_time(time), record))
+        })
+        .collect()
+    }
+
+    /// Files whose latest classification is in `target`, seen since `cutoff`, that were last
+    /// classified as something else before it (or not seen at all), with that earlier type
+    pub fn became(&self, target: Category, cutoff: SystemTime) -> Result<Vec<(Option<FileType>, SystemTime, Record)>> {
+        let mut query = self.conn.prepare(
+            "WITH ranked AS (
+                SELECT r.path, r.type, r.entropy, r.size, s.time,
+                       ROW_NUMBER() OVER (PARTITION BY r.path ORDER BY s.id DESC) AS latest_first,
+                       ROW_NUMBER() OVER (PARTITION BY r.path, s.time < ?1 ORDER BY s.id DESC) AS latest_in_period
+                FROM results r JOIN scans s ON s.id = r.scan_id
+            )
+            SELECT now.path, before.type, now.type, now.entropy, now.size, now.time
+            FROM ranked now
+            LEFT JOIN ranked before ON before.path = now.path AND before.time < ?1 AND before.latest_in_period = 1
+            WHERE now.latest_first = 1 AND now.time >= ?1
+            ORDER BY now.path",
+        )?;
+        let rows = query.query_map([unix_time(cutoff)], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+            ))
+        })?;
+
+        let mut changed = Vec::new();
+        for row in rows {
+            let (path, before, now, entropy, size, time) = row?;
+            let file_type = parse_type(&now)?;
+            let before = before.as_deref().map(parse_type).transpose()?;
+            let was_target = before.as_ref().is_some_and(|before| category(before) == target);
+            if category(&file_type) == target && !was_target {
+                let record = Record {
+                    path,
+                    file_type,
+                    entropy,
+                    size,
+                };
+                changed.push((before, from_unix_time(time), record));
+            }
+        }
+        Ok(changed)
+    }
+}
+
+fn parse_type(label: &str) -> Result<FileType> {
+    label.parse().map_err(anyhow::Error::msg)
+}
+
+fn unix_time(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)
+}
+
+fn from_unix_time(secs: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+}
+
+pub fn format_time(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time).to_string()
+}
+
+pub fn run(args: &HistoryArgs) -> Result<()> {
+    if !args.db.exists() {
+        bail!("History database not found: {}", args.db.display());
+    }
+    let history = History::open(&args.db)?;
+
+    if let Some(id) = args.scan {
+        let records = history.load(id)?;
+        let rows: Vec<_> = records.iter().map(|r| (None, None, r)).collect();
+        return print_records(&rows, args);
+    }
+    if let Some(path) = &args.file {
+        let timeline = history.timeline(path)?;
+        if timeline.is_empty() {
+            bail!("{} was not recorded by any scan", path);
+        }
+        let rows: Vec<_> = timeline.iter().map(|(id, time, r)| (Some(*id), Some(*time), r)).collect();
+        return print_records(&rows, args);
+    }
+    if let Some(target) = args.became {
+        let cutoff = SystemTime::now() - args.since;
+        return print_changes(&history.became(target, cutoff)?, args);
+    }
+    print_scans(&history.scans()?, args)
+}
+
+fn print_scans(scans: &[ScanInfo], args: &HistoryArgs) -> Result<()> {
+    if args.json {
+        let scans: Vec<_> = scans
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "id": s.id,
+                    "time": format_time(s.time),
+                    "roots": s.roots.lines().collect::<Vec<_>>(),
+                    "files": s.files,
+                    "encrypted": s.encrypted,
+                    "random": s.random,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "scans": scans }))?);
+        return Ok(());
+    }
+    if args.simple {
+        println!("Scan,Time,Roots,Files,Encrypted,Random");
+        for s in scans {
+            println!(
+                "{},{},{},{},{},{}",
+                s.id,
+                format_time(s.time),
+                escape_csv(&s.roots.replace('\n', ";")),
+                s.files,
+                s.encrypted,
+                s.random
+            );
+        }
+        return Ok(());
+    }
+
+    if scans.is_empty() {
+        println!("{}", "No scans recorded yet.".yellow());
+        return Ok(());
+    }
+    let mut table = new_table(&["Scan", "Time", "Roots", "Files", "Encrypted", "Random"]);
+    for s in scans {
+        let encrypted = if s.encrypted > 0 {
+            s.encrypted.to_string().red().to_string()
+        } else {
+            s.encrypted.to_string()
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(&s.id.to_string()),
+            Cell::new(&format_time(s.time)),
+            Cell::new(&s.roots.replace('\n', ", ")),
+            Cell::new(&s.files.to_string()),
+            Cell::new(&encrypted),
+            Cell::new(&s.random.to_string()),
+        ]));
+    }
+    table.printstd();
+    Ok(())
+}
+
+/// Records of one scan, or of one file across scans (with scan ID and time)
+fn print_records(rows: &[(Option<i64>, Option<SystemTime>, &Record)], args: &HistoryArgs) -> Result<()> {
+    if args.json {
+        let files: Vec<_> = rows
+            .iter()
+            .map(|(id, time, r)| {
+                let mut entry = serde_json::json!({
+                    "path": r.path,
+                    "type": r.file_type.display_compact(),
+                    "entropy": (r.entropy * 10000.0).round() / 10000.0,
+                    "size": r.size,
+                });
+                if let (Some(id), Some(time)) = (id, time) {
+                    entry["scan"] = (*id).into();
+                    entry["time"] = format_time(*time).into();
+                }
+                entry
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "files": files }))?);
+        return Ok(());
+    }
+    if args.simple {
+        println!("Scan,Time,Path,Type,Entropy,Size");
+        for (id, time, r) in rows {
+            println!(
+                "{},{},{},{},{:.2},{}",
+                id.map(|id| id.to_string()).unwrap_or_default(),
+                time.map(format_time).unwrap_or_default(),
+                escape_csv(&r.path),
+                r.file_type.display_compact(),
+                r.entropy,
+                r.size
+            );
+        }
+        return Ok(());
+    }
+
+    let mut table = new_table(&["Scan", "Time", "Path", "Type", "Entropy", "Size"]);
+    for (id, time, r) in rows {
+        table.add_row(Row::new(vec![
+            Cell::new(&id.map(|id| id.to_string()).unwrap_or_default()),
+            Cell::new(&time.map(format_time).unwrap_or_default()),
+            Cell::new(&r.path),
+            Cell::new(&r.file_type.display_plain()),
+            Cell::new(&entropy_colored(r.entropy)),
+            Cell::new(&format_size(r.size)),
+        ]));
+    }
+    table.printstd();
+    Ok(())
+}
+
+fn print_changes(changes: &[(Option<FileType>, SystemTime, Record)], args: &HistoryArgs) -> Result<()> {
+    if args.json {
+        let files: Vec<_> = changes
+            .iter()
+            .map(|(before, time, r)| {
+                serde_json::json!({
+                    "path": r.path,
+                    "before": before.as_ref().map(FileType::display_compact),
+                    "type": r.file_type.display_compact(),
+                    "entropy": (r.entropy * 10000.0).round() / 10000.0,
+                    "size": r.size,
+                    "seen": format_time(*time),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "files": files }))?);
+        return Ok(());
+    }
+    if args.simple {
+        println!("Path,Before,Type,Entropy,Size,Seen");
+        for (before, time, r) in changes {
+            println!(
+                "{},{},{},{:.2},{},{}",
+                escape_csv(&r.path),
+                before.as_ref().map(FileType::display_compact).unwrap_or_default(),
+                r.file_type.display_compact(),
+                r.entropy,
+                r.size,
+                format_time(*time)
+            );
+        }
+        return Ok(());
+    }
+
+    if changes.is_empty() {
+        println!("{}", "No files changed category in that period.".green());
+        return Ok(());
+    }
+    let mut table = new_table(&["Path", "Before", "Now", "Entropy", "Size", "Seen"]);
+    for (before, time, r) in changes {
+        table.add_row(Row::new(vec![
+            Cell::new(&r.path),
+            Cell::new(&before.as_ref().map_or_else(|| "(new)".to_string(), FileType::display_plain)),
+            Cell::new(&r.file_type.display_plain()),
+            Cell::new(&entropy_colored(r.entropy)),
+            Cell::new(&format_size(r.size)),
+            Cell::new(&format_time(*time)),
+        ]));
+    }
+    table.printstd();
+    println!("\n  {} {}", "•".cyan(), format!("{} file(s) changed category", changes.len()).bold());
+    Ok(())
+}
+
+pub fn new_table(headers: &[&str]) -> Table {
+    let mut table = Table::new();
+    let format = prettytable::format::FormatBuilder::new()
+        .column_separator(' ')
+        .borders(' ')
+        .padding(1, 1)
+        .build();
+    table.set_format(format);
+    table.add_row(Row::new(headers.iter().map(|h| Cell::new(h).style_spec("Fb")).collect()));
+    table
+}
+
+pub fn entropy_colored(entropy: f64) -> String {
+    let entropy_str = format!("{:.2}/8.0", entropy);
+    if entropy > 7.5 {
+        entropy_str.red().to_string()
+    } else if entropy > 6.0 {
+        entropy_str.yellow().to_string()
+    } else {
+        entropy_str.green().to_string()
+    }
+}
@@ -0,0 +1,235 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::{calculate_entropy_from_counts, platform};
+
+/// LSB planes at or above this entropy look like an embedded random payload
+pub const LSB_SUSPICIOUS_ENTROPY: f64 = 7.95;
+
+// Skip tiny images: too few bits for a meaningful plane entropy
+const MIN_LSB_SAMPLES: usize = 8 * 1024;
+
+/// Entropy (0-8 bits) of the least-significant-bit plane of a PNG or BMP image.
+/// LSBs are packed 8 per byte, so a plane carrying random or encrypted data approaches 8.0.
+pub fn lsb_plane_entropy(path: &Path, format: &str) -> Result<Option<f64>> {
+    let samples = match format {
+        "PNG" => png_samples(path)?,
+        "BMP" => bmp_samples(path)?,
+        _ => return Ok(None),
+    };
+
+    if samples.len() < MIN_LSB_SAMPLES {
+        return Ok(None);
+    }
+
+    let mut counts = [0u64; 256];
+    let mut packed = 0usize;
+    for bits in samples.chunks_exact(8) {
+        let byte = bits.iter().fold(0u8, |acc, &s| (acc << 1) | (s & 1));
+        counts[byte as usize] += 1;
+        packed += 1;
+    }
+
+    Ok(Some(calculate_entropy_from_counts(&counts, packed)))
+}
+
+fn png_samples(path: &Path) -> Result<Vec<u8>> {
+    let file = File::open(platform::long_path(path)).context("Failed to open image")?;
+    let decoder = png::Decoder::new(BufReader::new(file));
+    let mut reader = decoder.read_info().context("Failed to read PNG header")?;
+
+    if reader.info().bit_depth != png::BitDepth::Eight {
+        bail!("Only 8-bit PNG channels are analyzed");
+    }
+
+    let mut buffer = vec![0u8; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).context("Failed to decode PNG")?;
+    buffer.truncate(frame.buffer_size());
+    Ok(buffer)
+}
+
+fn bmp_samples(path: &Path) -> Result<Vec<u8>> {
+    let mut file = File::open(platform::long_path(path)).context("Failed to open image")?;
+    let mut header = [0u8; 54];
+    file.read_exact(&mut header).context("Failed to read BMP header")?;
+
+    let data_offset = u32::from_le_bytes([header[10], header[11], header[12], header[13]]);
+    let bits_per_pixel = u16::from_le_bytes([header[28], header[29]]);
+    let compression = u32::from_le_bytes([header[30], header[31], header[32], header[33]]);
+
+    // Only uncompressed true-color bitmaps carry payloads directly in channel LSBs
+    if compression != 0 || bits_per_pixel < 24 {
+        bail!("Unsupported BMP layout");
+    }
+
+    let mut pixels = Vec::new();
+    file.seek(SeekFrom::Start(u64::from(data_offset)))?;
+    file.read_to_end(&mut pixels).context("Failed to read BMP pixels")?;
+    Ok(pixels)
+}
+
+/// Basic privacy-relevant image metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImageMetadata {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub software: Option<String>,
+    pub created: Option<String>,
+    pub camera: Option<String>,
+    pub gps: bool,
+}
+
+// Metadata lives in the first segments; don't read whole photos for it
+const METADATA_READ_LIMIT: u64 = 1024 * 1024;
+
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_SOFTWARE: u16 = 0x0131;
+const TAG_DATETIME: u16 = 0x0132;
+const TAG_GPS_IFD: u16 = 0x8825;
+
+pub fn extract_metadata(path: &Path, format: &str) -> Result<ImageMetadata> {
+    let file = File::open(platform::long_path(path)).context("Failed to open image")?;
+    let mut data = Vec::new();
+    file.take(METADATA_READ_LIMIT)
+        .read_to_end(&mut data)
+        .context("Failed to read image")?;
+
+    let mut meta = ImageMetadata::default();
+    match format {
+        "PNG" => png_metadata(&data, &mut meta),
+        "JPG" | "JPEG" => jpeg_metadata(&data, &mut meta),
+        "GIF" if data.len() >= 10 => {
+            meta.width = Some(u32::from(u16::from_le_bytes([data[6], data[7]])));
+            meta.height = Some(u32::from(u16::from_le_bytes([data[8], data[9]])));
+        }
+        "BMP" if data.len() >= 26 => {
+            let width = i32::from_le_bytes([data[18], data[19], data[20], data[21]]);
+            let height = i32::from_le_bytes([data[22], data[23], data[24], data[25]]);
+            meta.width = Some(width.unsigned_abs());
+            meta.height = Some(height.unsigned_abs());
+        }
+        _ => {}
+    }
+    Ok(meta)
+}
+
+fn be_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn png_metadata(data: &[u8], meta: &mut ImageMetadata) {
+    let mut pos = 8;
+    while let (Some(len), Some(kind)) = (be_u32(data, pos), data.get(pos + 4..pos + 8)) {
+        let start = pos + 8;
+        let end = start + len as usize;
+        let Some(body) = data.get(start..end) else {
+            break;
+        };
+
+        match kind {
+            b"IHDR" => {
+                meta.width = be_u32(body, 0);
+                meta.height = be_u32(body, 4);
+            }
+            b"tEXt" => {
+                // keyword\0text
+                if let Some(sep) = body.iter().position(|&b| b == 0) {
+                    let keyword = String::from_utf8_lossy(&body[..sep]);
+                    let value = String::from_utf8_lossy(&body[sep + 1..]).trim().to_string();
+                    match keyword.as_ref() {
+                        "Software" => meta.software = Some(value),
+                        "Creation Time" => meta.created = Some(value),
+                        _ => {}
+                    }
+                }
+            }
+            b"eXIf" => parse_exif(body, meta),
+            b"IEND" => break,
+            _ => {}
+        }
+        pos = end + 4; // skip CRC
+    }
+}
+
+fn jpeg_metadata(data: &[u8], meta: &mut ImageMetadata) {
+    let mut pos = 2;
+    while pos + 4 <= data.len() && data[pos] == 0xFF {
+        let marker = data[pos + 1];
+        if marker == 0xD9 || marker == 0xDA {
+            break; // end of image / start of scan
+        }
+        let Some(len) = be_u16(data, pos + 2).map(usize::from) else {
+            break;
+        };
+        let body = &data[(pos + 4).min(data.len())..(pos + 2 + len).min(data.len())];
+
+        match marker {
+            0xE1 if body.starts_with(b"Exif\0\0") => parse_exif(&body[6..], meta),
+            // SOFn frames carry the dimensions (C4/C8/CC are other tables)
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                meta.height = be_u16(body, 1).map(u32::from);
+                meta.width = be_u16(body, 3).map(u32::from);
+            }
+            _ => {}
+        }
+        pos += 2 + len;
+    }
+}
+
+// Walk IFD0 of a TIFF/EXIF block for tool, date, camera and GPS presence
+fn parse_exif(tiff: &[u8], meta: &mut ImageMetadata) {
+    let little_endian = match tiff.get(..2) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        _ => return,
+    };
+    let read_u16 = |offset: usize| -> Option<u16> {
+        let bytes: [u8; 2] = tiff.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    };
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes: [u8; 4] = tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    };
+    let read_ascii = |entry: usize| -> Option<String> {
+        let count = read_u32(entry + 4)? as usize;
+        let offset = if count <= 4 { entry + 8 } else { read_u32(entry + 8)? as usize };
+        let raw = tiff.get(offset..offset + count)?;
+        let text = String::from_utf8_lossy(raw).trim_end_matches('\0').trim().to_string();
+        (!text.is_empty()).then_some(text)
+    };
+
+    let Some(ifd) = read_u32(4).map(|o| o as usize) else {
+        return;
+    };
+    let count = read_u16(ifd).unwrap_or(0) as usize;
+    let mut make = None;
+    let mut model = None;
+
+    for i in 0..count {
+        let entry = ifd + 2 + i * 12;
+        match read_u16(entry) {
+            Some(TAG_MAKE) => make = read_ascii(entry),
+            Some(TAG_MODEL) => model = read_ascii(entry),
+            Some(TAG_SOFTWARE) => meta.software = read_ascii(entry).or(meta.software.take()),
+            Some(TAG_DATETIME) => meta.created = read_ascii(entry).or(meta.created.take()),
+            Some(TAG_GPS_IFD) => meta.gps = true,
+            Some(_) => {}
+            None => break,
+        }
+    }
+
+    meta.camera = match (make, model) {
+        (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    };
+}
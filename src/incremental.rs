@@ -0,0 +1,138 @@
+use anyhow::{Context, Result};
+use enro::{AnalyzeOptions, FileAnalysis};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A result stays valid while the file keeps its size and modification time
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    size: u64,
+    modified: SystemTime,
+    analysis: FileAnalysis,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    // Results depend on what was read and inspected, so other options invalidate the whole cache;
+    // kept as written since detectors are recorded by name only
+    options: serde_json::Value,
+    entries: HashMap<PathBuf, Entry>,
+}
+
+/// Per-file results of earlier --incremental scans, keyed by absolute path
+pub struct ResultCache {
+    path: PathBuf,
+    options: serde_json::Value,
+    entries: HashMap<PathBuf, Entry>,
+    // Size and modification time of files about to be analyzed, taken before reading them so a
+    // file changed mid-analysis is analyzed again next time
+    pending: HashMap<PathBuf, (u64, SystemTime)>,
+}
+
+impl ResultCache {
+    /// An empty cache when the file does not exist yet or was written with other options
+    pub fn load(path: &Path, options: &AnalyzeOptions) -> Result<Self> {
+        let options = serde_json::to_value(options)?;
+        let entries = match File::open(path) {
+            Ok(file) => {
+                let cache: CacheFile = serde_json::from_reader(BufReader::new(file))
+                    .with_context(|| format!("{} is not an enro result cache", path.display()))?;
+                if cache.options == options {
+                    cache.entries
+                } else {
+                    HashMap::new()
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to open result cache {}", path.display())),
+        };
+        Ok(ResultCache {
+            path: path.to_path_buf(),
+            options,
+            entries,
+            pending: HashMap::new(),
+        })
+    }
+
+    /// Split `files` into cached results of unchanged files and the files that need analyzing
+    pub fn split(&mut self, files: Vec<PathBuf>) -> (Vec<FileAnalysis>, Vec<PathBuf>) {
+        let mut unchanged = Vec::new();
+        let mut changed = Vec::new();
+        for file in files {
+            let stamp = fs::metadata(&file).and_then(|m| Ok((m.len(), m.modified()?)));
+            let (Ok(stamp), Ok(key)) = (stamp, std::path::absolute(&file)) else {
+                changed.push(file);
+                continue;
+            };
+            match self.entries.get(&key) {
+                Some(entry) if (entry.size, entry.modified) == stamp => {
+                    let mut analysis = entry.analysis.clone();
+                    // Reported as given on this run's command line
+                    analysis.path = file;
+                    unchanged.push(analysis);
+                }
+                _ => {
+                    self.pending.insert(key, stamp);
+                    changed.push(file);
+                }
+            }
+        }
+        (unchanged, changed)
+    }
+
+    /// Store the results of files analyzed on this run and write the cache
+    pub fn save(mut self, results: &[FileAnalysis]) -> Result<()> {
+        for analysis in results {
+            let Ok(key) = std::path::absolute(&analysis.path) else {
+                continue;
+            };
+            if let Some((size, modified)) = self.pending.remove(&key) {
+                let entry = Entry {
+                    size,
+                    modified,
+                    analysis: analysis.clone(),
+                };
+                self.entries.insert(key, entry);
+            }
+        }
+
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+        let file = File::create(&temp).with_context(|| format!("Failed to write result cache {}", temp.display()))?;
+        let mut writer = BufWriter::new(file);
+        let cache = CacheFile {
+            options: self.options,
+            entries: self.entries,
+        };
+        serde_json::to_writer(&mut writer, &cache)?;
+        writer.flush()?;
+        fs::rename(&temp, &self.path).with_context(|| format!("Failed to write result cache {}", self.path.display()))
+    }
+}
+
+#[cfg(windows)]
+pub fn default_path() -> PathBuf {
+    std::env::var_os("LOCALAPPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("enro")
+        .join("results.json")
+}
+
+#[cfg(not(windows))]
+pub fn default_path() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("enro")
+        .join("results.json")
+}
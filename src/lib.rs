@@ -0,0 +1,1129 @@
+//! File type detection and entropy analysis: magic numbers, Shannon entropy and
+//! format-specific inspection (ZIP, OLE, PDF, images, Git objects) behind the `enro` CLI.
+//!
+//! ```no_run
+//! let analysis = enro::Analyzer::new().max_bytes(1 << 20).zip_entries(true).analyze_file("report.docx")?;
+//! println!("{} {:.2}", analysis.file_type.display_plain(), analysis.entropy);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod detector;
+pub mod fuzzy;
+pub mod git;
+pub mod hash;
+pub mod image;
+pub mod memory;
+pub mod ole;
+pub mod pagecache;
+mod pdf;
+mod pipeline;
+pub mod platform;
+mod pool;
+pub mod recovery;
+pub mod sample;
+pub mod secrets;
+pub mod throttle;
+pub mod tuning;
+pub mod zip;
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use sysinfo::System;
+use throttle::Throttled;
+
+/// Per-file analysis settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzeOptions {
+    /// Read at most this many bytes of each file (all of it when `None`)
+    pub max_bytes: Option<usize>,
+    /// Leading bytes kept in [`FileAnalysis::header`]
+    pub header_len: usize,
+    /// Measure every stream of OLE compound files
+    pub ole_streams: bool,
+    /// Keep the central directory of ZIP containers in [`FileAnalysis::entries`]
+    pub zip_entries: bool,
+    /// Check PNG/BMP least-significant-bit planes for hidden data
+    pub stego: bool,
+    /// Extract EXIF/PNG text metadata from images
+    pub metadata: bool,
+    /// Classify packfiles and loose objects under `.git/objects` by what they hold
+    pub git_objects: bool,
+    /// Skip unreadable regions instead of failing on I/O errors
+    pub keep_going_io: bool,
+    /// Digests of each file's full contents, in [`FileAnalysis::hashes`]; files are read to the end
+    /// even past `max_bytes`
+    #[serde(default)]
+    pub hashes: Vec<hash::HashAlgorithm>,
+    /// Read only these regions of files on disk that are larger than them; takes the place of
+    /// `max_bytes` and `keep_going_io` for those files
+    #[serde(default)]
+    pub sample: Option<sample::Sampling>,
+    /// Read files to the end (or `max_bytes`) even when their first 64 KiB identify an archive,
+    /// document or image; otherwise reading stops there and the entropy covers only those bytes
+    #[serde(default)]
+    pub full_entropy: bool,
+    /// Third-party detectors consulted after enro's own detection
+    #[serde(default, skip_deserializing)]
+    pub detectors: detector::Detectors,
+}
+
+impl AnalyzeOptions {
+    /// Bytes analysis reads from a file of this size: all of them when hashing, else the sampled
+    /// regions or up to `max_bytes`
+    pub fn read_len(&self, size: u64) -> u64 {
+        if !self.hashes.is_empty() {
+            return size;
+        }
+        match (self.sample, self.max_bytes) {
+            (Some(sample), _) => sample.sampled_len(size),
+            (None, Some(max)) => size.min(max as u64),
+            (None, None) => size,
+        }
+    }
+}
+
+/// Builder over [`AnalyzeOptions`] for embedding enro's classification
+#[derive(Debug, Clone, Default)]
+pub struct Analyzer {
+    options: AnalyzeOptions,
+}
+
+impl Analyzer {
+    /// Whole files, or their first 64 KiB when those identify the format; no optional inspections
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.options.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn header_len(mut self, header_len: usize) -> Self {
+        self.options.header_len = header_len;
+        self
+    }
+
+    pub fn ole_streams(mut self, enabled: bool) -> Self {
+        self.options.ole_streams = enabled;
+        self
+    }
+
+    pub fn zip_entries(mut self, enabled: bool) -> Self {
+        self.options.zip_entries = enabled;
+        self
+    }
+
+    pub fn stego(mut self, enabled: bool) -> Self {
+        self.options.stego = enabled;
+        self
+    }
+
+    pub fn metadata(mut self, enabled: bool) -> Self {
+        self.options.metadata = enabled;
+        self
+    }
+
+    pub fn git_objects(mut self, enabled: bool) -> Self {
+        self.options.git_objects = enabled;
+        self
+    }
+
+    pub fn keep_going_io(mut self, enabled: bool) -> Self {
+        self.options.keep_going_io = enabled;
+        self
+    }
+
+    pub fn full_entropy(mut self, enabled: bool) -> Self {
+        self.options.full_entropy = enabled;
+        self
+    }
+
+    pub fn hash(mut self, algorithm: hash::HashAlgorithm) -> Self {
+        self.options.hashes.push(algorithm);
+        self
+    }
+
+    /// Classify large files from a sample of regions, see [`sample::Sampling`]
+    pub fn sample(mut self, sampling: sample::Sampling) -> Self {
+        self.options.sample = Some(sampling);
+        self
+    }
+
+    /// Consult a third-party detector for every file, see [`detector`]
+    pub fn detector(mut self, detector: impl detector::Detector + 'static) -> Self {
+        self.options.detectors.push(std::sync::Arc::new(detector));
+        self
+    }
+
+    pub fn options(&self) -> &AnalyzeOptions {
+        &self.options
+    }
+
+    pub fn analyze_file(&self, path: impl AsRef<Path>) -> Result<FileAnalysis> {
+        analyze_file(path.as_ref(), &self.options)
+    }
+
+    pub fn analyze_reader<R: Read + ?Sized>(&self, path: impl Into<PathBuf>, reader: &mut R) -> Result<FileAnalysis> {
+        analyze_reader(path.into(), reader, &self.options)
+    }
+
+    pub fn analyze_bytes(&self, path: impl Into<PathBuf>, data: &[u8]) -> FileAnalysis {
+        analyze_bytes(path.into(), data, &self.options)
+    }
+}
+
+impl From<AnalyzeOptions> for Analyzer {
+    fn from(options: AnalyzeOptions) -> Self {
+        Analyzer { options }
+    }
+}
+
+/// What a file was classified as
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FileType {
+    Archive(String),
+    Document(String),
+    Image(String),
+    Encrypted,
+    Random,
+    PlainText,
+    Binary,
+    Compressed,
+    Git(String),
+}
+
+impl FileType {
+    /// Label with an icon, for terminals
+    pub fn display(&self) -> String {
+        match self {
+            FileType::Archive(name) => format!("📦 Archive ({})", name),
+            FileType::Document(name) => format!("📄 Document ({})", name),
+            FileType::Image(name) => format!("🖼️  Image ({})", name),
+            FileType::Encrypted => "🔒 Encrypted".to_string(),
+            FileType::Random => "🎲 Random Data".to_string(),
+            FileType::PlainText => "📄 Plain Text".to_string(),
+            FileType::Binary => "⚙️  Binary".to_string(),
+            FileType::Compressed => "🗜️  Compressed".to_string(),
+            FileType::Git(kind) => format!("🌿 Git ({})", kind),
+        }
+    }
+
+    /// Compact form used by machine-readable output, e.g. `Archive(ZIP)`
+    pub fn display_compact(&self) -> String {
+        match self {
+            FileType::Archive(name) => format!("Archive({})", name),
+            FileType::Document(name) => format!("Document({})", name),
+            FileType::Image(name) => format!("Image({})", name),
+            FileType::Encrypted => "Encrypted".to_string(),
+            FileType::Random => "Random".to_string(),
+            FileType::PlainText => "PlainText".to_string(),
+            FileType::Binary => "Binary".to_string(),
+            FileType::Compressed => "Compressed".to_string(),
+            FileType::Git(kind) => format!("Git({})", kind),
+        }
+    }
+
+    /// Label without icons, e.g. `Archive (ZIP)`
+    pub fn display_plain(&self) -> String {
+        match self {
+            FileType::Archive(name) => format!("Archive ({})", name),
+            FileType::Document(name) => format!("Document ({})", name),
+            FileType::Image(name) => format!("Image ({})", name),
+            FileType::Encrypted => "Encrypted".to_string(),
+            FileType::Random => "Random Data".to_string(),
+            FileType::PlainText => "Plain Text".to_string(),
+            FileType::Binary => "Binary".to_string(),
+            FileType::Compressed => "Compressed".to_string(),
+            FileType::Git(kind) => format!("Git ({})", kind),
+        }
+    }
+}
+
+/// Parses the compact form written by [`FileType::display_compact`], e.g. in JSON reports
+impl std::str::FromStr for FileType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let named = |prefix: &str| s.strip_prefix(prefix)?.strip_suffix(')').map(str::to_string);
+        match s {
+            "Encrypted" => Ok(FileType::Encrypted),
+            "Random" => Ok(FileType::Random),
+            "PlainText" => Ok(FileType::PlainText),
+            "Binary" => Ok(FileType::Binary),
+            "Compressed" => Ok(FileType::Compressed),
+            _ => named("Archive(")
+                .map(FileType::Archive)
+                .or_else(|| named("Document(").map(FileType::Document))
+                .or_else(|| named("Image(").map(FileType::Image))
+                .or_else(|| named("Git(").map(FileType::Git))
+                .ok_or_else(|| format!("unknown file type `{}`", s)),
+        }
+    }
+}
+
+/// Classification of one file or stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileAnalysis {
+    pub path: PathBuf,
+    pub file_type: FileType,
+    pub entropy: f64,
+    pub size: u64,
+    pub header: Vec<u8>,
+    pub notes: Vec<String>,
+    pub streams: Vec<ole::StreamInfo>,
+    pub entries: Vec<zip::ZipEntry>,
+    pub metadata: Option<image::ImageMetadata>,
+    /// Hex digests asked for with [`AnalyzeOptions::hashes`]
+    #[serde(default)]
+    pub hashes: Vec<(hash::HashAlgorithm, String)>,
+    /// Bytes read to classify the file and compute its digests; less than `size` when reading
+    /// stopped once the format was identified, at `max_bytes`, or after the sampled regions
+    #[serde(default)]
+    pub bytes_read: u64,
+}
+
+impl FileAnalysis {
+    pub fn new(path: PathBuf, file_type: FileType, entropy: f64, size: u64) -> Self {
+        FileAnalysis {
+            path,
+            file_type,
+            entropy,
+            size,
+            header: Vec::new(),
+            notes: Vec::new(),
+            streams: Vec::new(),
+            entries: Vec::new(),
+            metadata: None,
+            hashes: Vec::new(),
+            bytes_read: 0,
+        }
+    }
+
+    /// Type label with any inspection notes folded in, e.g. `Document (DOC, 2 high-entropy streams)`
+    pub fn type_label(&self, label: String) -> String {
+        if self.notes.is_empty() {
+            return label;
+        }
+        match label.strip_suffix(')') {
+            Some(prefix) => format!("{}, {})", prefix, self.notes.join(", ")),
+            None => format!("{} ({})", label, self.notes.join(", ")),
+        }
+    }
+}
+
+static CHUNK_SIZE: OnceLock<usize> = OnceLock::new();
+
+/// Read chunks of `bytes` instead of sizing them from available RAM. Must be called before the
+/// first analysis; only the first call takes effect.
+pub fn set_chunk_size(bytes: usize) {
+    let _ = CHUNK_SIZE.set(bytes);
+}
+
+/// Read chunk size: the one given to `set_chunk_size`, or from available RAM and the thread count,
+/// between 1 MB and 1 GB. Under a `memory::set_limit` budget, small enough that every thread can
+/// hold its buffers at once.
+pub fn get_optimal_chunk_size() -> usize {
+    *CHUNK_SIZE.get_or_init(|| {
+        let mut sys = System::new_all();
+        sys.refresh_memory();
+        
+        let available_ram = sys.available_memory() as usize;
+        let thread_count = rayon::current_num_threads();
+        
+        // Calculate: available_ram / (threads + 2), capped at 1GB
+        let divisor = (thread_count * 2).max(4); // Ensure at least 4 to avoid too large chunks
+        let chunk_size = available_ram / divisor;
+        const MAX_CHUNK: usize = 1024 * 1024 * 1024; // 1GB
+        const MIN_CHUNK: usize = 1024 * 1024; // 1MB minimum
+        
+        let chunk_size = chunk_size.clamp(MIN_CHUNK, MAX_CHUNK);
+        match memory::limit() {
+            // measure() holds the first chunk and up to three more blocks no larger than it
+            Some(limit) => chunk_size.min(limit / (thread_count * 4)).max(memory::MIN_CHUNK),
+            None => chunk_size,
+        }
+    })
+}
+
+/// Buffers analyzing `len` bytes holds at its peak: the first chunk, kept for inspection, and the
+/// blocks in flight past it, plus those digests are fed from past `max_bytes`
+fn buffer_len(len: usize, hashing: bool) -> usize {
+    let chunk_size = get_optimal_chunk_size();
+    let first = len.min(chunk_size);
+    let rest = (len - first).min(pipeline::BLOCKS * read_block_len(chunk_size));
+    first + rest + if hashing { pipeline::BLOCKS * HASH_BUFFER } else { 0 }
+}
+
+/// Results kept by content, e.g. across scans, which [`analyze_file_with_store`] consults once a
+/// file has been read and its digest is known
+pub trait ResultStore {
+    /// The digest results are kept by, computed in the same pass as the analysis
+    fn algorithm(&self) -> hash::HashAlgorithm;
+    /// A finished result for content with this hex digest, if one is kept
+    fn get(&self, digest: &str) -> Option<FileAnalysis>;
+    /// Keep a finished result; it holds nothing specific to the path it was analyzed under
+    fn put(&self, digest: &str, analysis: &FileAnalysis);
+}
+
+pub fn analyze_file(path: &Path, options: &AnalyzeOptions) -> Result<FileAnalysis> {
+    analyze_file_with_store(path, options, None)
+}
+
+/// Like [`analyze_file`], but a result `store` keeps for the same content is used instead of
+/// inspecting the file further, and a new result is added to it. The whole file is read for the
+/// store's digest, though only as much as `options` ask for is measured. Nothing is looked up or
+/// kept when part of the file was unreadable.
+pub fn analyze_file_with_store(
+    path: &Path,
+    options: &AnalyzeOptions,
+    store: Option<&dyn ResultStore>,
+) -> Result<FileAnalysis> {
+    let metadata = fs::metadata(platform::long_path(path)).context("Failed to read file metadata")?;
+    let size = metadata.len();
+
+    let mut file = pagecache::open(&platform::long_path(path)).context("Failed to open file")?;
+
+    // A pipe has no size up front and cannot be reopened; stream it like stdin
+    if platform::Special::of(metadata.file_type()) == Some(platform::Special::Fifo) {
+        let mut analysis = analyze_reader(path.to_path_buf(), &mut file, options)?;
+        analysis.notes.push("named pipe".to_string());
+        return Ok(analysis);
+    }
+
+    // The store's digest is left out of the result unless it was asked for
+    let key = store.map(|store| store.algorithm());
+    let mut algorithms = options.hashes.clone();
+    if let Some(key) = key.filter(|key| !algorithms.contains(key)) {
+        algorithms.push(key);
+    }
+
+    let bytes_to_read = if let Some(max) = options.max_bytes {
+        max.min(size as usize)
+    } else {
+        size as usize // Read entire file
+    };
+
+    // Digests need the whole file anyway, so there is no reading to save
+    let early_exit = !options.full_entropy && options.hashes.is_empty();
+    let mut unreadable = 0;
+    let regions = options.sample.and_then(|sample| sample.regions(size));
+    let read = regions.as_ref().map_or(bytes_to_read, |regions| regions.iter().map(|&(_, len)| len as usize).sum());
+    // Taken before the memory budget, so a file waiting for its device holds none of it
+    let lease = tuning::lease(path, &metadata);
+    let file = tuning::Tracked::new(file, lease.as_ref());
+    let _reserved = memory::reserve(buffer_len(read, !algorithms.is_empty()));
+    let mut measured = if let Some(regions) = &regions {
+        let mut file = Throttled(file);
+        let mut measured = measure_regions(&mut file, regions, early_exit)?;
+        // Digests still cover the whole file
+        if let Some(mut hashers) = hash::Hashers::new(&algorithms) {
+            file.seek(SeekFrom::Start(0))?;
+            let (_, hashed) =
+                pipeline::read_into(&mut file, &mut [], u64::MAX, HASH_BUFFER, |block| hashers.update(block))
+                    .context("Failed to read file")?;
+            measured.hashed = hashed;
+            measured.hashers = Some(hashers);
+        }
+        measured
+    } else if options.keep_going_io {
+        // Digests cover the whole file, so read on past max_bytes
+        let end = if algorithms.is_empty() { bytes_to_read as u64 } else { size };
+        let mut reader = Throttled(recovery::TolerantReader::new(file, end, true)?);
+        let mut measured = measure(&mut reader, bytes_to_read, &algorithms, early_exit)?;
+        hash_rest(&mut reader, &mut measured)?;
+        unreadable = reader.0.unreadable;
+        measured
+    } else if let Some(head) = ranged_head(bytes_to_read, !algorithms.is_empty()) {
+        // The first chunk is read as usual and kept; the rest is counted range by range
+        let mut file = Throttled(file);
+        let mut measured = measure(&mut file, head, &[], early_exit)?;
+        if measured.bytes_read == head {
+            let (counts, read) = count_ranges(path, head as u64, bytes_to_read as u64)?;
+            for (total, count) in measured.byte_counts.iter_mut().zip(counts) {
+                *total += count;
+            }
+            measured.bytes_read += read as usize;
+            measured.entropy = calculate_entropy_from_counts(&measured.byte_counts, measured.bytes_read);
+        }
+        measured
+    } else {
+        let mut file = Throttled(file);
+        let mut measured = measure(&mut file, bytes_to_read, &algorithms, early_exit)?;
+        hash_rest(&mut file, &mut measured)?;
+        measured
+    };
+
+    // A digest of what happened to be readable matches nothing
+    let mut hashes = match measured.hashers.take() {
+        Some(hashers) if unreadable == 0 => hashers.finish(),
+        _ => Vec::new(),
+    };
+    let digest = hashes.iter().find(|(algorithm, _)| Some(*algorithm) == key).map(|(_, hex)| hex.clone());
+    hashes.retain(|(algorithm, _)| options.hashes.contains(algorithm));
+    let store = store.zip(digest);
+    let mut analysis = match store.as_ref().and_then(|(store, digest)| store.get(digest)) {
+        Some(mut kept) => {
+            kept.path = path.to_path_buf();
+            kept
+        }
+        None => {
+            let mut analysis = FileAnalysis::new(path.to_path_buf(), measured.file_type.clone(), measured.entropy, size);
+            run_detectors(&mut analysis, &measured, options);
+            if regions.is_some() {
+                analysis.notes.push(format!("sampled {}", format_size(measured.bytes_read as u64)));
+            }
+            if unreadable > 0 {
+                analysis.notes.push(format!("{} unreadable", format_size(unreadable)));
+            }
+            analysis.hashes = hashes;
+            inspect_container(&mut analysis, &measured.first_chunk, Content::File(path), options);
+            analysis.header = measured.first_chunk[..measured.first_chunk.len().min(options.header_len)].to_vec();
+            if let Some((store, digest)) = &store {
+                store.put(digest, &analysis);
+            }
+            analysis
+        }
+    };
+    pool::give(measured.first_chunk);
+    // Specific to this path, so added after the store has seen the result
+    analysis.bytes_read = measured.bytes_read as u64 + measured.hashed;
+    if let Some(links) = platform::hard_link_count(&metadata).filter(|&n| n > 1) {
+        analysis.notes.push(format!("{} hard links", links));
+    }
+
+    Ok(analysis)
+}
+
+/// Analyze data that only exists as a stream (e.g. an archive member on stdin)
+pub fn analyze_reader<R: Read + ?Sized>(path: PathBuf, reader: &mut R, options: &AnalyzeOptions) -> Result<FileAnalysis> {
+    let limit = options.max_bytes.unwrap_or(usize::MAX);
+    let _reserved = memory::reserve(buffer_len(limit, !options.hashes.is_empty()));
+    let reader = &mut Throttled(reader);
+    let mut measured = measure(reader, limit, &options.hashes, false)?;
+    // Drain past max_bytes so the reported size is the real one
+    let rest = match measured.hashers.as_mut() {
+        Some(hashers) => pipeline::read_into(reader, &mut [], u64::MAX, HASH_BUFFER, |block| hashers.update(block))
+            .map(|(_, read)| read),
+        None => std::io::copy(reader, &mut std::io::sink()),
+    }
+    .context("Failed to read stream")?;
+
+    let size = measured.bytes_read as u64 + rest;
+    let mut analysis = FileAnalysis::new(path, measured.file_type.clone(), measured.entropy, size);
+    analysis.bytes_read = size;
+    analysis.hashes = measured.hashers.take().map(hash::Hashers::finish).unwrap_or_default();
+    run_detectors(&mut analysis, &measured, options);
+    inspect_container(&mut analysis, &measured.first_chunk, Content::Memory(&measured.first_chunk), options);
+    analysis.header = measured.first_chunk[..measured.first_chunk.len().min(options.header_len)].to_vec();
+    pool::give(measured.first_chunk);
+
+    Ok(analysis)
+}
+
+/// Analyze data already in memory; `path` only labels the result
+pub fn analyze_bytes(path: PathBuf, data: &[u8], options: &AnalyzeOptions) -> FileAnalysis {
+    let mut reader = data;
+    analyze_reader(path, &mut reader, options).expect("reading from memory cannot fail")
+}
+
+/// What one pass over a file's contents found
+struct Measurement {
+    file_type: FileType,
+    entropy: f64,
+    /// Kept for further inspection
+    first_chunk: Vec<u8>,
+    bytes_read: usize,
+    byte_counts: [u64; 256],
+    /// Fed everything read so far
+    hashers: Option<hash::Hashers>,
+    /// Read past `bytes_read` only to feed the digests
+    hashed: u64,
+}
+
+// Reads past max_bytes only feed the digests, so they do not need memory-sized chunks
+const HASH_BUFFER: usize = 1024 * 1024;
+// Past the first chunk, data is only counted and hashed; larger blocks would save few syscalls
+const READ_BLOCK: usize = 8 * 1024 * 1024;
+// Enough for every magic number and for infer to tell OOXML/OpenDocument apart from plain ZIP
+const PROBE_LEN: usize = 64 * 1024;
+// Past the first chunk, huge files are counted on several threads in ranges of this length
+const RANGE_LEN: u64 = 64 * 1024 * 1024;
+
+/// Type and entropy of up to `limit` bytes, keeping the first memory-sized chunk. With `early_exit`, stops
+/// after the first PROBE_LEN bytes when they identify the format.
+fn measure<R: Read + ?Sized>(
+    reader: &mut R,
+    limit: usize,
+    hashes: &[hash::HashAlgorithm],
+    early_exit: bool,
+) -> Result<Measurement> {
+    // Use dynamically calculated chunk size
+    let chunk_size = get_optimal_chunk_size();
+
+    let mut byte_counts = [0u64; 256];
+    let mut hashers = hash::Hashers::new(hashes);
+
+    // Kept for inspection
+    let mut first_chunk = pool::take(chunk_size.min(limit));
+    let mut bytes_read = 0;
+    let mut done = false;
+    if early_exit {
+        let probe = PROBE_LEN.min(first_chunk.len());
+        bytes_read = pipeline::fill(reader, &mut first_chunk[..probe]).context("Failed to read file chunk")?;
+        count_bytes(&first_chunk[..bytes_read], &mut byte_counts);
+        if let Some(hashers) = hashers.as_mut() {
+            hashers.update(&first_chunk[..bytes_read]);
+        }
+        done = bytes_read < probe || is_conclusive(&detect_file_type(&first_chunk[..bytes_read]));
+    }
+    let mut total_read = bytes_read;
+    if !done {
+        // Counted and hashed on a second thread while the next block is read
+        let remaining = (limit - bytes_read) as u64;
+        let (head, rest) =
+            pipeline::read_into(reader, &mut first_chunk[bytes_read..], remaining, read_block_len(chunk_size), |block| {
+                count_bytes(block, &mut byte_counts);
+                if let Some(hashers) = hashers.as_mut() {
+                    hashers.update(block);
+                }
+            })
+            .context("Failed to read file chunk")?;
+        bytes_read += head;
+        total_read += rest as usize;
+    }
+    first_chunk.truncate(bytes_read);
+    
+    // Detect file type from first chunk
+    let file_type = detect_file_type(&first_chunk);
+    
+    // Calculate entropy from aggregated byte counts
+    let entropy = calculate_entropy_from_counts(&byte_counts, total_read);
+
+    Ok(Measurement {
+        file_type,
+        entropy,
+        first_chunk,
+        bytes_read: total_read,
+        byte_counts,
+        hashers,
+        hashed: 0,
+    })
+}
+
+/// Type and entropy of `(offset, length)` regions of a file; the first, at offset 0, gives the type.
+/// With `early_exit`, the rest are skipped when the start of the first identifies the format.
+fn measure_regions<R: Read + Seek>(file: &mut R, regions: &[(u64, u64)], early_exit: bool) -> Result<Measurement> {
+    let mut measured = Measurement {
+        file_type: FileType::PlainText,
+        entropy: 0.0,
+        first_chunk: Vec::new(),
+        bytes_read: 0,
+        byte_counts: [0; 256],
+        hashers: None,
+        hashed: 0,
+    };
+    for &(offset, len) in regions {
+        file.seek(SeekFrom::Start(offset))?;
+        let region = measure(&mut Read::by_ref(file).take(len), len as usize, &[], early_exit && offset == 0)?;
+        let conclusive = offset == 0 && early_exit && is_conclusive(&region.file_type);
+        if offset == 0 {
+            measured.first_chunk = region.first_chunk;
+        } else {
+            pool::give(region.first_chunk);
+        }
+        for (total, count) in measured.byte_counts.iter_mut().zip(region.byte_counts) {
+            *total += count;
+        }
+        measured.bytes_read += region.bytes_read;
+        if conclusive {
+            break;
+        }
+    }
+    measured.file_type = detect_file_type(&measured.first_chunk);
+    measured.entropy = calculate_entropy_from_counts(&measured.byte_counts, measured.bytes_read);
+    Ok(measured)
+}
+
+/// Feed whatever `measure` left unread to the digests
+fn hash_rest<R: Read + ?Sized>(reader: &mut R, measured: &mut Measurement) -> Result<()> {
+    if let Some(hashers) = measured.hashers.as_mut() {
+        let (_, hashed) = pipeline::read_into(reader, &mut [], u64::MAX, HASH_BUFFER, |block| hashers.update(block))
+            .context("Failed to read file")?;
+        measured.hashed += hashed;
+    }
+    Ok(())
+}
+
+/// Length of the first chunk when the `len` bytes to read are enough to split what follows it
+/// across threads. Digests need the bytes in order, and under a memory budget or device tuning a
+/// thread waiting on its ranges could pick up another file's analysis and block on the budget or
+/// device turn it holds itself.
+fn ranged_head(len: usize, hashing: bool) -> Option<usize> {
+    let head = get_optimal_chunk_size();
+    let worth_it = len.saturating_sub(head) as u64 > RANGE_LEN && rayon::current_num_threads() > 1;
+    (worth_it && !hashing && memory::limit().is_none() && !tuning::enabled()).then_some(head)
+}
+
+/// Byte counts of `[start, end)` of the file at `path`, read in RANGE_LEN ranges by whichever
+/// threads are free, each through its own handle; with the number of bytes read
+fn count_ranges(path: &Path, start: u64, end: u64) -> Result<([u64; 256], u64)> {
+    let ranges: Vec<(u64, u64)> = (start..end)
+        .step_by(RANGE_LEN as usize)
+        .map(|offset| (offset, RANGE_LEN.min(end - offset)))
+        .collect();
+    ranges
+        .into_par_iter()
+        .map(|(offset, len)| {
+            let mut file = Throttled(pagecache::open(&platform::long_path(path)).context("Failed to open file")?);
+            file.seek(SeekFrom::Start(offset))?;
+            let mut reader = file.take(len);
+            let mut counts = [0u64; 256];
+            let mut read = 0;
+            let mut block = pool::take(READ_BLOCK.min(len as usize));
+            loop {
+                let n = pipeline::fill(&mut reader, &mut block).context("Failed to read file chunk")?;
+                count_bytes(&block[..n], &mut counts);
+                read += n as u64;
+                if n < block.len() {
+                    break;
+                }
+            }
+            pool::give(block);
+            Ok((counts, read))
+        })
+        .try_reduce(
+            || ([0; 256], 0),
+            |(mut counts, read), (more, more_read)| {
+                for (total, count) in counts.iter_mut().zip(more) {
+                    *total += count;
+                }
+                Ok((counts, read + more_read))
+            },
+        )
+}
+
+fn read_block_len(chunk_size: usize) -> usize {
+    chunk_size.min(READ_BLOCK)
+}
+
+// Let third-party detectors overrule the built-in classification
+fn run_detectors(analysis: &mut FileAnalysis, measured: &Measurement, options: &AnalyzeOptions) {
+    if options.detectors.is_empty() {
+        return;
+    }
+    let input = detector::DetectorInput {
+        path: &analysis.path,
+        header: &measured.first_chunk,
+        size: analysis.size,
+        entropy: measured.entropy,
+        bytes_read: measured.bytes_read,
+        byte_counts: &measured.byte_counts,
+        file_type: &measured.file_type,
+    };
+    if let Some((name, detection)) = options.detectors.detect(&input) {
+        let note = format!("{} {}%", detection.label.as_deref().unwrap_or(name), detection.confidence);
+        analysis.file_type = detection.file_type;
+        analysis.notes.push(note);
+    }
+}
+
+/// Full contents for format inspection: a file to reopen, or bytes already in memory
+#[derive(Clone, Copy)]
+enum Content<'a> {
+    File(&'a Path),
+    Memory(&'a [u8]),
+}
+
+trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
+
+impl<'a> Content<'a> {
+    fn open(self) -> Result<Box<dyn ReadSeek + 'a>> {
+        match self {
+            Content::File(path) => {
+                let file = pagecache::DropBehind::new(File::open(platform::long_path(path))?);
+                Ok(Box::new(std::io::BufReader::new(file)))
+            }
+            Content::Memory(data) => Ok(Box::new(std::io::Cursor::new(data))),
+        }
+    }
+}
+
+// Format-specific introspection that needs more than the leading bytes
+fn inspect_container(analysis: &mut FileAnalysis, head: &[u8], content: Content, options: &AnalyzeOptions) {
+    if options.git_objects && matches!(content, Content::File(_)) {
+        git::inspect(analysis, head, options.max_bytes);
+        if matches!(analysis.file_type, FileType::Git(_)) {
+            return;
+        }
+    }
+
+    if ole::is_cfb(head) {
+        inspect_ole(analysis, content, options);
+    } else if analysis.file_type == FileType::Document("PDF".to_string()) {
+        if let Ok(reader) = content.open() {
+            for risk in pdf::scan_risks(reader, options.max_bytes) {
+                analysis.notes.push(risk.to_string());
+            }
+        }
+    } else if let (FileType::Image(format), Content::File(path)) = (&analysis.file_type, content) {
+        if options.metadata {
+            if let Ok(metadata) = image::extract_metadata(path, format) {
+                if metadata.gps {
+                    analysis.notes.push("GPS location".to_string());
+                }
+                analysis.metadata = Some(metadata);
+            }
+        }
+        if options.stego {
+            if let Ok(Some(lsb_entropy)) = image::lsb_plane_entropy(path, format) {
+                if lsb_entropy >= image::LSB_SUSPICIOUS_ENTROPY {
+                    analysis
+                        .notes
+                        .push(format!("random LSB plane {:.2}, possible steganography", lsb_entropy));
+                }
+            }
+        }
+    } else if head.starts_with(b"PK\x03\x04") {
+        let entries = content.open().and_then(|mut reader| zip::read_central_directory(&mut reader));
+        if let Ok(entries) = entries {
+            inspect_zip(analysis, entries, options);
+        }
+    }
+}
+
+/// Notes from a ZIP central directory, read locally or through remote range requests
+pub fn inspect_zip(analysis: &mut FileAnalysis, entries: Vec<zip::ZipEntry>, options: &AnalyzeOptions) {
+    if matches!(analysis.file_type, FileType::Document(_)) && zip::has_macros(&entries) {
+        analysis.notes.push("contains macros".to_string());
+    }
+    for scheme in zip::encryption_schemes(&entries) {
+        analysis.notes.push(scheme.note());
+    }
+    if options.zip_entries {
+        analysis.entries = entries;
+    }
+}
+
+fn inspect_ole(analysis: &mut FileAnalysis, content: Content, options: &AnalyzeOptions) {
+    let cfb = content.open().and_then(ole::CompoundFile::open);
+
+    let mut cfb = match cfb {
+        Ok(cfb) => cfb,
+        Err(_) => {
+            analysis.notes.push("malformed OLE container".to_string());
+            return;
+        }
+    };
+
+    if cfb.has_macros() {
+        analysis.notes.push("contains macros".to_string());
+    }
+
+    if options.ole_streams {
+        let streams = cfb.streams();
+        let high = streams
+            .iter()
+            .filter(|s| s.entropy > 7.5 && s.size >= 1024)
+            .count();
+        if high > 0 {
+            analysis.notes.push(format!("{} high-entropy stream(s)", high));
+        }
+        analysis.streams = streams;
+    }
+}
+
+pub fn detect_file_type(data: &[u8]) -> FileType {
+    if data.is_empty() {
+        return FileType::PlainText;
+    }
+
+    // Check our custom magic numbers for archives
+    if let Some(archive_type) = check_magic_number(data) {
+        // OOXML/OpenDocument files are ZIP containers, but report them as documents
+        if archive_type == "ZIP" {
+            if let Some(kind) = infer::get(data) {
+                let mime = kind.mime_type();
+                if mime.starts_with("application/vnd.openxmlformats")
+                    || mime.starts_with("application/vnd.oasis.opendocument")
+                {
+                    return FileType::Document(kind.extension().to_uppercase());
+                }
+            }
+        }
+        return FileType::Archive(archive_type);
+    }
+
+    // Try infer crate as fallback for file type detection
+    if let Some(kind) = infer::get(data) {
+        let mime = kind.mime_type();
+        let ext = kind.extension().to_uppercase();
+        
+        // Archive types
+        if mime.starts_with("application/x-") || mime.starts_with("application/zip") 
+            || mime == "application/gzip" || mime == "application/x-bzip2" 
+            || mime == "application/x-xz" || mime == "application/x-tar" {
+            return FileType::Archive(ext);
+        }
+        
+        // Document formats (PDF, Office docs, etc.)
+        if mime == "application/pdf" {
+            return FileType::Document("PDF".to_string());
+        }
+        if mime.starts_with("application/vnd.openxmlformats") {
+            return FileType::Document(ext);
+        }
+        if mime.starts_with("application/vnd.ms-") || mime.starts_with("application/msword") {
+            return FileType::Document(ext);
+        }
+        
+        // Image formats
+        if mime.starts_with("image/") {
+            return FileType::Image(ext);
+        }
+        
+        // Other compressed formats
+        if mime.contains("compress") || mime.contains("zip") {
+            return FileType::Compressed;
+        }
+    }
+
+    // Calculate entropy to detect encryption/randomness
+    let entropy = calculate_entropy(data);
+
+    // High entropy (> 7.5) suggests encryption or compression
+    if entropy > 7.5 {
+        // Check if it's a compressed format we might have missed
+        if is_compressed_format(data) {
+            return FileType::Compressed;
+        }
+        // If very high entropy and not a known format, likely encrypted or random
+        if entropy > 7.9 {
+            return FileType::Encrypted;
+        }
+        return FileType::Random;
+    }
+
+    // Check if it's mostly text
+    if is_text_data(data) {
+        return FileType::PlainText;
+    }
+
+    FileType::Binary
+}
+
+/// Types told by a format signature rather than guessed from the byte statistics, so reading
+/// more of the file cannot change them
+fn is_conclusive(file_type: &FileType) -> bool {
+    matches!(file_type, FileType::Archive(_) | FileType::Document(_) | FileType::Image(_))
+}
+
+fn check_magic_number(data: &[u8]) -> Option<String> {
+    if data.len() < 4 {
+        return None;
+    }
+
+    // ZIP (PK)
+    if data.starts_with(&[0x50, 0x4B, 0x03, 0x04]) || data.starts_with(&[0x50, 0x4B, 0x05, 0x06]) {
+        return Some("ZIP".to_string());
+    }
+
+    // RAR
+    if data.starts_with(&[0x52, 0x61, 0x72, 0x21, 0x1A, 0x07]) {
+        return Some("RAR".to_string());
+    }
+
+    // 7z
+    if data.starts_with(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C]) {
+        return Some("7Z".to_string());
+    }
+
+    // GZIP
+    if data.starts_with(&[0x1F, 0x8B]) {
+        return Some("GZIP".to_string());
+    }
+
+    // TAR (POSIX)
+    if data.len() > 262 && &data[257..262] == b"ustar" {
+        return Some("TAR".to_string());
+    }
+
+    // BZ2
+    if data.starts_with(&[0x42, 0x5A, 0x68]) {
+        return Some("BZIP2".to_string());
+    }
+
+    // XZ
+    if data.starts_with(&[0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00]) {
+        return Some("XZ".to_string());
+    }
+
+    // ISO
+    if data.len() > 32774 && &data[32769..32774] == b"CD001" {
+        return Some("ISO".to_string());
+    }
+
+    // CAB
+    if data.starts_with(&[0x4D, 0x53, 0x43, 0x46]) {
+        return Some("CAB".to_string());
+    }
+
+    // ARJ
+    if data.starts_with(&[0x60, 0xEA]) {
+        return Some("ARJ".to_string());
+    }
+
+    // LZH/LHA
+    if data.len() > 2 && data[2..4] == [0x2D, 0x6C] {
+        return Some("LZH".to_string());
+    }
+
+    None
+}
+
+fn is_compressed_format(data: &[u8]) -> bool {
+    // Additional compressed format checks
+    if data.len() < 4 {
+        return false;
+    }
+
+    // ZSTD
+    if data.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+        return true;
+    }
+
+    // LZ4
+    if data.starts_with(&[0x04, 0x22, 0x4D, 0x18]) {
+        return true;
+    }
+
+    false
+}
+
+fn is_text_data(data: &[u8]) -> bool {
+    let sample_size = data.len().min(8192);
+    let sample = &data[..sample_size];
+    
+    // Check for null bytes which indicate binary
+    if sample.contains(&0) {
+        return false;
+    }
+    
+    // Try UTF-8 validation first; the sample may end partway through a character
+    let text = match std::str::from_utf8(sample) {
+        Ok(text) => Some(text),
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&sample[..e.valid_up_to()]).ok(),
+        Err(_) => None,
+    };
+    if let Some(text) = text {
+        // Valid UTF-8 - check if it's mostly printable
+        let printable = text.chars()
+            .filter(|c| c.is_whitespace() || !c.is_control())
+            .count();
+        if printable as f64 / text.chars().count() as f64 > 0.90 {
+            return true;
+        }
+    }
+    
+    // Check for Windows-1251 (Cyrillic) and other 8-bit encodings
+    // Windows-1251 uses ranges: 0x20-0x7E (ASCII), 0xA0-0xFF (Cyrillic), plus common control chars
+    // Non-short-circuiting `|`, so the count compiles to vector compares instead of branches
+    let valid_chars = sample
+        .iter()
+        .filter(|&&byte| {
+            (0x20..=0x7E).contains(&byte) |  // ASCII printable
+            (byte >= 0xA0) |                 // Extended ASCII / Cyrillic range (0xA0-0xFF)
+            (byte == b'\n') | (byte == b'\r') | (byte == b'\t')
+        })
+        .count();
+    
+    // If more than 95% are valid text characters (ASCII or extended), consider it text
+    valid_chars as f64 / sample_size as f64 > 0.95
+}
+
+pub fn calculate_entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let mut frequency = [0u64; 256];
+    count_bytes(data, &mut frequency);
+
+    calculate_entropy_from_counts(&frequency, data.len())
+}
+
+/// Add how often each byte value occurs in `data` to `counts`
+pub fn count_bytes(data: &[u8], counts: &mut [u64; 256]) {
+    // Below this, clearing and merging the tables costs more than it saves
+    const MIN_TABLED: usize = 4096;
+    // No table can overflow its u32 counts within a block
+    const BLOCK: usize = u32::MAX as usize;
+
+    if data.len() < MIN_TABLED {
+        for &byte in data {
+            counts[byte as usize] += 1;
+        }
+        return;
+    }
+    for block in data.chunks(BLOCK) {
+        // Neighbouring bytes go to different tables, so runs of one value (zero padding, text
+        // indentation) do not stall on each other's increments; u32 keeps all four in L1
+        let mut tables = [[0u32; 256]; 4];
+        let mut words = block.chunks_exact(8);
+        for word in &mut words {
+            let word = u64::from_le_bytes(word.try_into().expect("chunks of 8"));
+            tables[0][(word & 0xFF) as usize] += 1;
+            tables[1][(word >> 8 & 0xFF) as usize] += 1;
+            tables[2][(word >> 16 & 0xFF) as usize] += 1;
+            tables[3][(word >> 24 & 0xFF) as usize] += 1;
+            tables[0][(word >> 32 & 0xFF) as usize] += 1;
+            tables[1][(word >> 40 & 0xFF) as usize] += 1;
+            tables[2][(word >> 48 & 0xFF) as usize] += 1;
+            tables[3][(word >> 56) as usize] += 1;
+        }
+        for &byte in words.remainder() {
+            tables[0][byte as usize] += 1;
+        }
+        for (value, count) in counts.iter_mut().enumerate() {
+            *count += tables.iter().map(|table| table[value] as u64).sum::<u64>();
+        }
+    }
+}
+
+pub fn calculate_entropy_from_counts(frequency: &[u64; 256], total_bytes: usize) -> f64 {
+    if total_bytes == 0 {
+        return 0.0;
+    }
+
+    let len = total_bytes as f64;
+    let mut entropy = 0.0;
+
+    for &count in frequency {
+        if count > 0 {
+            let p = count as f64 / len;
+            entropy -= p * p.log2();
+        }
+    }
+
+    entropy
+}
+
+/// Human-readable size with two decimals, e.g. `1.50 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit_idx = 0;
+
+    while size >= 1024.0 && unit_idx < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit_idx += 1;
+    }
+
+    format!("{:.2} {}", size, UNITS[unit_idx])
+}
@@ -1,15 +1,67 @@
+mod actions;
+mod alerts;
+mod audit;
+mod baseline;
+mod checkpoint;
+mod ci;
+mod cluster;
+mod content_cache;
+#[cfg(feature = "object-store")]
+mod cloud;
+mod daemon;
+mod dedup;
+mod device;
+mod diff;
+mod entropy_map;
+mod estimate;
+mod eventlog;
+mod exec;
+mod export;
+mod filters;
+mod git_scan;
+mod hashset;
+mod history;
+mod incremental;
+mod metrics;
+mod oci;
+mod plugins;
+mod policy;
+mod profile;
+mod randomness;
+mod remote;
+mod report;
+mod rules;
+mod schedule;
+mod selftest;
+#[cfg(feature = "sftp")]
+mod sftp;
+#[cfg(feature = "smb")]
+mod smb;
+mod stream;
+mod strings;
+mod tui;
+mod volumes;
+mod walk;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod watch;
+mod xattrs;
+
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::builder::BoolishValueParser;
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
 use prettytable::{Cell, Row, Table};
+use enro::{analyze_file, format_size, zip, AnalyzeOptions, FileAnalysis, FileType};
 use rayon::prelude::*;
-use std::fs::{self, File};
-use std::io::Read;
+use std::fmt::Display;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Mutex, OnceLock};
-use sysinfo::System;
-use walkdir::WalkDir;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
 
 /// File encryption and randomness analyzer
 #[derive(Parser, Debug)]
@@ -25,92 +77,774 @@ use walkdir::WalkDir;
                   - Encrypted or highly compressed data\n  \
                   - Random data blobs\n  \
                   - Plain text files\n\n\
-                  Uses magic number detection and Shannon entropy calculation to classify files."
+                  Uses magic number detection and Shannon entropy calculation to classify files.\n\n\
+                  `enro PATH...` is short for `enro scan PATH...`.",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    scan: Args,
+}
+
+/// Options of a scan, shared by `enro scan`, `enro PATH...` and `enro watch`
+#[derive(clap::Args, Debug)]
+#[command(
+    group = clap::ArgGroup::new("result_filter").multiple(true),
+    group = clap::ArgGroup::new("raw_device").multiple(true)
 )]
 struct Args {
-    /// File or directory to analyze
-    #[arg(value_name = "PATH")]
-    path: PathBuf,
+    /// Files, directories, http(s)://, s3://, az://, gs://, sftp:// or smb:// URLs to analyze (`-` reads an archive from stdin, see --stdin-format)
+    #[arg(value_name = "PATH", required_unless_present_any = ["device", "files_from", "files_from0", "resume"])]
+    paths: Vec<PathBuf>,
+
+    /// Read paths to analyze from a file, one per line (`-` for stdin), instead of walking directories
+    #[arg(long, value_name = "FILE", conflicts_with = "files_from0")]
+    files_from: Option<PathBuf>,
+
+    /// Like --files-from, but paths are NUL-separated (`find -print0`)
+    #[arg(long, value_name = "FILE")]
+    files_from0: Option<PathBuf>,
+
+    /// Preset of sampling size, inspections and output for a common job; options given
+    /// explicitly take precedence
+    #[arg(long, env = "ENRO_PROFILE", value_enum, value_name = "PROFILE")]
+    profile: Option<profile::Profile>,
 
     /// Recursively scan directories
-    #[arg(short, long)]
+    #[arg(short, long, env = "ENRO_RECURSIVE", value_parser = BoolishValueParser::new())]
     recursive: bool,
 
+    /// Descend at most this many directory levels below each PATH (1 = its direct contents)
+    #[arg(long, env = "ENRO_MAX_DEPTH", value_name = "N", requires = "recursive")]
+    max_depth: Option<usize>,
+
+    /// Don't descend into directories on other filesystems (mount points, network shares, /proc)
+    #[arg(long, env = "ENRO_ONE_FILE_SYSTEM", value_parser = BoolishValueParser::new(), requires = "recursive")]
+    one_file_system: bool,
+
+    /// Follow symbolic links while walking directories (default; loops are detected and skipped)
+    #[arg(long, env = "ENRO_FOLLOW_SYMLINKS", value_parser = BoolishValueParser::new(), overrides_with = "no_follow_symlinks")]
+    follow_symlinks: bool,
+
+    /// Don't follow symbolic links while walking directories
+    #[arg(long, env = "ENRO_NO_FOLLOW_SYMLINKS", value_parser = BoolishValueParser::new(), overrides_with = "follow_symlinks")]
+    no_follow_symlinks: bool,
+
+    /// Descend into NTFS junctions (Windows). They are skipped by default, since system
+    /// junctions such as `C:\Documents and Settings` point back into trees walked anyway.
+    #[arg(long, env = "ENRO_FOLLOW_JUNCTIONS", value_parser = BoolishValueParser::new())]
+    follow_junctions: bool,
+
+    /// Skip every NTFS reparse point, including cloud placeholders that download on read (Windows)
+    #[arg(long, env = "ENRO_SKIP_REPARSE_POINTS", value_parser = BoolishValueParser::new())]
+    skip_reparse_points: bool,
+
+    /// Include dotfiles and hidden/system files while walking directories (default)
+    #[arg(long, env = "ENRO_HIDDEN", value_parser = BoolishValueParser::new(), overrides_with = "no_hidden")]
+    hidden: bool,
+
+    /// Skip dotfiles and hidden/system files while walking directories; the summary counts them
+    #[arg(long, env = "ENRO_NO_HIDDEN", value_parser = BoolishValueParser::new(), overrides_with = "hidden")]
+    no_hidden: bool,
+
     /// Minimum file size to analyze (in bytes)
-    #[arg(short, long, default_value = "0")]
+    #[arg(short, long, env = "ENRO_MIN_SIZE", default_value = "0")]
     min_size: u64,
 
+    /// Only analyze files modified within this long ago (`24h`, `7d`) or since this UTC time (`2024-05-01`)
+    #[arg(long, env = "ENRO_NEWER_THAN", value_name = "WHEN", value_parser = filters::parse_time)]
+    newer_than: Option<SystemTime>,
+
+    /// Only analyze files last modified before this long ago or before this UTC time
+    #[arg(long, env = "ENRO_OLDER_THAN", value_name = "WHEN", value_parser = filters::parse_time)]
+    older_than: Option<SystemTime>,
+
+    /// Only analyze files matching this glob while walking directories (repeatable).
+    /// Patterns without `/` match the file name, others the path below the scanned directory
+    #[arg(long, env = "ENRO_INCLUDE", value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files and directories matching this glob while walking directories (repeatable),
+    /// e.g. `node_modules` or `build/**`
+    #[arg(long, env = "ENRO_EXCLUDE", value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Don't descend into this directory while walking (repeatable), e.g. a network mount.
+    /// Scans of `/` leave out /proc, /sys, /dev, /run and /net (Linux) or /dev, /Volumes,
+    /// /System/Volumes and the automount points (macOS) unless --no-default-excludes is given.
+    #[arg(long, env = "ENRO_EXCLUDE_PATH", value_name = "PATH")]
+    exclude_path: Vec<PathBuf>,
+
+    /// Walk the pseudo-filesystems and automount points that scans of `/` leave out by default
+    #[arg(long, env = "ENRO_NO_DEFAULT_EXCLUDES", value_parser = BoolishValueParser::new())]
+    no_default_excludes: bool,
+
+    /// Only analyze files with these extensions while walking directories, e.g. `docx,xlsx,pdf`
+    #[arg(long, env = "ENRO_EXT", value_name = "EXT", value_delimiter = ',')]
+    ext: Vec<String>,
+
+    /// Skip files with these extensions while walking directories, e.g. `iso,mp4`
+    #[arg(long, env = "ENRO_NOT_EXT", value_name = "EXT", value_delimiter = ',')]
+    not_ext: Vec<String>,
+
+    /// Honor .gitignore, .ignore and git exclude rules during recursive scans (like ripgrep)
+    #[arg(long, env = "ENRO_RESPECT_GITIGNORE", value_parser = BoolishValueParser::new())]
+    respect_gitignore: bool,
+
     /// Maximum number of bytes to read for analysis (omit to scan entire file)
-    #[arg(short = 'b', long)]
+    #[arg(short = 'b', long, env = "ENRO_MAX_BYTES")]
     max_bytes: Option<usize>,
 
+    /// Classify files from regions instead of all their bytes: `head[=SIZE]`, `tail[=SIZE]` and
+    /// `stride[=N[xSIZE]]` joined by `+`, e.g. `head+tail+stride` (4MiB at each end, 16 stripes of
+    /// 1MiB in between) or `head=1MiB+stride=64x256KiB`. The head is always read; files no larger
+    /// than the sample are read whole.
+    #[arg(long, env = "ENRO_SAMPLE", value_name = "SPEC", value_parser = parse_sample, conflicts_with_all = ["max_bytes", "keep_going_io"])]
+    sample: Option<enro::sample::Sampling>,
+
+    /// Analyze a random subset of the files found, e.g. `5%` or `1000`, and estimate how many
+    /// files and bytes of each category all of them hold, with 95% confidence intervals
+    #[arg(long, env = "ENRO_SAMPLE_FILES", value_name = "SIZE", value_parser = estimate::parse_sample_size, conflicts_with_all = ["eager", "checkpoint", "resume"])]
+    sample_files: Option<estimate::SampleSize>,
+
+    /// Seed for --sample-files, so the same files are picked again from the same tree
+    #[arg(long, env = "ENRO_SAMPLE_SEED", value_name = "N", requires = "sample_files")]
+    sample_seed: Option<u64>,
+
+    /// Read files to the end even when their first 64KiB identify an archive, document or image.
+    /// Without it, reading stops there and the entropy shown covers only those bytes.
+    #[arg(long, env = "ENRO_FULL_ENTROPY", value_parser = BoolishValueParser::new())]
+    full_entropy: bool,
+
     /// Simple output format (no colors, no tables)
-    #[arg(short, long)]
+    #[arg(short, long, env = "ENRO_SIMPLE", value_parser = BoolishValueParser::new())]
     simple: bool,
 
     /// Show only summary (no individual file details)
-    #[arg(long)]
+    #[arg(long, env = "ENRO_SUMMARY_ONLY", value_parser = BoolishValueParser::new())]
     summary_only: bool,
 
+    /// Only walk and filter: print how many files and bytes a scan would analyze, then exit
+    #[arg(long, env = "ENRO_DRY_RUN", value_parser = BoolishValueParser::new())]
+    dry_run: bool,
+
+    /// With --dry-run, also print the path of every file that would be analyzed
+    #[arg(long, env = "ENRO_LIST", value_parser = BoolishValueParser::new(), requires = "dry_run")]
+    list: bool,
+
     /// Number of threads to use for parallel processing (default: CPU cores)
-    #[arg(short = 'j', long)]
+    #[arg(short = 'j', long, env = "ENRO_THREADS")]
     threads: Option<usize>,
 
+    /// Read at most RATE bytes per second across all threads, e.g. `50MB/s`, to leave disk and
+    /// network bandwidth to other workloads
+    #[arg(long, env = "ENRO_THROTTLE", value_name = "RATE", value_parser = parse_rate)]
+    throttle: Option<u64>,
+
+    /// Open at most RATE files per second, e.g. `200/s`, so a scan of a live NFS/SMB mount does
+    /// not flood the server with metadata requests
+    #[arg(long, env = "ENRO_RATE", value_name = "RATE", value_parser = parse_file_rate)]
+    rate: Option<f64>,
+
+    /// Run at the lowest priority: nice 19 and the idle IO class on Linux, background mode on Windows
+    #[arg(long, env = "ENRO_NICE", value_parser = BoolishValueParser::new())]
+    nice: bool,
+
+    /// Drop what was read from the OS page cache as the scan goes, so the cache keeps serving the
+    /// host's own workloads; files they had cached are dropped too. Linux only; files are read
+    /// with a sequential-access hint either way.
+    #[arg(long, env = "ENRO_NO_CACHE_POLLUTION", value_parser = BoolishValueParser::new())]
+    no_cache_pollution: bool,
+
+    /// Cap on the read buffers all threads hold at once, e.g. `2GiB`; fewer files are read in
+    /// parallel when it is reached
+    #[arg(long, env = "ENRO_MAX_MEMORY", value_name = "SIZE", value_parser = device::parse_size)]
+    max_memory: Option<u64>,
+
+    /// Read files in chunks of SIZE, e.g. `64MiB`, instead of sizing them from available RAM and
+    /// the thread count; under --max-memory, fewer files are then read at once
+    #[arg(long, env = "ENRO_CHUNK_SIZE", value_name = "SIZE", value_parser = device::parse_size)]
+    chunk_size: Option<u64>,
+
+    /// Measure each storage device's throughput in the first seconds of the scan and settle on how
+    /// many files to read from it at once and how large each read is, so a local disk and an NFS
+    /// mount scanned together each get their own
+    #[arg(long, env = "ENRO_TUNE", value_parser = BoolishValueParser::new(), conflicts_with_all = ["throttle", "rate"])]
+    tune: bool,
+
+    /// Analyze files as the walk finds them instead of listing them all first; on huge trees the
+    /// scan starts at once and the list is never held. Files are not started largest first, and
+    /// the progress total grows as the walk goes on.
+    #[arg(long, env = "ENRO_EAGER", value_parser = BoolishValueParser::new(), conflicts_with_all = ["checkpoint", "resume", "incremental", "dry_run"])]
+    eager: bool,
+
     /// Entropy threshold range (format: min-max, e.g., 7.5-8.0)
-    #[arg(short = 't', long, value_name = "MIN-MAX")]
+    #[arg(short = 't', long, env = "ENRO_THRESHOLD", value_name = "MIN-MAX", group = "result_filter")]
     threshold: Option<String>,
+
+    /// Only report files classified as one of these categories, e.g. `encrypted,random`
+    #[arg(long, env = "ENRO_ONLY", value_enum, value_name = "CATEGORY", value_delimiter = ',', group = "result_filter")]
+    only: Vec<Category>,
+
+    /// Leave files classified as one of these categories out of the report, e.g. `plaintext,image`
+    #[arg(long, env = "ENRO_HIDE", value_enum, value_name = "CATEGORY", value_delimiter = ',', group = "result_filter")]
+    hide: Vec<Category>,
+
+    /// Order the report deterministically (default: the order files were found in)
+    #[arg(long, env = "ENRO_SORT", value_enum, value_name = "KEY")]
+    sort: Option<SortKey>,
+
+    /// Report the files that --threshold/--only/--hide would reject instead of the ones they keep
+    #[arg(long, env = "ENRO_INVERT_MATCH", value_parser = BoolishValueParser::new(), requires = "result_filter")]
+    invert_match: bool,
+
+    /// Include a hexdump of the first N bytes of each file in the output
+    #[arg(long, env = "ENRO_PREVIEW", value_name = "N")]
+    preview: Option<usize>,
+
+    /// Add a column with the first N header bytes in hex (default: 16)
+    #[arg(
+        long,
+        env = "ENRO_HEADER_HEX",
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "16",
+        value_parser = clap::value_parser!(u8).range(1..=32)
+    )]
+    header_hex: Option<u8>,
+
+    /// List streams inside OLE/CFB containers (doc/xls/msi) with per-stream entropy
+    #[arg(long, env = "ENRO_OLE_STREAMS", value_parser = BoolishValueParser::new())]
+    ole_streams: bool,
+
+    /// List the entries of ZIP archives with their sizes and encryption. Remote archives
+    /// fetch only the central directory, via range requests.
+    #[arg(long, env = "ENRO_ZIP_ENTRIES", value_parser = BoolishValueParser::new())]
+    zip_entries: bool,
+
+    /// Analyze least-significant-bit planes of PNG/BMP images for hidden payloads
+    #[arg(long, env = "ENRO_STEGO", value_parser = BoolishValueParser::new())]
+    stego: bool,
+
+    /// Extract basic image metadata (dimensions, creation tool, GPS presence)
+    #[arg(long, env = "ENRO_METADATA", value_parser = BoolishValueParser::new())]
+    metadata: bool,
+
+    /// Also analyze extended attributes and, on macOS, resource forks (`..namedfork/rsrc`),
+    /// each reported as its own entry
+    #[arg(long, env = "ENRO_XATTRS", value_parser = BoolishValueParser::new())]
+    xattrs: bool,
+
+    /// Decode Git loose objects and packfiles inside .git directories instead of reporting them as compressed data
+    #[arg(long, env = "ENRO_GIT_OBJECTS", value_parser = BoolishValueParser::new())]
+    git_objects: bool,
+
+    /// Load a third-party detector that may reclassify files after the built-in detection
+    /// (repeatable): a JSON signature pack, Rhai classification rules, or a WebAssembly module run
+    /// sandboxed (`wasm` feature)
+    #[arg(long, env = "ENRO_DETECTOR", value_name = "FILE", value_parser = clap::value_parser!(plugins::DetectorPlugin))]
+    detector: Vec<plugins::DetectorPlugin>,
+
+    /// List split archive volumes (.z01, .part1.rar, .7z.001) individually instead of grouping them
+    #[arg(long, env = "ENRO_NO_GROUP_VOLUMES", value_parser = BoolishValueParser::new())]
+    no_group_volumes: bool,
+
+    /// Hash files (BLAKE3) and list each set of identical files only once, under its first path
+    #[arg(long, env = "ENRO_DEDUPE", value_parser = BoolishValueParser::new())]
+    dedupe: bool,
+
+    /// Report groups of identical files (BLAKE3) and the space taken by the extra copies
+    #[arg(long, env = "ENRO_DUPLICATES", value_parser = BoolishValueParser::new(), conflicts_with = "simple")]
+    duplicates: bool,
+
+    /// Compute these digests of every file in the same read pass and report them, e.g.
+    /// `sha256,blake3` (md5, sha1, sha256, blake3, tlsh, ssdeep). Files are read to the end even with --max-bytes
+    #[arg(long, env = "ENRO_HASH", value_name = "ALG", value_delimiter = ',', value_parser = clap::value_parser!(enro::hash::HashAlgorithm))]
+    hash: Vec<enro::hash::HashAlgorithm>,
+
+    /// Match digests against a hash set (repeatable): `good:FILE` (the default, e.g. NSRL) leaves
+    /// known files out of the report, `bad:FILE` flags them. Text lists or `enro hashset` output
+    #[arg(long, env = "ENRO_HASHSET", value_name = "[good:|bad:]FILE", value_parser = clap::value_parser!(hashset::KnownHashes))]
+    hashset: Vec<hashset::KnownHashes>,
+
+    /// Report groups of near-identical files (TLSH similarity and entropy), such as mass-copied
+    /// payloads or templated ransom notes
+    #[arg(long, env = "ENRO_CLUSTER", value_parser = BoolishValueParser::new(), conflicts_with = "simple")]
+    cluster: bool,
+
+    /// Largest TLSH distance between files of a cluster; lower is stricter
+    #[arg(long, env = "ENRO_CLUSTER_DISTANCE", value_name = "N", default_value_t = 50, requires = "cluster")]
+    cluster_distance: u32,
+
+    /// List every path that was not analyzed and why (hidden, excluded, too small, unreadable...)
+    #[arg(long, env = "ENRO_SHOW_SKIPPED", value_parser = BoolishValueParser::new(), conflicts_with = "simple")]
+    show_skipped: bool,
+
+    /// JSON output format (for scripting)
+    #[arg(long, env = "ENRO_JSON", value_parser = BoolishValueParser::new(), conflicts_with = "simple")]
+    json: bool,
+
+    /// Also list files that failed to analyze in --json output, each with an `error` field
+    #[arg(long, env = "ENRO_JSON_ERRORS", value_parser = BoolishValueParser::new(), requires = "json")]
+    json_errors: bool,
+
+    /// Print each file's --simple row or --json object, one per line, as soon as it is analyzed
+    /// instead of holding the report until the end; with --json, a last line carries the summary.
+    /// Volumes of split archives are listed one by one.
+    #[arg(long, env = "ENRO_STREAM", value_parser = BoolishValueParser::new(), conflicts_with_all = ["sort", "dedupe", "dry_run"])]
+    stream: bool,
+
+    /// On read errors skip the bad region and classify what could be read, noting the unreadable byte count
+    #[arg(long, env = "ENRO_KEEP_GOING_IO", value_parser = BoolishValueParser::new())]
+    keep_going_io: bool,
+
+    /// POST a JSON alert here when encrypted files, ransomware extensions or directories over
+    /// --alert-ratio are found (Slack/Teams incoming webhooks show its `text`)
+    #[arg(long, env = "ENRO_ALERT_WEBHOOK", value_name = "URL")]
+    alert_webhook: Option<String>,
+
+    /// Share of encrypted files above which a directory of 5+ files raises an alert
+    #[arg(long, env = "ENRO_ALERT_RATIO", value_name = "FRACTION", default_value_t = 0.5, requires = "alert_webhook")]
+    alert_ratio: f64,
+
+    /// Log each encrypted/random file and a scan summary to the local syslog (RFC 5424, /dev/log)
+    #[arg(long, env = "ENRO_SYSLOG", value_parser = BoolishValueParser::new())]
+    syslog: bool,
+
+    /// Send the same RFC 5424 events to a syslog server over UDP (`HOST:PORT`)
+    #[arg(long, env = "ENRO_SYSLOG_SERVER", value_name = "HOST:PORT")]
+    syslog_server: Option<String>,
+
+    /// Log the same events to the systemd journal with ENRO_* fields (ENRO_PATH, ENRO_TYPE, ...)
+    #[arg(long, env = "ENRO_JOURNALD", value_parser = BoolishValueParser::new())]
+    journald: bool,
+
+    /// Append a JSON line per run to this file: arguments, start and end time, counts, findings and
+    /// exit status, each line chained to the one before by its SHA-256 (see `enro verify-audit-log`)
+    #[arg(long, env = "ENRO_AUDIT_LOG", value_name = "FILE")]
+    audit_log: Option<PathBuf>,
+
+    /// Append this run's results to a SQLite history database, queried with `enro history`
+    #[arg(long, env = "ENRO_HISTORY", value_name = "DB")]
+    history: Option<PathBuf>,
+
+    /// Save progress to this file every 30s (file list and results so far) so an interrupted scan
+    /// can be continued with --resume; removed once the scan completes
+    #[arg(long, env = "ENRO_CHECKPOINT", value_name = "FILE")]
+    checkpoint: Option<PathBuf>,
+
+    /// Continue an interrupted scan from its checkpoint instead of walking again; progress keeps
+    /// being saved to the same file
+    #[arg(long, env = "ENRO_RESUME", value_name = "FILE", conflicts_with_all = ["files_from", "files_from0"])]
+    resume: Option<PathBuf>,
+
+    /// Reuse the results of files whose size and modification time are unchanged since the last
+    /// --incremental scan, and save this run's results for the next one
+    #[arg(long, env = "ENRO_INCREMENTAL", value_parser = BoolishValueParser::new())]
+    incremental: bool,
+
+    /// Result cache for --incremental [default: $XDG_CACHE_HOME/enro/results.json or
+    /// ~/.cache/enro/results.json; %LOCALAPPDATA%\enro\results.json on Windows]
+    #[arg(long, env = "ENRO_CACHE_FILE", value_name = "FILE", requires = "incremental")]
+    cache_file: Option<PathBuf>,
+
+    /// Reuse the result of any earlier --content-cache scan of the same content, found by BLAKE3
+    /// hash wherever the copy lives. Every file is read whole for its hash, even past --max-bytes.
+    #[arg(long, env = "ENRO_CONTENT_CACHE", value_parser = BoolishValueParser::new())]
+    content_cache: bool,
+
+    /// Result cache for --content-cache [default: $XDG_CACHE_HOME/enro/content.json or
+    /// ~/.cache/enro/content.json; %LOCALAPPDATA%\enro\content.json on Windows]
+    #[arg(long, env = "ENRO_CONTENT_CACHE_FILE", value_name = "FILE", requires = "content_cache")]
+    content_cache_file: Option<PathBuf>,
+
+    /// Compare against a committed `--json` report and exit non-zero only for findings it does not list
+    #[arg(long, env = "ENRO_BASELINE", value_name = "FILE")]
+    baseline: Option<PathBuf>,
+
+    /// Categories that count as findings for --baseline
+    #[arg(long, env = "ENRO_FAIL_ON_NEW", value_enum, value_name = "CATEGORY", value_delimiter = ',', default_value = "encrypted,random", requires = "baseline")]
+    fail_on_new: Vec<Category>,
+
+    /// Exit with status 1 when any analyzed file matches: a category (`encrypted`), an entropy
+    /// comparison (`entropy>7.9`) or `mismatched-extension`; comma-separated or repeated
+    #[arg(long, env = "ENRO_FAIL_ON", value_name = "CONDITION", value_delimiter = ',', value_parser = clap::value_parser!(policy::FailOn))]
+    fail_on: Vec<policy::FailOn>,
+
+    /// Act on every reported file (after --only/--hide/--threshold): `quarantine:DIR` moves it and
+    /// makes it read-only, `move:DIR` moves it, `copy:DIR` copies it; each is logged with its
+    /// BLAKE3 hash to DIR/enro-manifest.jsonl
+    #[arg(long, env = "ENRO_ACTION", value_name = "ACTION:DIR", value_parser = clap::value_parser!(actions::FileAction))]
+    action: Option<actions::FileAction>,
+
+    /// Run a command for each reported file, e.g. `--exec 'upload-sample {}'`: `{}` is the path
+    /// (appended when absent), `{type}` and `{entropy}` its classification; no shell is involved
+    #[arg(long, env = "ENRO_EXEC", value_name = "CMD", value_parser = clap::value_parser!(exec::CommandTemplate), conflicts_with = "exec_batch")]
+    exec: Option<exec::CommandTemplate>,
+
+    /// Run a command once with all reported files as arguments, in place of a standalone `{}` or
+    /// at the end
+    #[arg(long, env = "ENRO_EXEC_BATCH", value_name = "CMD", value_parser = clap::value_parser!(exec::CommandTemplate))]
+    exec_batch: Option<exec::CommandTemplate>,
+
+    /// Write the reported files (after --only/--hide/--threshold) in this format instead of the
+    /// report: `stix` for a STIX 2.1 bundle of file objects with hashes and indicators
+    #[arg(long, env = "ENRO_EXPORT", value_enum, value_name = "FORMAT")]
+    export: Option<export::ExportFormat>,
+
+    /// Write the --export output to this file and show the usual report as well
+    #[arg(long, env = "ENRO_EXPORT_FILE", value_name = "FILE", requires = "export")]
+    export_file: Option<PathBuf>,
+
+    /// One-flag pipeline step: CSV report (or --json) without colors or progress bar, exit status 1
+    /// per --fail-on (default `encrypted,random` without --baseline), and GitHub Actions
+    /// annotations for the files that fail the run
+    #[arg(long, env = "ENRO_CI", value_parser = BoolishValueParser::new())]
+    ci: bool,
+
+    /// Archive format of data streamed on stdin when PATH is `-`; members are analyzed without touching disk
+    #[arg(long, env = "ENRO_STDIN_FORMAT", value_enum, value_name = "FORMAT")]
+    stdin_format: Option<stream::StdinFormat>,
+
+    // The device options deliberately have no ENRO_* variable: a value left in the environment
+    // must never start reading a raw disk, or skip the confirmation before it does.
+
+    /// Profile a raw block device or disk image: per-block entropy map and volume signatures. This
+    /// and the other device options (--allow-device, --yes, --offset, --length, --block-size) are
+    /// only taken from the command line, never from ENRO_* variables.
+    #[arg(long, value_name = "DEVICE", group = "raw_device")]
+    device: Option<PathBuf>,
+
+    /// Accept a block device as PATH and profile it like --device. It is only ever opened read-only.
+    #[arg(long, group = "raw_device")]
+    allow_device: bool,
+
+    /// Start reading the device at this byte offset (e.g. 1MiB)
+    #[arg(long, value_name = "SIZE", default_value = "0", value_parser = device::parse_size, requires = "raw_device")]
+    offset: u64,
+
+    /// Read at most this many bytes of the device (e.g. 10GiB)
+    #[arg(long, value_name = "SIZE", value_parser = device::parse_size, requires = "raw_device")]
+    length: Option<u64>,
+
+    /// Entropy profiling block size for --device
+    #[arg(long, value_name = "SIZE", default_value = "1MiB", value_parser = device::parse_size, requires = "raw_device")]
+    block_size: u64,
+
+    /// Read raw devices without asking for confirmation
+    #[arg(long, requires = "raw_device")]
+    yes: bool,
+}
+
+impl Args {
+    /// Directory walks follow symlinks unless --no-follow-symlinks is given last
+    fn follows_symlinks(&self) -> bool {
+        !self.no_follow_symlinks
+    }
+
+    /// Directory walks include hidden entries unless --no-hidden is given last
+    fn includes_hidden(&self) -> bool {
+        !self.no_hidden
+    }
+
+    /// Modification-time filter from --newer-than/--older-than
+    fn time_window(&self) -> filters::TimeWindow {
+        filters::TimeWindow {
+            newer_than: self.newer_than,
+            older_than: self.older_than,
+        }
+    }
+
+    /// Whether --only/--hide keep files of this category in the report
+    fn shows_category(&self, category: Category) -> bool {
+        (self.only.is_empty() || self.only.contains(&category)) && !self.hide.contains(&category)
+    }
+
+    /// Number of leading bytes to keep per file for preview/header output
+    fn header_capture_len(&self) -> usize {
+        self.preview
+            .unwrap_or(0)
+            .max(self.header_hex.unwrap_or(0) as usize)
+    }
+
+    /// Output meant for other programs: no banners, progress or colors
+    fn machine_readable(&self) -> bool {
+        self.simple || self.json || self.exports_to_stdout()
+    }
+
+    /// --export without --export-file takes the report's place
+    fn exports_to_stdout(&self) -> bool {
+        self.export.is_some() && self.export_file.is_none()
+    }
+
+    fn reads_stdin(&self) -> bool {
+        self.paths.len() == 1 && self.paths[0] == Path::new("-")
+    }
+
+    fn analyze_options(&self) -> AnalyzeOptions {
+        AnalyzeOptions {
+            max_bytes: self.max_bytes,
+            header_len: self.header_capture_len(),
+            ole_streams: self.ole_streams,
+            zip_entries: self.zip_entries,
+            stego: self.stego,
+            metadata: self.metadata,
+            git_objects: self.git_objects,
+            keep_going_io: self.keep_going_io,
+            hashes: {
+                let mut hashes = self.hash.clone();
+                let clustering = self.cluster.then_some(enro::hash::HashAlgorithm::Tlsh);
+                let exporting = self.export.map_or(&[][..], export::ExportFormat::hashes);
+                for algorithm in hashset::algorithms(&self.hashset)
+                    .chain(clustering)
+                    .chain(exporting.iter().copied())
+                {
+                    if !hashes.contains(&algorithm) {
+                        hashes.push(algorithm);
+                    }
+                }
+                hashes
+            },
+            sample: self.sample,
+            full_entropy: self.full_entropy,
+            detectors: self.detector.iter().map(|plugin| plugin.detector.clone()).collect(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-enum FileType {
-    Archive(String),
-    Document(String),
-    Image(String),
+/// Parse --throttle: a byte size per second, e.g. `50MB/s` or `1GiB`
+fn parse_rate(s: &str) -> Result<u64, String> {
+    let size = s.trim().strip_suffix("/s").unwrap_or(s);
+    match device::parse_size(size)? {
+        0 => Err("the rate must be more than 0".to_string()),
+        rate => Ok(rate),
+    }
+}
+
+/// Parse --rate: files per second, e.g. `200/s`, `0.5/s` or `200`
+fn parse_file_rate(s: &str) -> Result<f64, String> {
+    let count = s.trim().strip_suffix("/s").unwrap_or(s.trim());
+    match count.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        Ok(_) => Err("the rate must be more than 0".to_string()),
+        Err(_) => Err(format!("'{}' is not a number of files per second", s)),
+    }
+}
+
+/// Parse --sample: `head`, `tail` and `stride` parts joined by `+`, each with optional sizes
+fn parse_sample(s: &str) -> Result<enro::sample::Sampling, String> {
+    use enro::sample::{Sampling, DEFAULT_HEAD, DEFAULT_STRIPES, DEFAULT_STRIPE_LEN, DEFAULT_TAIL};
+    let mut sampling = Sampling {
+        head: DEFAULT_HEAD,
+        tail: 0,
+        stripes: 0,
+        stripe_len: 0,
+    };
+    for part in s.split('+') {
+        let (name, value) = match part.trim().split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (part.trim(), None),
+        };
+        match name.to_ascii_lowercase().as_str() {
+            "head" => sampling.head = value.map_or(Ok(DEFAULT_HEAD), device::parse_size)?,
+            "tail" => sampling.tail = value.map_or(Ok(DEFAULT_TAIL), device::parse_size)?,
+            "stride" => {
+                let (stripes, len) = match value.map(|v| v.split_once(['x', 'X']).unwrap_or((v, ""))) {
+                    Some((stripes, len)) => (
+                        stripes.parse().map_err(|_| format!("invalid stripe count: {}", stripes))?,
+                        if len.is_empty() { DEFAULT_STRIPE_LEN } else { device::parse_size(len)? },
+                    ),
+                    None => (DEFAULT_STRIPES, DEFAULT_STRIPE_LEN),
+                };
+                sampling.stripes = stripes;
+                sampling.stripe_len = len;
+            }
+            _ => return Err(format!("unknown sample part `{}`; expected head, tail or stride", name)),
+        }
+    }
+    if sampling.head == 0 {
+        return Err("the head cannot be empty: file types are detected from the first bytes".to_string());
+    }
+    Ok(sampling)
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Analyze files, directories and URLs (the default when no subcommand is given)
+    Scan(Box<Args>),
+    /// Keep watching paths and analyze files as they are created or modified
+    Watch(watch::WatchArgs),
+    /// Run statistical randomness tests on a file: chi-square, mean, Monte Carlo pi, serial correlation
+    VerifyRandom(randomness::VerifyRandomArgs),
+    /// Check that an --audit-log file has not been edited: every entry must chain to the one before
+    VerifyAuditLog(audit::VerifyAuditLogArgs),
+    /// Extract printable ASCII/UTF-16LE strings with offsets and per-string entropy
+    Strings(strings::StringsArgs),
+    /// Scan every blob reachable from any ref for encrypted/random content and secrets
+    GitScan(git_scan::GitScanArgs),
+    /// Check the files staged for commit for encrypted/random content and secrets (pre-commit hook)
+    GitStaged(git_scan::GitStagedArgs),
+    /// Scan Docker/OCI image layers, attributing findings to the layer that added them
+    Image(oci::ImageArgs),
+    /// Serve analysis requests from other local tools over a Unix domain socket or named pipe
+    Daemon(daemon::DaemonArgs),
+    /// Query the scan history recorded with --history: past scans, one file over time, category changes
+    History(history::HistoryArgs),
+    /// Compare two scans (JSON reports or history scan IDs): added, removed and reclassified files
+    Diff(diff::DiffArgs),
+    /// Summarize the scan history over a period as text or HTML: classification counts over time,
+    /// newly encrypted files and the fastest-growing directories
+    Report(report::ReportArgs),
+    /// Convert hash lists (NSRL, sha256sum output, one digest per line) to the compact binary
+    /// format --hashset loads fastest
+    Hashset(hashset::HashsetArgs),
+    /// Check every detector and statistic against a corpus generated in memory, e.g. after an upgrade
+    SelfTest(selftest::SelfTestArgs),
+    /// Browse a scan interactively: live progress, sorting, type/entropy filters, hex preview and
+    /// marking files for export
+    Tui(Box<tui::TuiArgs>),
+}
+
+/// Report orderings for --sort
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum SortKey {
+    /// By path, byte-wise
+    Path,
+    /// Highest entropy first
+    Entropy,
+    /// Largest first
+    Size,
+}
+
+/// Classification groups for --only/--hide, one per FileType variant
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Category {
+    Archive,
+    Document,
+    Image,
     Encrypted,
     Random,
-    PlainText,
+    Plaintext,
     Binary,
     Compressed,
+    Git,
 }
 
-impl FileType {
-    #[allow(dead_code)]
-    fn display(&self) -> String {
-        match self {
-            FileType::Archive(name) => format!("📦 Archive ({})", name),
-            FileType::Document(name) => format!("📄 Document ({})", name),
-            FileType::Image(name) => format!("🖼️  Image ({})", name),
-            FileType::Encrypted => "🔒 Encrypted".to_string(),
-            FileType::Random => "🎲 Random Data".to_string(),
-            FileType::PlainText => "📄 Plain Text".to_string(),
-            FileType::Binary => "⚙️  Binary".to_string(),
-            FileType::Compressed => "🗜️  Compressed".to_string(),
-        }
+fn category(file_type: &FileType) -> Category {
+    match file_type {
+        FileType::Archive(_) => Category::Archive,
+        FileType::Document(_) => Category::Document,
+        FileType::Image(_) => Category::Image,
+        FileType::Encrypted => Category::Encrypted,
+        FileType::Random => Category::Random,
+        FileType::PlainText => Category::Plaintext,
+        FileType::Binary => Category::Binary,
+        FileType::Compressed => Category::Compressed,
+        FileType::Git(_) => Category::Git,
     }
+}
+
+fn main() -> Result<ExitCode> {
+    clear_disabled_env_flags(&Cli::command());
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        None => return scan(cli.scan),
+        Some(Command::Scan(args)) => return scan(*args),
+        Some(Command::Watch(watch_args)) => watch::run(&watch_args),
+        Some(Command::VerifyRandom(verify_args)) => randomness::run(&verify_args),
+        Some(Command::VerifyAuditLog(verify_args)) => audit::verify(&verify_args),
+        Some(Command::Strings(strings_args)) => strings::run(&strings_args),
+        Some(Command::GitScan(scan_args)) => git_scan::run_scan(&scan_args),
+        Some(Command::GitStaged(staged_args)) => return git_scan::run_staged(&staged_args),
+        Some(Command::Image(image_args)) => oci::run(&image_args),
+        Some(Command::Daemon(daemon_args)) => daemon::run(&daemon_args),
+        Some(Command::History(history_args)) => history::run(&history_args),
+        Some(Command::Diff(diff_args)) => diff::run(&diff_args),
+        Some(Command::Report(report_args)) => report::run(&report_args),
+        Some(Command::Hashset(hashset_args)) => hashset::run(&hashset_args),
+        Some(Command::SelfTest(self_test_args)) => return selftest::run(&self_test_args),
+        Some(Command::Tui(tui_args)) => tui::run(&tui_args),
+    };
+    result.map(|()| ExitCode::SUCCESS)
+}
 
-    fn display_plain(&self) -> String {
-        match self {
-            FileType::Archive(name) => format!("Archive ({})", name),
-            FileType::Document(name) => format!("Document ({})", name),
-            FileType::Image(name) => format!("Image ({})", name),
-            FileType::Encrypted => "Encrypted".to_string(),
-            FileType::Random => "Random Data".to_string(),
-            FileType::PlainText => "Plain Text".to_string(),
-            FileType::Binary => "Binary".to_string(),
-            FileType::Compressed => "Compressed".to_string(),
+/// `ENRO_JSON=false` (or 0/no/off) leaves a flag off, but clap would still count the variable
+/// as given and report conflicts such as --simple with --json; such variables are dropped
+fn clear_disabled_env_flags(command: &clap::Command) {
+    for arg in command.get_arguments() {
+        let Some(name) = arg.get_env().filter(|_| matches!(arg.get_action(), ArgAction::SetTrue)) else {
+            continue;
+        };
+        let disabled = std::env::var(name).is_ok_and(|value| {
+            matches!(value.trim().to_ascii_lowercase().as_str(), "" | "0" | "false" | "no" | "n" | "off" | "f")
+        });
+        if disabled {
+            std::env::remove_var(name);
         }
     }
+    for subcommand in command.get_subcommands() {
+        clear_disabled_env_flags(subcommand);
+    }
 }
 
-struct FileAnalysis {
-    path: PathBuf,
-    file_type: FileType,
-    entropy: f64,
-    size: u64,
-}
+fn scan(mut args: Args) -> Result<ExitCode> {
+    if let Some(profile) = args.profile {
+        profile.apply(&mut args);
+    }
+    if args.ci {
+        ci::apply(&mut args);
+    }
+    let args = &args;
+    if args.exports_to_stdout() && (args.simple || args.json) {
+        anyhow::bail!(
+            "--export replaces the report on standard output; add --export-file to keep the --simple or --json report"
+        );
+    }
+    if args.stream && !(args.simple || args.json) {
+        anyhow::bail!("--stream prints --simple rows or --json lines; add one of them");
+    }
+    if args.dry_run && (args.reads_stdin() || args.device.is_some()) {
+        anyhow::bail!("--dry-run only walks directories; stdin and devices cannot be enumerated without reading them");
+    }
+    // Before any threads start, so they all inherit it
+    if args.nice {
+        enro::platform::lower_priority().context("Failed to lower priority for --nice")?;
+    }
+    if let Some(rate) = args.throttle {
+        enro::throttle::set_rate(rate);
+    }
+    if let Some(rate) = args.rate {
+        enro::throttle::set_file_rate(rate);
+    }
+    enro::pagecache::set_drop_behind(args.no_cache_pollution);
+    // Opened up front so a bad --syslog-server fails before the scan, not after it
+    let event_log = eventlog::EventLog::open(args.syslog, args.syslog_server.as_deref(), args.journald)?;
+    let audit_log = args.audit_log.as_deref().map(audit::AuditLog::open).transpose()?;
+    let baseline = args.baseline.as_deref().map(baseline::Baseline::load).transpose()?;
+
+    if let Some(device) = &args.device {
+        // Not a clap conflict: that would let --offset/--length slip through without --device
+        if !args.paths.is_empty() {
+            anyhow::bail!("--device cannot be combined with a PATH argument");
+        }
+        return device::run(device, args).map(|()| ExitCode::SUCCESS);
+    }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+    // Whole disks are profiled block by block rather than read as one huge file
+    if let Some(device) = args.paths.iter().find(|p| device::is_block_device(p)) {
+        if !args.allow_device {
+            anyhow::bail!("{} is a block device; pass --allow-device to profile it", device.display());
+        }
+        if args.paths.len() > 1 {
+            anyhow::bail!("A block device must be the only PATH");
+        }
+        return device::run(device, args).map(|()| ExitCode::SUCCESS);
+    }
 
     // Configure thread pool if specified
     if let Some(threads) = args.threads {
@@ -119,21 +853,606 @@ fn main() -> Result<()> {
             .build_global()
             .context("Failed to set thread count")?;
     }
+    if let Some(max_memory) = args.max_memory {
+        if max_memory == 0 {
+            anyhow::bail!("--max-memory must be more than 0");
+        }
+        enro::memory::set_limit(usize::try_from(max_memory).unwrap_or(usize::MAX));
+    }
+    if let Some(chunk_size) = args.chunk_size {
+        // Format detection needs the first 64KiB in one piece
+        if chunk_size < 64 * 1024 {
+            anyhow::bail!("--chunk-size must be at least 64KiB");
+        }
+        enro::set_chunk_size(usize::try_from(chunk_size).unwrap_or(usize::MAX));
+    }
+    enro::tuning::set_enabled(args.tune);
+    let range = entropy_range(args);
+    let streamer = args.stream.then_some(Streamer { args, range });
+    if let Some(streamer) = &streamer {
+        streamer.start();
+    }
 
-    let files = collect_files(&args)?;
+    let (results, mut skipped, errors, estimate) = if args.reads_stdin() {
+        let format = args
+            .stdin_format
+            .context("Reading an archive from stdin requires --stdin-format")?;
+        let results = stream::analyze_stdin(format, &args.analyze_options(), args.min_size)?;
+        if let Some(streamer) = &streamer {
+            results.iter().for_each(|analysis| streamer.file(analysis));
+        }
+        if results.is_empty() {
+            if !args.machine_readable() {
+                println!("{}", "No files to analyze.".yellow());
+            }
+            if let Some(audit_log) = &audit_log {
+                audit_log.record(&args.paths, &[], &walk::Skipped::default(), 0, false)?;
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+        (results, walk::Skipped::default(), Vec::new(), None)
+    } else {
+        let urls: Vec<&str> = args
+            .paths
+            .iter()
+            .filter(|p| remote::is_url(p))
+            .filter_map(|p| p.to_str())
+            .collect();
+        let content_cache = if args.content_cache {
+            let path = args.content_cache_file.clone().unwrap_or_else(content_cache::default_path);
+            Some(content_cache::ContentCache::load(&path, &args.analyze_options())?)
+        } else {
+            None
+        };
+        // Files found before --sample-files picked some of them
+        let mut population = None;
+        let checkpoint::Checkpoint {
+            files,
+            mut skipped,
+            mut results,
+            mut errors,
+        } = if args.eager {
+            if !args.machine_readable() {
+                println!("Analyzing files as they are found...\n");
+            }
+            let found = analyze_found_files(args, content_cache.as_ref(), streamer.as_ref())?;
+            if found.files.is_empty() && urls.is_empty() {
+                if !args.machine_readable() {
+                    println!("{}", "No files to analyze.".yellow());
+                }
+                if let Some(audit_log) = &audit_log {
+                    audit_log.record(&args.paths, &[], &found.skipped, 0, false)?;
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+            found
+        } else {
+            // A resumed scan takes its file list and finished work from the checkpoint
+            let mut state = match &args.resume {
+                Some(path) => checkpoint::Checkpoint::load(path)?,
+                None => {
+                    let (mut files, skipped) = walk::collect_files(args)?;
+                    if let Some(size) = args.sample_files {
+                        population = Some(files.len());
+                        files = estimate::draw(files, size, args.sample_seed);
+                    }
+                    checkpoint::Checkpoint {
+                        files,
+                        skipped,
+                        ..Default::default()
+                    }
+                }
+            };
+            let mut cache = if args.incremental {
+                let path = args.cache_file.clone().unwrap_or_else(incremental::default_path);
+                Some(incremental::ResultCache::load(&path, &args.analyze_options())?)
+            } else {
+                None
+            };
+            let (unchanged, remaining) = match &mut cache {
+                Some(cache) => cache.split(state.remaining()),
+                None => (Vec::new(), state.remaining()),
+            };
+
+            if args.dry_run {
+                display_dry_run(&remaining, &urls, unchanged.len(), &state.skipped, args);
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            if state.files.is_empty() && urls.is_empty() {
+                if !args.machine_readable() {
+                    println!("{}", "No files to analyze.".yellow());
+                }
+                if let Some(audit_log) = &audit_log {
+                    audit_log.record(&args.paths, &[], &state.skipped, 0, false)?;
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            if !args.machine_readable() {
+                if args.resume.is_some() {
+                    println!("Resuming: {} of {} file(s) left to analyze...\n", remaining.len(), state.files.len());
+                } else if let Some(population) = population {
+                    println!("Analyzing a random sample of {} of {} file(s)...\n", state.files.len(), population);
+                } else {
+                    let total = state.files.len() + urls.len();
+                    if unchanged.is_empty() {
+                        println!("Analyzing {} file(s)...\n", total);
+                    } else {
+                        println!("Analyzing {} file(s), {} unchanged since the last scan...\n", total, unchanged.len());
+                    }
+                }
+            }
+
+            state.results.extend(unchanged);
+            // Known before anything is read: results from before a resume and unchanged files
+            if let Some(streamer) = &streamer {
+                state.results.iter().for_each(|analysis| streamer.file(analysis));
+                state.errors.iter().for_each(|error| streamer.error(error));
+            }
+            let checkpointer = match args.checkpoint.as_deref().or(args.resume.as_deref()) {
+                Some(path) => Some(checkpoint::Checkpointer::new(path, std::mem::take(&mut state))?),
+                None => None,
+            };
+            let (new_results, new_errors) =
+                analyze_files(&remaining, args, checkpointer.as_ref(), content_cache.as_ref(), streamer.as_ref());
+            if let Some(cache) = cache {
+                if let Err(e) = cache.save(&new_results) {
+                    eprintln!("Warning: {:#}", e);
+                }
+            }
+            // The checkpointer has its own copy of every result, including those from before a resume
+            match checkpointer {
+                Some(checkpointer) => checkpointer.finish(),
+                None => {
+                    state.results.extend(new_results);
+                    state.errors.extend(new_errors);
+                    state
+                }
+            }
+        };
+        if let Some(cache) = content_cache {
+            if cache.hits() > 0 && !args.machine_readable() {
+                println!("{} file(s) had content analyzed before; their results were reused.\n", cache.hits());
+            }
+            if let Err(e) = cache.save() {
+                eprintln!("Warning: {:#}", e);
+            }
+        }
+        if args.tune && !args.machine_readable() {
+            display_tuning(&enro::tuning::devices());
+        }
+        errors.sort_by(|a, b| a.path.cmp(&b.path));
+        for error in &errors {
+            let reason = if error.denied {
+                walk::SkipReason::Denied
+            } else {
+                walk::SkipReason::Unreadable
+            };
+            skipped.add(reason, &error.path);
+        }
+        // Only files stand for the population; attributes and URLs were not sampled
+        let estimate = population.map(|population| estimate::Estimate::new(&results, population));
+        let analyzed = results.len();
+        if args.xattrs {
+            results.extend(xattrs::analyze_all(&files, &args.analyze_options()));
+        }
+        results.extend(remote::analyze_urls(&urls, args));
+        if let Some(streamer) = &streamer {
+            results[analyzed..].iter().for_each(|analysis| streamer.file(analysis));
+        }
+        (results, skipped, errors, estimate)
+    };
+
+    // Content hashing needs the files on disk
+    let duplicates = if (args.dedupe || args.duplicates) && !args.reads_stdin() {
+        dedup::find_duplicates(&results)
+    } else {
+        Vec::new()
+    };
+    let results = if args.dedupe {
+        dedup::collapse(results, &duplicates)
+    } else {
+        results
+    };
+
+    let results = if args.no_group_volumes {
+        results
+    } else {
+        volumes::group_volumes(results)
+    };
+    let results = hashset::apply(&args.hashset, results, &mut skipped);
+    let clusters = if args.cluster {
+        cluster::find_clusters(&results, args.cluster_distance)
+    } else {
+        Vec::new()
+    };
+
+    // Alerts cover everything analyzed, whatever the report filters below leave out
+    if let Some(url) = &args.alert_webhook {
+        alerts::Alerter::new(url, args.alert_ratio).notify(&results);
+    }
+    if let Some(event_log) = &event_log {
+        event_log.findings(&results);
+        event_log.summary(&results, &skipped, errors.len());
+    }
+    let history_scan = match &args.history {
+        Some(db) => Some(history::History::open(db)?.record(&args.paths, &results)?),
+        None => None,
+    };
+    let new_findings = baseline.map(|baseline| baseline.new_findings(&results, &args.fail_on_new));
+    let violations = policy::violations(&results, &args.fail_on);
+    let failed = !violations.is_empty() || new_findings.as_ref().is_some_and(|findings| !findings.is_empty());
+    if let Some(audit_log) = &audit_log {
+        audit_log.record(&args.paths, &results, &skipped, errors.len(), failed)?;
+    }
+
+    let mut filtered_results: Vec<FileAnalysis> = results.into_iter().filter(|r| shown(r, range, args)).collect();
+
+    // Ties fall back to the path so equal keys never depend on thread scheduling
+    match args.sort {
+        Some(SortKey::Path) => filtered_results.sort_by(|a, b| a.path.cmp(&b.path)),
+        Some(SortKey::Entropy) => filtered_results.sort_by(|a, b| b.entropy.total_cmp(&a.entropy).then_with(|| a.path.cmp(&b.path))),
+        Some(SortKey::Size) => filtered_results.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path))),
+        None => {}
+    }
+
+    if let (Some(format), None) = (args.export, &args.export_file) {
+        export::write(format, &filtered_results, &mut std::io::stdout().lock())?;
+    } else if args.json {
+        display_json(&filtered_results, &skipped, &errors, &duplicates, &clusters, estimate.as_ref(), args);
+    } else if args.simple {
+        // --stream printed the rows as the files were analyzed
+        if !args.stream {
+            display_simple(&filtered_results, args);
+        }
+    } else if args.summary_only {
+        display_summary_only(&filtered_results, &skipped);
+    } else {
+        display_results(&filtered_results, &skipped, args);
+        if args.ole_streams {
+            display_streams(&filtered_results);
+        }
+        if args.zip_entries {
+            display_zip_entries(&filtered_results);
+        }
+        if args.metadata {
+            display_metadata(&filtered_results);
+        }
+        if !args.hash.is_empty() {
+            display_hashes(&filtered_results);
+        }
+        if let Some(preview) = args.preview.filter(|&n| n > 0) {
+            display_previews(&filtered_results, preview);
+        }
+    }
+    if let Some(estimate) = estimate.as_ref().filter(|_| !args.machine_readable()) {
+        display_estimate(estimate);
+    }
+    if args.duplicates && !args.machine_readable() {
+        display_duplicates(&duplicates);
+    }
+    if args.cluster && !args.machine_readable() {
+        display_clusters(&clusters);
+    }
+    if args.show_skipped && !args.machine_readable() {
+        display_skipped(&skipped);
+    }
+    if let (Some(format), Some(path)) = (args.export, &args.export_file) {
+        export::write_file(format, &filtered_results, path)?;
+        if !args.machine_readable() {
+            println!(
+                "  {} {}\n",
+                "•".cyan(),
+                format!("Exported {} file(s) to {}", filtered_results.len(), path.display()).bold()
+            );
+        }
+    }
+    // After the report, which may still read the files (--preview), and before --action moves them
+    if let Some(command) = &args.exec {
+        command.run_each(&filtered_results, args.machine_readable())?;
+    }
+    if let Some(command) = &args.exec_batch {
+        command.run_batch(&filtered_results, args.machine_readable())?;
+    }
+    if let Some(action) = &args.action {
+        action.apply(&filtered_results, args.machine_readable())?;
+    }
+    if !args.machine_readable() {
+        display_errors(&errors);
+        if let (Some(id), Some(db)) = (history_scan, &args.history) {
+            println!("\n  {} Recorded as scan {} in {}", "•".cyan(), id, db.display());
+        }
+    }
+    if args.ci {
+        ci::annotate(&violations, new_findings.as_deref());
+    } else {
+        if let (Some(findings), Some(path)) = (&new_findings, &args.baseline) {
+            baseline::report(findings, path, args.machine_readable());
+        }
+        policy::report(&violations, args.machine_readable());
+    }
+
+    Ok(if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS })
+}
+
+/// The --threshold range, if one was given and parses
+fn entropy_range(args: &Args) -> Option<(f64, f64)> {
+    args.threshold.as_ref().and_then(|threshold| {
+        let range = threshold
+            .split_once('-')
+            .and_then(|(min_str, max_str)| Some((min_str.parse::<f64>().ok()?, max_str.parse::<f64>().ok()?)));
+        if range.is_none() {
+            eprintln!("Warning: Invalid threshold format. Expected format: min-max (e.g., 7.5-8.0)");
+        }
+        range
+    })
+}
+
+/// Whether the report keeps a result: its entropy is in range and its classification is shown;
+/// --invert-match keeps exactly the files these filters reject
+fn shown(analysis: &FileAnalysis, range: Option<(f64, f64)>, args: &Args) -> bool {
+    let in_range = range.is_none_or(|(min, max)| analysis.entropy >= min && analysis.entropy <= max);
+    (in_range && args.shows_category(category(&analysis.file_type))) != args.invert_match
+}
+
+/// --stream: prints each result as soon as it is known, filtered like the report at the end
+struct Streamer<'a> {
+    args: &'a Args,
+    range: Option<(f64, f64)>,
+}
 
-    if files.is_empty() {
-        if !args.simple {
-            println!("{}", "No files to analyze.".yellow());
+impl Streamer<'_> {
+    /// The --simple header, before any row
+    fn start(&self) {
+        if self.args.simple {
+            self.print(simple_header(self.args));
         }
-        return Ok(());
     }
 
-    if !args.simple {
-        println!("Analyzing {} file(s)...\n", files.len());
+    fn emit(&self, result: &Result<FileAnalysis, FileError>) {
+        match result {
+            Ok(analysis) => self.file(analysis),
+            Err(error) => self.error(error),
+        }
+    }
+
+    fn file(&self, analysis: &FileAnalysis) {
+        let mut analysis = std::borrow::Cow::Borrowed(analysis);
+        if !self.args.hashset.is_empty() && !hashset::annotate(&self.args.hashset, analysis.to_mut()) {
+            return;
+        }
+        if !shown(&analysis, self.range, self.args) {
+            return;
+        }
+        if self.args.json {
+            self.print(json_entry(&analysis, self.args));
+        } else {
+            self.print(simple_row(&analysis, self.args));
+        }
+    }
+
+    fn error(&self, error: &FileError) {
+        if self.args.json_errors {
+            self.print(json_error(error));
+        }
+    }
+
+    /// One line, whole, whichever worker it comes from. Once the reader has gone away (`| head`)
+    /// nothing further can be shown, so the scan ends there; a checkpoint is left to resume from.
+    fn print(&self, line: impl Display) {
+        if let Err(e) = writeln!(io::stdout().lock(), "{}", line) {
+            if e.kind() == io::ErrorKind::BrokenPipe {
+                std::process::exit(0);
+            }
+            eprintln!("Warning: Failed to write to stdout: {}", e);
+        }
     }
+}
+
+// How often the progress bar picks up the workers' counters
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+// Files --eager lets the walk get ahead of the workers by
+const FOUND_QUEUE: usize = 4096;
+
+/// A file that was found but could not be analyzed
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct FileError {
+    path: PathBuf,
+    message: String,
+    denied: bool,
+}
+
+/// Analyze files in parallel with a progress bar
+fn analyze_files(
+    files: &[PathBuf],
+    args: &Args,
+    checkpointer: Option<&checkpoint::Checkpointer>,
+    content_cache: Option<&content_cache::ContentCache>,
+    streamer: Option<&Streamer>,
+) -> (Vec<FileAnalysis>, Vec<FileError>) {
+    // Progress counts bytes, so one huge file among many small ones does not throw off the ETA
+    let options = args.analyze_options();
+    let weights: Vec<u64> = files
+        .par_iter()
+        .map(|path| std::fs::metadata(path).map_or(0, |m| options.read_len(m.len())))
+        .collect();
+    let pb = progress_bar(weights.iter().sum(), args);
+
+    // Largest files first and smaller ones around them, so the scan does not end with one worker
+    // still reading a huge file while the others sit idle
+    let mut order: Vec<usize> = (0..files.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(weights[i]));
+
+    // Workers only bump these; a separate thread copies them to the bar, so no lock is taken per file
+    let files_done = AtomicUsize::new(0);
+    let bytes_done = AtomicU64::new(0);
+    let redraw = || {
+        pb.set_position(bytes_done.load(Ordering::Relaxed));
+        pb.set_prefix(format!("{}/{} files", files_done.load(Ordering::Relaxed), files.len()));
+    };
+    redraw();
 
-    let pb = ProgressBar::new(files.len() as u64);
+    // Use parallel processing with rayon
+    let (results, mut errors): (Vec<FileAnalysis>, Vec<FileError>) = std::thread::scope(|scope| {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let redraw = &redraw;
+        scope.spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(PROGRESS_INTERVAL) {
+                redraw();
+            }
+        });
+        let mut analyzed: Vec<(usize, Result<FileAnalysis, FileError>)> = order
+            .iter()
+            // Hands out files in order as workers free up; par_iter would split the list up front
+            .par_bridge()
+            .map(|&i| {
+                let result = analyze_one(&files[i], &options, content_cache);
+                if let Some(checkpointer) = checkpointer {
+                    checkpointer.record(&result);
+                }
+                if let Some(streamer) = streamer {
+                    pb.suspend(|| streamer.emit(&result));
+                }
+                bytes_done.fetch_add(weights[i], Ordering::Relaxed);
+                files_done.fetch_add(1, Ordering::Relaxed);
+
+                (i, result)
+            })
+            .collect();
+        drop(stop);
+        analyzed.sort_unstable_by_key(|&(i, _)| i);
+        analyzed.into_par_iter().map(|(_, result)| result).partition_map(|result| match result {
+            Ok(analysis) => rayon::iter::Either::Left(analysis),
+            Err(error) => rayon::iter::Either::Right(error),
+        })
+    });
+    redraw();
+    errors.sort_by(|a, b| a.path.cmp(&b.path));
+    finish_progress(&pb, args);
+
+    (results, errors)
+}
+
+/// --eager: analyze files while the walk is still finding them. The finished scan lists its files
+/// and results in the order `walk::collect_files` would have.
+fn analyze_found_files(
+    args: &Args,
+    content_cache: Option<&content_cache::ContentCache>,
+    streamer: Option<&Streamer>,
+) -> Result<checkpoint::Checkpoint> {
+    let options = args.analyze_options();
+    let pb = progress_bar(0, args);
+
+    // The walk adds to the found counts and workers to the done ones; the bar grows with the walk
+    let files_found = AtomicUsize::new(0);
+    let bytes_found = AtomicU64::new(0);
+    let files_done = AtomicUsize::new(0);
+    let bytes_done = AtomicU64::new(0);
+    let redraw = || {
+        pb.set_length(bytes_found.load(Ordering::Relaxed));
+        pb.set_position(bytes_done.load(Ordering::Relaxed));
+        pb.set_prefix(format!(
+            "{}/{} files",
+            files_done.load(Ordering::Relaxed),
+            files_found.load(Ordering::Relaxed)
+        ));
+    };
+    redraw();
+
+    let (walked, mut analyzed) = std::thread::scope(|scope| {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let redraw = &redraw;
+        scope.spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(PROGRESS_INTERVAL) {
+                redraw();
+            }
+        });
+        // Bounded, so a walk far ahead of the workers waits instead of queueing the whole tree
+        let (found, to_analyze) = mpsc::sync_channel::<(usize, PathBuf, u64)>(FOUND_QUEUE);
+        let (files_found, bytes_found, options) = (&files_found, &bytes_found, &options);
+        let walk = scope.spawn(move || {
+            walk::stream_files(args, &|group, path| {
+                let weight = std::fs::metadata(&path).map_or(0, |m| options.read_len(m.len()));
+                bytes_found.fetch_add(weight, Ordering::Relaxed);
+                files_found.fetch_add(1, Ordering::Relaxed);
+                // Fails only if the workers are gone, and then nothing more is analyzed anyway
+                let _ = found.send((group, path, weight));
+            })
+        });
+        let analyzed: Vec<(usize, Result<FileAnalysis, FileError>)> = to_analyze
+            .into_iter()
+            .par_bridge()
+            .map(|(group, path, weight)| {
+                let result = analyze_one(&path, options, content_cache);
+                if let Some(streamer) = streamer {
+                    pb.suspend(|| streamer.emit(&result));
+                }
+                bytes_done.fetch_add(weight, Ordering::Relaxed);
+                files_done.fetch_add(1, Ordering::Relaxed);
+                (group, result)
+            })
+            .collect();
+        let walked = walk.join().expect("walk thread panicked");
+        drop(stop);
+        (walked, analyzed)
+    });
+    redraw();
+    let skipped = match walked {
+        Ok(skipped) => skipped,
+        Err(e) => {
+            pb.finish_and_clear();
+            return Err(e);
+        }
+    };
+    finish_progress(&pb, args);
+
+    // Files under one PATH argument arrive in walk order; collect_files sorts them
+    let path_of = |result: &Result<FileAnalysis, FileError>| match result {
+        Ok(analysis) => analysis.path.clone(),
+        Err(error) => error.path.clone(),
+    };
+    analyzed.par_sort_unstable_by_key(|(group, result)| (*group, path_of(result)));
+    let files = analyzed.iter().map(|(_, result)| path_of(result)).collect();
+    let (results, errors) = analyzed.into_par_iter().map(|(_, result)| result).partition_map(|result| match result {
+        Ok(analysis) => rayon::iter::Either::Left(analysis),
+        Err(error) => rayon::iter::Either::Right(error),
+    });
+    Ok(checkpoint::Checkpoint {
+        files,
+        skipped,
+        results,
+        errors,
+    })
+}
+
+fn analyze_one(
+    file_path: &Path,
+    options: &AnalyzeOptions,
+    content_cache: Option<&content_cache::ContentCache>,
+) -> Result<FileAnalysis, FileError> {
+    enro::throttle::pace_file();
+    let analysis = match content_cache {
+        Some(cache) => cache.analyze(file_path, options),
+        None => analyze_file(file_path, options),
+    };
+    analysis.map_err(|e| FileError {
+        path: file_path.to_path_buf(),
+        message: format!("{:#}", e),
+        denied: e
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|io| io.kind() == std::io::ErrorKind::PermissionDenied),
+    })
+}
+
+/// Progress bar over `len` bytes, hidden in CI
+fn progress_bar(len: u64, args: &Args) -> ProgressBar {
+    let pb = if args.ci {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(len)
+    };
     
     // Get terminal width and calculate bar width
     let term_width = if let Some((terminal_size::Width(w), _)) = terminal_size::terminal_size() {
@@ -142,9 +1461,9 @@ fn main() -> Result<()> {
         80 // Default width if unable to detect
     };
     
-    // Reserve space for other elements: spinner (2) + brackets (2) + elapsed (18) + pos/len (15) + eta (10) + padding (10) = ~57
-    let bar_width = if term_width > 60 {
-        (term_width - 60).min(40)
+    // Reserve space for other elements: spinner (2) + brackets (2) + elapsed (18) + files (16) + bytes (22) + eta (10) + padding (10) = ~80
+    let bar_width = if term_width > 80 {
+        (term_width - 80).min(40)
     } else {
         20 // Minimum bar width
     };
@@ -152,462 +1471,622 @@ fn main() -> Result<()> {
     pb.set_style(
         ProgressStyle::default_bar()
             .template(&format!(
-                "{{spinner:.green}} [{{elapsed_precise}}] [{{bar:{}.cyan/blue}}] {{pos}}/{{len}} ({{eta}})",
+                "{{spinner:.green}} [{{elapsed_precise}}] [{{bar:{}.cyan/blue}}] {{prefix}} {{bytes}}/{{total_bytes}} ({{eta}})",
                 bar_width
             ))
             .unwrap()
             .progress_chars("#>-"),
     );
+    pb
+}
 
-    // Use parallel processing with rayon
-    let pb_mutex = Mutex::new(&pb);
-    let results: Vec<FileAnalysis> = files
-        .par_iter()
-        .filter_map(|file_path| {
-            if let Ok(pb_guard) = pb_mutex.lock() {
-                pb_guard.set_message(format!("{}", file_path.display()));
-            }
-            
-            let result = analyze_file(file_path, args.max_bytes).ok();
-            
-            if let Ok(pb_guard) = pb_mutex.lock() {
-                pb_guard.inc(1);
-            }
-            
-            result
-        })
-        .collect();
-
-    if !args.simple {
+fn finish_progress(pb: &ProgressBar, args: &Args) {
+    if !args.machine_readable() {
         pb.finish_with_message("Analysis complete!");
     } else {
         pb.finish_and_clear();
     }
+}
 
-    // Filter by entropy threshold if provided
-    let filtered_results = if let Some(threshold) = &args.threshold {
-        if let Some((min_str, max_str)) = threshold.split_once('-') {
-            if let (Ok(min), Ok(max)) = (min_str.parse::<f64>(), max_str.parse::<f64>()) {
-                results.into_iter()
-                    .filter(|r| r.entropy >= min && r.entropy <= max)
-                    .collect()
-            } else {
-                eprintln!("Warning: Invalid threshold format. Expected format: min-max (e.g., 7.5-8.0)");
-                results
-            }
-        } else {
-            eprintln!("Warning: Invalid threshold format. Expected format: min-max (e.g., 7.5-8.0)");
-            results
-        }
+// Show paths relative to the current directory when possible
+fn display_path(path: &Path) -> String {
+    if let Ok(cwd) = std::env::current_dir() {
+        path.strip_prefix(&cwd).unwrap_or(path).display().to_string()
     } else {
-        results
-    };
+        path.display().to_string()
+    }
+}
 
-    if args.simple {
-        display_simple(&filtered_results);
-    } else if args.summary_only {
-        display_summary_only(&filtered_results);
+fn escape_csv(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') || s.contains('\r') {
+        format!("\"{}\"", s.replace('"', "\"\""))
     } else {
-        display_results(&filtered_results);
+        s.to_string()
     }
-
-    Ok(())
 }
 
-fn collect_files(args: &Args) -> Result<Vec<PathBuf>> {
-    let mut files = Vec::new();
-
-    if args.path.is_file() {
-        files.push(args.path.clone());
-    } else if args.path.is_dir() {
-        if args.recursive {
-            for entry in WalkDir::new(&args.path)
-                .follow_links(true)
-                .into_iter()
-                .filter_map(|e| e.ok())
-            {
-                if entry.file_type().is_file() {
-                    if let Ok(metadata) = entry.metadata() {
-                        if metadata.len() >= args.min_size {
-                            files.push(entry.path().to_path_buf());
-                        }
-                    }
-                }
-            }
-        } else {
-            for entry in fs::read_dir(&args.path)? {
-                let entry = entry?;
-                if entry.file_type()?.is_file() {
-                    if let Ok(metadata) = entry.metadata() {
-                        if metadata.len() >= args.min_size {
-                            files.push(entry.path());
-                        }
-                    }
-                }
-            }
-        }
-    } else {
-        anyhow::bail!("Path does not exist: {}", args.path.display());
+fn display_simple(results: &[FileAnalysis], args: &Args) {
+    println!("{}", simple_header(args));
+    for analysis in results {
+        println!("{}", simple_row(analysis, args));
     }
+}
 
-    Ok(files)
+fn simple_header(args: &Args) -> String {
+    let mut header = String::from("Path,Type,Entropy,Size");
+    if args.header_hex.is_some() {
+        header.push_str(",Header");
+    }
+    if args.preview.is_some_and(|n| n > 0) {
+        header.push_str(",Preview");
+    }
+    for algorithm in &args.hash {
+        header.push(',');
+        header.push_str(&algorithm.key().to_uppercase());
+    }
+    header
 }
 
-// Calculate optimal chunk size based on available RAM and thread count
-fn get_optimal_chunk_size() -> usize {
-    static CHUNK_SIZE: OnceLock<usize> = OnceLock::new();
-    
-    *CHUNK_SIZE.get_or_init(|| {
-        let mut sys = System::new_all();
-        sys.refresh_memory();
-        
-        let available_ram = sys.available_memory() as usize;
-        let thread_count = rayon::current_num_threads();
-        
-        // Calculate: available_ram / (threads + 2), capped at 1GB
-        let divisor = (thread_count * 2).max(4); // Ensure at least 4 to avoid too large chunks
-        let chunk_size = available_ram / divisor;
-        const MAX_CHUNK: usize = 1024 * 1024 * 1024; // 1GB
-        const MIN_CHUNK: usize = 1024 * 1024; // 1MB minimum
-        
-        chunk_size.min(MAX_CHUNK).max(MIN_CHUNK)
-    })
+fn simple_row(analysis: &FileAnalysis, args: &Args) -> String {
+    let file_path = display_path(&analysis.path);
+
+    let type_str = analysis.type_label(analysis.file_type.display_compact());
+
+    let mut row = format!(
+        "{},{},{:.2},{}",
+        escape_csv(&file_path),
+        escape_csv(&type_str),
+        analysis.entropy,
+        analysis.size
+    );
+    if let Some(n) = args.header_hex {
+        row.push(',');
+        row.push_str(&format_hex(leading_bytes(&analysis.header, n as usize)));
+    }
+    if let Some(n) = args.preview.filter(|&n| n > 0) {
+        row.push(',');
+        row.push_str(&format_hex(leading_bytes(&analysis.header, n)));
+    }
+    for algorithm in &args.hash {
+        row.push(',');
+        row.push_str(digest(analysis, *algorithm).unwrap_or_default());
+    }
+    row
 }
 
-fn analyze_file(path: &Path, max_bytes: Option<usize>) -> Result<FileAnalysis> {
-    let metadata = fs::metadata(path).context("Failed to read file metadata")?;
-    let size = metadata.len();
+fn display_json(
+    results: &[FileAnalysis],
+    skipped: &walk::Skipped,
+    errors: &[FileError],
+    duplicates: &[dedup::DuplicateGroup],
+    clusters: &[cluster::Cluster],
+    estimate: Option<&estimate::Estimate>,
+    args: &Args,
+) {
+    let mut type_counts = std::collections::BTreeMap::new();
+    for analysis in results {
+        *type_counts.entry(analysis.file_type.display_compact()).or_insert(0u64) += 1;
+    }
+    let avg_entropy = if results.is_empty() {
+        0.0
+    } else {
+        results.iter().map(|a| a.entropy).sum::<f64>() / results.len() as f64
+    };
 
-    let mut file = File::open(path).context("Failed to open file")?;
-    
-    // Use dynamically calculated chunk size
-    let chunk_size = get_optimal_chunk_size();
-    
-    let bytes_to_read = if let Some(max) = max_bytes {
-        max.min(size as usize)
+    let summary = serde_json::json!({
+        "total_files": results.len(),
+        "average_entropy": (avg_entropy * 10000.0).round() / 10000.0,
+        "high_entropy_files": results.iter().filter(|a| a.entropy > 7.5).count(),
+        "known_bad_files": results.iter().filter(|a| hashset::is_known_bad(a)).count(),
+        "types": type_counts,
+        "skipped": walk::SkipReason::ALL
+            .iter()
+            .map(|&reason| (reason.key().to_string(), skipped.count(reason).into()))
+            .collect::<serde_json::Map<_, _>>(),
+    });
+    // --stream printed the files as they were analyzed
+    let mut report = if args.stream {
+        serde_json::json!({ "summary": summary })
     } else {
-        size as usize // Read entire file
+        let mut files: Vec<serde_json::Value> = results.iter().map(|analysis| json_entry(analysis, args)).collect();
+        if args.json_errors {
+            files.extend(errors.iter().map(json_error));
+        }
+        serde_json::json!({ "files": files, "summary": summary })
     };
-    
-    // For small files, read all at once
-    if bytes_to_read <= chunk_size {
-        let mut buffer = vec![0u8; bytes_to_read];
-        let bytes_read = file.read(&mut buffer).context("Failed to read file")?;
-        buffer.truncate(bytes_read);
-        
-        let file_type = detect_file_type(&buffer);
-        let entropy = calculate_entropy(&buffer);
-        
-        return Ok(FileAnalysis {
-            path: path.to_path_buf(),
-            file_type,
-            entropy,
-            size,
+    if args.duplicates {
+        report["duplicates"] = duplicates
+            .iter()
+            .map(|group| {
+                serde_json::json!({
+                    "blake3": group.hash.to_hex().as_str(),
+                    "size": group.size,
+                    "wasted": group.wasted(),
+                    "paths": group.paths.iter().map(|p| display_path(p)).collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+    }
+    if let Some(estimate) = estimate {
+        let interval = |interval: estimate::Interval, scale: f64| {
+            let round = |v: f64| (v * scale).round() / scale;
+            serde_json::json!({ "estimate": round(interval.estimate), "low": round(interval.low), "high": round(interval.high) })
+        };
+        report["estimate"] = serde_json::json!({
+            "population_files": estimate.population,
+            "sampled_files": estimate.sampled,
+            "confidence": estimate::CONFIDENCE,
+            "total_bytes": interval(estimate.total_bytes, 1.0),
+            "categories": estimate
+                .categories
+                .iter()
+                .map(|c| {
+                    let name = c.category.to_possible_value().map_or_else(String::new, |v| v.get_name().to_string());
+                    let entry = serde_json::json!({
+                        "sampled": c.sampled,
+                        "files": interval(c.files, 1.0),
+                        "file_share": interval(c.file_share, 10000.0),
+                        "bytes": interval(c.bytes, 1.0),
+                        "byte_share": interval(c.byte_share, 10000.0),
+                    });
+                    (name, entry)
+                })
+                .collect::<serde_json::Map<_, _>>(),
         });
     }
-    
-    // For large files, read in chunks and aggregate statistics
-    let mut total_read = 0;
-    let mut first_chunk = Vec::new();
-    let mut byte_counts = [0u64; 256];
-    
-    while total_read < bytes_to_read {
-        let current_chunk_size = chunk_size.min(bytes_to_read - total_read);
-        let mut chunk = vec![0u8; current_chunk_size];
-        let bytes_read = file.read(&mut chunk).context("Failed to read file chunk")?;
-        
-        if bytes_read == 0 {
-            break; // EOF
-        }
-        
-        chunk.truncate(bytes_read);
-        
-        // Save first chunk for file type detection
-        if total_read == 0 {
-            first_chunk = chunk.clone();
-        }
-        
-        // Count byte frequencies for entropy calculation
-        for &byte in &chunk {
-            byte_counts[byte as usize] += 1;
-        }
-        
-        total_read += bytes_read;
+    if args.cluster {
+        report["clusters"] = clusters
+            .iter()
+            .map(|cluster| {
+                serde_json::json!({
+                    "files": cluster.paths.len(),
+                    "min_entropy": (cluster.min_entropy * 10000.0).round() / 10000.0,
+                    "max_entropy": (cluster.max_entropy * 10000.0).round() / 10000.0,
+                    "max_distance": cluster.max_distance,
+                    "paths": cluster.paths.iter().map(|p| display_path(p)).collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+    }
+    if args.show_skipped {
+        report["skipped"] = skipped
+            .paths
+            .iter()
+            .map(|(path, reason)| serde_json::json!({ "path": display_path(path), "reason": reason.key() }))
+            .collect();
     }
-    
-    // Detect file type from first chunk
-    let file_type = detect_file_type(&first_chunk);
-    
-    // Calculate entropy from aggregated byte counts
-    let entropy = calculate_entropy_from_counts(&byte_counts, total_read);
-
-    Ok(FileAnalysis {
-        path: path.to_path_buf(),
-        file_type,
-        entropy,
-        size,
-    })
-}
 
-fn detect_file_type(data: &[u8]) -> FileType {
-    if data.is_empty() {
-        return FileType::PlainText;
+    if args.stream {
+        // The last of the stream's lines
+        println!("{}", report);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
     }
+}
 
-    // Check our custom magic numbers for archives
-    if let Some(archive_type) = check_magic_number(data) {
-        return FileType::Archive(archive_type);
+fn json_entry(analysis: &FileAnalysis, args: &Args) -> serde_json::Value {
+    let mut entry = serde_json::json!({
+        "path": display_path(&analysis.path),
+        "type": analysis.file_type.display_compact(),
+        "entropy": (analysis.entropy * 10000.0).round() / 10000.0,
+        "size": analysis.size,
+        "notes": analysis.notes,
+    });
+    if let Some(n) = args.header_hex {
+        entry["header"] = format_hex(leading_bytes(&analysis.header, n as usize)).into();
+    }
+    if let Some(n) = args.preview.filter(|&n| n > 0) {
+        entry["preview"] = format_hex(leading_bytes(&analysis.header, n)).into();
+    }
+    if !analysis.hashes.is_empty() {
+        entry["hashes"] = analysis
+            .hashes
+            .iter()
+            .map(|(algorithm, hex)| (algorithm.key().to_string(), hex.as_str().into()))
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+    }
+    if !analysis.streams.is_empty() {
+        entry["streams"] = analysis
+            .streams
+            .iter()
+            .map(|s| serde_json::json!({ "name": s.name, "size": s.size, "entropy": s.entropy }))
+            .collect();
     }
+    if !analysis.entries.is_empty() {
+        entry["entries"] = analysis
+            .entries
+            .iter()
+            .map(|e| {
+                serde_json::json!({
+                    "name": e.name,
+                    "size": e.size,
+                    "encryption": e.encryption.map(|scheme| scheme.label()),
+                })
+            })
+            .collect();
+    }
+    if let Some(meta) = &analysis.metadata {
+        entry["metadata"] = serde_json::json!({
+            "width": meta.width,
+            "height": meta.height,
+            "software": meta.software,
+            "created": meta.created,
+            "camera": meta.camera,
+            "gps": meta.gps,
+        });
+    }
+    entry
+}
+
+fn json_error(error: &FileError) -> serde_json::Value {
+    serde_json::json!({ "path": display_path(&error.path), "error": error.message })
+}
+
+fn digest(analysis: &FileAnalysis, algorithm: enro::hash::HashAlgorithm) -> Option<&str> {
+    analysis
+        .hashes
+        .iter()
+        .find(|(a, _)| *a == algorithm)
+        .map(|(_, hex)| hex.as_str())
+}
+
+fn display_hashes(results: &[FileAnalysis]) {
+    println!("{}", "HASHES".bold());
 
-    // Try infer crate as fallback for file type detection
-    if let Some(kind) = infer::get(data) {
-        let mime = kind.mime_type();
-        let ext = kind.extension().to_uppercase();
-        
-        // Archive types
-        if mime.starts_with("application/x-") || mime.starts_with("application/zip") 
-            || mime == "application/gzip" || mime == "application/x-bzip2" 
-            || mime == "application/x-xz" || mime == "application/x-tar" {
-            return FileType::Archive(ext);
+    for analysis in results {
+        println!("\n  {}", display_path(&analysis.path).cyan());
+        if analysis.hashes.is_empty() {
+            println!("    {}", "(not hashed: unreadable regions)".dimmed());
         }
-        
-        // Document formats (PDF, Office docs, etc.)
-        if mime == "application/pdf" {
-            return FileType::Document("PDF".to_string());
+        for (algorithm, hex) in &analysis.hashes {
+            println!("    {:<7} {}", algorithm.key(), hex);
         }
-        if mime.starts_with("application/vnd.openxmlformats") {
-            return FileType::Document(ext);
+    }
+
+    println!();
+}
+
+fn display_metadata(results: &[FileAnalysis]) {
+    let images: Vec<&FileAnalysis> = results.iter().filter(|a| a.metadata.is_some()).collect();
+    if images.is_empty() {
+        return;
+    }
+
+    println!("{}", "IMAGE METADATA".bold());
+
+    for analysis in images {
+        let Some(meta) = &analysis.metadata else {
+            continue;
+        };
+        let mut fields = Vec::new();
+        if let (Some(w), Some(h)) = (meta.width, meta.height) {
+            fields.push(format!("{}x{}", w, h));
         }
-        if mime.starts_with("application/vnd.ms-") || mime.starts_with("application/msword") {
-            return FileType::Document(ext);
+        if let Some(camera) = &meta.camera {
+            fields.push(format!("camera: {}", camera));
         }
-        
-        // Image formats
-        if mime.starts_with("image/") {
-            return FileType::Image(ext);
+        if let Some(software) = &meta.software {
+            fields.push(format!("software: {}", software));
         }
-        
-        // Other compressed formats
-        if mime.contains("compress") || mime.contains("zip") {
-            return FileType::Compressed;
+        if let Some(created) = &meta.created {
+            fields.push(format!("created: {}", created));
         }
+        let gps = if meta.gps { "  GPS".red().bold().to_string() } else { String::new() };
+
+        println!("  {}  {}{}", display_path(&analysis.path).cyan(), fields.join(", "), gps);
     }
 
-    // Calculate entropy to detect encryption/randomness
-    let entropy = calculate_entropy(data);
+    println!();
+}
 
-    // High entropy (> 7.5) suggests encryption or compression
-    if entropy > 7.5 {
-        // Check if it's a compressed format we might have missed
-        if is_compressed_format(data) {
-            return FileType::Compressed;
-        }
-        // If very high entropy and not a known format, likely encrypted or random
-        if entropy > 7.9 {
-            return FileType::Encrypted;
-        }
-        return FileType::Random;
+fn display_streams(results: &[FileAnalysis]) {
+    let containers: Vec<&FileAnalysis> = results.iter().filter(|a| !a.streams.is_empty()).collect();
+    if containers.is_empty() {
+        return;
     }
 
-    // Check if it's mostly text
-    if is_text_data(data) {
-        return FileType::PlainText;
+    println!("{}", "OLE STREAMS".bold());
+
+    for analysis in containers {
+        println!("\n  {}", display_path(&analysis.path).cyan());
+        for stream in &analysis.streams {
+            let entropy_str = format!("{:.2}/8.0", stream.entropy);
+            let entropy_colored = if stream.entropy > 7.5 {
+                entropy_str.red()
+            } else if stream.entropy > 6.0 {
+                entropy_str.yellow()
+            } else {
+                entropy_str.green()
+            };
+            println!(
+                "    {:<40} {}  {}",
+                stream.name,
+                entropy_colored,
+                format_size(stream.size)
+            );
+        }
     }
 
-    FileType::Binary
+    println!();
 }
 
-fn check_magic_number(data: &[u8]) -> Option<String> {
-    if data.len() < 4 {
-        return None;
+fn display_zip_entries(results: &[FileAnalysis]) {
+    let archives: Vec<&FileAnalysis> = results.iter().filter(|a| !a.entries.is_empty()).collect();
+    if archives.is_empty() {
+        return;
     }
 
-    // ZIP (PK)
-    if data.starts_with(&[0x50, 0x4B, 0x03, 0x04]) || data.starts_with(&[0x50, 0x4B, 0x05, 0x06]) {
-        return Some("ZIP".to_string());
+    println!("{}", "ZIP ENTRIES".bold());
+
+    for analysis in archives {
+        println!("\n  {}", display_path(&analysis.path).cyan());
+        for entry in &analysis.entries {
+            let encryption = match entry.encryption {
+                Some(zip::ZipEncryption::ZipCrypto) => "ZipCrypto (weak)".red().to_string(),
+                Some(scheme) => scheme.label().yellow().to_string(),
+                None => String::new(),
+            };
+            println!("    {:<40} {:>10}  {}", entry.name, format_size(entry.size), encryption);
+        }
     }
 
-    // RAR
-    if data.starts_with(&[0x52, 0x61, 0x72, 0x21, 0x1A, 0x07]) {
-        return Some("RAR".to_string());
-    }
+    println!();
+}
 
-    // 7z
-    if data.starts_with(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C]) {
-        return Some("7Z".to_string());
+fn display_errors(errors: &[FileError]) {
+    if errors.is_empty() {
+        return;
     }
 
-    // GZIP
-    if data.starts_with(&[0x1F, 0x8B]) {
-        return Some("GZIP".to_string());
+    println!("{}", format!("ERRORS ({} file(s) could not be analyzed)", errors.len()).red().bold());
+    for error in errors {
+        println!("  {}  {}", display_path(&error.path).cyan(), error.message);
     }
+    println!();
+}
 
-    // TAR (POSIX)
-    if data.len() > 262 && &data[257..262] == b"ustar" {
-        return Some("TAR".to_string());
+fn display_skipped_counts(skipped: &walk::Skipped) {
+    for reason in walk::SkipReason::ALL {
+        let count = skipped.count(reason);
+        if count > 0 {
+            println!(
+                "  {} {}",
+                "•".cyan(),
+                format!("{} Skipped: {}", reason.label(), count).dimmed()
+            );
+        }
     }
+}
 
-    // BZ2
-    if data.starts_with(&[0x42, 0x5A, 0x68]) {
-        return Some("BZIP2".to_string());
+/// What --dry-run found: the files a scan would analyze and their total size
+fn display_dry_run(files: &[PathBuf], urls: &[&str], unchanged: usize, skipped: &walk::Skipped, args: &Args) {
+    let sizes: Vec<u64> = files
+        .iter()
+        .map(|path| std::fs::metadata(path).map_or(0, |m| m.len()))
+        .collect();
+    let bytes: u64 = sizes.iter().sum();
+    let options = args.analyze_options();
+    let read: u64 = sizes.iter().map(|&size| options.read_len(size)).sum();
+
+    if args.json {
+        let skipped: serde_json::Map<String, serde_json::Value> = walk::SkipReason::ALL
+            .iter()
+            .filter(|&&reason| skipped.count(reason) > 0)
+            .map(|&reason| (reason.key().to_string(), skipped.count(reason).into()))
+            .collect();
+        let mut output = serde_json::json!({
+            "files": files.len(),
+            "bytes": bytes,
+            "bytes_read": read,
+            "urls": urls,
+            "unchanged": unchanged,
+            "skipped": skipped,
+        });
+        if args.list {
+            output["paths"] = files.iter().map(|path| display_path(path)).collect();
+        }
+        println!("{}", serde_json::to_string_pretty(&output).unwrap_or_default());
+        return;
     }
-
-    // XZ
-    if data.starts_with(&[0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00]) {
-        return Some("XZ".to_string());
+    if args.simple {
+        if args.list {
+            println!("Path,Size");
+            for (path, size) in files.iter().zip(&sizes) {
+                println!("{},{}", escape_csv(&display_path(path)), size);
+            }
+        } else {
+            println!("Files,Bytes,BytesRead");
+            println!("{},{},{}", files.len(), bytes, read);
+        }
+        return;
     }
 
-    // ISO
-    if data.len() > 32774 && &data[32769..32774] == b"CD001" {
-        return Some("ISO".to_string());
+    if args.list {
+        for path in files {
+            println!("{}", display_path(path));
+        }
+        for url in urls {
+            println!("{}", url);
+        }
+        println!();
     }
-
-    // CAB
-    if data.starts_with(&[0x4D, 0x53, 0x43, 0x46]) {
-        return Some("CAB".to_string());
+    println!("{}", "DRY RUN".bold());
+    println!(
+        "  {} {}",
+        "•".cyan(),
+        format!("{} file(s) would be analyzed, {}", files.len(), format_size(bytes)).bold()
+    );
+    if read < bytes {
+        println!(
+            "  {} {}",
+            "•".cyan(),
+            format!("Only {} would be read", format_size(read)).bold()
+        );
     }
-
-    // ARJ
-    if data.starts_with(&[0x60, 0xEA]) {
-        return Some("ARJ".to_string());
+    if !urls.is_empty() {
+        println!("  {} {}", "•".cyan(), format!("{} URL(s) would be fetched", urls.len()).bold());
     }
-
-    // LZH/LHA
-    if data.len() > 2 && data[2..4] == [0x2D, 0x6C] {
-        return Some("LZH".to_string());
+    if unchanged > 0 {
+        println!(
+            "  {} {}",
+            "•".cyan(),
+            format!("{} file(s) unchanged since the last scan", unchanged).dimmed()
+        );
+    }
+    display_skipped_counts(skipped);
+    println!();
+    if args.show_skipped {
+        display_skipped(skipped);
     }
-
-    None
 }
 
-fn is_compressed_format(data: &[u8]) -> bool {
-    // Additional compressed format checks
-    if data.len() < 4 {
-        return false;
+fn display_skipped(skipped: &walk::Skipped) {
+    if skipped.paths.is_empty() {
+        return;
     }
 
-    // ZSTD
-    if data.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
-        return true;
+    println!("{}", "SKIPPED".bold());
+    for (path, reason) in &skipped.paths {
+        println!("  {:<24} {}", reason.key().dimmed(), display_path(path));
     }
+    println!();
+}
 
-    // LZ4
-    if data.starts_with(&[0x04, 0x22, 0x4D, 0x18]) {
-        return true;
+fn display_tuning(devices: &[enro::tuning::Tuned]) {
+    for device in devices {
+        println!(
+            "Reads on {}: {} file(s) at a time, up to {} per read ({}/s{})",
+            device.mount.display(),
+            device.readers,
+            format_size(device.read_len as u64),
+            format_size(device.bytes_per_sec as u64),
+            if device.settled { "" } else { ", still tuning when the scan ended" }
+        );
+    }
+    if !devices.is_empty() {
+        println!();
     }
-
-    false
 }
 
-fn is_text_data(data: &[u8]) -> bool {
-    let sample_size = data.len().min(8192);
-    let sample = &data[..sample_size];
-    
-    // Check for null bytes which indicate binary
-    if sample.contains(&0) {
-        return false;
-    }
-    
-    // Try UTF-8 validation first
-    if let Ok(text) = std::str::from_utf8(sample) {
-        // Valid UTF-8 - check if it's mostly printable
-        let printable = text.chars()
-            .filter(|c| c.is_whitespace() || !c.is_control())
-            .count();
-        if printable as f64 / text.chars().count() as f64 > 0.90 {
-            return true;
-        }
+fn display_estimate(estimate: &estimate::Estimate) {
+    println!(
+        "{} ({} of {} file(s) sampled, {:.0}% confidence)",
+        "ESTIMATE".bold(),
+        estimate.sampled,
+        estimate.population,
+        estimate::CONFIDENCE * 100.0
+    );
+    if estimate.sampled == 0 {
+        println!("\n  No sampled file could be analyzed.\n");
+        return;
     }
-    
-    // Check for Windows-1251 (Cyrillic) and other 8-bit encodings
-    // Windows-1251 uses ranges: 0x20-0x7E (ASCII), 0xA0-0xFF (Cyrillic), plus common control chars
-    let mut valid_chars = 0;
-    for &byte in sample {
-        if (byte >= 0x20 && byte <= 0x7E) ||  // ASCII printable
-           byte >= 0xA0 ||                     // Extended ASCII / Cyrillic range (0xA0-0xFF)
-           byte == b'\n' || byte == b'\r' || byte == b'\t' {
-            valid_chars += 1;
-        }
+
+    let percent = |share: estimate::Interval| format!("{:.1}%–{:.1}%", share.low * 100.0, share.high * 100.0);
+    println!();
+    for c in &estimate.categories {
+        let name = c.category.to_possible_value().map_or_else(String::new, |v| v.get_name().to_string());
+        println!(
+            "  {} {:<11} {} of files (~{:.0}), {} of bytes (~{})",
+            "•".cyan(),
+            name,
+            percent(c.file_share).bold(),
+            c.files.estimate,
+            percent(c.byte_share).bold(),
+            format_size(c.bytes.estimate as u64)
+        );
     }
-    
-    // If more than 95% are valid text characters (ASCII or extended), consider it text
-    valid_chars as f64 / sample_size as f64 > 0.95
+    println!(
+        "\n  {} {}\n",
+        "•".cyan(),
+        format!(
+            "All files: ~{} ({} to {})",
+            format_size(estimate.total_bytes.estimate as u64),
+            format_size(estimate.total_bytes.low as u64),
+            format_size(estimate.total_bytes.high as u64)
+        )
+        .bold()
+    );
 }
 
-fn calculate_entropy(data: &[u8]) -> f64 {
-    if data.is_empty() {
-        return 0.0;
+fn display_duplicates(groups: &[dedup::DuplicateGroup]) {
+    println!("{}", "DUPLICATES".bold());
+
+    if groups.is_empty() {
+        println!("\n  No identical files found.\n");
+        return;
     }
 
-    let mut frequency = [0u64; 256];
-    
-    for &byte in data {
-        frequency[byte as usize] += 1;
+    for group in groups {
+        println!(
+            "\n  {}  {} x {}",
+            group.hash.to_hex()[..16].dimmed(),
+            group.paths.len(),
+            format_size(group.size)
+        );
+        for path in &group.paths {
+            println!("    {}", display_path(path).cyan());
+        }
     }
 
-    calculate_entropy_from_counts(&frequency, data.len())
+    let wasted: u64 = groups.iter().map(|g| g.wasted()).sum();
+    println!(
+        "\n  {} {}\n",
+        "•".cyan(),
+        format!("{} group(s), {} wasted by extra copies", groups.len(), format_size(wasted)).bold()
+    );
 }
 
-fn calculate_entropy_from_counts(frequency: &[u64; 256], total_bytes: usize) -> f64 {
-    if total_bytes == 0 {
-        return 0.0;
-    }
+fn display_clusters(clusters: &[cluster::Cluster]) {
+    println!("{}", "SIMILAR FILES".bold());
 
-    let len = total_bytes as f64;
-    let mut entropy = 0.0;
+    if clusters.is_empty() {
+        println!("\n  No near-identical files found.\n");
+        return;
+    }
 
-    for &count in frequency {
-        if count > 0 {
-            let p = count as f64 / len;
-            entropy -= p * p.log2();
+    for cluster in clusters {
+        println!(
+            "\n  {} files  {}",
+            cluster.paths.len(),
+            format!(
+                "entropy {:.2}-{:.2}, TLSH distance up to {}",
+                cluster.min_entropy, cluster.max_entropy, cluster.max_distance
+            )
+            .dimmed()
+        );
+        for path in &cluster.paths {
+            println!("    {}", display_path(path).cyan());
         }
     }
 
-    entropy
+    let files: usize = clusters.iter().map(|c| c.paths.len()).sum();
+    println!(
+        "\n  {} {}\n",
+        "•".cyan(),
+        format!("{} cluster(s) of {} similar files", clusters.len(), files).bold()
+    );
 }
 
-fn escape_csv(s: &str) -> String {
-    if s.contains(',') || s.contains('"') || s.contains('\n') || s.contains('\r') {
-        format!("\"{}\"", s.replace('"', "\"\""))
-    } else {
-        s.to_string()
-    }
-}
+fn display_previews(results: &[FileAnalysis], preview_len: usize) {
+    println!("{}", "PREVIEW".bold());
 
-fn display_simple(results: &[FileAnalysis]) {
-    println!("Path,Type,Entropy,Size");
     for analysis in results {
-        let file_path = if let Ok(cwd) = std::env::current_dir() {
-            analysis.path.strip_prefix(&cwd)
-                .unwrap_or(&analysis.path)
-                .display()
-                .to_string()
-        } else {
-            analysis.path.display().to_string()
-        };
-
-        let type_str = match &analysis.file_type {
-            FileType::Archive(name) => format!("Archive({})", name),
-            FileType::Document(name) => format!("Document({})", name),
-            FileType::Image(name) => format!("Image({})", name),
-            FileType::Encrypted => "Encrypted".to_string(),
-            FileType::Random => "Random".to_string(),
-            FileType::PlainText => "PlainText".to_string(),
-            FileType::Binary => "Binary".to_string(),
-            FileType::Compressed => "Compressed".to_string(),
-        };
+        let file_path = display_path(&analysis.path);
 
-        println!(
-            "{},{},{:.2},{}",
-            escape_csv(&file_path),
-            escape_csv(&type_str),
-            analysis.entropy,
-            analysis.size
-        );
+        println!("\n  {}", file_path.cyan());
+        if analysis.header.is_empty() {
+            println!("    {}", "(empty)".dimmed());
+        }
+        for line in format_hexdump(leading_bytes(&analysis.header, preview_len)) {
+            println!("    {}", line);
+        }
     }
+
+    println!();
 }
 
-fn display_results(results: &[FileAnalysis]) {
+fn display_results(results: &[FileAnalysis], skipped: &walk::Skipped, args: &Args) {
     // Get terminal width for dynamic bar sizing
     let term_width = if let Some((terminal_size::Width(w), _)) = terminal_size::terminal_size() {
         w as usize
@@ -633,24 +2112,21 @@ fn display_results(results: &[FileAnalysis]) {
         .build();
     table.set_format(format);
     
-    table.add_row(Row::new(vec![
+    let mut header_row = Row::new(vec![
         Cell::new("File").style_spec("Fb"),
         Cell::new("Type").style_spec("Fb"),
         Cell::new("Entropy").style_spec("Fb"),
         Cell::new("Size").style_spec("Fb"),
-    ]));
+    ]);
+    if args.header_hex.is_some() {
+        header_row.add_cell(Cell::new("Header").style_spec("Fb"));
+    }
+    table.add_row(header_row);
 
     for analysis in results {
-        let file_path = if let Ok(cwd) = std::env::current_dir() {
-            analysis.path.strip_prefix(&cwd)
-                .unwrap_or(&analysis.path)
-                .display()
-                .to_string()
-        } else {
-            analysis.path.display().to_string()
-        };
+        let file_path = display_path(&analysis.path);
 
-        let type_str = analysis.file_type.display_plain();
+        let type_str = analysis.type_label(analysis.file_type.display_plain());
         let entropy_str = format!("{:.2}/8.0", analysis.entropy);
         let size_str = format_size(analysis.size);
 
@@ -662,12 +2138,22 @@ fn display_results(results: &[FileAnalysis]) {
             entropy_str.green().to_string()
         };
 
-        table.add_row(Row::new(vec![
-            Cell::new(&file_path),
+        let path_cell = if hashset::is_known_bad(analysis) {
+            Cell::new(&file_path.red().bold().to_string())
+        } else {
+            Cell::new(&file_path)
+        };
+        let mut row = Row::new(vec![
+            path_cell,
             Cell::new(&type_str),
             Cell::new(&entropy_colored),
             Cell::new(&size_str),
-        ]));
+        ]);
+        if let Some(n) = args.header_hex {
+            let header_str = format_hex(leading_bytes(&analysis.header, n as usize));
+            row.add_cell(Cell::new(&header_str.dimmed().to_string()));
+        }
+        table.add_row(row);
     }
 
     table.printstd();
@@ -693,6 +2179,7 @@ fn display_results(results: &[FileAnalysis]) {
         "•".cyan(),
         format!("Average Entropy: {:.2}/8.0", avg_entropy).bold()
     );
+    display_skipped_counts(skipped);
 
     let high_entropy_count = results.iter().filter(|a| a.entropy > 7.5).count();
     if high_entropy_count > 0 {
@@ -708,10 +2195,19 @@ fn display_results(results: &[FileAnalysis]) {
         );
     }
 
+    let known_bad_count = results.iter().filter(|a| hashset::is_known_bad(a)).count();
+    if known_bad_count > 0 {
+        println!(
+            "  {} {}",
+            "⚠️".red(),
+            format!("{} file(s) in known-bad hash sets", known_bad_count).red().bold()
+        );
+    }
+
     println!();
 }
 
-fn display_summary_only(results: &[FileAnalysis]) {
+fn display_summary_only(results: &[FileAnalysis], skipped: &walk::Skipped) {
     // Get terminal width for dynamic bar sizing
     let term_width = if let Some((terminal_size::Width(w), _)) = terminal_size::terminal_size() {
         w as usize
@@ -752,6 +2248,7 @@ fn display_summary_only(results: &[FileAnalysis]) {
         "•".cyan(),
         format!("Average Entropy: {:.2}/8.0", avg_entropy).bold()
     );
+    display_skipped_counts(skipped);
 
     let high_entropy_count = results.iter().filter(|a| a.entropy > 7.5).count();
     if high_entropy_count > 0 {
@@ -770,15 +2267,25 @@ fn display_summary_only(results: &[FileAnalysis]) {
     println!("\n{}", thin_separator.dimmed());
 }
 
-fn format_size(bytes: u64) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
-    let mut size = bytes as f64;
-    let mut unit_idx = 0;
+fn leading_bytes(data: &[u8], n: usize) -> &[u8] {
+    &data[..n.min(data.len())]
+}
 
-    while size >= 1024.0 && unit_idx < UNITS.len() - 1 {
-        size /= 1024.0;
-        unit_idx += 1;
-    }
+fn format_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
 
-    format!("{:.2} {}", size, UNITS[unit_idx])
+// Classic 16-bytes-per-line hexdump: offset, hex bytes, printable ASCII
+fn format_hexdump(data: &[u8]) -> Vec<String> {
+    data.chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| if (0x20..=0x7E).contains(&b) { b as char } else { '.' })
+                .collect();
+            format!("{:08x}  {:<47}  |{}|", i * 16, hex.join(" "), ascii)
+        })
+        .collect()
 }
@@ -0,0 +1,58 @@
+//! A process-wide cap on the read buffers analyses hold at once
+
+use std::sync::{Condvar, Mutex, OnceLock};
+
+// Chunks stay at least this large however small the budget, so reads do not degrade into syscalls
+pub(crate) const MIN_CHUNK: usize = 64 * 1024;
+
+struct Budget {
+    limit: usize,
+    used: Mutex<usize>,
+    freed: Condvar,
+}
+
+static BUDGET: OnceLock<Budget> = OnceLock::new();
+
+/// Cap the buffers all analyses hold at once to `bytes`. Analyses wait for earlier ones to finish
+/// rather than exceed it, and chunks are sized so every thread fits. Must be called before the
+/// first analysis; only the first call takes effect.
+pub fn set_limit(bytes: usize) {
+    let _ = BUDGET.set(Budget {
+        limit: bytes,
+        used: Mutex::new(0),
+        freed: Condvar::new(),
+    });
+}
+
+/// The budget set with `set_limit`, if any
+pub fn limit() -> Option<usize> {
+    BUDGET.get().map(|budget| budget.limit)
+}
+
+/// Bytes held against the budget, given back on drop
+pub(crate) struct Reservation {
+    bytes: usize,
+}
+
+/// Block until `bytes` fit in the budget and hold them. A request larger than the whole budget
+/// waits for everything else to be released, so it still runs, just alone.
+pub(crate) fn reserve(bytes: usize) -> Option<Reservation> {
+    let budget = BUDGET.get()?;
+    let bytes = bytes.min(budget.limit);
+    let mut used = budget.used.lock().unwrap();
+    while *used + bytes > budget.limit {
+        used = budget.freed.wait(used).unwrap();
+    }
+    *used += bytes;
+    Some(Reservation { bytes })
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let Some(budget) = BUDGET.get() else {
+            return;
+        };
+        *budget.used.lock().unwrap() -= self.bytes;
+        budget.freed.notify_all();
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use enro::FileAnalysis;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{category, Category};
+
+// Same cut-off as the red entropy column of the reports
+const HIGH_ENTROPY: f64 = 7.5;
+
+/// Counters of the long-running modes (watch, daemon), exposed in the Prometheus text format
+#[derive(Default)]
+pub struct Metrics {
+    files: AtomicU64,
+    bytes: AtomicU64,
+    high_entropy: AtomicU64,
+    errors: AtomicU64,
+    categories: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Metrics {
+    pub fn new() -> Arc<Self> {
+        // Every category is exported from the start, so rates work before the first match
+        let categories = Category::value_variants()
+            .iter()
+            .map(|c| (category_name(*c), 0))
+            .collect();
+        Arc::new(Metrics {
+            categories: Mutex::new(categories),
+            ..Metrics::default()
+        })
+    }
+
+    /// Count an analyzed file
+    pub fn record(&self, analysis: &FileAnalysis) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(analysis.bytes_read, Ordering::Relaxed);
+        if analysis.entropy > HIGH_ENTROPY {
+            self.high_entropy.fetch_add(1, Ordering::Relaxed);
+        }
+        *self
+            .categories
+            .lock()
+            .unwrap()
+            .entry(category_name(category(&analysis.file_type)))
+            .or_insert(0) += 1;
+    }
+
+    pub fn record_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let mut counter = |name: &str, help: &str, value: u64| {
+            let _ = write!(out, "# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}\n");
+        };
+        counter("enro_files_scanned_total", "Files analyzed.", self.files.load(Ordering::Relaxed));
+        counter("enro_bytes_read_total", "Bytes read for analysis.", self.bytes.load(Ordering::Relaxed));
+        counter(
+            "enro_high_entropy_files_total",
+            "Files with entropy above 7.5 bits per byte.",
+            self.high_entropy.load(Ordering::Relaxed),
+        );
+        counter("enro_errors_total", "Files that could not be analyzed.", self.errors.load(Ordering::Relaxed));
+
+        out.push_str("# HELP enro_files_by_category_total Files analyzed, by classification.\n");
+        out.push_str("# TYPE enro_files_by_category_total counter\n");
+        for (name, count) in self.categories.lock().unwrap().iter() {
+            let _ = writeln!(out, "enro_files_by_category_total{{category=\"{}\"}} {}", name, count);
+        }
+        out
+    }
+}
+
+fn category_name(category: Category) -> String {
+    category
+        .to_possible_value()
+        .map_or_else(|| format!("{:?}", category), |value| value.get_name().to_string())
+}
+
+/// Answer `GET /metrics` on `addr` from a background thread
+pub fn serve(addr: SocketAddr, metrics: Arc<Metrics>) -> Result<()> {
+    let listener = TcpListener::bind(addr).with_context(|| format!("Failed to listen on {}", addr))?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = respond(stream, &metrics) {
+                eprintln!("Warning: metrics request failed: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn respond(stream: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    // A stalled scraper must not hold up the others
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Headers are not needed, but are read so the client sees a clean close
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics.render()),
+        _ => ("404 Not Found", "Metrics are served at /metrics\n".to_string()),
+    };
+    let mut writer = &stream;
+    write!(
+        writer,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
@@ -0,0 +1,355 @@
+use anyhow::{bail, Context, Result};
+use clap::Args as ClapArgs;
+use colored::Colorize;
+use enro::{calculate_entropy, detect_file_type, format_size, secrets, FileType};
+use flate2::read::GzDecoder;
+use prettytable::{Cell, Row, Table};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::Command as ProcessCommand;
+
+use crate::escape_csv;
+
+/// Scan the layers of a Docker/OCI image
+#[derive(ClapArgs, Debug)]
+pub struct ImageArgs {
+    /// Image tarball (`docker save` or OCI layout archive), or an image reference in the local Docker daemon
+    #[arg(value_name = "TAR|REF")]
+    pub source: String,
+
+    /// Report every file, not only encrypted/random files and secrets
+    #[arg(long)]
+    pub all: bool,
+
+    /// Minimum file size to analyze (in bytes)
+    #[arg(short, long, default_value = "0")]
+    pub min_size: u64,
+
+    /// Maximum number of bytes of each file to analyze (omit to scan entire file)
+    #[arg(short = 'b', long)]
+    pub max_bytes: Option<usize>,
+
+    /// Simple output format (CSV, no colors)
+    #[arg(short, long)]
+    pub simple: bool,
+
+    /// JSON output format (for scripting)
+    #[arg(long, conflicts_with = "simple")]
+    pub json: bool,
+}
+
+/// Location of a member's data inside the outer image tarball
+#[derive(Clone, Copy)]
+struct Span {
+    offset: u64,
+    size: u64,
+}
+
+struct LayerFile {
+    layer: usize,
+    path: String,
+    file_type: FileType,
+    entropy: f64,
+    size: u64,
+    secrets: Vec<&'static str>,
+    /// Later layer that deleted or replaced this file (it still ships in the image)
+    hidden_by: Option<usize>,
+}
+
+impl LayerFile {
+    fn is_finding(&self) -> bool {
+        !self.secrets.is_empty() || matches!(self.file_type, FileType::Encrypted | FileType::Random)
+    }
+
+    fn notes(&self) -> Vec<String> {
+        let mut notes: Vec<String> = self.secrets.iter().map(|s| s.to_string()).collect();
+        if let Some(layer) = self.hidden_by {
+            notes.push(format!("hidden by layer {}", layer + 1));
+        }
+        notes
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim_start_matches("./").trim_start_matches('/').to_string()
+}
+
+// Member offsets of the outer tarball, so layers can be read in manifest order
+fn index_tarball(file: &mut File) -> Result<HashMap<String, Span>> {
+    let mut spans = HashMap::new();
+    let mut archive = tar::Archive::new(&mut *file);
+    for entry in archive.entries().context("Failed to read image tarball")? {
+        let entry = entry?;
+        let name = normalize(&entry.path()?.to_string_lossy());
+        spans.insert(
+            name,
+            Span {
+                offset: entry.raw_file_position(),
+                size: entry.size(),
+            },
+        );
+    }
+    Ok(spans)
+}
+
+fn read_member(file: &mut File, spans: &HashMap<String, Span>, name: &str) -> Result<Vec<u8>> {
+    let span = spans
+        .get(name)
+        .with_context(|| format!("Missing {} in image tarball", name))?;
+    file.seek(SeekFrom::Start(span.offset))?;
+    let mut data = Vec::new();
+    file.take(span.size).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn blob_path(digest: &str) -> String {
+    format!("blobs/{}", digest.replacen(':', "/", 1))
+}
+
+/// Layer member names, oldest first
+fn layer_paths(file: &mut File, spans: &HashMap<String, Span>) -> Result<Vec<String>> {
+    // `docker save` (both legacy and OCI-style layouts) writes manifest.json
+    if spans.contains_key("manifest.json") {
+        let manifest: serde_json::Value = serde_json::from_slice(&read_member(file, spans, "manifest.json")?)
+            .context("Invalid manifest.json")?;
+        let layers = manifest[0]["Layers"]
+            .as_array()
+            .context("manifest.json lists no layers")?;
+        return Ok(layers.iter().filter_map(|l| l.as_str()).map(normalize).collect());
+    }
+
+    // Plain OCI image layout: index.json -> (nested index ->) manifest -> layers
+    let mut doc: serde_json::Value = serde_json::from_slice(&read_member(file, spans, "index.json")?)
+        .context("Invalid index.json")?;
+    while let Some(digest) = doc["manifests"][0]["digest"].as_str() {
+        let blob = read_member(file, spans, &blob_path(digest))?;
+        doc = serde_json::from_slice(&blob).context("Invalid OCI manifest")?;
+    }
+    let layers = doc["layers"].as_array().context("OCI manifest lists no layers")?;
+    Ok(layers
+        .iter()
+        .filter_map(|l| l["digest"].as_str())
+        .map(blob_path)
+        .collect())
+}
+
+/// Short layer identifier: the digest for blobs, the directory for legacy `<id>/layer.tar`
+fn layer_id(path: &str) -> String {
+    let id = match path.strip_prefix("blobs/") {
+        Some(blob) => blob.rsplit('/').next().unwrap_or(blob),
+        None => path.split('/').next().unwrap_or(path),
+    };
+    id.chars().take(12).collect()
+}
+
+// Mark files from earlier layers that a whiteout in `layer` removes
+fn apply_whiteout(files: &mut [LayerFile], live: &mut HashMap<String, usize>, target: &str, layer: usize) {
+    let prefix = format!("{}/", target);
+    live.retain(|path, &mut idx| {
+        let removed = path == target || path.starts_with(&prefix) || target.is_empty();
+        if removed {
+            files[idx].hidden_by = Some(layer);
+        }
+        !removed
+    });
+}
+
+fn scan_layer<R: Read>(
+    reader: R,
+    layer: usize,
+    args: &ImageArgs,
+    files: &mut Vec<LayerFile>,
+    live: &mut HashMap<String, usize>,
+) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().context("Failed to read layer")? {
+        let mut entry = entry?;
+        let path = normalize(&entry.path()?.to_string_lossy());
+        let (dir, name) = path.rsplit_once('/').unwrap_or(("", &path));
+
+        // Whiteouts: `.wh.<name>` deletes a path, `.wh..wh..opq` empties a directory
+        if name == ".wh..wh..opq" {
+            apply_whiteout(files, live, dir, layer);
+            continue;
+        }
+        if let Some(deleted) = name.strip_prefix(".wh.") {
+            let target = if dir.is_empty() { deleted.to_string() } else { format!("{}/{}", dir, deleted) };
+            apply_whiteout(files, live, &target, layer);
+            continue;
+        }
+
+        if !entry.header().entry_type().is_file() || entry.size() < args.min_size {
+            continue;
+        }
+
+        let size = entry.size();
+        let limit = args.max_bytes.map_or(u64::MAX, |m| m as u64);
+        let mut data = Vec::new();
+        entry.by_ref().take(limit).read_to_end(&mut data)?;
+
+        if let Some(previous) = live.insert(path.clone(), files.len()) {
+            files[previous].hidden_by = Some(layer);
+        }
+        files.push(LayerFile {
+            layer,
+            path,
+            file_type: detect_file_type(&data),
+            entropy: calculate_entropy(&data),
+            size,
+            secrets: secrets::find_secrets(&data),
+            hidden_by: None,
+        });
+    }
+    Ok(())
+}
+
+// Export an image from the local Docker daemon into a temporary tarball
+fn docker_save(reference: &str, dir: &Path) -> Result<PathBuf> {
+    let output = dir.join("image.tar");
+    let status = ProcessCommand::new("docker")
+        .arg("save")
+        .arg("-o")
+        .arg(&output)
+        .arg(reference)
+        .status()
+        .context("Failed to run `docker save` (is Docker installed?)")?;
+    if !status.success() {
+        bail!("`docker save {}` failed", reference);
+    }
+    Ok(output)
+}
+
+pub fn run(args: &ImageArgs) -> Result<()> {
+    let machine_readable = args.simple || args.json;
+
+    let temp_dir;
+    let tarball = if Path::new(&args.source).exists() {
+        PathBuf::from(&args.source)
+    } else {
+        temp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+        docker_save(&args.source, temp_dir.path())?
+    };
+
+    let mut file = File::open(&tarball).context("Failed to open image tarball")?;
+    let spans = index_tarball(&mut file)?;
+    let layers = layer_paths(&mut file, &spans)?;
+
+    if !machine_readable {
+        println!("Scanning {} layer(s) of {}...\n", layers.len(), args.source);
+    }
+
+    let mut files = Vec::new();
+    let mut live = HashMap::new();
+    for (index, layer) in layers.iter().enumerate() {
+        let span = spans
+            .get(layer)
+            .with_context(|| format!("Missing layer {} in image tarball", layer))?;
+        file.seek(SeekFrom::Start(span.offset))?;
+        let mut reader = BufReader::new((&mut file).take(span.size));
+
+        // Layers are plain or gzip-compressed tarballs
+        let mut magic = [0u8; 2];
+        reader.read_exact(&mut magic).context("Failed to read layer")?;
+        let reader = std::io::Cursor::new(magic).chain(reader);
+        let result = if magic == [0x1f, 0x8b] {
+            scan_layer(GzDecoder::new(reader), index, args, &mut files, &mut live)
+        } else {
+            scan_layer(reader, index, args, &mut files, &mut live)
+        };
+        result.with_context(|| format!("Failed to scan layer {}", layer_id(layer)))?;
+    }
+
+    let layer_ids: Vec<String> = layers.iter().map(|l| layer_id(l)).collect();
+    let reports: Vec<&LayerFile> = files.iter().filter(|f| args.all || f.is_finding()).collect();
+
+    if args.json {
+        let files: Vec<serde_json::Value> = reports
+            .iter()
+            .map(|f| {
+                serde_json::json!({
+                    "layer": f.layer + 1,
+                    "layer_id": layer_ids[f.layer],
+                    "path": f.path,
+                    "type": f.file_type.display_compact(),
+                    "entropy": (f.entropy * 10000.0).round() / 10000.0,
+                    "size": f.size,
+                    "secrets": f.secrets,
+                    "hidden_by": f.hidden_by.map(|l| l + 1),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "files": files }))?);
+    } else if args.simple {
+        println!("Layer,Layer ID,Path,Type,Entropy,Size,Notes");
+        for f in &reports {
+            println!(
+                "{},{},{},{},{:.2},{},{}",
+                f.layer + 1,
+                layer_ids[f.layer],
+                escape_csv(&f.path),
+                f.file_type.display_compact(),
+                f.entropy,
+                f.size,
+                escape_csv(&f.notes().join("; "))
+            );
+        }
+    } else {
+        display_layer_table(&reports, &layer_ids);
+    }
+
+    Ok(())
+}
+
+fn display_layer_table(reports: &[&LayerFile], layer_ids: &[String]) {
+    if reports.is_empty() {
+        println!("{}", "No findings in image layers.".green());
+        return;
+    }
+
+    let mut table = Table::new();
+    let format = prettytable::format::FormatBuilder::new()
+        .column_separator(' ')
+        .borders(' ')
+        .padding(1, 1)
+        .build();
+    table.set_format(format);
+    table.add_row(Row::new(vec![
+        Cell::new("Layer").style_spec("Fb"),
+        Cell::new("Path").style_spec("Fb"),
+        Cell::new("Type").style_spec("Fb"),
+        Cell::new("Entropy").style_spec("Fb"),
+        Cell::new("Size").style_spec("Fb"),
+        Cell::new("Notes").style_spec("Fb"),
+    ]));
+
+    for f in reports {
+        let entropy_str = format!("{:.2}/8.0", f.entropy);
+        let entropy_colored = if f.entropy > 7.5 {
+            entropy_str.red().to_string()
+        } else if f.entropy > 6.0 {
+            entropy_str.yellow().to_string()
+        } else {
+            entropy_str.green().to_string()
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(&format!("{} {}", f.layer + 1, layer_ids[f.layer].dimmed())),
+            Cell::new(&f.path),
+            Cell::new(&f.file_type.display_plain()),
+            Cell::new(&entropy_colored),
+            Cell::new(&format_size(f.size)),
+            Cell::new(&f.notes().join(", ").yellow().to_string()),
+        ]));
+    }
+
+    table.printstd();
+
+    let secrets = reports.iter().filter(|f| !f.secrets.is_empty()).count();
+    let hidden = reports.iter().filter(|f| f.hidden_by.is_some()).count();
+    println!(
+        "\n  {} {}",
+        "•".cyan(),
+        format!("{} file(s) reported, {} with secrets, {} hidden by later layers", reports.len(), secrets, hidden).bold()
+    );
+}
@@ -0,0 +1,313 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::calculate_entropy;
+
+/// Compound File Binary (OLE2) signature
+pub const CFB_MAGIC: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+const FREESECT: u32 = 0xFFFF_FFFF;
+const ENDOFCHAIN: u32 = 0xFFFF_FFFE;
+const NOSTREAM: u32 = 0xFFFF_FFFF;
+const HEADER_DIFAT_ENTRIES: usize = 109;
+const DIR_ENTRY_SIZE: usize = 128;
+// Real documents have a few hundred directory entries; more is read as a hostile file
+const MAX_DIR_ENTRIES: usize = 65_536;
+
+const TYPE_STORAGE: u8 = 1;
+const TYPE_STREAM: u8 = 2;
+const TYPE_ROOT: u8 = 5;
+
+pub fn is_cfb(data: &[u8]) -> bool {
+    data.starts_with(&CFB_MAGIC)
+}
+
+/// A stream inside a compound file, with its full storage path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamInfo {
+    pub name: String,
+    pub size: u64,
+    pub entropy: f64,
+}
+
+struct DirEntry {
+    name: String,
+    object_type: u8,
+    left: u32,
+    right: u32,
+    child: u32,
+    start_sector: u32,
+    size: u64,
+}
+
+/// Minimal read-only CFB parser: enough to walk the directory and read streams
+pub struct CompoundFile<R: Read + Seek> {
+    reader: R,
+    sector_size: usize,
+    mini_sector_size: usize,
+    mini_cutoff: u64,
+    fat: Vec<u32>,
+    mini_fat: Vec<u32>,
+    mini_stream: Vec<u8>,
+    entries: Vec<DirEntry>,
+}
+
+fn u16_at(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+}
+
+fn u64_at(buf: &[u8], offset: usize) -> u64 {
+    u64::from(u32_at(buf, offset)) | (u64::from(u32_at(buf, offset + 4)) << 32)
+}
+
+impl<R: Read + Seek> CompoundFile<R> {
+    pub fn open(mut reader: R) -> Result<Self> {
+        let mut header = [0u8; 512];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut header).context("Failed to read CFB header")?;
+
+        if !is_cfb(&header) {
+            bail!("Not a compound file");
+        }
+
+        let sector_shift = u16_at(&header, 0x1E);
+        let mini_sector_shift = u16_at(&header, 0x20);
+        if !(7..=16).contains(&sector_shift) || mini_sector_shift >= sector_shift {
+            bail!("Invalid CFB sector size");
+        }
+
+        let mut cfb = CompoundFile {
+            reader,
+            sector_size: 1 << sector_shift,
+            mini_sector_size: 1 << mini_sector_shift,
+            mini_cutoff: u64::from(u32_at(&header, 0x38)),
+            fat: Vec::new(),
+            mini_fat: Vec::new(),
+            mini_stream: Vec::new(),
+            entries: Vec::new(),
+        };
+
+        let num_fat_sectors = u32_at(&header, 0x2C) as usize;
+        let first_dir_sector = u32_at(&header, 0x30);
+        let first_mini_fat_sector = u32_at(&header, 0x3C);
+        let mut difat_sector = u32_at(&header, 0x44);
+        let num_difat_sectors = u32_at(&header, 0x48) as usize;
+
+        // Collect FAT sector locations from the header DIFAT and any DIFAT sectors
+        let mut fat_sectors: Vec<u32> = (0..HEADER_DIFAT_ENTRIES)
+            .map(|i| u32_at(&header, 0x4C + i * 4))
+            .filter(|&s| s != FREESECT)
+            .collect();
+
+        let entries_per_sector = cfb.sector_size / 4;
+        for _ in 0..num_difat_sectors {
+            if difat_sector == ENDOFCHAIN || difat_sector == FREESECT {
+                break;
+            }
+            let sector = cfb.read_sector(difat_sector)?;
+            for i in 0..entries_per_sector - 1 {
+                let s = u32_at(&sector, i * 4);
+                if s != FREESECT {
+                    fat_sectors.push(s);
+                }
+            }
+            difat_sector = u32_at(&sector, (entries_per_sector - 1) * 4);
+        }
+        fat_sectors.truncate(num_fat_sectors);
+
+        for sector_id in fat_sectors {
+            let sector = cfb.read_sector(sector_id)?;
+            cfb.fat
+                .extend((0..entries_per_sector).map(|i| u32_at(&sector, i * 4)));
+        }
+
+        // Directory entries
+        let dir_data = cfb.read_chain(first_dir_sector, Some((MAX_DIR_ENTRIES * DIR_ENTRY_SIZE) as u64))?;
+        // Version 3 files only define the low 32 bits of the stream size
+        let size_mask = if cfb.sector_size == 512 { u64::from(u32::MAX) } else { u64::MAX };
+        for raw in dir_data.chunks_exact(DIR_ENTRY_SIZE) {
+            let name_len = (u16_at(raw, 0x40) as usize).min(64);
+            let units: Vec<u16> = (0..name_len.saturating_sub(2) / 2)
+                .map(|i| u16_at(raw, i * 2))
+                .collect();
+            cfb.entries.push(DirEntry {
+                name: String::from_utf16_lossy(&units),
+                object_type: raw[0x42],
+                left: u32_at(raw, 0x44),
+                right: u32_at(raw, 0x48),
+                child: u32_at(raw, 0x4C),
+                start_sector: u32_at(raw, 0x74),
+                size: u64_at(raw, 0x78) & size_mask,
+            });
+        }
+
+        let root = cfb
+            .entries
+            .first()
+            .filter(|e| e.object_type == TYPE_ROOT)
+            .context("CFB root entry missing")?;
+        let (mini_start, mini_size) = (root.start_sector, root.size);
+
+        // Mini FAT and mini stream back small streams
+        let mini_fat_data = cfb.read_chain(first_mini_fat_sector, None)?;
+        cfb.mini_fat = mini_fat_data.chunks_exact(4).map(|c| u32_at(c, 0)).collect();
+        cfb.mini_stream = cfb.read_chain(mini_start, Some(mini_size))?;
+
+        Ok(cfb)
+    }
+
+    fn read_sector(&mut self, sector_id: u32) -> Result<Vec<u8>> {
+        let offset = (u64::from(sector_id) + 1) * self.sector_size as u64;
+        let mut sector = vec![0u8; self.sector_size];
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.reader
+            .read_exact(&mut sector)
+            .with_context(|| format!("Failed to read CFB sector {}", sector_id))?;
+        Ok(sector)
+    }
+
+    // Follow a FAT chain; the step limit protects against cyclic chains
+    fn read_chain(&mut self, start: u32, size: Option<u64>) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut sector_id = start;
+        let mut steps = 0;
+
+        while sector_id != ENDOFCHAIN && sector_id != FREESECT {
+            if steps > self.fat.len() {
+                bail!("Cyclic FAT chain in compound file");
+            }
+            if size.is_some_and(|s| data.len() as u64 >= s) {
+                break;
+            }
+            data.extend(self.read_sector(sector_id)?);
+            sector_id = *self
+                .fat
+                .get(sector_id as usize)
+                .context("FAT chain points outside the file")?;
+            steps += 1;
+        }
+
+        if let Some(size) = size {
+            data.truncate(size as usize);
+        }
+        Ok(data)
+    }
+
+    fn read_mini_chain(&self, start: u32, size: u64) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut sector_id = start;
+        let mut steps = 0;
+
+        while sector_id != ENDOFCHAIN && sector_id != FREESECT && (data.len() as u64) < size {
+            if steps > self.mini_fat.len() {
+                bail!("Cyclic mini FAT chain in compound file");
+            }
+            let offset = sector_id as usize * self.mini_sector_size;
+            let chunk = self
+                .mini_stream
+                .get(offset..offset + self.mini_sector_size)
+                .context("Mini sector outside the mini stream")?;
+            data.extend_from_slice(chunk);
+            sector_id = *self
+                .mini_fat
+                .get(sector_id as usize)
+                .context("Mini FAT chain out of range")?;
+            steps += 1;
+        }
+
+        data.truncate(size as usize);
+        Ok(data)
+    }
+
+    /// Full paths of all streams, e.g. `Macros/VBA/dir`
+    pub fn stream_paths(&self) -> Vec<(String, usize)> {
+        let mut paths = Vec::new();
+        if let Some(root) = self.entries.first() {
+            self.walk(root.child, &mut paths);
+        }
+        paths
+    }
+
+    // In-order walk of the red-black trees, on a stack of its own since a hostile file can chain
+    // every entry into one branch
+    fn walk(&self, root: u32, out: &mut Vec<(String, usize)>) {
+        enum Step {
+            Enter(u32, String),
+            Visit(usize, String),
+        }
+        let mut visited = vec![false; self.entries.len()];
+        let mut stack = vec![Step::Enter(root, String::new())];
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Enter(id, prefix) => {
+                    let idx = id as usize;
+                    if id == NOSTREAM || idx >= self.entries.len() || visited[idx] {
+                        continue;
+                    }
+                    visited[idx] = true;
+                    let entry = &self.entries[idx];
+                    // Popped in reverse: left subtree, the entry itself, right subtree
+                    stack.push(Step::Enter(entry.right, prefix.clone()));
+                    stack.push(Step::Visit(idx, prefix.clone()));
+                    stack.push(Step::Enter(entry.left, prefix));
+                }
+                Step::Visit(idx, prefix) => {
+                    let entry = &self.entries[idx];
+                    let path = if prefix.is_empty() {
+                        entry.name.clone()
+                    } else {
+                        format!("{}/{}", prefix, entry.name)
+                    };
+                    match entry.object_type {
+                        TYPE_STREAM => out.push((path, idx)),
+                        TYPE_STORAGE => stack.push(Step::Enter(entry.child, path)),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// VBA projects live in `Macros` (Word), `_VBA_PROJECT_CUR` (Excel) or `VBA` storages
+    pub fn has_macros(&self) -> bool {
+        self.stream_paths().iter().any(|(path, _)| {
+            path.split('/').any(|part| {
+                part.eq_ignore_ascii_case("VBA")
+                    || part.eq_ignore_ascii_case("_VBA_PROJECT_CUR")
+                    || part.eq_ignore_ascii_case("Macros")
+            })
+        })
+    }
+
+    pub fn read_stream(&mut self, entry_idx: usize) -> Result<Vec<u8>> {
+        let (start, size) = {
+            let entry = &self.entries[entry_idx];
+            (entry.start_sector, entry.size)
+        };
+        if size < self.mini_cutoff {
+            self.read_mini_chain(start, size)
+        } else {
+            self.read_chain(start, Some(size))
+        }
+    }
+
+    /// Enumerate every stream with its size and entropy
+    pub fn streams(&mut self) -> Vec<StreamInfo> {
+        self.stream_paths()
+            .into_iter()
+            .filter_map(|(name, idx)| {
+                let data = self.read_stream(idx).ok()?;
+                Some(StreamInfo {
+                    name,
+                    size: data.len() as u64,
+                    entropy: calculate_entropy(&data),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,96 @@
+//! Reading files without crowding other programs' data out of the OS page cache
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::platform;
+
+// Read between asking the OS to drop what was read
+const DROP_EVERY: u64 = 8 * 1024 * 1024;
+
+static DROP_BEHIND: AtomicBool = AtomicBool::new(false);
+
+/// Have analyses drop what they read from the OS page cache as they go, so a large scan leaves
+/// the cache to the programs that were using it. Files that were cached before the scan are
+/// dropped as well. Takes effect on Linux; Windows only gets the sequential-scan hint `open`
+/// always gives.
+pub fn set_drop_behind(drop: bool) {
+    DROP_BEHIND.store(drop, Ordering::Relaxed);
+}
+
+/// Open `path` to be read front to back
+pub fn open(path: &Path) -> io::Result<DropBehind> {
+    platform::open_sequential(path).map(DropBehind::new)
+}
+
+/// A file that, under `set_drop_behind`, drops what was read through it from the page cache every
+/// few MiB and when it is closed
+pub struct DropBehind {
+    file: File,
+    /// Shares the file's offset, to tell where reading got to; none when nothing is dropped
+    hint: Option<File>,
+    /// Where reading started since the last drop
+    start: Option<u64>,
+    pending: u64,
+}
+
+impl DropBehind {
+    pub fn new(file: File) -> Self {
+        let hint = if DROP_BEHIND.load(Ordering::Relaxed) { file.try_clone().ok() } else { None };
+        DropBehind {
+            file,
+            hint,
+            start: None,
+            pending: 0,
+        }
+    }
+
+    fn drop_read(&mut self) {
+        self.pending = 0;
+        let Some(hint) = &mut self.hint else {
+            return;
+        };
+        let (Some(start), Ok(end)) = (self.start, hint.stream_position()) else {
+            return;
+        };
+        if end > start {
+            // Pages still being read ahead at the last drop were kept, so go over those again
+            let from = start.saturating_sub(DROP_EVERY);
+            platform::drop_cached(hint, from, end - from);
+        }
+        self.start = Some(end);
+    }
+}
+
+impl Read for DropBehind {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let (Some(hint), None) = (&mut self.hint, self.start) {
+            self.start = hint.stream_position().ok();
+        }
+        let n = self.file.read(buf)?;
+        self.pending += n as u64;
+        if self.pending >= DROP_EVERY {
+            self.drop_read();
+        }
+        Ok(n)
+    }
+}
+
+impl Seek for DropBehind {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.drop_read();
+        self.start = None;
+        self.file.seek(pos)
+    }
+}
+
+impl Drop for DropBehind {
+    fn drop(&mut self) {
+        // Read-ahead past the last drop is cached too; a length of 0 runs to the end of the file
+        if let Some(hint) = &self.hint {
+            platform::drop_cached(hint, 0, 0);
+        }
+    }
+}
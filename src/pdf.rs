@@ -0,0 +1,67 @@
+use std::io::Read;
+
+// Name tokens that make a PDF worth a closer look, with the note they produce
+const RISK_MARKERS: &[(&[u8], &str)] = &[
+    (b"/JavaScript", "JavaScript"),
+    (b"/JS", "JavaScript"),
+    (b"/EmbeddedFile", "embedded files"),
+    (b"/Launch", "launch action"),
+];
+
+const SCAN_CHUNK: usize = 1024 * 1024;
+// Enough overlap to catch a marker split across two reads
+const OVERLAP: usize = 64;
+
+/// Undo `#xx` hex escapes in PDF names (`/J#61vaScript` -> `/JavaScript`)
+fn unescape_names(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] == b'#' && i + 2 < data.len() {
+            let hex = std::str::from_utf8(&data[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(data[i]);
+        i += 1;
+    }
+    out
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+/// Scan a PDF for risky name markers, reading at most `limit` bytes
+pub fn scan_risks<R: Read>(reader: R, limit: Option<usize>) -> Vec<&'static str> {
+    let mut reader = reader.take(limit.map_or(u64::MAX, |l| l as u64));
+    let mut found: Vec<&'static str> = Vec::new();
+    let mut window: Vec<u8> = Vec::new();
+    let mut chunk = vec![0u8; SCAN_CHUNK];
+
+    loop {
+        let n = match reader.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        window.extend_from_slice(&chunk[..n]);
+
+        let normalized = unescape_names(&window);
+        for &(marker, note) in RISK_MARKERS {
+            if !found.contains(&note) && contains(&normalized, marker) {
+                found.push(note);
+            }
+        }
+        if RISK_MARKERS.iter().all(|(_, note)| found.contains(note)) {
+            break;
+        }
+
+        let keep = window.len().min(OVERLAP);
+        window.drain(..window.len() - keep);
+    }
+
+    found
+}
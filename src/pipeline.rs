@@ -0,0 +1,149 @@
+//! Reading on the calling thread while a second thread counts and hashes what was read, so slow
+//! storage and heavy digests overlap instead of taking turns
+
+use std::io::{self, Read};
+use std::slice::ChunksMut;
+use std::sync::mpsc;
+
+use crate::pool;
+
+// Pooled blocks in flight: one being consumed, one queued for it, one being read
+pub(crate) const BLOCKS: usize = 3;
+
+enum Block<'a> {
+    /// Part of the caller's buffer, filled in place
+    Head(&'a [u8]),
+    /// A pooled buffer and how much of it was read
+    Pooled(Vec<u8>, usize),
+}
+
+impl Block<'_> {
+    fn data(&self) -> &[u8] {
+        match self {
+            Block::Head(data) => data,
+            Block::Pooled(buffer, len) => &buffer[..*len],
+        }
+    }
+}
+
+/// Where the next block is read to: the caller's buffer while it lasts, then pooled buffers
+struct Source<'a> {
+    heads: ChunksMut<'a, u8>,
+    head_len: usize,
+    total: u64,
+    limit: u64,
+    ended: bool,
+}
+
+impl<'a> Source<'a> {
+    fn next<R: Read + ?Sized>(&mut self, reader: &mut R, spare: impl FnOnce() -> Vec<u8>) -> io::Result<Option<Block<'a>>> {
+        if self.ended {
+            return Ok(None);
+        }
+        if let Some(slice) = self.heads.next() {
+            let n = fill(reader, slice)?;
+            self.head_len += n;
+            self.total += n as u64;
+            self.ended = n < slice.len() || self.total >= self.limit;
+            let slice: &'a [u8] = slice;
+            return Ok((n > 0).then(|| Block::Head(&slice[..n])));
+        }
+        let mut buffer = spare();
+        let want = buffer.len().min(usize::try_from(self.limit - self.total).unwrap_or(usize::MAX));
+        let n = fill(reader, &mut buffer[..want])?;
+        self.total += n as u64;
+        self.ended = n < want || self.total >= self.limit;
+        if n == 0 {
+            pool::give(buffer);
+            return Ok(None);
+        }
+        Ok(Some(Block::Pooled(buffer, n)))
+    }
+}
+
+/// Read up to `limit` bytes in blocks of `block_len`, first into `head` and then into pooled
+/// buffers, handing each block to `consume` on a second thread while the next is read. Returns
+/// how much of `head` was filled and how many bytes were read in all. A reader that ends within
+/// the first block is consumed without a second thread.
+pub(crate) fn read_into<R: Read + ?Sized>(
+    reader: &mut R,
+    head: &mut [u8],
+    limit: u64,
+    block_len: usize,
+    mut consume: impl FnMut(&[u8]) + Send,
+) -> io::Result<(usize, u64)> {
+    let mut source = Source {
+        heads: head.chunks_mut(block_len),
+        head_len: 0,
+        total: 0,
+        limit,
+        ended: limit == 0,
+    };
+    let Some(first) = source.next(reader, || pool::take(block_len))? else {
+        return Ok((0, 0));
+    };
+    if source.ended {
+        consume(first.data());
+        if let Block::Pooled(buffer, _) = first {
+            pool::give(buffer);
+        }
+        return Ok((source.head_len, source.total));
+    }
+
+    let (full, to_consume) = mpsc::sync_channel::<Block>(1);
+    let (empty, emptied) = mpsc::channel::<Vec<u8>>();
+    let result = std::thread::scope(|scope| {
+        scope.spawn(move || {
+            for block in to_consume {
+                consume(block.data());
+                if let Block::Pooled(buffer, _) = block {
+                    // Fails only once reading is over, and the buffer is then no longer wanted
+                    let _ = empty.send(buffer);
+                }
+            }
+        });
+
+        let mut allocated = usize::from(matches!(first, Block::Pooled(..)));
+        let mut queued = Some(first);
+        let mut error = None;
+        while let Some(block) = queued.take() {
+            if full.send(block).is_err() {
+                // The consumer panicked; the scope passes that on
+                break;
+            }
+            let spare = || match emptied.try_recv() {
+                Ok(buffer) => buffer,
+                Err(_) if allocated < BLOCKS => {
+                    allocated += 1;
+                    pool::take(block_len)
+                }
+                Err(_) => emptied.recv().unwrap_or_else(|_| pool::take(block_len)),
+            };
+            match source.next(reader, spare) {
+                Ok(next) => queued = next,
+                Err(e) => error = Some(e),
+            }
+        }
+        // Ends the consumer's loop, so the scope can join it
+        drop(full);
+        error.map_or(Ok((source.head_len, source.total)), Err)
+    });
+    for buffer in emptied.try_iter() {
+        pool::give(buffer);
+    }
+    result
+}
+
+/// Read until `buf` is full or the reader ends; returns how much was read
+pub(crate) fn fill<R: Read + ?Sized>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
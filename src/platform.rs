@@ -0,0 +1,201 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files that are neither regular files nor directories
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Special {
+    Fifo,
+    Socket,
+    CharDevice,
+    BlockDevice,
+}
+
+impl Special {
+    #[cfg(unix)]
+    pub fn of(file_type: fs::FileType) -> Option<Self> {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            Some(Special::Fifo)
+        } else if file_type.is_socket() {
+            Some(Special::Socket)
+        } else if file_type.is_char_device() {
+            Some(Special::CharDevice)
+        } else if file_type.is_block_device() {
+            Some(Special::BlockDevice)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn of(_file_type: fs::FileType) -> Option<Self> {
+        None
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Special::Fifo => "named pipe",
+            Special::Socket => "socket",
+            Special::CharDevice => "character device",
+            Special::BlockDevice => "block device",
+        }
+    }
+}
+
+/// Extended-length form (`\\?\C:\...`, `\\?\UNC\server\share\...`) of paths past the legacy
+/// MAX_PATH limit, for opening files deep in node_modules or backup trees. Reports keep the
+/// path as found.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    // Directories must leave room for an 8.3 file name under MAX_PATH (260)
+    const LEGACY_LIMIT: usize = 248;
+    let Some(text) = path.to_str() else {
+        return Cow::Borrowed(path);
+    };
+    if text.len() < LEGACY_LIMIT || text.starts_with(r"\\?\") {
+        return Cow::Borrowed(path);
+    }
+    // Verbatim paths skip normalization, so `.`, `..` and `/` must be resolved first
+    let Some(absolute) = std::path::absolute(path).ok().and_then(|p| p.to_str().map(str::to_string)) else {
+        return Cow::Borrowed(path);
+    };
+    Cow::Owned(match absolute.strip_prefix(r"\\") {
+        Some(unc) => PathBuf::from(format!(r"\\?\UNC\{}", unc)),
+        None => PathBuf::from(format!(r"\\?\{}", absolute)),
+    })
+}
+
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+/// Number of directory entries sharing this file's content, where the platform exposes it
+#[cfg(unix)]
+pub fn hard_link_count(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.nlink())
+}
+
+#[cfg(not(unix))]
+pub fn hard_link_count(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Run at the lowest CPU and IO priority, so scans yield to everything else on the host. Threads
+/// inherit it, so call this before any are started.
+#[cfg(unix)]
+pub fn lower_priority() -> std::io::Result<()> {
+    // SAFETY: plain syscalls on the current process with no pointers involved
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 19) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    #[cfg(target_os = "linux")]
+    {
+        // ioprio_set(IOPRIO_WHO_PROCESS, self, IOPRIO_CLASS_IDLE): disk time only when nobody else wants it
+        const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+        const IOPRIO_IDLE: libc::c_long = 3 << 13;
+        // SAFETY: ioprio_set takes only integers
+        if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, IOPRIO_IDLE) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Run at the lowest CPU and IO priority, so scans yield to everything else on the host. Threads
+/// inherit it, so call this before any are started.
+#[cfg(windows)]
+pub fn lower_priority() -> std::io::Result<()> {
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, SetPriorityClass, PROCESS_MODE_BACKGROUND_BEGIN};
+    // SAFETY: GetCurrentProcess returns a pseudo-handle that is always valid
+    if unsafe { SetPriorityClass(GetCurrentProcess(), PROCESS_MODE_BACKGROUND_BEGIN) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn lower_priority() -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "not supported on this platform"))
+}
+
+/// Open a file to be read front to back, hinting the OS to read ahead further and to recycle what
+/// was read sooner
+#[cfg(windows)]
+pub fn open_sequential(path: &Path) -> std::io::Result<fs::File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use windows_sys::Win32::Storage::FileSystem::FILE_FLAG_SEQUENTIAL_SCAN;
+    fs::OpenOptions::new().read(true).custom_flags(FILE_FLAG_SEQUENTIAL_SCAN).open(path)
+}
+
+/// Open a file to be read front to back, hinting the OS to read ahead further and to recycle what
+/// was read sooner
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn open_sequential(path: &Path) -> std::io::Result<fs::File> {
+    use std::os::fd::AsRawFd;
+    let file = fs::File::open(path)?;
+    // SAFETY: the descriptor stays open for the call; a hint the kernel rejects changes nothing
+    unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_SEQUENTIAL) };
+    Ok(file)
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "android")))]
+pub fn open_sequential(path: &Path) -> std::io::Result<fs::File> {
+    fs::File::open(path)
+}
+
+/// Ask the OS to evict `len` bytes of `file` at `offset` from its page cache, where it can
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn drop_cached(file: &fs::File, offset: u64, len: u64) {
+    use std::os::fd::AsRawFd;
+    let (Ok(offset), Ok(len)) = (libc::off_t::try_from(offset), libc::off_t::try_from(len)) else {
+        return;
+    };
+    // SAFETY: the descriptor stays open for the call; it only drops clean pages, so nothing is lost
+    unsafe { libc::posix_fadvise(file.as_raw_fd(), offset, len, libc::POSIX_FADV_DONTNEED) };
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn drop_cached(_file: &fs::File, _offset: u64, _len: u64) {}
+
+/// Which storage device a file is on: its device number on Unix, its volume elsewhere
+#[cfg(unix)]
+pub fn device_id(_path: &Path, metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.dev()
+}
+
+/// Which storage device a file is on: its device number on Unix, its volume elsewhere
+#[cfg(not(unix))]
+pub fn device_id(path: &Path, _metadata: &fs::Metadata) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::hash::DefaultHasher::new();
+    mount_point(path).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Where the device holding `path` is mounted, as far as the path shows: its topmost ancestor on
+/// the same device on Unix, its drive or share elsewhere
+#[cfg(unix)]
+pub fn mount_point(path: &Path) -> PathBuf {
+    use std::os::unix::fs::MetadataExt;
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let Ok(dev) = fs::metadata(&path).map(|m| m.dev()) else {
+        return path;
+    };
+    path.ancestors()
+        .take_while(|dir| fs::metadata(dir).is_ok_and(|m| m.dev() == dev))
+        .last()
+        .unwrap_or(&path)
+        .to_path_buf()
+}
+
+/// Where the device holding `path` is mounted, as far as the path shows: its topmost ancestor on
+/// the same device on Unix, its drive or share elsewhere
+#[cfg(not(unix))]
+pub fn mount_point(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    path.components().take_while(|c| !matches!(c, std::path::Component::Normal(_))).collect()
+}
@@ -0,0 +1,128 @@
+use anyhow::{bail, Context, Result};
+use enro::detector::{Detection, Detector, DetectorInput};
+use enro::FileType;
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// A detector loaded with --detector, by file extension: `.json` for a signature pack, `.rhai` for
+/// classification rules, `.wasm` for a sandboxed WebAssembly module
+#[derive(Clone)]
+pub struct DetectorPlugin {
+    pub path: PathBuf,
+    pub detector: Arc<dyn Detector>,
+}
+
+impl fmt::Debug for DetectorPlugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.detector.name(), self.path.display())
+    }
+}
+
+impl FromStr for DetectorPlugin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = PathBuf::from(s);
+        let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+        let detector: Result<Arc<dyn Detector>> = match extension.as_deref() {
+            Some("json") => SignaturePack::load(&path).map(|pack| Arc::new(pack) as _),
+            Some("rhai") => crate::rules::RuleScript::load(&path).map(|rules| Arc::new(rules) as _),
+            #[cfg(feature = "wasm")]
+            Some("wasm") => crate::wasm::WasmDetector::load(&path).map(|module| Arc::new(module) as _),
+            #[cfg(not(feature = "wasm"))]
+            Some("wasm") => return Err(format!("{}: enro was built without the `wasm` feature", s)),
+            _ => {
+                return Err(format!(
+                    "`{}` is not a detector; expected a .json signature pack, .rhai rules or a .wasm module",
+                    s
+                ))
+            }
+        };
+        Ok(DetectorPlugin {
+            detector: detector.map_err(|e| format!("{:#}", e))?,
+            path,
+        })
+    }
+}
+
+/// Formats recognized by their magic bytes, as declared in a JSON file:
+///
+/// `{"name": "acme/1", "signatures": [{"magic": "41434d45", "offset": 0, "type": "Document(ACMECAD)", "confidence": 95}]}`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignaturePack {
+    name: String,
+    signatures: Vec<Signature>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Signature {
+    /// Hex, spaces allowed
+    magic: String,
+    #[serde(default)]
+    offset: usize,
+    /// Only match files whose entropy is in this range, e.g. to tell an encrypted variant apart
+    min_entropy: Option<f64>,
+    max_entropy: Option<f64>,
+    /// Compact form, as in JSON reports: `Document(ACMECAD)`, `Encrypted`, ...
+    #[serde(rename = "type")]
+    file_type: String,
+    #[serde(default = "full_confidence")]
+    confidence: u8,
+    #[serde(skip)]
+    parsed: Option<(Vec<u8>, FileType)>,
+}
+
+fn full_confidence() -> u8 {
+    100
+}
+
+impl SignaturePack {
+    fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut pack: SignaturePack =
+            serde_json::from_str(&text).with_context(|| format!("{} is not a signature pack", path.display()))?;
+        for (i, signature) in pack.signatures.iter_mut().enumerate() {
+            let context = || format!("{}: signature {}", path.display(), i + 1);
+            let hex: String = signature.magic.chars().filter(|c| !c.is_whitespace()).collect();
+            if hex.is_empty() || !hex.is_ascii() || !hex.len().is_multiple_of(2) {
+                bail!("{}: magic must be an even number of hex digits", context());
+            }
+            let magic = (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                .collect::<Result<Vec<u8>, _>>()
+                .with_context(context)?;
+            let file_type = signature.file_type.parse().map_err(anyhow::Error::msg).with_context(context)?;
+            if signature.confidence > 100 {
+                bail!("{}: confidence must be 0-100", context());
+            }
+            signature.parsed = Some((magic, file_type));
+        }
+        Ok(pack)
+    }
+}
+
+impl Detector for SignaturePack {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn detect(&self, input: &DetectorInput) -> Option<Detection> {
+        self.signatures.iter().find_map(|signature| {
+            let (magic, file_type) = signature.parsed.as_ref()?;
+            let matched = input.header.get(signature.offset..).is_some_and(|data| data.starts_with(magic))
+                && signature.min_entropy.is_none_or(|min| input.entropy >= min)
+                && signature.max_entropy.is_none_or(|max| input.entropy <= max);
+            matched.then(|| Detection {
+                file_type: file_type.clone(),
+                confidence: signature.confidence,
+                label: None,
+            })
+        })
+    }
+}
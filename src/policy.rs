@@ -0,0 +1,177 @@
+use clap::ValueEnum;
+use colored::Colorize;
+use enro::FileAnalysis;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{category, display_path, Category};
+
+/// One --fail-on condition: a category, an entropy comparison, or an extension that does not match
+/// the detected content
+#[derive(Clone, Debug)]
+pub enum FailOn {
+    Category(Category),
+    Entropy(Comparison, f64),
+    MismatchedExtension,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+impl Comparison {
+    fn symbol(self) -> &'static str {
+        match self {
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+        }
+    }
+}
+
+impl FromStr for FailOn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("mismatched-extension") {
+            return Ok(FailOn::MismatchedExtension);
+        }
+        if let Some(rest) = s.strip_prefix("entropy") {
+            // Two-character operators first, so `>=` is not read as `>` followed by `=7.5`
+            let (comparison, value) = [
+                (">=", Comparison::GreaterOrEqual),
+                ("<=", Comparison::LessOrEqual),
+                (">", Comparison::Greater),
+                ("<", Comparison::Less),
+            ]
+            .into_iter()
+            .find_map(|(symbol, comparison)| Some((comparison, rest.trim_start().strip_prefix(symbol)?)))
+            .ok_or_else(|| format!("expected a comparison such as `entropy>7.9`, got `{}`", s))?;
+            let value: f64 = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("`{}` is not an entropy value (0-8)", value.trim()))?;
+            return Ok(FailOn::Entropy(comparison, value));
+        }
+        Category::from_str(s, true).map(FailOn::Category).map_err(|_| {
+            let categories: Vec<String> = Category::value_variants()
+                .iter()
+                .filter_map(|c| c.to_possible_value().map(|v| v.get_name().to_string()))
+                .collect();
+            format!(
+                "`{}` is not a category ({}), `entropy>N` or `mismatched-extension`",
+                s,
+                categories.join(", ")
+            )
+        })
+    }
+}
+
+impl fmt::Display for FailOn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailOn::Category(category) => match category.to_possible_value() {
+                Some(value) => f.write_str(value.get_name()),
+                None => write!(f, "{:?}", category),
+            },
+            FailOn::Entropy(comparison, value) => write!(f, "entropy{}{}", comparison.symbol(), value),
+            FailOn::MismatchedExtension => f.write_str("mismatched-extension"),
+        }
+    }
+}
+
+impl FailOn {
+    fn matches(&self, analysis: &FileAnalysis) -> bool {
+        match self {
+            FailOn::Category(target) => category(&analysis.file_type) == *target,
+            FailOn::Entropy(comparison, value) => match comparison {
+                Comparison::Greater => analysis.entropy > *value,
+                Comparison::GreaterOrEqual => analysis.entropy >= *value,
+                Comparison::Less => analysis.entropy < *value,
+                Comparison::LessOrEqual => analysis.entropy <= *value,
+            },
+            FailOn::MismatchedExtension => mismatched_extension(analysis),
+        }
+    }
+}
+
+/// What content a well-known extension promises; unknown extensions never count as mismatched
+fn expected_categories(extension: &str) -> Option<&'static [Category]> {
+    use Category::*;
+    let expected: &[Category] = match extension {
+        "txt" | "md" | "csv" | "tsv" | "log" | "json" | "xml" | "html" | "htm" | "ini" | "cfg" | "conf" | "yaml"
+        | "yml" | "toml" | "sh" | "bat" | "ps1" | "py" | "rs" | "c" | "h" | "cpp" | "js" | "ts" | "java" | "go"
+        | "sql" => &[Plaintext],
+        "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp" | "tif" | "tiff" | "ico" => &[Image],
+        "pdf" => &[Document],
+        // Office Open XML and OpenDocument are ZIP containers, legacy Office files OLE containers
+        "docx" | "xlsx" | "pptx" | "odt" | "ods" | "odp" | "doc" | "xls" | "ppt" => &[Document, Archive],
+        "zip" | "rar" | "7z" | "tar" | "iso" => &[Archive],
+        "gz" | "tgz" | "bz2" | "xz" | "zst" => &[Archive, Compressed],
+        _ => return None,
+    };
+    Some(expected)
+}
+
+/// Whether the extension claims one kind of content and the file holds another, e.g. a `.jpg`
+/// that is encrypted
+pub fn mismatched_extension(analysis: &FileAnalysis) -> bool {
+    let Some(extension) = analysis.path.extension().and_then(|e| e.to_str()) else {
+        return false;
+    };
+    expected_categories(&extension.to_ascii_lowercase())
+        .is_some_and(|expected| !expected.contains(&category(&analysis.file_type)))
+}
+
+/// Files matching any of `conditions`, each with the first condition it matched
+pub fn violations<'a>(results: &[FileAnalysis], conditions: &'a [FailOn]) -> Vec<(FileAnalysis, &'a FailOn)> {
+    let mut violations: Vec<_> = results
+        .iter()
+        .filter_map(|analysis| Some((analysis.clone(), conditions.iter().find(|c| c.matches(analysis))?)))
+        .collect();
+    violations.sort_by(|a, b| a.0.path.cmp(&b.0.path));
+    violations
+}
+
+/// List the files that fail the run; machine-readable runs get them on stderr so stdout stays
+/// parseable
+pub fn report(violations: &[(FileAnalysis, &FailOn)], machine_readable: bool) {
+    if violations.is_empty() {
+        return;
+    }
+    if machine_readable {
+        for (analysis, condition) in violations {
+            eprintln!(
+                "Failed --fail-on {}: {} ({}, entropy {:.2})",
+                condition,
+                display_path(&analysis.path),
+                analysis.file_type.display_compact(),
+                analysis.entropy
+            );
+        }
+        eprintln!("{} file(s) matched --fail-on", violations.len());
+        return;
+    }
+
+    println!("\n{}", "FAILED CHECKS (--fail-on)".red().bold());
+    for (analysis, condition) in violations {
+        println!(
+            "  {}  {}  {:.2}/8.0  ({})",
+            display_path(&analysis.path),
+            analysis.file_type.display_plain(),
+            analysis.entropy,
+            condition
+        );
+    }
+    println!(
+        "\n  {} {}",
+        "•".cyan(),
+        format!("{} file(s) matched --fail-on", violations.len()).red().bold()
+    );
+}
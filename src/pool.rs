@@ -0,0 +1,37 @@
+//! Read buffers kept per thread and reused across chunks and files, instead of allocated for each
+
+use std::cell::RefCell;
+
+// A file's first chunk, kept for inspection, and the one being read
+const KEPT: usize = 2;
+// Larger buffers are freed, so a thread that once read a huge file does not hold its chunks forever
+const MAX_KEPT_LEN: usize = 64 * 1024 * 1024;
+
+thread_local! {
+    static FREE: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A buffer of `len` bytes, holding whatever it was last used for
+pub(crate) fn take(len: usize) -> Vec<u8> {
+    match FREE.with_borrow_mut(Vec::pop) {
+        Some(mut buffer) if buffer.capacity() >= len => {
+            buffer.resize(len, 0);
+            buffer
+        }
+        // Fresh zeroed memory comes from the OS untouched, so a large buffer only costs the pages
+        // a read fills; growing a kept one would write every byte
+        _ => vec![0; len],
+    }
+}
+
+/// Hand a buffer from `take` back for reuse on this thread
+pub(crate) fn give(buffer: Vec<u8>) {
+    if buffer.capacity() > MAX_KEPT_LEN {
+        return;
+    }
+    FREE.with_borrow_mut(|free| {
+        if free.len() < KEPT {
+            free.push(buffer);
+        }
+    });
+}
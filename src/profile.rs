@@ -0,0 +1,66 @@
+use crate::{Args, Category, SortKey};
+
+// Leading bytes read per file by the sampling profiles
+const QUICK_SAMPLE: usize = 1024 * 1024;
+const RANSOMWARE_SAMPLE: usize = 4 * 1024 * 1024;
+
+/// Presets for --profile. They only fill in what the command line left unset, so any
+/// explicit option still wins.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Profile {
+    /// First 1 MiB of each file, no container inspection, summary only
+    Quick,
+    /// Whole files with every inspection: OLE streams, ZIP entries, image metadata and
+    /// steganography, extended attributes, Git objects, duplicates
+    Thorough,
+    /// Report only encrypted/random files, highest entropy first, with encrypted archive
+    /// entries and OLE streams inspected
+    Ransomware,
+    /// Whole files, tolerant of I/O errors, every inspection, header bytes, no symlink
+    /// following and a full account of skipped paths
+    Forensics,
+}
+
+impl Profile {
+    pub fn apply(self, args: &mut Args) {
+        match self {
+            Profile::Quick => {
+                args.max_bytes.get_or_insert(QUICK_SAMPLE);
+                if !args.simple && !args.json {
+                    args.summary_only = true;
+                }
+            }
+            Profile::Thorough => {
+                enable_inspections(args);
+                // Duplicate groups are a table of their own, not part of CSV output
+                args.duplicates |= !args.simple;
+            }
+            Profile::Ransomware => {
+                args.max_bytes.get_or_insert(RANSOMWARE_SAMPLE);
+                args.zip_entries = true;
+                args.ole_streams = true;
+                if args.threshold.is_none() && args.only.is_empty() && args.hide.is_empty() {
+                    args.only = vec![Category::Encrypted, Category::Random];
+                }
+                args.sort.get_or_insert(SortKey::Entropy);
+            }
+            Profile::Forensics => {
+                enable_inspections(args);
+                args.keep_going_io = true;
+                args.no_follow_symlinks = !args.follow_symlinks;
+                args.header_hex.get_or_insert(16);
+                args.show_skipped |= !args.simple;
+                args.duplicates |= !args.simple;
+            }
+        }
+    }
+}
+
+fn enable_inspections(args: &mut Args) {
+    args.ole_streams = true;
+    args.zip_entries = true;
+    args.stego = true;
+    args.metadata = true;
+    args.xattrs = true;
+    args.git_objects = true;
+}
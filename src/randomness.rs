@@ -0,0 +1,302 @@
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+use colored::Colorize;
+use enro::{calculate_entropy_from_counts, format_size, platform};
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+// Two-sided significance of every test: |z| beyond this fails at the 0.1% level
+const CRITICAL_Z: f64 = 3.29;
+// Below this the statistics are too noisy to tell random data from anything else
+const MIN_SAMPLE: u64 = 4096;
+// Monte Carlo points are built from 24-bit x and y coordinates, like `ent`
+const MONTE_CARLO_BYTES: usize = 6;
+
+/// Test whether a file's bytes are statistically indistinguishable from random
+#[derive(ClapArgs, Debug)]
+pub struct VerifyRandomArgs {
+    /// File to test
+    #[arg(value_name = "FILE")]
+    pub file: PathBuf,
+
+    /// Maximum number of bytes to read (omit to test the entire file)
+    #[arg(short = 'b', long)]
+    pub max_bytes: Option<usize>,
+
+    /// Simple output format (CSV, no colors)
+    #[arg(short, long, conflicts_with = "json")]
+    pub simple: bool,
+
+    /// Output results as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Running statistics over a byte stream
+struct Accumulator {
+    counts: [u64; 256],
+    total: u64,
+    sum: u64,
+    // Monte Carlo: points inside the quarter circle out of all points
+    inside: u64,
+    points: u64,
+    point: [u8; MONTE_CARLO_BYTES],
+    point_len: usize,
+    // Serial correlation of each byte with the next, wrapping around at the end
+    first: Option<u8>,
+    last: u8,
+    sum_products: f64,
+    sum_squares: f64,
+}
+
+struct Results {
+    bytes: u64,
+    entropy: f64,
+    chi_square: f64,
+    p_value: f64,
+    mean: f64,
+    pi: f64,
+    serial_correlation: f64,
+}
+
+impl Accumulator {
+    fn new() -> Self {
+        Accumulator {
+            counts: [0; 256],
+            total: 0,
+            sum: 0,
+            inside: 0,
+            points: 0,
+            point: [0; MONTE_CARLO_BYTES],
+            point_len: 0,
+            first: None,
+            last: 0,
+            sum_products: 0.0,
+            sum_squares: 0.0,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.counts[byte as usize] += 1;
+            self.sum += byte as u64;
+
+            self.point[self.point_len] = byte;
+            self.point_len += 1;
+            if self.point_len == MONTE_CARLO_BYTES {
+                let coordinate = |b: &[u8]| ((b[0] as u64) << 16 | (b[1] as u64) << 8 | b[2] as u64) as f64;
+                let radius = ((1u64 << 24) - 1) as f64;
+                let (x, y) = (coordinate(&self.point[..3]), coordinate(&self.point[3..]));
+                if x * x + y * y <= radius * radius {
+                    self.inside += 1;
+                }
+                self.points += 1;
+                self.point_len = 0;
+            }
+
+            match self.first {
+                None => self.first = Some(byte),
+                Some(_) => self.sum_products += self.last as f64 * byte as f64,
+            }
+            self.sum_squares += byte as f64 * byte as f64;
+            self.last = byte;
+        }
+        self.total += data.len() as u64;
+    }
+
+    fn finish(self) -> Results {
+        let n = self.total as f64;
+        let expected = n / 256.0;
+        let chi_square: f64 = self
+            .counts
+            .iter()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum();
+
+        let sum = self.sum as f64;
+        let products = self.sum_products + self.last as f64 * self.first.unwrap_or(0) as f64;
+        let denominator = n * self.sum_squares - sum * sum;
+        let serial_correlation = if denominator > 0.0 {
+            (n * products - sum * sum) / denominator
+        } else {
+            // Constant data is perfectly correlated
+            1.0
+        };
+
+        Results {
+            bytes: self.total,
+            entropy: calculate_entropy_from_counts(&self.counts, self.total as usize),
+            chi_square,
+            p_value: chi_square_upper_tail(chi_square, 255.0),
+            mean: if self.total > 0 { sum / n } else { 0.0 },
+            pi: if self.points > 0 { 4.0 * self.inside as f64 / self.points as f64 } else { 0.0 },
+            serial_correlation,
+        }
+    }
+}
+
+impl Results {
+    // Bytes uniform on 0..=255 have mean 127.5 and standard deviation sqrt((256² - 1) / 12)
+    fn mean_passes(&self) -> bool {
+        let sigma = ((256.0f64 * 256.0 - 1.0) / 12.0).sqrt() / (self.bytes as f64).sqrt();
+        (self.mean - 127.5).abs() <= CRITICAL_Z * sigma
+    }
+
+    // Extremely good fits are as suspicious as bad ones (counters, generated patterns)
+    fn chi_square_passes(&self) -> bool {
+        (0.001..=0.999).contains(&self.p_value)
+    }
+
+    fn pi_passes(&self) -> bool {
+        let points = (self.bytes / MONTE_CARLO_BYTES as u64) as f64;
+        let p = PI / 4.0;
+        let sigma = 4.0 * (p * (1.0 - p) / points).sqrt();
+        (self.pi - PI).abs() <= CRITICAL_Z * sigma
+    }
+
+    fn correlation_passes(&self) -> bool {
+        self.serial_correlation.abs() <= CRITICAL_Z / (self.bytes as f64).sqrt()
+    }
+
+    fn is_random(&self) -> bool {
+        self.chi_square_passes() && self.mean_passes() && self.pi_passes() && self.correlation_passes()
+    }
+}
+
+/// Probability of a chi-square value at least this large, via the Wilson-Hilferty
+/// normal approximation (accurate to a few digits for 255 degrees of freedom)
+fn chi_square_upper_tail(chi_square: f64, dof: f64) -> f64 {
+    let variance = 2.0 / (9.0 * dof);
+    let z = ((chi_square / dof).cbrt() - (1.0 - variance)) / variance.sqrt();
+    0.5 * erfc(z / std::f64::consts::SQRT_2)
+}
+
+// Complementary error function (Numerical Recipes' Chebyshev fit, relative error < 1.2e-7)
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807 + t * (-1.13520398 + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let result = t * poly.exp();
+    if x >= 0.0 {
+        result
+    } else {
+        2.0 - result
+    }
+}
+
+/// Whether data is large enough to judge and passes every test
+pub fn passes(data: &[u8]) -> bool {
+    let mut accumulator = Accumulator::new();
+    accumulator.update(data);
+    let results = accumulator.finish();
+    results.bytes >= MIN_SAMPLE && results.is_random()
+}
+
+pub fn run(args: &VerifyRandomArgs) -> Result<()> {
+    let file = File::open(platform::long_path(&args.file))
+        .with_context(|| format!("Failed to open file: {}", args.file.display()))?;
+    let limit = args.max_bytes.map_or(u64::MAX, |max| max as u64);
+    let mut reader = file.take(limit);
+
+    let mut accumulator = Accumulator::new();
+    let mut chunk = vec![0u8; 1024 * 1024];
+    loop {
+        let n = reader.read(&mut chunk).context("Failed to read file")?;
+        if n == 0 {
+            break;
+        }
+        accumulator.update(&chunk[..n]);
+    }
+    let results = accumulator.finish();
+    let enough = results.bytes >= MIN_SAMPLE;
+
+    if args.json {
+        let output = serde_json::json!({
+            "path": args.file.display().to_string(),
+            "bytes": results.bytes,
+            "entropy": results.entropy,
+            "chi_square": results.chi_square,
+            "p_value": results.p_value,
+            "mean": results.mean,
+            "monte_carlo_pi": results.pi,
+            "serial_correlation": results.serial_correlation,
+            "passed": {
+                "chi_square": results.chi_square_passes(),
+                "mean": results.mean_passes(),
+                "monte_carlo_pi": results.pi_passes(),
+                "serial_correlation": results.correlation_passes(),
+            },
+            "random": enough && results.is_random(),
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if args.simple {
+        println!("Test,Value,Passed");
+        println!("entropy,{:.6},", results.entropy);
+        println!("chi_square,{:.2},{}", results.chi_square, results.chi_square_passes());
+        println!("p_value,{:.4},", results.p_value);
+        println!("mean,{:.4},{}", results.mean, results.mean_passes());
+        println!("monte_carlo_pi,{:.6},{}", results.pi, results.pi_passes());
+        println!("serial_correlation,{:.6},{}", results.serial_correlation, results.correlation_passes());
+        println!("random,,{}", enough && results.is_random());
+        return Ok(());
+    }
+
+    let verdict = |passed: bool| if passed { "pass".green() } else { "FAIL".red() };
+    println!(
+        "{} ({}, {} bytes)\n",
+        args.file.display().to_string().bold(),
+        format_size(results.bytes),
+        results.bytes
+    );
+    println!("  {:<20}{:.6} bits/byte (8 = random)", "Entropy", results.entropy);
+    println!(
+        "  {:<20}{:<36}{}",
+        "Chi-square",
+        format!("{:.2} (p = {:.4})", results.chi_square, results.p_value),
+        verdict(results.chi_square_passes())
+    );
+    println!(
+        "  {:<20}{:<36}{}",
+        "Arithmetic mean",
+        format!("{:.4} (127.5 = random)", results.mean),
+        verdict(results.mean_passes())
+    );
+    println!(
+        "  {:<20}{:<36}{}",
+        "Monte Carlo pi",
+        format!("{:.6} (error {:.2}%)", results.pi, (results.pi - PI).abs() / PI * 100.0),
+        verdict(results.pi_passes())
+    );
+    println!(
+        "  {:<20}{:<36}{}",
+        "Serial correlation",
+        format!("{:.6} (0 = random)", results.serial_correlation),
+        verdict(results.correlation_passes())
+    );
+
+    println!();
+    if !enough {
+        println!(
+            "{} Only {} bytes: at least {} are needed for a meaningful verdict",
+            "•".cyan(),
+            results.bytes,
+            MIN_SAMPLE
+        );
+    } else if results.is_random() {
+        println!("{} {}", "•".cyan(), "Consistent with random or encrypted data".green().bold());
+    } else {
+        println!("{} {}", "•".cyan(), "Not random: structure detectable in the byte stream".yellow().bold());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+
+// Step over bad regions a sector group at a time, growing the step while errors continue
+const MIN_SKIP: u64 = 4 * 1024;
+const MAX_SKIP: u64 = 1024 * 1024;
+
+/// Reader for damaged media, bounded to `end`: with `keep_going` read errors skip ahead
+/// instead of failing, and the number of bytes that could not be read is tallied
+pub struct TolerantReader<F = File> {
+    file: F,
+    pos: u64,
+    end: u64,
+    keep_going: bool,
+    skip: u64,
+    pub unreadable: u64,
+}
+
+impl<F: Read + Seek> TolerantReader<F> {
+    /// Reads from the file's current position up to absolute offset `end`
+    pub fn new(mut file: F, end: u64, keep_going: bool) -> io::Result<Self> {
+        let pos = file.stream_position()?;
+        Ok(TolerantReader {
+            file,
+            pos,
+            end,
+            keep_going,
+            skip: MIN_SKIP,
+            unreadable: 0,
+        })
+    }
+
+    /// Absolute offset of the next byte, past any skipped regions
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+}
+
+impl<F: Read + Seek> Read for TolerantReader<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.pos >= self.end {
+                return Ok(0);
+            }
+            let want = buf.len().min((self.end - self.pos).try_into().unwrap_or(usize::MAX));
+            match self.file.read(&mut buf[..want]) {
+                Ok(n) => {
+                    self.pos += n as u64;
+                    self.skip = MIN_SKIP;
+                    return Ok(n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) if !self.keep_going => return Err(e),
+                Err(_) => {
+                    let skipped = self.skip.min(self.end - self.pos);
+                    self.pos += skipped;
+                    self.unreadable += skipped;
+                    self.skip = (self.skip * 2).min(MAX_SKIP);
+                    self.file.seek(SeekFrom::Start(self.pos))?;
+                }
+            }
+        }
+    }
+}
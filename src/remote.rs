@@ -0,0 +1,250 @@
+use anyhow::{bail, Context, Result};
+use enro::{analyze_reader, zip, AnalyzeOptions, FileAnalysis, FileType};
+use rayon::prelude::*;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::Args;
+
+/// URL prefixes served by optional backends, the cargo feature providing each and whether it is built in
+const BACKENDS: &[(&str, &str, bool)] = &[
+    ("s3://", "s3", cfg!(feature = "s3")),
+    ("az://", "azure", cfg!(feature = "azure")),
+    ("gs://", "gcs", cfg!(feature = "gcs")),
+    ("sftp://", "sftp", cfg!(feature = "sftp")),
+    ("smb://", "smb", cfg!(feature = "smb")),
+];
+
+/// Whether a PATH argument names a remote resource (HTTP(S), object storage, SFTP, SMB) instead of a local file
+pub fn is_url(path: &Path) -> bool {
+    path.to_str().is_some_and(|s| {
+        s.starts_with("http://")
+            || s.starts_with("https://")
+            || BACKENDS.iter().any(|(prefix, _, _)| s.starts_with(prefix))
+    })
+}
+
+pub fn agent() -> &'static ureq::Agent {
+    static AGENT: OnceLock<ureq::Agent> = OnceLock::new();
+    AGENT.get_or_init(|| {
+        ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(30))
+            .timeout_read(Duration::from_secs(60))
+            .user_agent(concat!("enro/", env!("CARGO_PKG_VERSION")))
+            .build()
+    })
+}
+
+/// Analyze every URL in parallel; failures are reported and left out
+pub fn analyze_urls(urls: &[&str], args: &Args) -> Vec<FileAnalysis> {
+    urls.par_iter()
+        .flat_map_iter(|url| match analyze(url, args) {
+            Ok(results) => results,
+            Err(e) => {
+                eprintln!("Warning: {:#}", e);
+                Vec::new()
+            }
+        })
+        .collect()
+}
+
+fn analyze(url: &str, args: &Args) -> Result<Vec<FileAnalysis>> {
+    match BACKENDS.iter().find(|(prefix, _, _)| url.starts_with(prefix)) {
+        Some((_, feature, false)) => bail!("{}: enro was built without the `{}` feature", url, feature),
+        Some(("sftp://", _, _)) => analyze_sftp(url, args),
+        Some(("smb://", _, _)) => analyze_smb(url, args),
+        Some(_) => analyze_objects(url, &args.analyze_options(), args.min_size),
+        None => Ok(vec![analyze_url(url, &args.analyze_options())?]),
+    }
+}
+
+#[cfg(feature = "sftp")]
+fn analyze_sftp(url: &str, args: &Args) -> Result<Vec<FileAnalysis>> {
+    crate::sftp::analyze(url, args)
+}
+
+#[cfg(not(feature = "sftp"))]
+fn analyze_sftp(_url: &str, _args: &Args) -> Result<Vec<FileAnalysis>> {
+    unreachable!("the SFTP backend is not built in")
+}
+
+#[cfg(feature = "smb")]
+fn analyze_smb(url: &str, args: &Args) -> Result<Vec<FileAnalysis>> {
+    crate::smb::analyze(url, args)
+}
+
+#[cfg(not(feature = "smb"))]
+fn analyze_smb(_url: &str, _args: &Args) -> Result<Vec<FileAnalysis>> {
+    unreachable!("the SMB backend is not built in")
+}
+
+#[cfg(feature = "object-store")]
+fn analyze_objects(url: &str, options: &AnalyzeOptions, min_size: u64) -> Result<Vec<FileAnalysis>> {
+    crate::cloud::analyze_objects(url, options, min_size)
+}
+
+#[cfg(not(feature = "object-store"))]
+fn analyze_objects(_url: &str, _options: &AnalyzeOptions, _min_size: u64) -> Result<Vec<FileAnalysis>> {
+    unreachable!("no object storage backend is built in")
+}
+
+/// Classify a remote file without saving it. With --max-bytes and no digests only that prefix is
+/// requested (Range), otherwise the body is streamed through once.
+fn analyze_url(url: &str, options: &AnalyzeOptions) -> Result<FileAnalysis> {
+    let mut request = agent().get(url);
+    if let Some(max) = options.max_bytes.filter(|_| options.hashes.is_empty()) {
+        request = request.set("Range", &format!("bytes=0-{}", max.saturating_sub(1)));
+    }
+    let response = request.call().context("Failed to fetch")?;
+
+    // A partial response carries the full size in Content-Range
+    let total = if response.status() == 206 {
+        response
+            .header("Content-Range")
+            .and_then(|range| range.rsplit_once('/'))
+            .and_then(|(_, total)| total.parse::<u64>().ok())
+    } else {
+        response.header("Content-Length").and_then(|len| len.parse::<u64>().ok())
+    };
+
+    // Servers that ignore Range send everything; analyze_body stops reading at the cap anyway
+    let mut analysis = analyze_body(url.to_string(), response.into_reader(), total, options)
+        .with_context(|| format!("Failed to read {}", url))?;
+    if total.is_some() {
+        inspect_zip_ranges(&mut analysis, options, |range| fetch_range(url, range));
+    }
+
+    Ok(analysis)
+}
+
+/// Classify a remote body of `size` bytes, reported under `name`. Digests cover the whole file, so
+/// it is only cut off at --max-bytes when none are wanted. A body that ends or fails past what
+/// classification counts keeps its classification but loses its digests, which would only match
+/// the prefix.
+pub fn analyze_body<R: Read>(name: String, reader: R, size: Option<u64>, options: &AnalyzeOptions) -> Result<FileAnalysis> {
+    let counted = options.max_bytes.map_or(u64::MAX, |max| max as u64);
+    let limit = if options.hashes.is_empty() { counted } else { u64::MAX };
+    let mut body = Body {
+        inner: reader.take(limit),
+        read: 0,
+        counted,
+        failed: false,
+    };
+    let mut analysis = analyze_reader(PathBuf::from(name), &mut body, options)?;
+    if let Some(size) = size {
+        analysis.size = size;
+    }
+    if !analysis.hashes.is_empty() && (body.failed || analysis.bytes_read < analysis.size) {
+        analysis.hashes.clear();
+        analysis.notes.push(format!(
+            "read {} of {}, no digests",
+            enro::format_size(analysis.bytes_read),
+            enro::format_size(analysis.size)
+        ));
+    }
+    Ok(analysis)
+}
+
+/// A remote body whose errors past the counted prefix end it instead of failing the analysis
+struct Body<R> {
+    inner: R,
+    read: u64,
+    counted: u64,
+    failed: bool,
+}
+
+impl<R: Read> Read for Body<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner.read(buf) {
+            Ok(n) => {
+                self.read += n as u64;
+                Ok(n)
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => Err(e),
+            Err(_) if self.read >= self.counted => {
+                self.failed = true;
+                Ok(0)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn fetch_range(url: &str, range: Range<u64>) -> io::Result<Vec<u8>> {
+    let response = agent()
+        .get(url)
+        .set("Range", &format!("bytes={}-{}", range.start, range.end - 1))
+        .call()
+        .map_err(io::Error::other)?;
+    if response.status() != 206 {
+        return Err(io::Error::other("server does not support range requests"));
+    }
+
+    let mut data = Vec::new();
+    response.into_reader().take(range.end - range.start).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Read the central directory of a remote ZIP that was only partly downloaded, fetching just
+/// the ranges it needs, so entries and encryption are known without transferring the archive
+pub fn inspect_zip_ranges<F>(analysis: &mut FileAnalysis, options: &AnalyzeOptions, fetch: F)
+where
+    F: FnMut(Range<u64>) -> io::Result<Vec<u8>>,
+{
+    // Anything within the first chunk was already inspected in memory
+    let inspected = options.max_bytes.unwrap_or(usize::MAX).min(enro::get_optimal_chunk_size());
+    if analysis.size <= inspected as u64 || !is_zip(&analysis.file_type) {
+        return;
+    }
+
+    let mut reader = RangeReader { fetch, len: analysis.size, pos: 0 };
+    if let Ok(entries) = zip::read_central_directory(&mut reader) {
+        enro::inspect_zip(analysis, entries, options);
+    }
+}
+
+// ZIP containers, including the OOXML/OpenDocument formats reported as documents
+fn is_zip(file_type: &FileType) -> bool {
+    match file_type {
+        FileType::Archive(name) => name == "ZIP",
+        FileType::Document(name) => matches!(name.as_str(), "DOCX" | "XLSX" | "PPTX" | "ODT" | "ODS" | "ODP"),
+        _ => false,
+    }
+}
+
+/// Seekable view of a remote file of known length; every read is its own range request
+struct RangeReader<F> {
+    fetch: F,
+    len: u64,
+    pos: u64,
+}
+
+impl<F: FnMut(Range<u64>) -> io::Result<Vec<u8>>> Read for RangeReader<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let end = self.len.min(self.pos + buf.len() as u64);
+        if end <= self.pos {
+            return Ok(0);
+        }
+
+        let data = (self.fetch)(self.pos..end)?;
+        let n = data.len().min(buf.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<F> Seek for RangeReader<F> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        self.pos = target.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before start"))?;
+        Ok(self.pos)
+    }
+}
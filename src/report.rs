@@ -0,0 +1,489 @@
+use anyhow::{bail, Context, Result};
+use clap::{Args as ClapArgs, ValueEnum};
+use colored::Colorize;
+use enro::{format_size, FileType};
+use prettytable::{Cell, Row};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::history::{format_time, new_table, History, Record, ScanInfo};
+use crate::{category, Category};
+
+/// Summarize the scans recorded over a period: classification counts over time, newly encrypted
+/// files and the directories that grew the most
+#[derive(ClapArgs, Debug)]
+pub struct ReportArgs {
+    /// History database written by `enro scan --history`
+    #[arg(long, value_name = "DB")]
+    pub history: PathBuf,
+
+    /// Period to summarize, ending now (`30d`, `12weeks`, `24h`)
+    #[arg(long, value_name = "DURATION", default_value = "30d", value_parser = humantime::parse_duration)]
+    pub last: Duration,
+
+    /// Report format
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: ReportFormat,
+
+    /// Write the report to this file instead of standard output
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// How many of the fastest-growing directories to list
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    pub top: usize,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ReportFormat {
+    /// Plain text tables
+    Text,
+    /// A self-contained HTML page
+    Html,
+}
+
+/// One scan in the period, with its file count for each category
+struct ScanCounts<'a> {
+    scan: &'a ScanInfo,
+    /// Indexed like `Category::value_variants()`
+    counts: Vec<u64>,
+}
+
+#[derive(Default)]
+struct DirectoryGrowth {
+    path: String,
+    size_before: u64,
+    size_now: u64,
+    files_before: u64,
+    files_now: u64,
+}
+
+impl DirectoryGrowth {
+    fn growth(&self) -> i64 {
+        self.size_now as i64 - self.size_before as i64
+    }
+}
+
+struct Report<'a> {
+    db: &'a Path,
+    since: SystemTime,
+    until: SystemTime,
+    last: Duration,
+    scans: Vec<ScanCounts<'a>>,
+    /// Categories any scan in the period saw, as indexes into `Category::value_variants()`
+    categories: Vec<usize>,
+    newly_encrypted: Vec<(Option<FileType>, SystemTime, Record)>,
+    /// The scans directory growth is measured between
+    growth_range: Option<(&'a ScanInfo, &'a ScanInfo)>,
+    growing: Vec<DirectoryGrowth>,
+}
+
+impl Report<'_> {
+    /// How each category's count moved from the first to the last scan of the period
+    fn changes(&self) -> Vec<(Category, i64)> {
+        let (Some(first), Some(last)) = (self.scans.first(), self.scans.last()) else {
+            return Vec::new();
+        };
+        self.categories
+            .iter()
+            .map(|&i| (Category::value_variants()[i], last.counts[i] as i64 - first.counts[i] as i64))
+            .filter(|&(_, delta)| delta != 0)
+            .collect()
+    }
+}
+
+fn category_name(category: Category) -> &'static str {
+    match category {
+        Category::Archive => "Archive",
+        Category::Document => "Document",
+        Category::Image => "Image",
+        Category::Encrypted => "Encrypted",
+        Category::Random => "Random",
+        Category::Plaintext => "Plaintext",
+        Category::Binary => "Binary",
+        Category::Compressed => "Compressed",
+        Category::Git => "Git",
+    }
+}
+
+fn category_color(category: Category) -> &'static str {
+    match category {
+        Category::Archive => "#8e6bbf",
+        Category::Document => "#4a90d9",
+        Category::Image => "#47a8a0",
+        Category::Encrypted => "#d9534f",
+        Category::Random => "#f0ad4e",
+        Category::Plaintext => "#5cb85c",
+        Category::Binary => "#777777",
+        Category::Compressed => "#b8a36b",
+        Category::Git => "#e07b39",
+    }
+}
+
+pub fn run(args: &ReportArgs) -> Result<()> {
+    if !args.history.exists() {
+        bail!("History database not found: {}", args.history.display());
+    }
+    let history = History::open(&args.history)?;
+    let until = SystemTime::now();
+    let since = until.checked_sub(args.last).unwrap_or(UNIX_EPOCH);
+
+    let all = history.scans()?;
+    let in_period: Vec<&ScanInfo> = all.iter().filter(|s| s.time >= since).collect();
+    let variants = Category::value_variants();
+    let mut scans = Vec::new();
+    for &scan in &in_period {
+        let mut counts = vec![0; variants.len()];
+        for (file_type, count) in history.type_counts(scan.id)? {
+            let index = variants
+                .iter()
+                .position(|&c| c == category(&file_type))
+                .expect("every category is a variant");
+            counts[index] += count;
+        }
+        scans.push(ScanCounts { scan, counts });
+    }
+    let categories = (0..variants.len())
+        .filter(|&i| scans.iter().any(|s| s.counts[i] > 0))
+        .collect();
+
+    // Growth counts from the last scan before the period when there is one, so a single scan in
+    // the period still has something to compare with
+    let before_period = all.iter().rfind(|s| s.time < since);
+    let growth_range = match (before_period.or(in_period.first().copied()), in_period.last()) {
+        (Some(from), Some(&to)) if from.id != to.id => Some((from, to)),
+        _ => None,
+    };
+    let growing = match growth_range {
+        Some((from, to)) => directory_growth(&history.load(from.id)?, &history.load(to.id)?, args.top),
+        None => Vec::new(),
+    };
+
+    let report = Report {
+        db: &args.history,
+        since,
+        until,
+        last: args.last,
+        scans,
+        categories,
+        newly_encrypted: history.became(Category::Encrypted, since)?,
+        growth_range,
+        growing,
+    };
+    let rendered = match args.format {
+        ReportFormat::Text => render_text(&report)?,
+        ReportFormat::Html => render_html(&report)?,
+    };
+    match &args.output {
+        Some(path) => {
+            fs::write(path, rendered).with_context(|| format!("Failed to write {}", path.display()))?;
+            println!("  {} {}", "•".cyan(), format!("Report written to {}", path.display()).bold());
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+/// Total size and file count per directory in both scans, keeping the `top` directories that grew
+fn directory_growth(before: &[Record], now: &[Record], top: usize) -> Vec<DirectoryGrowth> {
+    let mut directories: BTreeMap<String, DirectoryGrowth> = BTreeMap::new();
+    let mut add = |record: &Record, is_now: bool| {
+        let directory = match Path::new(&record.path).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.display().to_string(),
+            _ => ".".to_string(),
+        };
+        let entry = directories.entry(directory.clone()).or_insert_with(|| DirectoryGrowth {
+            path: directory,
+            ..Default::default()
+        });
+        if is_now {
+            entry.size_now += record.size;
+            entry.files_now += 1;
+        } else {
+            entry.size_before += record.size;
+            entry.files_before += 1;
+        }
+    };
+    for record in before {
+        add(record, false);
+    }
+    for record in now {
+        add(record, true);
+    }
+
+    let mut growing: Vec<DirectoryGrowth> = directories.into_values().filter(|d| d.growth() > 0).collect();
+    // Stable, so ties stay in path order
+    growing.sort_by_key(|d| std::cmp::Reverse(d.growth()));
+    growing.truncate(top);
+    growing
+}
+
+fn signed(delta: i64) -> String {
+    format!("{:+}", delta)
+}
+
+fn signed_size(delta: i64) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{}{}", sign, format_size(delta.unsigned_abs()))
+}
+
+fn render_text(report: &Report) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "enro report: {}", report.db.display())?;
+    writeln!(
+        out,
+        "{} to {} (last {}), {} scan(s)",
+        format_time(report.since),
+        format_time(report.until),
+        humantime::format_duration(report.last),
+        report.scans.len()
+    )?;
+
+    writeln!(out, "\nCLASSIFICATION COUNTS")?;
+    if report.scans.is_empty() {
+        writeln!(out, "  No scans recorded in this period.")?;
+    } else {
+        let mut headers = vec!["Scan", "Time", "Files"];
+        headers.extend(report.categories.iter().map(|&i| category_name(Category::value_variants()[i])));
+        let mut table = new_table(&headers);
+        for s in &report.scans {
+            let mut cells = vec![
+                Cell::new(&s.scan.id.to_string()),
+                Cell::new(&format_time(s.scan.time)),
+                Cell::new(&s.scan.files.to_string()),
+            ];
+            cells.extend(report.categories.iter().map(|&i| Cell::new(&s.counts[i].to_string())));
+            table.add_row(Row::new(cells));
+        }
+        write!(out, "{}", table)?;
+        if report.scans.len() > 1 {
+            let changes: Vec<String> = report
+                .changes()
+                .iter()
+                .map(|&(c, delta)| format!("{} {}", category_name(c), signed(delta)))
+                .collect();
+            let changes = if changes.is_empty() {
+                "no change".to_string()
+            } else {
+                changes.join(", ")
+            };
+            writeln!(
+                out,
+                "  From scan {} to scan {}: {}",
+                report.scans[0].scan.id,
+                report.scans[report.scans.len() - 1].scan.id,
+                changes
+            )?;
+        }
+    }
+
+    writeln!(out, "\nNEWLY ENCRYPTED ({})", report.newly_encrypted.len())?;
+    if report.newly_encrypted.is_empty() {
+        writeln!(out, "  No files became encrypted in this period.")?;
+    } else {
+        let mut table = new_table(&["Path", "Before", "Entropy", "Size", "Seen"]);
+        for (before, time, r) in &report.newly_encrypted {
+            table.add_row(Row::new(vec![
+                Cell::new(&r.path),
+                Cell::new(&before.as_ref().map_or_else(|| "(new)".to_string(), FileType::display_plain)),
+                Cell::new(&format!("{:.2}/8.0", r.entropy)),
+                Cell::new(&format_size(r.size)),
+                Cell::new(&format_time(*time)),
+            ]));
+        }
+        write!(out, "{}", table)?;
+    }
+
+    match report.growth_range {
+        Some((from, to)) => {
+            writeln!(out, "\nTOP GROWING DIRECTORIES (scan {} to scan {})", from.id, to.id)?;
+            if report.growing.is_empty() {
+                writeln!(out, "  No directory grew.")?;
+            } else {
+                let mut table = new_table(&["Directory", "Files", "Before", "Now", "Growth"]);
+                for d in &report.growing {
+                    table.add_row(Row::new(vec![
+                        Cell::new(&d.path),
+                        Cell::new(&format!("{} ({})", d.files_now, signed(d.files_now as i64 - d.files_before as i64))),
+                        Cell::new(&format_size(d.size_before)),
+                        Cell::new(&format_size(d.size_now)),
+                        Cell::new(&signed_size(d.growth())),
+                    ]));
+                }
+                write!(out, "{}", table)?;
+            }
+        }
+        None => {
+            writeln!(out, "\nTOP GROWING DIRECTORIES")?;
+            writeln!(out, "  Needs at least two scans to compare.")?;
+        }
+    }
+    Ok(out)
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin: 0.5em 0 1em; }
+th, td { padding: 0.3em 0.8em; border-bottom: 1px solid #ddd; text-align: left; }
+td.num { text-align: right; font-variant-numeric: tabular-nums; }
+.bar { display: flex; width: 16em; height: 0.9em; background: #eee; }
+.bar span { display: block; height: 100%; }
+.legend span { display: inline-block; width: 0.8em; height: 0.8em; margin: 0 0.3em 0 1em; }
+.none, .period { color: #666; }
+.up { color: #c9302c; }";
+
+fn render_html(report: &Report) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>enro report {}</title>", escape_html(&format_time(report.until)))?;
+    writeln!(out, "<style>\n{}\n</style>\n</head>\n<body>", STYLE)?;
+    writeln!(out, "<h1>enro report</h1>")?;
+    writeln!(
+        out,
+        "<p class=\"period\">{} to {} (last {}), {} scan(s) in <code>{}</code></p>",
+        escape_html(&format_time(report.since)),
+        escape_html(&format_time(report.until)),
+        humantime::format_duration(report.last),
+        report.scans.len(),
+        escape_html(&report.db.display().to_string())
+    )?;
+
+    writeln!(out, "<h2>Classification counts</h2>")?;
+    if report.scans.is_empty() {
+        writeln!(out, "<p class=\"none\">No scans recorded in this period.</p>")?;
+    } else {
+        write!(out, "<p class=\"legend\">")?;
+        for &i in &report.categories {
+            let c = Category::value_variants()[i];
+            write!(out, "<span style=\"background:{}\"></span>{}", category_color(c), category_name(c))?;
+        }
+        writeln!(out, "</p>")?;
+        write!(out, "<table>\n<tr><th>Scan</th><th>Time</th><th>Files</th>")?;
+        for &i in &report.categories {
+            write!(out, "<th>{}</th>", category_name(Category::value_variants()[i]))?;
+        }
+        writeln!(out, "<th></th></tr>")?;
+        for s in &report.scans {
+            write!(
+                out,
+                "<tr><td class=\"num\">{}</td><td>{}</td><td class=\"num\">{}</td>",
+                s.scan.id,
+                escape_html(&format_time(s.scan.time)),
+                s.scan.files
+            )?;
+            for &i in &report.categories {
+                write!(out, "<td class=\"num\">{}</td>", s.counts[i])?;
+            }
+            write!(out, "<td><div class=\"bar\">")?;
+            for &i in &report.categories {
+                if s.counts[i] == 0 {
+                    continue;
+                }
+                let c = Category::value_variants()[i];
+                write!(
+                    out,
+                    "<span style=\"width:{:.2}%;background:{}\" title=\"{}: {}\"></span>",
+                    s.counts[i] as f64 * 100.0 / s.scan.files.max(1) as f64,
+                    category_color(c),
+                    category_name(c),
+                    s.counts[i]
+                )?;
+            }
+            writeln!(out, "</div></td></tr>")?;
+        }
+        writeln!(out, "</table>")?;
+        if report.scans.len() > 1 {
+            let changes: Vec<String> = report
+                .changes()
+                .iter()
+                .map(|&(c, delta)| {
+                    let class = if matches!(c, Category::Encrypted | Category::Random) && delta > 0 {
+                        " class=\"up\""
+                    } else {
+                        ""
+                    };
+                    format!("{} <span{}>{}</span>", category_name(c), class, signed(delta))
+                })
+                .collect();
+            let changes = if changes.is_empty() {
+                "no change".to_string()
+            } else {
+                changes.join(", ")
+            };
+            writeln!(
+                out,
+                "<p>From scan {} to scan {}: {}</p>",
+                report.scans[0].scan.id,
+                report.scans[report.scans.len() - 1].scan.id,
+                changes
+            )?;
+        }
+    }
+
+    writeln!(out, "<h2>Newly encrypted files ({})</h2>", report.newly_encrypted.len())?;
+    if report.newly_encrypted.is_empty() {
+        writeln!(out, "<p class=\"none\">No files became encrypted in this period.</p>")?;
+    } else {
+        writeln!(out, "<table>\n<tr><th>Path</th><th>Before</th><th>Entropy</th><th>Size</th><th>Seen</th></tr>")?;
+        for (before, time, r) in &report.newly_encrypted {
+            writeln!(
+                out,
+                "<tr><td><code>{}</code></td><td>{}</td><td class=\"num\">{:.2}</td><td class=\"num\">{}</td><td>{}</td></tr>",
+                escape_html(&r.path),
+                escape_html(&before.as_ref().map_or_else(|| "(new)".to_string(), FileType::display_plain)),
+                r.entropy,
+                format_size(r.size),
+                escape_html(&format_time(*time))
+            )?;
+        }
+        writeln!(out, "</table>")?;
+    }
+
+    writeln!(out, "<h2>Top growing directories</h2>")?;
+    match report.growth_range {
+        Some((from, to)) => {
+            writeln!(
+                out,
+                "<p class=\"period\">Scan {} ({}) to scan {} ({})</p>",
+                from.id,
+                escape_html(&format_time(from.time)),
+                to.id,
+                escape_html(&format_time(to.time))
+            )?;
+            if report.growing.is_empty() {
+                writeln!(out, "<p class=\"none\">No directory grew.</p>")?;
+            } else {
+                writeln!(
+                    out,
+                    "<table>\n<tr><th>Directory</th><th>Files</th><th>Before</th><th>Now</th><th>Growth</th></tr>"
+                )?;
+                for d in &report.growing {
+                    writeln!(
+                        out,
+                        "<tr><td><code>{}</code></td><td class=\"num\">{} ({})</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                        escape_html(&d.path),
+                        d.files_now,
+                        signed(d.files_now as i64 - d.files_before as i64),
+                        format_size(d.size_before),
+                        format_size(d.size_now),
+                        signed_size(d.growth())
+                    )?;
+                }
+                writeln!(out, "</table>")?;
+            }
+        }
+        None => writeln!(out, "<p class=\"none\">Needs at least two scans to compare.</p>")?,
+    }
+    writeln!(out, "</body>\n</html>")?;
+    Ok(out)
+}
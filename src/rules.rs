@@ -0,0 +1,140 @@
+use anyhow::{anyhow, Context, Result};
+use enro::detector::{Detection, Detector, DetectorInput};
+use enro::FileType;
+use rhai::{Blob, Engine, EvalAltResult, Scope, AST};
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::category;
+
+// Rules are site policy, not untrusted code, but a typo like `while true {}` should not hang a scan
+const MAX_OPERATIONS: u64 = 1_000_000;
+// Enough for signatures; `header` is copied into every script run
+const HEADER_LIMIT: usize = 64 << 10;
+
+thread_local! {
+    // The strongest classify() call of the script running on this thread
+    static VERDICT: RefCell<Option<(String, u8)>> = const { RefCell::new(None) };
+}
+
+/// Classification rules written in Rhai and run for every file, e.g.
+///
+/// `if entropy > 7.8 && ext == "docx" && !magic_matched { classify("SuspectedRansomware", 90) }`
+///
+/// Scripts see `path`, `name`, `ext` (lowercase, no dot), `size`, `entropy`, `bytes_read`, `type`
+/// (compact form such as `Document(DOCX)`), `category` (`document`, `encrypted`, ...),
+/// `magic_matched` (the built-in detection recognized a format) and `header` (leading bytes as a
+/// blob). `classify(label, confidence)` takes a file type such as `Encrypted`, or any other label,
+/// which keeps the type and is noted instead; the most confident call wins.
+pub struct RuleScript {
+    name: String,
+    engine: Engine,
+    ast: AST,
+    warned: AtomicBool,
+}
+
+impl RuleScript {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        // stdout belongs to the report
+        engine.on_print(|text| eprintln!("{}", text));
+        engine.on_debug(|text, _, position| eprintln!("{:?}: {}", position, text));
+        engine.register_fn("classify", |label: &str, confidence: i64| classify(label, confidence));
+        engine.register_fn("classify", |label: &str| classify(label, 100));
+
+        let script = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let ast = engine
+            .compile(&script)
+            .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+        Ok(RuleScript {
+            name: path.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+            engine,
+            ast,
+            warned: AtomicBool::new(false),
+        })
+    }
+
+    fn run(&self, input: &DetectorInput) -> Result<Option<Detection>, Box<EvalAltResult>> {
+        let extension = input.path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        let category = category(input.file_type);
+        let mut scope = Scope::new();
+        scope.push_constant("path", input.path.display().to_string());
+        scope.push_constant(
+            "name",
+            input.path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+        );
+        scope.push_constant("ext", extension.unwrap_or_default());
+        scope.push_constant("size", input.size as i64);
+        scope.push_constant("entropy", input.entropy);
+        scope.push_constant("bytes_read", input.bytes_read as i64);
+        scope.push_constant("type", input.file_type.display_compact());
+        scope.push_constant(
+            "category",
+            clap::ValueEnum::to_possible_value(&category).map_or_else(String::new, |v| v.get_name().to_string()),
+        );
+        scope.push_constant(
+            "magic_matched",
+            matches!(
+                input.file_type,
+                FileType::Archive(_) | FileType::Document(_) | FileType::Image(_) | FileType::Git(_)
+            ),
+        );
+        scope.push_constant("header", Blob::from(&input.header[..input.header.len().min(HEADER_LIMIT)]));
+
+        VERDICT.with_borrow_mut(|verdict| *verdict = None);
+        let result = self.engine.run_ast_with_scope(&mut scope, &self.ast);
+        let verdict = VERDICT.with_borrow_mut(Option::take);
+        result?;
+
+        Ok(verdict.map(|(label, confidence)| match label.parse::<FileType>() {
+            Ok(file_type) => Detection {
+                file_type,
+                confidence,
+                label: None,
+            },
+            Err(_) => Detection {
+                file_type: input.file_type.clone(),
+                confidence,
+                label: Some(label),
+            },
+        }))
+    }
+}
+
+fn classify(label: &str, confidence: i64) -> Result<(), Box<EvalAltResult>> {
+    let confidence = u8::try_from(confidence)
+        .ok()
+        .filter(|&c| c <= 100)
+        .ok_or_else(|| format!("classify(): confidence must be 0-100, got {}", confidence))?;
+    VERDICT.with_borrow_mut(|verdict| {
+        if verdict.as_ref().is_none_or(|(_, best)| confidence > *best) {
+            *verdict = Some((label.to_string(), confidence));
+        }
+    });
+    Ok(())
+}
+
+impl Detector for RuleScript {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn detect(&self, input: &DetectorInput) -> Option<Detection> {
+        match self.run(input) {
+            Ok(detection) => detection,
+            Err(e) => {
+                if !self.warned.swap(true, Ordering::Relaxed) {
+                    eprintln!(
+                        "Warning: rules {} failed on {}: {} (further failures are not shown)",
+                        self.name,
+                        input.path.display(),
+                        e
+                    );
+                }
+                None
+            }
+        }
+    }
+}
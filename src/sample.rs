@@ -0,0 +1,60 @@
+//! Reading a few regions of huge files instead of all of them or only their start
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_HEAD: u64 = 4 * 1024 * 1024;
+pub const DEFAULT_TAIL: u64 = 4 * 1024 * 1024;
+pub const DEFAULT_STRIPES: u32 = 16;
+pub const DEFAULT_STRIPE_LEN: u64 = 1024 * 1024;
+
+/// Regions to read from each file: its start, which classification needs, its end, and stripes
+/// spread evenly in between
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Sampling {
+    pub head: u64,
+    pub tail: u64,
+    pub stripes: u32,
+    pub stripe_len: u64,
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Sampling {
+            head: DEFAULT_HEAD,
+            tail: DEFAULT_TAIL,
+            stripes: DEFAULT_STRIPES,
+            stripe_len: DEFAULT_STRIPE_LEN,
+        }
+    }
+}
+
+impl Sampling {
+    /// Bytes sampled from a file of this size
+    pub fn sampled_len(&self, size: u64) -> u64 {
+        let total = self
+            .head
+            .saturating_add(self.tail)
+            .saturating_add(self.stripe_len.saturating_mul(self.stripes as u64));
+        total.min(size)
+    }
+
+    /// `(offset, length)` of each region in file order, or `None` when the regions would cover
+    /// the whole file anyway
+    pub fn regions(&self, size: u64) -> Option<Vec<(u64, u64)>> {
+        if self.sampled_len(size) >= size {
+            return None;
+        }
+        let mut regions = vec![(0, self.head)];
+        let middle = size - self.head - self.tail;
+        let stripes = self.stripes as u64;
+        if stripes > 0 && self.stripe_len > 0 {
+            // Equal gaps before, between and after the stripes
+            let gap = (middle - stripes * self.stripe_len) / (stripes + 1);
+            regions.extend((0..stripes).map(|i| (self.head + gap * (i + 1) + self.stripe_len * i, self.stripe_len)));
+        }
+        if self.tail > 0 {
+            regions.push((size - self.tail, self.tail));
+        }
+        Some(regions)
+    }
+}
@@ -0,0 +1,43 @@
+use chrono::Local;
+use croner::Cron;
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// A --schedule cron expression: `minute hour day-of-month month day-of-week` in local time, such
+/// as `0 2 * * *` for 2am daily, or a nickname such as `@daily`
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    expression: String,
+    cron: Cron,
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cron = Cron::new(s.trim())
+            .parse()
+            .map_err(|e| format!("`{}` is not a cron expression: {}", s, e))?;
+        Ok(Schedule {
+            expression: s.trim().to_string(),
+            cron,
+        })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+impl Schedule {
+    /// The first time the schedule fires after now; `None` for one that never does, like `0 0 31 2 *`
+    pub fn next(&self) -> Option<SystemTime> {
+        self.cron
+            .find_next_occurrence(&Local::now(), false)
+            .ok()
+            .map(SystemTime::from)
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{bail, Context, Result};
+use enro::FileAnalysis;
+use ssh2::{CheckResult, KnownHostFileKind, Session, Sftp};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use crate::remote::analyze_body;
+use crate::Args;
+
+// Tried in this order after the SSH agent, like OpenSSH does
+const DEFAULT_KEYS: &[&str] = &["id_ed25519", "id_ecdsa", "id_rsa"];
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Analyze a remote file, or the files of a remote directory (walked per -r/--max-depth),
+/// reading at most --max-bytes of each over the wire
+pub fn analyze(location: &str, args: &Args) -> Result<Vec<FileAnalysis>> {
+    let url = Url::parse(location).with_context(|| format!("Invalid URL: {}", location))?;
+    let host = url.host_str().with_context(|| format!("No host in {}", location))?;
+    let port = url.port().unwrap_or(22);
+    let user = match url.username() {
+        "" => std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .context("No user in the URL and none in the environment")?,
+        user => user.to_string(),
+    };
+    let root = PathBuf::from(
+        percent_encoding::percent_decode_str(url.path())
+            .decode_utf8()
+            .context("SFTP path is not valid UTF-8")?
+            .into_owned(),
+    );
+
+    let session = connect(host, port, &user).with_context(|| format!("Failed to connect to {}", location))?;
+    let sftp = session.sftp()?;
+
+    let mut files = Vec::new();
+    let stat = sftp.stat(&root).with_context(|| format!("Path does not exist: {}", location))?;
+    if stat.is_dir() {
+        collect(&sftp, &root, 0, args, &mut files)?;
+    } else {
+        files.push((root, stat.size.unwrap_or(0)));
+    }
+
+    // Paths are reported as URLs so remote findings are never mistaken for local ones
+    let server = &url[..url::Position::BeforePath];
+    let options = args.analyze_options();
+    let mut results = Vec::new();
+    for (path, size) in files {
+        let name = format!("{}{}", server, path.display());
+        let analyzed = sftp
+            .open(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|mut file| analyze_body(name.clone(), &mut file, Some(size), &options));
+        match analyzed {
+            Ok(analysis) => results.push(analysis),
+            Err(e) => eprintln!("Warning: {}: {:#}", name, e),
+        }
+    }
+
+    Ok(results)
+}
+
+fn connect(host: &str, port: u16, user: &str) -> Result<Session> {
+    let mut session = Session::new()?;
+    session.set_tcp_stream(TcpStream::connect((host, port))?);
+    session.handshake()?;
+    verify_host_key(&session, host, port)?;
+
+    // Agent first, then the default key files
+    if session.userauth_agent(user).is_err() {
+        let ssh_dir = home_dir().map(|home| home.join(".ssh")).unwrap_or_default();
+        for key in DEFAULT_KEYS.iter().map(|name| ssh_dir.join(name)).filter(|k| k.exists()) {
+            if session.userauth_pubkey_file(user, None, &key, None).is_ok() {
+                break;
+            }
+        }
+    }
+    if !session.authenticated() {
+        bail!("Authentication as {} failed (tried the SSH agent and ~/.ssh keys)", user);
+    }
+
+    Ok(session)
+}
+
+/// Refuse servers whose host key is not already trusted in ~/.ssh/known_hosts
+fn verify_host_key(session: &Session, host: &str, port: u16) -> Result<()> {
+    let known_hosts_file = home_dir()
+        .context("Cannot locate ~/.ssh/known_hosts")?
+        .join(".ssh")
+        .join("known_hosts");
+    let mut known_hosts = session.known_hosts()?;
+    known_hosts
+        .read_file(&known_hosts_file, KnownHostFileKind::OpenSSH)
+        .with_context(|| format!("Failed to read {}", known_hosts_file.display()))?;
+
+    let (key, _) = session.host_key().context("Server sent no host key")?;
+    match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => bail!("Host key for {} does not match known_hosts", host),
+        CheckResult::NotFound | CheckResult::Failure => {
+            bail!("{} is not in known_hosts; connect once with ssh to trust it", host)
+        }
+    }
+}
+
+fn collect(sftp: &Sftp, dir: &Path, depth: usize, args: &Args, files: &mut Vec<(PathBuf, u64)>) -> Result<()> {
+    let entries = sftp
+        .readdir(dir)
+        .with_context(|| format!("Failed to list {}", dir.display()))?;
+    for (path, stat) in entries {
+        if stat.is_dir() {
+            if args.recursive && args.max_depth.is_none_or(|max| depth + 1 < max) {
+                if let Err(e) = collect(sftp, &path, depth + 1, args, files) {
+                    eprintln!("Warning: {:#}", e);
+                }
+            }
+        } else if stat.is_file() && stat.size.unwrap_or(0) >= args.min_size {
+            files.push((path, stat.size.unwrap_or(0)));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use anyhow::{bail, Context, Result};
+use enro::FileAnalysis;
+use smb::{Client, ClientConfig, FileAccessMask, FileCreateArgs, FileDirectoryInformation, GetLen, Resource, UncPath};
+use url::Url;
+
+use crate::remote::analyze_body;
+use crate::Args;
+
+/// Analyze a file on a share, or the files of a shared directory (walked per -r/--max-depth),
+/// reading at most --max-bytes of each over the wire
+pub fn analyze(location: &str, args: &Args) -> Result<Vec<FileAnalysis>> {
+    let url = Url::parse(location).with_context(|| format!("Invalid URL: {}", location))?;
+    let server = url.host_str().with_context(|| format!("No server in {}", location))?;
+    let decoded = percent_encoding::percent_decode_str(url.path())
+        .decode_utf8()
+        .context("SMB path is not valid UTF-8")?;
+    let (share, root) = match decoded.trim_start_matches('/').split_once('/') {
+        Some((share, path)) => (share.to_string(), path.trim_end_matches('/').to_string()),
+        None => (decoded.trim_start_matches('/').to_string(), String::new()),
+    };
+    if share.is_empty() {
+        bail!("No share in {}", location);
+    }
+
+    let (user, password) = credentials(&url)?;
+    let mut config = ClientConfig::default();
+    config.connection.port = url.port();
+    let client = Client::new(config);
+    let share_path = UncPath::new(server)?.with_share(&share)?;
+    client
+        .share_connect(&share_path, &user, password)
+        .with_context(|| format!("Failed to connect to {}", location))?;
+
+    let mut files = Vec::new();
+    let resource = open(&client, &share_path, &root).with_context(|| format!("Path does not exist: {}", location))?;
+    match resource {
+        Resource::Directory(dir) => {
+            let _ = dir.close();
+            collect(&client, &share_path, &root, 0, args, &mut files)?;
+        }
+        Resource::File(file) => {
+            let size = file.get_len().unwrap_or(0);
+            let _ = file.close();
+            files.push((root, size));
+        }
+        Resource::Pipe(pipe) => {
+            let _ = pipe.close();
+            bail!("{} is a named pipe", location);
+        }
+    }
+
+    // Paths are reported as URLs so share findings are never mistaken for local ones
+    let base = format!("{}/{}", &url[..url::Position::BeforePath], share);
+    let options = args.analyze_options();
+    let mut results = Vec::new();
+    for (path, size) in files {
+        let name = format!("{}/{}", base, path);
+        let analyzed = open(&client, &share_path, &path).and_then(|resource| match resource {
+            Resource::File(mut file) => {
+                let analysis = analyze_body(name.clone(), &mut file, Some(size), &options);
+                let _ = file.close();
+                analysis
+            }
+            _ => bail!("Not a regular file"),
+        });
+        match analyzed {
+            Ok(analysis) => results.push(analysis),
+            Err(e) => eprintln!("Warning: {}: {:#}", name, e),
+        }
+    }
+
+    let _ = client.close();
+    Ok(results)
+}
+
+/// User and password from the URL, falling back to `SMB_USER`/`SMB_PASSWORD`, then the login name.
+/// A domain is given as `DOMAIN;user` or `user@DOMAIN`; no password means guest access.
+fn credentials(url: &Url) -> Result<(String, String)> {
+    let decode = |s: &str| percent_encoding::percent_decode_str(s).decode_utf8_lossy().into_owned();
+    let user = match url.username() {
+        "" => std::env::var("SMB_USER")
+            .or_else(|_| std::env::var("USER"))
+            .or_else(|_| std::env::var("USERNAME"))
+            .context("No user in the URL and none in the environment")?,
+        // smb:// URLs write DOMAIN\user as DOMAIN;user
+        user => decode(user).replacen(';', "\\", 1),
+    };
+    let password = match url.password() {
+        Some(password) => decode(password),
+        None => std::env::var("SMB_PASSWORD").unwrap_or_default(),
+    };
+    Ok((user, password))
+}
+
+fn open(client: &Client, share: &UncPath, path: &str) -> Result<Resource> {
+    let target = share.clone().with_path(path);
+    let access = FileAccessMask::new().with_generic_read(true);
+    Ok(client.create_file(&target, &FileCreateArgs::make_open_existing(access))?)
+}
+
+/// Entries of an open directory as (share-relative path, size, is directory)
+fn list(dir: &smb::Directory, parent: &str) -> Result<Vec<(String, u64, bool)>> {
+    let mut entries = Vec::new();
+    for entry in dir.query::<FileDirectoryInformation>("*")? {
+        let entry = entry?;
+        let name = entry.file_name.to_string();
+        if name == "." || name == ".." {
+            continue;
+        }
+        let path = if parent.is_empty() { name } else { format!("{}/{}", parent, name) };
+        entries.push((path, entry.end_of_file, entry.file_attributes.directory()));
+    }
+    Ok(entries)
+}
+
+fn collect(client: &Client, share: &UncPath, dir: &str, depth: usize, args: &Args, files: &mut Vec<(String, u64)>) -> Result<()> {
+    let entries = match open(client, share, dir) {
+        Ok(Resource::Directory(handle)) => {
+            let entries = list(&handle, dir);
+            let _ = handle.close();
+            entries
+        }
+        Ok(_) => bail!("{} is not a directory", dir),
+        Err(e) => Err(e),
+    }
+    .with_context(|| format!("Failed to list {}", dir))?;
+    for (path, size, is_dir) in entries {
+        if is_dir {
+            if args.recursive && args.max_depth.is_none_or(|max| depth + 1 < max) {
+                if let Err(e) = collect(client, share, &path, depth + 1, args, files) {
+                    eprintln!("Warning: {:#}", e);
+                }
+            }
+        } else if size >= args.min_size {
+            files.push((path, size));
+        }
+    }
+    Ok(())
+}
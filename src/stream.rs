@@ -0,0 +1,72 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use enro::{analyze_reader, zip, AnalyzeOptions, FileAnalysis};
+use flate2::read::GzDecoder;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+
+/// Archive formats that can be analyzed member by member from stdin
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum StdinFormat {
+    /// Tar archive, optionally gzip-compressed
+    Tar,
+    /// ZIP archive (members with data descriptors must be deflated)
+    Zip,
+}
+
+/// Analyze each regular member of an archive streamed on stdin
+pub fn analyze_stdin(format: StdinFormat, options: &AnalyzeOptions, min_size: u64) -> Result<Vec<FileAnalysis>> {
+    let mut input = std::io::stdin().lock();
+    let mut results = Vec::new();
+
+    let outcome = match format {
+        StdinFormat::Tar => {
+            let gzipped = input.fill_buf()?.starts_with(&[0x1F, 0x8B]);
+            if gzipped {
+                analyze_tar(GzDecoder::new(input), options, min_size, &mut results)
+            } else {
+                analyze_tar(input, options, min_size, &mut results)
+            }
+        }
+        StdinFormat::Zip => {
+            zip::for_each_local_entry(&mut BufReader::new(input), |entry, data| {
+                if entry.is_dir() {
+                    return Ok(());
+                }
+                let mut analysis = analyze_reader(PathBuf::from(&entry.name), data, options)?;
+                if let Some(encryption) = entry.encryption {
+                    analysis.notes.push(encryption.note());
+                }
+                if analysis.size >= min_size {
+                    results.push(analysis);
+                }
+                Ok(())
+            })
+        }
+    };
+
+    // A stream cannot be re-read, so keep whatever was analyzed before the damage
+    if let Err(err) = outcome {
+        eprintln!("Warning: stopped reading stdin: {:#}", err);
+    }
+
+    Ok(results)
+}
+
+fn analyze_tar<R: Read>(
+    reader: R,
+    options: &AnalyzeOptions,
+    min_size: u64,
+    results: &mut Vec<FileAnalysis>,
+) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().context("Failed to read tar stream")? {
+        let mut entry = entry.context("Failed to read tar entry")?;
+        if !entry.header().entry_type().is_file() || entry.size() < min_size {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        results.push(analyze_reader(path, &mut entry, options)?);
+    }
+    Ok(())
+}
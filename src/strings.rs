@@ -0,0 +1,170 @@
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+use colored::Colorize;
+use enro::calculate_entropy;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+use crate::escape_csv;
+
+/// Extract printable strings from a file
+#[derive(ClapArgs, Debug)]
+pub struct StringsArgs {
+    /// File to extract strings from
+    #[arg(value_name = "FILE")]
+    pub file: PathBuf,
+
+    /// Minimum string length (in characters)
+    #[arg(short = 'n', long, default_value = "4")]
+    pub min_len: usize,
+
+    /// Maximum number of bytes to read (omit to scan entire file)
+    #[arg(short = 'b', long)]
+    pub max_bytes: Option<usize>,
+
+    /// Only report strings with entropy at or above this value
+    #[arg(short = 'e', long)]
+    pub min_entropy: Option<f64>,
+
+    /// Simple output format (CSV, no colors)
+    #[arg(short, long)]
+    pub simple: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StringEncoding {
+    Ascii,
+    Utf16Le,
+}
+
+impl StringEncoding {
+    fn name(&self) -> &'static str {
+        match self {
+            StringEncoding::Ascii => "ascii",
+            StringEncoding::Utf16Le => "utf16le",
+        }
+    }
+}
+
+pub struct ExtractedString {
+    pub offset: usize,
+    pub encoding: StringEncoding,
+    pub text: String,
+    pub entropy: f64,
+}
+
+fn is_printable(byte: u8) -> bool {
+    (0x20..=0x7E).contains(&byte) || byte == b'\t'
+}
+
+/// Extract ASCII and UTF-16LE strings, ordered by offset
+pub fn extract_strings(data: &[u8], min_len: usize) -> Vec<ExtractedString> {
+    let min_len = min_len.max(1);
+    let mut found = Vec::new();
+
+    // ASCII runs
+    let mut start = 0;
+    for i in 0..=data.len() {
+        if i < data.len() && is_printable(data[i]) {
+            continue;
+        }
+        if i - start >= min_len {
+            push_string(&mut found, start, StringEncoding::Ascii, &data[start..i]);
+        }
+        start = i + 1;
+    }
+
+    // UTF-16LE runs (printable ASCII code units), checked at both alignments
+    for alignment in 0..2 {
+        let mut run = Vec::new();
+        let mut run_start = alignment;
+        let mut i = alignment;
+        while i + 1 < data.len() {
+            if is_printable(data[i]) && data[i + 1] == 0 {
+                if run.is_empty() {
+                    run_start = i;
+                }
+                run.push(data[i]);
+            } else {
+                if run.len() >= min_len {
+                    push_string(&mut found, run_start, StringEncoding::Utf16Le, &run);
+                }
+                run.clear();
+            }
+            i += 2;
+        }
+        if run.len() >= min_len {
+            push_string(&mut found, run_start, StringEncoding::Utf16Le, &run);
+        }
+    }
+
+    found.sort_by_key(|s| s.offset);
+    found
+}
+
+fn push_string(found: &mut Vec<ExtractedString>, offset: usize, encoding: StringEncoding, bytes: &[u8]) {
+    found.push(ExtractedString {
+        offset,
+        encoding,
+        text: String::from_utf8_lossy(bytes).into_owned(),
+        entropy: calculate_entropy(bytes),
+    });
+}
+
+pub fn run(args: &StringsArgs) -> Result<()> {
+    let file = File::open(&args.file)
+        .with_context(|| format!("Failed to open file: {}", args.file.display()))?;
+
+    let limit = args.max_bytes.map_or(u64::MAX, |max| max as u64);
+    let mut data = Vec::new();
+    file.take(limit)
+        .read_to_end(&mut data)
+        .context("Failed to read file")?;
+
+    let strings: Vec<ExtractedString> = extract_strings(&data, args.min_len)
+        .into_iter()
+        .filter(|s| args.min_entropy.is_none_or(|min| s.entropy >= min))
+        .collect();
+
+    if args.simple {
+        println!("Offset,Encoding,Entropy,String");
+        for s in &strings {
+            println!(
+                "{},{},{:.2},{}",
+                s.offset,
+                s.encoding.name(),
+                s.entropy,
+                escape_csv(&s.text)
+            );
+        }
+        return Ok(());
+    }
+
+    for s in &strings {
+        let entropy_str = format!("{:.2}", s.entropy);
+        let entropy_colored = if s.entropy > 4.5 {
+            entropy_str.red()
+        } else if s.entropy > 3.5 {
+            entropy_str.yellow()
+        } else {
+            entropy_str.green()
+        };
+
+        println!(
+            "{}  {:<7}  {}  {}",
+            format!("{:>10x}", s.offset).dimmed(),
+            s.encoding.name(),
+            entropy_colored,
+            s.text
+        );
+    }
+
+    println!(
+        "\n{} {} string(s) found",
+        "•".cyan(),
+        strings.len().to_string().bold()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! Process-wide caps on how fast analyses read and how fast a scan opens files
+
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+struct Limiter {
+    per_sec: f64,
+    /// When what was used so far is paid off at the allowed rate
+    next: Mutex<Instant>,
+}
+
+impl Limiter {
+    fn new(per_sec: f64) -> Self {
+        Limiter {
+            per_sec,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Sleep for as long as `amount` more takes at the allowed rate. Time spent idle is not saved
+    /// up, so a pause is never followed by a burst.
+    fn pay(&self, amount: f64) {
+        let now = Instant::now();
+        let wake = {
+            let mut next = self.next.lock().unwrap();
+            *next = (*next).max(now) + Duration::from_secs_f64(amount / self.per_sec);
+            *next
+        };
+        std::thread::sleep(wake.saturating_duration_since(now));
+    }
+}
+
+static BYTES: OnceLock<Limiter> = OnceLock::new();
+static FILES: OnceLock<Limiter> = OnceLock::new();
+
+/// Read no more than `bytes_per_sec` across all analyses, averaged over time. Only the first call
+/// takes effect.
+pub fn set_rate(bytes_per_sec: u64) {
+    let _ = BYTES.set(Limiter::new(bytes_per_sec as f64));
+}
+
+/// Let `pace_file` pass no more than `files_per_sec` files, spread evenly. Only the first call
+/// takes effect.
+pub fn set_file_rate(files_per_sec: f64) {
+    let _ = FILES.set(Limiter::new(files_per_sec));
+}
+
+/// Wait until another file may be opened at the rate set with `set_file_rate`; callers walking
+/// many files call it before each one
+pub fn pace_file() {
+    if let Some(limiter) = FILES.get() {
+        limiter.pay(1.0);
+    }
+}
+
+fn pay(bytes: usize) {
+    if let Some(limiter) = BYTES.get() {
+        limiter.pay(bytes as f64);
+    }
+}
+
+/// Reader that keeps to the rate set with `set_rate`
+pub(crate) struct Throttled<R>(pub(crate) R);
+
+impl<R: Read> Read for Throttled<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.0.read(buf)?;
+        pay(n);
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for Throttled<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
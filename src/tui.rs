@@ -0,0 +1,620 @@
+use anyhow::{Context, Result};
+use clap::{Args as ClapArgs, ValueEnum};
+use enro::{analyze_file, format_size, platform, FileAnalysis};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, Gauge, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::entropy_map::EntropyMap;
+use crate::{category, display_path, escape_csv, walk, Args, Category, SortKey};
+
+// Leading bytes shown in the hex pane
+const PREVIEW_BYTES: usize = 1024;
+// Step of the minimum entropy filter
+const ENTROPY_STEP: f64 = 0.5;
+
+/// Scan in an interactive terminal browser: live progress, then sort, filter, preview and mark
+/// files for export
+#[derive(ClapArgs, Debug)]
+pub struct TuiArgs {
+    #[command(flatten)]
+    scan: Args,
+
+    /// File that `e` writes the marked files to, as CSV
+    #[arg(long, env = "ENRO_EXPORT", value_name = "FILE", default_value = "enro-marked.csv")]
+    export: PathBuf,
+}
+
+/// The scan and everything the browser shows of it
+struct App {
+    results: Vec<FileAnalysis>,
+    errors: usize,
+    total: usize,
+    skipped: usize,
+    /// Indices into `results` passing the filters, in display order
+    visible: Vec<usize>,
+    dirty: bool,
+    selected: usize,
+    offset: usize,
+    sort: SortKey,
+    category: Option<Category>,
+    min_entropy: f64,
+    marked: HashSet<PathBuf>,
+    preview: Option<(PathBuf, Vec<u8>)>,
+    export: PathBuf,
+    status: String,
+    /// The side pane shows the entropy map instead of the hex preview
+    map_view: bool,
+    map: Option<(PathBuf, Result<EntropyMap, String>)>,
+    /// The map being computed, with the flag that abandons it when the selection moves on
+    map_pending: Option<(PathBuf, Arc<AtomicBool>)>,
+    map_sender: Sender<(PathBuf, Result<EntropyMap, String>)>,
+    map_receiver: Receiver<(PathBuf, Result<EntropyMap, String>)>,
+    map_cursor: usize,
+    /// Blocks per map row at the last draw, for moving the cursor a row at a time
+    map_columns: usize,
+}
+
+impl App {
+    fn finished(&self) -> bool {
+        self.results.len() + self.errors >= self.total
+    }
+
+    fn refresh(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        let selected_path = self.selected_analysis().map(|a| a.path.clone());
+        let mut visible: Vec<usize> = (0..self.results.len())
+            .filter(|&i| {
+                let analysis = &self.results[i];
+                analysis.entropy >= self.min_entropy
+                    && self.category.is_none_or(|c| category(&analysis.file_type) == c)
+            })
+            .collect();
+        let results = &self.results;
+        match self.sort {
+            SortKey::Path => visible.sort_by(|&a, &b| results[a].path.cmp(&results[b].path)),
+            SortKey::Entropy => visible.sort_by(|&a, &b| {
+                results[b].entropy.total_cmp(&results[a].entropy).then_with(|| results[a].path.cmp(&results[b].path))
+            }),
+            SortKey::Size => visible.sort_by(|&a, &b| {
+                results[b].size.cmp(&results[a].size).then_with(|| results[a].path.cmp(&results[b].path))
+            }),
+        }
+        self.visible = visible;
+        // Stay on the same file while results stream in or the order changes
+        if let Some(path) = selected_path {
+            if let Some(position) = self.visible.iter().position(|&i| self.results[i].path == path) {
+                self.selected = position;
+            }
+        }
+        self.selected = self.selected.min(self.visible.len().saturating_sub(1));
+        self.dirty = false;
+    }
+
+    fn selected_analysis(&self) -> Option<&FileAnalysis> {
+        self.visible.get(self.selected).map(|&i| &self.results[i])
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let last = self.visible.len().saturating_sub(1) as isize;
+        self.selected = (self.selected as isize + delta).clamp(0, last.max(0)) as usize;
+    }
+
+    fn cycle_sort(&mut self) {
+        let keys = SortKey::value_variants();
+        let next = keys.iter().position(|&k| k == self.sort).map_or(0, |i| (i + 1) % keys.len());
+        self.sort = keys[next];
+        self.dirty = true;
+    }
+
+    fn cycle_category(&mut self) {
+        let categories = Category::value_variants();
+        self.category = match self.category {
+            None => categories.first().copied(),
+            Some(current) => categories
+                .iter()
+                .position(|&c| c == current)
+                .and_then(|i| categories.get(i + 1).copied()),
+        };
+        self.dirty = true;
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(path) = self.selected_analysis().map(|a| a.path.clone()) {
+            if !self.marked.remove(&path) {
+                self.marked.insert(path);
+            }
+            self.move_selection(1);
+        }
+    }
+
+    fn mark_visible(&mut self) {
+        let paths: Vec<PathBuf> = self.visible.iter().map(|&i| self.results[i].path.clone()).collect();
+        self.marked.extend(paths);
+    }
+
+    fn export(&mut self) {
+        self.status = match self.write_export() {
+            Ok(0) => "Nothing marked; mark files with space or `a`".to_string(),
+            Ok(n) => format!("Exported {} file(s) to {}", n, self.export.display()),
+            Err(e) => format!("Export failed: {:#}", e),
+        };
+    }
+
+    fn write_export(&self) -> Result<usize> {
+        let mut marked: Vec<&FileAnalysis> = self.results.iter().filter(|a| self.marked.contains(&a.path)).collect();
+        if marked.is_empty() {
+            return Ok(0);
+        }
+        marked.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut file = File::create(&self.export).with_context(|| format!("Failed to create {}", self.export.display()))?;
+        writeln!(file, "Path,Type,Entropy,Size")?;
+        for analysis in &marked {
+            writeln!(
+                file,
+                "{},{},{:.2},{}",
+                escape_csv(&display_path(&analysis.path)),
+                analysis.file_type.display_compact(),
+                analysis.entropy,
+                analysis.size
+            )?;
+        }
+        Ok(marked.len())
+    }
+
+    /// Start computing the selected file's entropy map in the background, unless it is already
+    /// shown or on its way
+    fn request_map(&mut self) {
+        let Some(path) = self.selected_analysis().map(|a| a.path.clone()) else {
+            return;
+        };
+        if self.map.as_ref().is_some_and(|(p, _)| *p == path) || self.map_pending.as_ref().is_some_and(|(p, _)| *p == path) {
+            return;
+        }
+        if let Some((_, cancel)) = self.map_pending.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.map_pending = Some((path.clone(), cancel.clone()));
+        let sender = self.map_sender.clone();
+        std::thread::spawn(move || {
+            let map = match EntropyMap::compute(&path, &cancel) {
+                Ok(Some(map)) => Ok(map),
+                Ok(None) => return,
+                Err(e) => Err(format!("{:#}", e)),
+            };
+            let _ = sender.send((path, map));
+        });
+    }
+
+    fn receive_maps(&mut self) {
+        while let Ok((path, map)) = self.map_receiver.try_recv() {
+            if self.map_pending.as_ref().is_some_and(|(p, _)| *p == path) {
+                self.map_pending = None;
+                self.map = Some((path, map));
+                self.map_cursor = 0;
+            }
+        }
+    }
+
+    fn move_map_cursor(&mut self, delta: isize) {
+        let blocks = match &self.map {
+            Some((_, Ok(map))) => map.blocks.len(),
+            _ => return,
+        };
+        let last = blocks.saturating_sub(1) as isize;
+        self.map_cursor = (self.map_cursor as isize + delta).clamp(0, last.max(0)) as usize;
+    }
+
+    /// Leading bytes of the selected file, read once per selection
+    fn preview(&mut self) -> Option<&[u8]> {
+        let path = self.selected_analysis()?.path.clone();
+        if self.preview.as_ref().is_none_or(|(cached, _)| *cached != path) {
+            let mut data = Vec::new();
+            if let Ok(file) = File::open(platform::long_path(&path)) {
+                let _ = file.take(PREVIEW_BYTES as u64).read_to_end(&mut data);
+            }
+            self.preview = Some((path, data));
+        }
+        self.preview.as_ref().map(|(_, data)| data.as_slice())
+    }
+}
+
+pub fn run(args: &TuiArgs) -> Result<()> {
+    let scan = &args.scan;
+    let (files, skipped) = walk::collect_files(scan)?;
+    let skipped = walk::SkipReason::ALL.iter().map(|&reason| skipped.count(reason)).sum();
+
+    // Analysis runs in the background so the browser shows results as they arrive; `None`
+    // stands for a file that could not be analyzed
+    let (sender, receiver) = mpsc::channel();
+    let options = scan.analyze_options();
+    let total = files.len();
+    std::thread::spawn(move || {
+        files.par_iter().for_each_with(sender, |sender, path| {
+            let _ = sender.send(analyze_file(path, &options).ok());
+        });
+    });
+
+    let (map_sender, map_receiver) = mpsc::channel();
+    let mut app = App {
+        results: Vec::with_capacity(total),
+        errors: 0,
+        total,
+        skipped,
+        visible: Vec::new(),
+        dirty: true,
+        selected: 0,
+        offset: 0,
+        sort: scan.sort.unwrap_or(SortKey::Entropy),
+        category: None,
+        min_entropy: 0.0,
+        marked: HashSet::new(),
+        preview: None,
+        export: args.export.clone(),
+        status: String::new(),
+        map_view: false,
+        map: None,
+        map_pending: None,
+        map_sender,
+        map_receiver,
+        map_cursor: 0,
+        map_columns: 1,
+    };
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app, &receiver);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App, receiver: &Receiver<Option<FileAnalysis>>) -> Result<()> {
+    loop {
+        for message in receiver.try_iter() {
+            match message {
+                Some(analysis) => app.results.push(analysis),
+                None => app.errors += 1,
+            }
+            app.dirty = true;
+        }
+        app.refresh();
+        app.receive_maps();
+        if app.map_view {
+            app.request_map();
+        }
+        terminal.draw(|frame| draw(frame, app))?;
+
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let page = terminal.size()?.height.saturating_sub(8).max(1) as isize;
+        // A status message stays until the next key, then the key help returns
+        app.status.clear();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Down | KeyCode::Char('j') => app.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => app.move_selection(-1),
+            KeyCode::PageDown => app.move_selection(page),
+            KeyCode::PageUp => app.move_selection(-page),
+            KeyCode::Home | KeyCode::Char('g') => app.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => app.move_selection(isize::MAX / 2),
+            KeyCode::Char('s') => app.cycle_sort(),
+            KeyCode::Char('t') => app.cycle_category(),
+            KeyCode::Char('+') | KeyCode::Char('=') => {
+                app.min_entropy = (app.min_entropy + ENTROPY_STEP).min(8.0);
+                app.dirty = true;
+            }
+            KeyCode::Char('-') => {
+                app.min_entropy = (app.min_entropy - ENTROPY_STEP).max(0.0);
+                app.dirty = true;
+            }
+            KeyCode::Char(' ') => app.toggle_mark(),
+            KeyCode::Char('a') => app.mark_visible(),
+            KeyCode::Char('c') => app.marked.clear(),
+            KeyCode::Char('e') => app.export(),
+            KeyCode::Char('m') => app.map_view = !app.map_view,
+            KeyCode::Left | KeyCode::Char('h') if app.map_view => app.move_map_cursor(-1),
+            KeyCode::Right | KeyCode::Char('l') if app.map_view => app.move_map_cursor(1),
+            KeyCode::Char('[') if app.map_view => app.move_map_cursor(-(app.map_columns as isize)),
+            KeyCode::Char(']') if app.map_view => app.move_map_cursor(app.map_columns as isize),
+            _ => {}
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(5), Constraint::Length(1)])
+        .split(frame.area());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(rows[1]);
+
+    draw_progress(frame, app, rows[0]);
+    draw_table(frame, app, columns[0]);
+    if app.map_view {
+        draw_map(frame, app, columns[1]);
+    } else {
+        draw_preview(frame, app, columns[1]);
+    }
+
+    let help = if !app.status.is_empty() {
+        app.status.clone()
+    } else if app.map_view {
+        "↑↓ file  ←→ block  [ ] row  m hex view  s sort  t type  +/- min entropy  space mark  e export  q quit".to_string()
+    } else {
+        "↑↓ move  s sort  t type  +/- min entropy  space mark  a mark all  c clear  e export  m map  q quit".to_string()
+    };
+    frame.render_widget(Paragraph::new(help).style(Style::default().fg(Color::DarkGray)), rows[2]);
+}
+
+fn draw_progress(frame: &mut Frame, app: &App, area: Rect) {
+    let done = app.results.len() + app.errors;
+    let label = format!(
+        "{}/{} analyzed, {} error(s), {} skipped{}",
+        done,
+        app.total,
+        app.errors,
+        app.skipped,
+        if app.finished() { " — done" } else { "" }
+    );
+    let ratio = if app.total == 0 { 1.0 } else { done as f64 / app.total as f64 };
+    let gauge = Gauge::default()
+        .block(Block::bordered().title(" enro "))
+        .gauge_style(Style::default().fg(if app.finished() { Color::Green } else { Color::Cyan }))
+        .ratio(ratio.min(1.0))
+        .label(label);
+    frame.render_widget(gauge, area);
+}
+
+fn entropy_color(entropy: f64) -> Color {
+    if entropy > 7.5 {
+        Color::Red
+    } else if entropy > 6.0 {
+        Color::Yellow
+    } else {
+        Color::Green
+    }
+}
+
+fn draw_table(frame: &mut Frame, app: &mut App, area: Rect) {
+    // Rows are built only for the window on screen, so large scans stay responsive
+    let height = area.height.saturating_sub(3) as usize;
+    if app.selected < app.offset {
+        app.offset = app.selected;
+    } else if height > 0 && app.selected >= app.offset + height {
+        app.offset = app.selected + 1 - height;
+    }
+
+    let rows: Vec<Row> = app
+        .visible
+        .iter()
+        .skip(app.offset)
+        .take(height)
+        .map(|&i| {
+            let analysis = &app.results[i];
+            let mark = if app.marked.contains(&analysis.path) { "*" } else { " " };
+            Row::new(vec![
+                Cell::from(mark),
+                Cell::from(display_path(&analysis.path)),
+                Cell::from(analysis.file_type.display_plain()),
+                Cell::from(format!("{:.2}", analysis.entropy)).style(Style::default().fg(entropy_color(analysis.entropy))),
+                Cell::from(format_size(analysis.size)),
+            ])
+        })
+        .collect();
+
+    let sort = match app.sort {
+        SortKey::Path => "path",
+        SortKey::Entropy => "entropy",
+        SortKey::Size => "size",
+    };
+    let filter = app
+        .category
+        .and_then(|c| c.to_possible_value())
+        .map_or("all".to_string(), |v| v.get_name().to_string());
+    let title = format!(
+        " {} of {} files | sort: {} | type: {} | entropy ≥ {:.1} | {} marked ",
+        app.visible.len(),
+        app.results.len(),
+        sort,
+        filter,
+        app.min_entropy,
+        app.marked.len()
+    );
+    let header = Row::new(vec!["", "Path", "Type", "Entropy", "Size"]).style(Style::default().add_modifier(Modifier::BOLD));
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(1),
+            Constraint::Min(20),
+            Constraint::Length(22),
+            Constraint::Length(7),
+            Constraint::Length(10),
+        ],
+    )
+    .header(header)
+    .block(Block::bordered().title(title))
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = TableState::default();
+    if !app.visible.is_empty() {
+        state.select(Some(app.selected - app.offset));
+    }
+    frame.render_stateful_widget(table, area, &mut state);
+}
+
+fn draw_preview(frame: &mut Frame, app: &mut App, area: Rect) {
+    let Some(analysis) = app.selected_analysis().cloned() else {
+        frame.render_widget(Block::bordered().title(" Preview "), area);
+        return;
+    };
+
+    let mut lines = vec![
+        Line::from(display_path(&analysis.path)),
+        Line::from(vec![
+            Span::raw(format!("{}  ", analysis.file_type.display_plain())),
+            Span::styled(format!("{:.4}/8.0", analysis.entropy), Style::default().fg(entropy_color(analysis.entropy))),
+            Span::raw(format!("  {}", format_size(analysis.size))),
+        ]),
+    ];
+    for note in &analysis.notes {
+        lines.push(Line::styled(note.clone(), Style::default().fg(Color::Yellow)));
+    }
+    lines.push(Line::from(""));
+
+    // 16 bytes per line when the pane is wide enough for offset, hex and ASCII columns
+    let width = if area.width >= 78 { 16 } else { 8 };
+    if let Some(data) = app.preview() {
+        for (row, chunk) in data.chunks(width).enumerate() {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect();
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:08x}  ", row * width), Style::default().fg(Color::DarkGray)),
+                Span::raw(format!("{:<w$}  ", hex.join(" "), w = width * 3 - 1)),
+                Span::styled(ascii, Style::default().fg(Color::Cyan)),
+            ]));
+        }
+    }
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Preview ")), area);
+}
+
+/// Entropy bands of the map, lowest first, with the color each is drawn in
+const MAP_BANDS: &[(f64, Color)] = &[
+    (2.0, Color::Blue),
+    (4.0, Color::Cyan),
+    (6.0, Color::Green),
+    (7.5, Color::Yellow),
+    (f64::INFINITY, Color::Red),
+];
+
+fn band_color(entropy: f64) -> Color {
+    MAP_BANDS
+        .iter()
+        .find(|&&(upper, _)| entropy < upper)
+        .map_or(Color::Red, |&(_, color)| color)
+}
+
+fn draw_map(frame: &mut Frame, app: &mut App, area: Rect) {
+    let block = Block::bordered().title(" Entropy map ");
+    let selected = app.selected_analysis().map(|a| a.path.clone());
+    let map = match (&app.map, &selected) {
+        (Some((path, map)), Some(selected)) if path == selected => map,
+        (_, Some(_)) => {
+            frame.render_widget(Paragraph::new("Computing entropy map...").block(block), area);
+            return;
+        }
+        (_, None) => {
+            frame.render_widget(block, area);
+            return;
+        }
+    };
+    let map = match map {
+        Ok(map) => map,
+        Err(e) => {
+            frame.render_widget(Paragraph::new(e.clone()).style(Style::default().fg(Color::Red)).block(block), area);
+            return;
+        }
+    };
+
+    let inner_width = area.width.saturating_sub(2) as usize;
+    let inner_height = area.height.saturating_sub(2) as usize;
+    // Offset label, then one cell per block
+    let columns = inner_width.saturating_sub(10).max(8);
+    app.map_columns = columns;
+    let cursor = app.map_cursor.min(map.blocks.len().saturating_sub(1));
+
+    let mut lines = vec![Line::from(format!(
+        "{}  {} blocks of {}",
+        format_size(map.size),
+        map.blocks.len(),
+        format_size(map.block_size)
+    ))];
+
+    // Below the map: cursor details, legend and as many signatures as fit
+    let shown_signatures = map.signatures.len().min(8);
+    let footer = 3 + if map.signatures.is_empty() { 0 } else { shown_signatures + 1 };
+    let map_rows = map.blocks.len().div_ceil(columns);
+    let visible_rows = inner_height.saturating_sub(1 + footer).max(1);
+    let first_row = (cursor / columns).saturating_sub(visible_rows - 1).min(map_rows.saturating_sub(visible_rows));
+    for row in first_row..(first_row + visible_rows).min(map_rows) {
+        let start = row * columns;
+        let mut spans = vec![Span::styled(
+            format!("{:08x}  ", start as u64 * map.block_size),
+            Style::default().fg(Color::DarkGray),
+        )];
+        for (i, &entropy) in map.blocks.iter().enumerate().skip(start).take(columns) {
+            let symbol = if map.has_signature(i) { "▒" } else { "█" };
+            let mut style = Style::default().fg(band_color(entropy));
+            if i == cursor {
+                style = style.bg(Color::White).add_modifier(Modifier::REVERSED);
+            }
+            spans.push(Span::styled(symbol, style));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    lines.push(Line::from(""));
+    if let Some(&entropy) = map.blocks.get(cursor) {
+        let start = cursor as u64 * map.block_size;
+        let end = (start + map.block_size).min(map.size);
+        lines.push(Line::from(vec![
+            Span::raw(format!("Block {} at {:#x}-{:#x}: ", cursor, start, end.saturating_sub(1))),
+            Span::styled(format!("{:.4}/8.0", entropy), Style::default().fg(band_color(entropy))),
+        ]));
+    }
+    let mut legend = Vec::new();
+    let mut lower = 0.0;
+    for &(upper, color) in MAP_BANDS {
+        legend.push(Span::styled("█", Style::default().fg(color)));
+        legend.push(Span::raw(if upper.is_finite() {
+            format!(" {}-{}  ", lower, upper)
+        } else {
+            format!(" ≥{}  ", lower)
+        }));
+        lower = upper;
+    }
+    legend.push(Span::raw("▒ signature"));
+    lines.push(Line::from(legend));
+
+    if !map.signatures.is_empty() {
+        let more = map.signatures.len() - shown_signatures;
+        lines.push(Line::styled(
+            match (more, map.truncated) {
+                (0, false) => "Signatures:".to_string(),
+                (_, false) => format!("Signatures ({} more):", more),
+                (_, true) => format!("Signatures ({}+ more):", more),
+            },
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        for &(offset, name) in map.signatures.iter().take(shown_signatures) {
+            lines.push(Line::from(format!("  {:#010x}  {}", offset, name)));
+        }
+    }
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
@@ -0,0 +1,238 @@
+//! Settling, per storage device, how many files to read at once and how large each read is, from
+//! the throughput the first seconds of a scan reach
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::platform;
+
+// How long each setting is tried for
+const TRIAL: Duration = Duration::from_secs(1);
+// How much faster a setting has to be to replace the best one so far, so noise does not decide
+const GAIN: f64 = 1.05;
+// Read sizes in the order they are tried, starting from the default; a read never spans more than
+// a pipeline block anyway
+const READ_LENS: [usize; 3] = [8 * 1024 * 1024, 1024 * 1024, 256 * 1024];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static DEVICES: OnceLock<Mutex<HashMap<u64, Arc<Device>>>> = OnceLock::new();
+
+/// Have analyses tune their reads per storage device: one file at a time at first, then twice as
+/// many for as long as that reads faster, then smaller reads for as long as those do. A local
+/// disk settles on a few readers, a network mount on many.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// What a device's reads were tuned to
+#[derive(Debug, Clone)]
+pub struct Tuned {
+    /// Where the device is mounted, as far as the scanned paths show
+    pub mount: PathBuf,
+    /// Files read from it at once
+    pub readers: usize,
+    /// Largest single read
+    pub read_len: usize,
+    /// Throughput the settings reached while they were tried
+    pub bytes_per_sec: f64,
+    /// False when the scan ended before every trial did
+    pub settled: bool,
+}
+
+/// Every device read from so far, by mount point
+pub fn devices() -> Vec<Tuned> {
+    let Some(devices) = DEVICES.get() else {
+        return Vec::new();
+    };
+    let mut tuned: Vec<Tuned> = devices.lock().unwrap().values().map(|device| device.tuned()).collect();
+    tuned.sort_by(|a, b| a.mount.cmp(&b.mount));
+    tuned
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Setting {
+    readers: usize,
+    read_len: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    Readers,
+    /// Trying `READ_LENS[i]`
+    ReadLen(usize),
+    Settled,
+}
+
+struct State {
+    stage: Stage,
+    current: Setting,
+    best: Setting,
+    best_rate: f64,
+    /// Files being read
+    active: usize,
+    trial_started: Instant,
+    trial_bytes: u64,
+}
+
+struct Device {
+    mount: PathBuf,
+    state: Mutex<State>,
+    freed: Condvar,
+    /// Copies of the state's, read without its lock once tuning has settled
+    settled: AtomicBool,
+    read_len: AtomicUsize,
+}
+
+impl Device {
+    fn new(mount: PathBuf) -> Self {
+        let setting = Setting {
+            readers: 1,
+            read_len: READ_LENS[0],
+        };
+        Device {
+            mount,
+            state: Mutex::new(State {
+                stage: Stage::Readers,
+                current: setting,
+                best: setting,
+                best_rate: 0.0,
+                active: 0,
+                trial_started: Instant::now(),
+                trial_bytes: 0,
+            }),
+            freed: Condvar::new(),
+            settled: AtomicBool::new(false),
+            read_len: AtomicUsize::new(setting.read_len),
+        }
+    }
+
+    fn tuned(&self) -> Tuned {
+        let state = self.state.lock().unwrap();
+        Tuned {
+            mount: self.mount.clone(),
+            readers: state.current.readers,
+            read_len: state.current.read_len,
+            bytes_per_sec: state.best_rate,
+            settled: state.stage == Stage::Settled,
+        }
+    }
+
+    /// Count `bytes` read, and move on to the next setting once this one has been tried for long
+    /// enough
+    fn record(&self, bytes: usize) {
+        if self.settled.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.trial_bytes += bytes as u64;
+        let elapsed = state.trial_started.elapsed();
+        if state.stage == Stage::Settled || elapsed < TRIAL {
+            return;
+        }
+        let rate = state.trial_bytes as f64 / elapsed.as_secs_f64();
+        let improved = rate > state.best_rate * GAIN;
+        if improved {
+            state.best = state.current;
+            state.best_rate = rate;
+        }
+        let best = state.best;
+        let max_readers = rayon::current_num_threads();
+        let (stage, next) = match state.stage {
+            Stage::Readers if improved && best.readers < max_readers => (
+                Stage::Readers,
+                Setting {
+                    readers: (best.readers * 2).min(max_readers),
+                    ..best
+                },
+            ),
+            Stage::Readers => (Stage::ReadLen(1), Setting { read_len: READ_LENS[1], ..best }),
+            Stage::ReadLen(i) if improved && i + 1 < READ_LENS.len() => {
+                (Stage::ReadLen(i + 1), Setting { read_len: READ_LENS[i + 1], ..best })
+            }
+            _ => (Stage::Settled, best),
+        };
+        state.stage = stage;
+        state.current = next;
+        state.trial_started = Instant::now();
+        state.trial_bytes = 0;
+        self.read_len.store(state.current.read_len, Ordering::Relaxed);
+        self.settled.store(state.stage == Stage::Settled, Ordering::Relaxed);
+        // The number of readers may have gone up
+        self.freed.notify_all();
+    }
+}
+
+/// Wait until another file may be read from the device holding `path`, and hold that turn until
+/// the lease is dropped. None unless `set_enabled`.
+pub(crate) fn lease(path: &Path, metadata: &fs::Metadata) -> Option<Lease> {
+    if !enabled() {
+        return None;
+    }
+    let id = platform::device_id(path, metadata);
+    let device = {
+        let mut devices = DEVICES.get_or_init(Default::default).lock().unwrap();
+        devices.entry(id).or_insert_with(|| Arc::new(Device::new(platform::mount_point(path)))).clone()
+    };
+    let mut state = device.state.lock().unwrap();
+    while state.active >= state.current.readers {
+        state = device.freed.wait(state).unwrap();
+    }
+    state.active += 1;
+    drop(state);
+    Some(Lease { device })
+}
+
+/// A turn at reading from one device
+pub(crate) struct Lease {
+    device: Arc<Device>,
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.device.state.lock().unwrap().active -= 1;
+        self.device.freed.notify_all();
+    }
+}
+
+/// Reader whose reads count towards the trials of the device a lease is on, each kept to the
+/// device's read size; passes reads through untouched without a lease
+pub(crate) struct Tracked<R> {
+    inner: R,
+    device: Option<Arc<Device>>,
+}
+
+impl<R> Tracked<R> {
+    pub(crate) fn new(inner: R, lease: Option<&Lease>) -> Self {
+        Tracked {
+            inner,
+            device: lease.map(|lease| lease.device.clone()),
+        }
+    }
+}
+
+impl<R: Read> Read for Tracked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(device) = &self.device else {
+            return self.inner.read(buf);
+        };
+        let len = buf.len().min(device.read_len.load(Ordering::Relaxed));
+        let n = self.inner.read(&mut buf[..len])?;
+        device.record(n);
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for Tracked<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
@@ -0,0 +1,121 @@
+use enro::{FileAnalysis, FileType};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Where a file sits inside a split (multi-volume) archive
+struct VolumeKey {
+    /// Logical archive path shared by all volumes, e.g. `dir/backup.7z`
+    logical: PathBuf,
+    format: &'static str,
+    index: u32,
+    /// `.zip`/`.rar` heads only count as volumes when numbered parts exist
+    needs_parts: bool,
+}
+
+fn digits(s: &str) -> Option<u32> {
+    (!s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| s.parse().ok())
+        .flatten()
+}
+
+fn format_for_ext(ext: &str) -> Option<&'static str> {
+    match ext {
+        "7z" => Some("7Z"),
+        "zip" => Some("ZIP"),
+        "rar" => Some("RAR"),
+        "tar" => Some("TAR"),
+        "gz" | "tgz" => Some("GZIP"),
+        _ => None,
+    }
+}
+
+fn volume_key(path: &Path) -> Option<VolumeKey> {
+    let name = path.file_name()?.to_str()?;
+    let lower = name.to_ascii_lowercase();
+    let parent = path.parent().unwrap_or(Path::new(""));
+    let (stem, ext) = lower.rsplit_once('.')?;
+    let base = &name[..stem.len()];
+
+    let key = |logical: String, format, index, needs_parts| {
+        Some(VolumeKey {
+            logical: parent.join(logical),
+            format,
+            index,
+            needs_parts,
+        })
+    };
+
+    // backup.7z.001, backup.zip.002, ...
+    if ext.len() == 3 && digits(ext).is_some() {
+        let inner_ext = stem.rsplit_once('.')?.1;
+        return key(base.to_string(), format_for_ext(inner_ext)?, digits(ext)?, false);
+    }
+
+    // backup.part1.rar, backup.part01.rar
+    if ext == "rar" {
+        if let Some((prefix, part)) = stem.rsplit_once(".part") {
+            if let Some(index) = digits(part) {
+                return key(format!("{}.rar", &name[..prefix.len()]), "RAR", index, false);
+            }
+        }
+        return key(name.to_string(), "RAR", 0, true);
+    }
+
+    // backup.z01, backup.z02 ... backup.zip (last volume)
+    if let Some(index) = ext.strip_prefix('z').and_then(digits) {
+        return key(format!("{}.zip", base), "ZIP", index, false);
+    }
+    if ext == "zip" {
+        return key(name.to_string(), "ZIP", u32::MAX, true);
+    }
+
+    // Old-style RAR: backup.rar, backup.r00, backup.r01 ...
+    if let Some(index) = ext.strip_prefix('r').and_then(digits) {
+        return key(format!("{}.rar", base), "RAR", index + 1, false);
+    }
+
+    None
+}
+
+/// Collapse the volumes of split archives into one logical entry with combined size
+pub fn group_volumes(results: Vec<FileAnalysis>) -> Vec<FileAnalysis> {
+    let mut grouped: HashMap<PathBuf, Vec<(VolumeKey, FileAnalysis)>> = HashMap::new();
+    let mut output = Vec::with_capacity(results.len());
+
+    for analysis in results {
+        match volume_key(&analysis.path) {
+            Some(key) => grouped.entry(key.logical.clone()).or_default().push((key, analysis)),
+            None => output.push(analysis),
+        }
+    }
+
+    for (logical, mut volumes) in grouped {
+        if volumes.len() < 2 || volumes.iter().all(|(key, _)| key.needs_parts) {
+            output.extend(volumes.into_iter().map(|(_, a)| a));
+            continue;
+        }
+
+        volumes.sort_by_key(|(key, _)| key.index);
+        let count = volumes.len();
+        let format = volumes[0].0.format;
+        let total_size: u64 = volumes.iter().map(|(_, a)| a.size).sum();
+        // Size-weighted mean of the per-volume entropies
+        let entropy = if total_size > 0 {
+            volumes.iter().map(|(_, a)| a.entropy * a.size as f64).sum::<f64>() / total_size as f64
+        } else {
+            0.0
+        };
+
+        let (_, mut first) = volumes.swap_remove(0);
+        if !matches!(first.file_type, FileType::Archive(_)) {
+            first.file_type = FileType::Archive(format.to_string());
+        }
+        first.path = logical;
+        first.entropy = entropy;
+        first.size = total_size;
+        first.notes.push(format!("{} volumes", count));
+        output.push(first);
+    }
+
+    output
+}
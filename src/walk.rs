@@ -0,0 +1,633 @@
+use anyhow::{Context, Result};
+use ignore::{ParallelVisitor, ParallelVisitorBuilder, WalkBuilder, WalkState};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::{Entry, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use enro::platform::Special;
+
+use crate::filters::{ExcludedPaths, PathFilter};
+use crate::remote;
+use crate::Args;
+
+/// Project-local ignore file with gitignore syntax, read automatically
+const ENROIGNORE: &str = ".enroignore";
+
+/// Why an entry was not analyzed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SkipReason {
+    /// Hidden file or directory pruned by --no-hidden
+    Hidden,
+    /// Extra path to a file already found through another hard link
+    HardLink,
+    /// Socket, named pipe or device node met while walking directories
+    Special,
+    /// NTFS junction not followed, or reparse point skipped by --skip-reparse-points
+    ReparsePoint,
+    /// Symbolic link not followed under --no-follow-symlinks
+    Symlink,
+    /// Directory or file the walk was not allowed to read
+    Denied,
+    /// Smaller than --min-size
+    TooSmall,
+    /// Left out by include/exclude patterns, extensions or the time window
+    Excluded,
+    /// Found but failed to open or read
+    Unreadable,
+    /// Analyzed, but its digest is in a known-good --hashset
+    KnownGood,
+}
+
+impl SkipReason {
+    pub const ALL: [SkipReason; 10] = [
+        SkipReason::Hidden,
+        SkipReason::HardLink,
+        SkipReason::Special,
+        SkipReason::ReparsePoint,
+        SkipReason::Symlink,
+        SkipReason::Denied,
+        SkipReason::TooSmall,
+        SkipReason::Excluded,
+        SkipReason::Unreadable,
+        SkipReason::KnownGood,
+    ];
+
+    /// Field name in JSON output
+    pub fn key(&self) -> &'static str {
+        match self {
+            SkipReason::Hidden => "hidden",
+            SkipReason::HardLink => "hard_links",
+            SkipReason::Special => "special",
+            SkipReason::ReparsePoint => "reparse_points",
+            SkipReason::Symlink => "symlinks",
+            SkipReason::Denied => "permission_denied",
+            SkipReason::TooSmall => "too_small",
+            SkipReason::Excluded => "excluded",
+            SkipReason::Unreadable => "unreadable",
+            SkipReason::KnownGood => "known_good",
+        }
+    }
+
+    /// Summary line label
+    pub fn label(&self) -> &'static str {
+        match self {
+            SkipReason::Hidden => "Hidden Entries",
+            SkipReason::HardLink => "Hard-Linked Paths",
+            SkipReason::Special => "Sockets/Pipes/Devices",
+            SkipReason::ReparsePoint => "Junctions/Reparse Points",
+            SkipReason::Symlink => "Symbolic Links",
+            SkipReason::Denied => "Permission Denied",
+            SkipReason::TooSmall => "Below Minimum Size",
+            SkipReason::Excluded => "Excluded by Filters",
+            SkipReason::Unreadable => "Unreadable",
+            SkipReason::KnownGood => "Known-Good Hashes",
+        }
+    }
+}
+
+/// Entries left out of a scan, reported in the summary so a clean scan can be told apart
+/// from one that missed part of the tree
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Skipped {
+    counts: [usize; SkipReason::ALL.len()],
+    /// Each skipped path with its reason, collected only for --show-skipped
+    pub paths: Vec<(PathBuf, SkipReason)>,
+    list: bool,
+}
+
+impl Skipped {
+    pub fn add(&mut self, reason: SkipReason, path: &Path) {
+        self.counts[reason as usize] += 1;
+        if self.list {
+            self.paths.push((path.to_path_buf(), reason));
+        }
+    }
+
+    pub fn count(&self, reason: SkipReason) -> usize {
+        self.counts[reason as usize]
+    }
+}
+
+/// Skips recorded by the walker threads
+struct SkipCounter {
+    counts: [AtomicUsize; SkipReason::ALL.len()],
+    paths: Option<Mutex<Vec<(PathBuf, SkipReason)>>>,
+}
+
+impl SkipCounter {
+    fn new(list: bool) -> Self {
+        SkipCounter {
+            counts: Default::default(),
+            paths: list.then(Mutex::default),
+        }
+    }
+
+    fn add(&self, reason: SkipReason, path: &Path) {
+        self.counts[reason as usize].fetch_add(1, Ordering::Relaxed);
+        if let Some(paths) = &self.paths {
+            paths.lock().unwrap().push((path.to_path_buf(), reason));
+        }
+    }
+
+    fn into_skipped(self) -> Skipped {
+        let list = self.paths.is_some();
+        let mut paths = self.paths.map(|p| p.into_inner().unwrap()).unwrap_or_default();
+        // Walker threads finish in arbitrary order
+        paths.sort_by(|a, b| a.0.cmp(&b.0));
+        Skipped {
+            counts: self.counts.map(AtomicUsize::into_inner),
+            paths,
+            list,
+        }
+    }
+}
+
+/// Identity of a file independent of the path it was reached by
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum FileId {
+    #[cfg_attr(not(unix), allow(dead_code))]
+    Inode(u64, u64),
+    Path(PathBuf),
+}
+
+impl FileId {
+    #[cfg(unix)]
+    fn of(path: &Path) -> Self {
+        use std::os::unix::fs::MetadataExt;
+        match fs::metadata(path) {
+            Ok(metadata) => FileId::Inode(metadata.dev(), metadata.ino()),
+            Err(_) => FileId::Path(canonical(path)),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn of(path: &Path) -> Self {
+        FileId::Path(canonical(path))
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Every file to analyze: PATH arguments (walked per the traversal flags) plus --files-from lists
+pub fn collect_files(args: &Args) -> Result<(Vec<PathBuf>, Skipped)> {
+    let filter = Arc::new(PathFilter::new(&args.include, &args.exclude)?.with_extensions(&args.ext, &args.not_ext));
+    let counter = Arc::new(SkipCounter::new(args.show_skipped));
+    let mut files = Vec::new();
+    for path in args.paths.iter().filter(|p| !remote::is_url(p)) {
+        collect_path(path, args, &filter, &counter, &mut Found::List(&mut files))?;
+    }
+    let mut skipped = Arc::into_inner(counter).expect("walker threads have finished").into_skipped();
+
+    for path in listed_paths(args)? {
+        match check_listed(&path, args) {
+            Ok(()) => files.push(path),
+            Err(Some(reason)) => skipped.add(reason, &path),
+            Err(None) => {}
+        }
+    }
+
+    // Overlapping arguments, symlinks and hard links can all lead to a file already found;
+    // it is analyzed once, under the first path
+    let mut seen: HashMap<FileId, PathBuf> = HashMap::new();
+    files.retain(|f| match seen.entry(FileId::of(f)) {
+        Entry::Occupied(first) => {
+            if canonical(first.get()) != canonical(f) {
+                skipped.add(SkipReason::HardLink, f);
+            }
+            false
+        }
+        Entry::Vacant(slot) => {
+            slot.insert(f.clone());
+            true
+        }
+    });
+
+    Ok((files, skipped))
+}
+
+/// Like `collect_files`, but hands each file to `found` as soon as the walk accepts it, along with
+/// its place in `collect_files` order: the index of the PATH argument it was found under, then
+/// of its --files-from entry. Within an argument, files arrive in no particular order.
+pub fn stream_files(args: &Args, found: &(dyn Fn(usize, PathBuf) + Sync)) -> Result<Skipped> {
+    let filter = Arc::new(PathFilter::new(&args.include, &args.exclude)?.with_extensions(&args.ext, &args.not_ext));
+    let counter = Arc::new(SkipCounter::new(args.show_skipped));
+    // The same rule as collect_files, applied as files arrive: the first path found wins
+    let seen: Mutex<HashMap<FileId, PathBuf>> = Mutex::default();
+    let first_sighting = |file: &Path| match seen.lock().unwrap().entry(FileId::of(file)) {
+        Entry::Occupied(first) => {
+            if canonical(first.get()) != canonical(file) {
+                counter.add(SkipReason::HardLink, file);
+            }
+            false
+        }
+        Entry::Vacant(slot) => {
+            slot.insert(file.to_path_buf());
+            true
+        }
+    };
+
+    let paths: Vec<&PathBuf> = args.paths.iter().filter(|p| !remote::is_url(p)).collect();
+    for (index, path) in paths.iter().enumerate() {
+        let emit = |file: PathBuf| {
+            if first_sighting(&file) {
+                found(index, file);
+            }
+        };
+        collect_path(path, args, &filter, &counter, &mut Found::Stream(&emit))?;
+    }
+    for (line, path) in listed_paths(args)?.into_iter().enumerate() {
+        match check_listed(&path, args) {
+            Ok(()) if first_sighting(&path) => found(paths.len() + line, path),
+            Ok(()) => {}
+            Err(Some(reason)) => counter.add(reason, &path),
+            Err(None) => {}
+        }
+    }
+    Ok(Arc::into_inner(counter).expect("walker threads have finished").into_skipped())
+}
+
+/// Entries of --files-from or --files-from0, in list order
+fn listed_paths(args: &Args) -> Result<Vec<PathBuf>> {
+    match args
+        .files_from
+        .as_ref()
+        .map(|f| (f, b'\n'))
+        .or(args.files_from0.as_ref().map(|f| (f, b'\0')))
+    {
+        Some((list, separator)) => read_path_list(list, separator),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// `Ok` when a --files-from entry is to be analyzed, else why it is skipped; `None` for
+/// directories and other entries left out without a count
+fn check_listed(path: &Path, args: &Args) -> Result<(), Option<SkipReason>> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => metadata_skip_reason(&metadata, args).map_or(Ok(()), |r| Err(Some(r))),
+        // Named explicitly, so a writer is presumably on the other end
+        Ok(metadata) if Special::of(metadata.file_type()) == Some(Special::Fifo) => Ok(()),
+        Ok(_) => Err(None),
+        Err(_) => {
+            eprintln!("Warning: skipping missing path: {}", path.display());
+            Err(Some(SkipReason::Unreadable))
+        }
+    }
+}
+
+/// Where the walk puts the files it accepts
+enum Found<'a> {
+    /// Into a list, each PATH argument's files sorted
+    List(&'a mut Vec<PathBuf>),
+    /// To a callback, as soon as the walk reaches them
+    Stream(&'a (dyn Fn(PathBuf) + Sync)),
+}
+
+impl Found<'_> {
+    fn push(&mut self, file: PathBuf) {
+        match self {
+            Found::List(files) => files.push(file),
+            Found::Stream(emit) => emit(file),
+        }
+    }
+}
+
+fn collect_path(
+    path: &Path,
+    args: &Args,
+    filter: &Arc<PathFilter>,
+    counter: &Arc<SkipCounter>,
+    files: &mut Found,
+) -> Result<()> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+    } else if path.is_dir() {
+        let accept = |entry: ignore::DirEntry| -> Option<PathBuf> {
+            // Opening these could block forever or have side effects
+            if entry.file_type().and_then(Special::of).is_some() {
+                counter.add(SkipReason::Special, entry.path());
+                return None;
+            }
+            // Followed links report their target's type, so only unfollowed ones get here
+            if entry.file_type().is_some_and(|t| t.is_symlink()) {
+                counter.add(SkipReason::Symlink, entry.path());
+                return None;
+            }
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                return None;
+            }
+            if !filter.includes_file(path, entry.path()) {
+                counter.add(SkipReason::Excluded, entry.path());
+                return None;
+            }
+            let reason = match entry.metadata() {
+                Ok(metadata) => metadata_skip_reason(&metadata, args),
+                Err(_) => Some(SkipReason::Unreadable),
+            };
+            match reason {
+                Some(reason) => {
+                    counter.add(reason, entry.path());
+                    None
+                }
+                None => Some(entry.into_path()),
+            }
+        };
+
+        // Directories are read in parallel, each thread batching its own finds unless they are
+        // streamed
+        let found = Mutex::new(Vec::new());
+        let stream = match files {
+            Found::Stream(emit) => Some(*emit),
+            Found::List(_) => None,
+        };
+        walk_builder(path, args, filter, counter).build_parallel().visit(&mut CollectorBuilder {
+            accept: &accept,
+            counter,
+            found: &found,
+            stream,
+        });
+
+        // Parallel traversal finishes in arbitrary order
+        let mut found = found.into_inner().unwrap();
+        found.sort();
+        for file in found {
+            files.push(file);
+        }
+    } else if let Some(special) = fs::metadata(path).ok().and_then(|m| Special::of(m.file_type())) {
+        // A named pipe given as an argument is read like stdin; other special files are not data
+        if special == Special::Fifo {
+            files.push(path.to_path_buf());
+        } else {
+            eprintln!("Warning: {} is a {}, skipping", path.display(), special.label());
+            counter.add(SkipReason::Special, path);
+        }
+    } else {
+        anyhow::bail!("Path does not exist: {}", path.display());
+    }
+
+    Ok(())
+}
+
+struct CollectorBuilder<'a, F> {
+    accept: &'a F,
+    counter: &'a SkipCounter,
+    found: &'a Mutex<Vec<PathBuf>>,
+    stream: Option<&'a (dyn Fn(PathBuf) + Sync)>,
+}
+
+impl<'a, F> ParallelVisitorBuilder<'a> for CollectorBuilder<'a, F>
+where
+    F: Fn(ignore::DirEntry) -> Option<PathBuf> + Sync,
+{
+    fn build(&mut self) -> Box<dyn ParallelVisitor + 'a> {
+        Box::new(Collector {
+            accept: self.accept,
+            counter: self.counter,
+            found: self.found,
+            stream: self.stream,
+            batch: Vec::new(),
+        })
+    }
+}
+
+/// Per-thread visitor; hands its files over once when the walk is done, or each as it is found
+/// when streaming
+struct Collector<'a, F> {
+    accept: &'a F,
+    counter: &'a SkipCounter,
+    found: &'a Mutex<Vec<PathBuf>>,
+    stream: Option<&'a (dyn Fn(PathBuf) + Sync)>,
+    batch: Vec<PathBuf>,
+}
+
+impl<F> ParallelVisitor for Collector<'_, F>
+where
+    F: Fn(ignore::DirEntry) -> Option<PathBuf> + Sync,
+{
+    fn visit(&mut self, entry: Result<ignore::DirEntry, ignore::Error>) -> WalkState {
+        if let Some(file) = walk_entry(entry, self.counter).and_then(self.accept) {
+            match self.stream {
+                Some(emit) => emit(file),
+                None => self.batch.push(file),
+            }
+        }
+        WalkState::Continue
+    }
+}
+
+impl<F> Drop for Collector<'_, F> {
+    fn drop(&mut self) {
+        if let Ok(mut found) = self.found.lock() {
+            found.append(&mut self.batch);
+        }
+    }
+}
+
+/// Why the size or modification-time filters reject a file, if they do
+fn metadata_skip_reason(metadata: &fs::Metadata, args: &Args) -> Option<SkipReason> {
+    if metadata.len() < args.min_size {
+        Some(SkipReason::TooSmall)
+    } else if !args.time_window().contains(metadata.modified()) {
+        Some(SkipReason::Excluded)
+    } else {
+        None
+    }
+}
+
+/// Walker for one PATH argument. `.enroignore` files are always honored;
+/// .gitignore/.ignore/git exclude rules only with --respect-gitignore.
+fn walk_builder(path: &Path, args: &Args, filter: &Arc<PathFilter>, counter: &Arc<SkipCounter>) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(path);
+    builder
+        .standard_filters(args.respect_gitignore)
+        .hidden(false)
+        .parents(true)
+        .follow_links(args.follows_symlinks())
+        .same_file_system(args.one_file_system)
+        .threads(args.threads.unwrap_or(0))
+        .add_custom_ignore_filename(ENROIGNORE);
+    builder.max_depth(if args.recursive { args.max_depth } else { Some(1) });
+
+    let root = path.to_path_buf();
+    let mut excluded = ExcludedPaths::new(&args.exclude_path);
+    if !args.no_default_excludes {
+        excluded = excluded.with_system_defaults(path);
+    }
+    let filter = Arc::clone(filter);
+    let counter = Arc::clone(counter);
+    let skip_hidden = !args.includes_hidden();
+    let skip_git_dir = args.respect_gitignore;
+    let skip_reparse_points = args.skip_reparse_points;
+    let follow_junctions = args.follow_junctions;
+    builder.filter_entry(move |entry| {
+        if entry.depth() == 0 {
+            return true;
+        }
+        if skip_hidden && is_hidden(entry) {
+            counter.add(SkipReason::Hidden, entry.path());
+            return false;
+        }
+        if excluded.contains(entry.path()) {
+            counter.add(SkipReason::Excluded, entry.path());
+            return false;
+        }
+        if skip_reparse_points && is_reparse_point(entry.path()) {
+            counter.add(SkipReason::ReparsePoint, entry.path());
+            return false;
+        }
+        if !entry.file_type().is_some_and(|t| t.is_dir()) {
+            return true;
+        }
+        // Loops are caught either way, but junctions would scan whole trees twice
+        if !follow_junctions && is_junction(entry.path()) {
+            counter.add(SkipReason::ReparsePoint, entry.path());
+            return false;
+        }
+        // Repository metadata is never tracked content
+        if skip_git_dir && entry.file_name() == ".git" {
+            return false;
+        }
+        if !filter.includes_dir(&root, entry.path()) {
+            counter.add(SkipReason::Excluded, entry.path());
+            return false;
+        }
+        true
+    });
+    builder
+}
+
+/// Dotfiles everywhere, plus entries with the hidden or system attribute on Windows
+fn is_hidden(entry: &ignore::DirEntry) -> bool {
+    entry.file_name().as_encoded_bytes().starts_with(b".") || has_hidden_attribute(entry)
+}
+
+#[cfg(windows)]
+fn has_hidden_attribute(entry: &ignore::DirEntry) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+    entry
+        .metadata()
+        .is_ok_and(|m| m.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0)
+}
+
+#[cfg(not(windows))]
+fn has_hidden_attribute(_entry: &ignore::DirEntry) -> bool {
+    false
+}
+
+/// Junctions, symlinks, mount points, cloud placeholders and other NTFS reparse points
+#[cfg(windows)]
+fn is_reparse_point(path: &Path) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+    fs::symlink_metadata(path).is_ok_and(|m| m.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0)
+}
+
+#[cfg(not(windows))]
+fn is_reparse_point(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(windows)]
+fn is_junction(path: &Path) -> bool {
+    is_reparse_point(path) && junction::exists(path).unwrap_or(false)
+}
+
+#[cfg(not(windows))]
+fn is_junction(_path: &Path) -> bool {
+    false
+}
+
+// Unreadable entries are counted, symlink loops skipped with a warning
+fn walk_entry(entry: Result<ignore::DirEntry, ignore::Error>, counter: &SkipCounter) -> Option<ignore::DirEntry> {
+    match entry {
+        Ok(entry) => Some(entry),
+        Err(e) if is_loop(&e) => {
+            eprintln!("Warning: {}, skipping", e);
+            None
+        }
+        Err(e) => {
+            let reason = match e.io_error().map(std::io::Error::kind) {
+                Some(std::io::ErrorKind::PermissionDenied) => SkipReason::Denied,
+                _ => SkipReason::Unreadable,
+            };
+            counter.add(reason, error_path(&e).unwrap_or(Path::new("")));
+            None
+        }
+    }
+}
+
+fn error_path(err: &ignore::Error) -> Option<&Path> {
+    match err {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => error_path(err),
+        _ => None,
+    }
+}
+
+fn is_loop(err: &ignore::Error) -> bool {
+    match err {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithPath { err, .. } | ignore::Error::WithDepth { err, .. } => is_loop(err),
+        _ => false,
+    }
+}
+
+/// Paths from a list file (or stdin for `-`), split on `separator`
+fn read_path_list(list: &Path, separator: u8) -> Result<Vec<PathBuf>> {
+    let mut data = Vec::new();
+    if list == Path::new("-") {
+        std::io::stdin().read_to_end(&mut data).context("Failed to read path list from stdin")?;
+    } else {
+        data = fs::read(list).with_context(|| format!("Failed to read path list {}", list.display()))?;
+    }
+
+    Ok(split_path_list(&data, separator))
+}
+
+/// Paths in `data`, split on `separator`. Lines written on Windows end in `\r\n`, so a newline
+/// list drops the `\r`; a NUL-separated one keeps every byte, since names may end in `\r` too.
+fn split_path_list(data: &[u8], separator: u8) -> Vec<PathBuf> {
+    data.split(|&b| b == separator)
+        .map(|line| match separator {
+            b'\n' => line.strip_suffix(b"\r").unwrap_or(line),
+            _ => line,
+        })
+        .filter(|line| !line.is_empty())
+        .map(path_from_bytes)
+        .collect()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newline_list_drops_carriage_returns() {
+        let paths = split_path_list(b"a.txt\r\ndir/b.txt\n\nc\r\n", b'\n');
+        assert_eq!(paths, [PathBuf::from("a.txt"), PathBuf::from("dir/b.txt"), PathBuf::from("c")]);
+    }
+
+    #[test]
+    fn nul_list_keeps_every_byte() {
+        let paths = split_path_list(b"name\r\0with\nnewline\0\0", b'\0');
+        assert_eq!(paths, [PathBuf::from("name\r"), PathBuf::from("with\nnewline")]);
+    }
+}
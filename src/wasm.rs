@@ -0,0 +1,154 @@
+use anyhow::{bail, Context, Result};
+use enro::detector::{Detection, Detector, DetectorInput};
+use enro::FileType;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use wasmtime::{Config, Engine, InstancePre, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+// Per file: the guest gets a fresh instance with this much memory and this much fuel (roughly one
+// unit per WebAssembly instruction), so a runaway detector costs a bounded amount and cannot carry
+// state from one file to the next
+const MEMORY_LIMIT: usize = 64 << 20;
+const FUEL_PER_FILE: u64 = 100_000_000;
+// Signatures sit near the start; copying whole first chunks into every instance is not worth it
+const HEADER_LIMIT: usize = 64 << 10;
+// `TYPE CONFIDENCE` is short; the guest does not get to make the host allocate more
+const RESULT_LIMIT: usize = 1024;
+
+/// A detector compiled to WebAssembly. Modules import nothing, so they have no filesystem,
+/// network or clock, and export:
+///
+/// - `memory`
+/// - `enro_alloc(len: i32) -> i32`: a buffer of `len` bytes in guest memory
+/// - `enro_detect(counts: i32, header: i32, header_len: i32, size: i64, entropy: f64) -> i64`:
+///   `counts` points at 256 little-endian u64 byte counts; returns 0 to leave the file alone, or
+///   `ptr << 32 | len` of UTF-8 text `TYPE CONFIDENCE`, e.g. `Document(ACMECAD) 95`
+pub struct WasmDetector {
+    name: String,
+    engine: Engine,
+    instance: InstancePre<StoreLimits>,
+    // A failing module would fail on most files; one warning is enough
+    warned: AtomicBool,
+}
+
+/// A fresh instance of the module, for one file
+struct Guest {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    detect: TypedFunc<(i32, i32, i32, i64, f64), i64>,
+}
+
+impl Guest {
+    /// Copy `data` into a buffer the guest allocated, returning its address
+    fn write(&mut self, data: &[u8]) -> Result<i32> {
+        let ptr = self.alloc.call(&mut self.store, data.len() as i32)?;
+        self.memory.write(&mut self.store, ptr as u32 as usize, data)?;
+        Ok(ptr)
+    }
+}
+
+impl WasmDetector {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module = Module::from_file(&engine, path).with_context(|| format!("Failed to load {}", path.display()))?;
+        if let Some(import) = module.imports().next() {
+            bail!(
+                "{}: detector modules must not import anything, but it imports {}::{}",
+                path.display(),
+                import.module(),
+                import.name()
+            );
+        }
+        let instance = Linker::new(&engine).instantiate_pre(&module)?;
+        let detector = WasmDetector {
+            name: path.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+            engine,
+            instance,
+            warned: AtomicBool::new(false),
+        };
+        // Missing or mistyped exports show up now rather than as a warning mid-scan
+        detector
+            .instantiate()
+            .with_context(|| format!("{} is not an enro detector", path.display()))?;
+        Ok(detector)
+    }
+
+    fn instantiate(&self) -> Result<Guest> {
+        let limits = StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).instances(1).build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL_PER_FILE)?;
+        let instance = self.instance.instantiate(&mut store)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .context("missing export `memory`")?;
+        let alloc = instance.get_typed_func(&mut store, "enro_alloc")?;
+        let detect = instance.get_typed_func(&mut store, "enro_detect")?;
+        Ok(Guest {
+            store,
+            memory,
+            alloc,
+            detect,
+        })
+    }
+
+    fn run(&self, input: &DetectorInput) -> Result<Option<Detection>> {
+        let mut guest = self.instantiate()?;
+        let header = &input.header[..input.header.len().min(HEADER_LIMIT)];
+        let counts: Vec<u8> = input.byte_counts.iter().flat_map(|count| count.to_le_bytes()).collect();
+        let counts_ptr = guest.write(&counts)?;
+        let header_ptr = guest.write(header)?;
+
+        let result = guest.detect.call(
+            &mut guest.store,
+            (counts_ptr, header_ptr, header.len() as i32, input.size as i64, input.entropy),
+        )?;
+        if result == 0 {
+            return Ok(None);
+        }
+        let (ptr, len) = ((result as u64 >> 32) as usize, (result as u64 & 0xffff_ffff) as usize);
+        if len > RESULT_LIMIT {
+            bail!("result of {} bytes, expected at most {}", len, RESULT_LIMIT);
+        }
+        let mut text = vec![0; len];
+        guest.memory.read(&guest.store, ptr, &mut text)?;
+        let text = String::from_utf8(text).context("result is not UTF-8")?;
+        let (file_type, confidence) = text
+            .trim()
+            .rsplit_once(' ')
+            .with_context(|| format!("expected `TYPE CONFIDENCE`, got `{}`", text))?;
+        Ok(Some(Detection {
+            file_type: file_type.parse::<FileType>().map_err(anyhow::Error::msg)?,
+            confidence: confidence
+                .parse()
+                .with_context(|| format!("`{}` is not a confidence (0-100)", confidence))?,
+            label: None,
+        }))
+    }
+}
+
+impl Detector for WasmDetector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn detect(&self, input: &DetectorInput) -> Option<Detection> {
+        match self.run(input) {
+            Ok(detection) => detection,
+            Err(e) => {
+                if !self.warned.swap(true, Ordering::Relaxed) {
+                    eprintln!(
+                        "Warning: detector {} failed on {}: {:#} (further failures are not shown)",
+                        self.name,
+                        input.path.display(),
+                        e
+                    );
+                }
+                None
+            }
+        }
+    }
+}
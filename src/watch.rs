@@ -0,0 +1,301 @@
+use anyhow::{Context, Result};
+use clap::builder::BoolishValueParser;
+use clap::Args as ClapArgs;
+use colored::Colorize;
+use enro::{analyze_file, format_size, AnalyzeOptions, FileAnalysis};
+use notify::{EventKind, RecursiveMode, Watcher};
+use rayon::prelude::*;
+use std::collections::BTreeSet;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
+
+use crate::alerts::Alerter;
+use crate::eventlog::EventLog;
+use crate::filters::PathFilter;
+use crate::metrics::Metrics;
+use crate::schedule::Schedule;
+use crate::{category, display_path, escape_csv, Category};
+
+/// Watch directories and analyze every file created or changed in them
+#[derive(ClapArgs, Debug)]
+pub struct WatchArgs {
+    /// Files or directories to watch
+    #[arg(value_name = "PATH", required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// Watch subdirectories too
+    #[arg(short, long, env = "ENRO_RECURSIVE", value_parser = BoolishValueParser::new())]
+    pub recursive: bool,
+
+    /// Analyze the files already present before waiting for changes
+    #[arg(long, env = "ENRO_SCAN_EXISTING", value_parser = BoolishValueParser::new())]
+    pub scan_existing: bool,
+
+    /// Wait for this long without further changes before analyzing a file, so files still
+    /// being written are read once (`500ms`, `5s`)
+    #[arg(long, env = "ENRO_DEBOUNCE", value_name = "DURATION", default_value = "2s", value_parser = humantime::parse_duration)]
+    pub debounce: Duration,
+
+    /// Also rescan every watched file on this cron schedule, in local time (`0 2 * * *` for 2am
+    /// daily, `@hourly`), to catch what change events miss
+    #[arg(long, env = "ENRO_SCHEDULE", value_name = "CRON", value_parser = clap::value_parser!(Schedule))]
+    pub schedule: Option<Schedule>,
+
+    /// Only analyze files matching this glob (repeatable)
+    #[arg(long, env = "ENRO_INCLUDE", value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Skip files and directories matching this glob (repeatable)
+    #[arg(long, env = "ENRO_EXCLUDE", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Only report files classified as one of these categories, e.g. `encrypted,random`
+    #[arg(long, env = "ENRO_ONLY", value_enum, value_name = "CATEGORY", value_delimiter = ',')]
+    pub only: Vec<Category>,
+
+    /// Maximum number of bytes to read for analysis (omit to scan entire file)
+    #[arg(short = 'b', long, env = "ENRO_MAX_BYTES")]
+    pub max_bytes: Option<usize>,
+
+    /// Simple output format (CSV lines, no colors)
+    #[arg(short, long, env = "ENRO_SIMPLE", value_parser = BoolishValueParser::new(), conflicts_with = "json")]
+    pub simple: bool,
+
+    /// One JSON object per analyzed file (NDJSON)
+    #[arg(long, env = "ENRO_JSON", value_parser = BoolishValueParser::new())]
+    pub json: bool,
+
+    /// Serve Prometheus metrics (files scanned, bytes read, counts per classification) at
+    /// http://ADDR/metrics, e.g. `127.0.0.1:9184`
+    #[arg(long, env = "ENRO_METRICS_ADDR", value_name = "ADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// POST a JSON alert here when encrypted files, ransomware extensions or directories over
+    /// --alert-ratio appear (Slack/Teams incoming webhooks show its `text`)
+    #[arg(long, env = "ENRO_ALERT_WEBHOOK", value_name = "URL")]
+    pub alert_webhook: Option<String>,
+
+    /// Share of encrypted files above which a directory of 5+ files raises an alert
+    #[arg(long, env = "ENRO_ALERT_RATIO", value_name = "FRACTION", default_value_t = 0.5, requires = "alert_webhook")]
+    pub alert_ratio: f64,
+
+    /// Log each encrypted/random file as it is analyzed to the local syslog (RFC 5424, /dev/log)
+    #[arg(long, env = "ENRO_SYSLOG", value_parser = BoolishValueParser::new())]
+    pub syslog: bool,
+
+    /// Send the same RFC 5424 events to a syslog server over UDP (`HOST:PORT`)
+    #[arg(long, env = "ENRO_SYSLOG_SERVER", value_name = "HOST:PORT")]
+    pub syslog_server: Option<String>,
+
+    /// Log the same events to the systemd journal with ENRO_* fields (ENRO_PATH, ENRO_TYPE, ...)
+    #[arg(long, env = "ENRO_JOURNALD", value_parser = BoolishValueParser::new())]
+    pub journald: bool,
+}
+
+pub fn run(args: &WatchArgs) -> Result<()> {
+    let filter = PathFilter::new(&args.include, &args.exclude)?;
+    let options = AnalyzeOptions {
+        max_bytes: args.max_bytes,
+        ..AnalyzeOptions::default()
+    };
+    let mode = if args.recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+
+    let metrics = Metrics::new();
+    if let Some(addr) = args.metrics_addr {
+        crate::metrics::serve(addr, metrics.clone())?;
+    }
+
+    let event_log = EventLog::open(args.syslog, args.syslog_server.as_deref(), args.journald)?;
+    let mut alerter = args.alert_webhook.as_deref().map(|url| Alerter::new(url, args.alert_ratio));
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to start the file watcher")?;
+    for path in &args.paths {
+        watcher
+            .watch(path, mode)
+            .with_context(|| format!("Failed to watch {}", path.display()))?;
+    }
+
+    let mut deliver = |results: &[FileAnalysis]| {
+        if let Some(alerter) = &mut alerter {
+            alerter.notify(results);
+        }
+        if let Some(event_log) = &event_log {
+            event_log.findings(results);
+        }
+        report(results, args);
+    };
+
+    if args.simple {
+        println!("Time,Path,Type,Entropy,Size");
+    }
+    if args.scan_existing {
+        let existing = existing_files(&args.paths, args.recursive);
+        deliver(&analyze(&existing, args, &filter, &options, &metrics));
+    }
+    let mut next_scan = args.schedule.as_ref().and_then(Schedule::next);
+    if !args.simple && !args.json {
+        eprintln!("Watching {} path(s) for changes, Ctrl-C to stop", args.paths.len());
+        if let Some(next) = next_scan {
+            eprintln!("Next scheduled scan at {}", humantime::format_rfc3339_seconds(next));
+        }
+    }
+
+    // Changes are batched until the tree has been quiet for the debounce period; a scheduled scan
+    // that comes due first takes them along
+    let mut pending = BTreeSet::new();
+    loop {
+        let until_scan = next_scan.map(|next| next.duration_since(SystemTime::now()).unwrap_or_default());
+        let timeout = match (pending.is_empty(), until_scan) {
+            (true, until_scan) => until_scan,
+            (false, Some(until_scan)) => Some(until_scan.min(args.debounce)),
+            (false, None) => Some(args.debounce),
+        };
+        let event = match timeout {
+            Some(timeout) => rx.recv_timeout(timeout),
+            None => rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        };
+        match event {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    pending.extend(event.paths);
+                }
+            }
+            Ok(Err(e)) => eprintln!("Warning: {}", e),
+            Err(mpsc::RecvTimeoutError::Timeout) if next_scan.is_some_and(|next| next <= SystemTime::now()) => {
+                pending.clear();
+                let files = existing_files(&args.paths, args.recursive);
+                let results = analyze(&files, args, &filter, &options, &metrics);
+                deliver(&results);
+                next_scan = args.schedule.as_ref().and_then(Schedule::next);
+                if !args.simple && !args.json {
+                    eprintln!(
+                        "Scheduled scan analyzed {} file(s){}",
+                        results.len(),
+                        next_scan.map_or_else(String::new, |next| format!(
+                            ", next at {}",
+                            humantime::format_rfc3339_seconds(next)
+                        ))
+                    );
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                if pending.is_empty() {
+                    continue;
+                }
+                let changed: Vec<PathBuf> = std::mem::take(&mut pending).into_iter().collect();
+                deliver(&analyze(&changed, args, &filter, &options, &metrics));
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+/// Files already under the watched paths, walked as deep as the watch reaches
+pub fn existing_files(paths: &[PathBuf], recursive: bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        let walker = ignore::WalkBuilder::new(path)
+            .standard_filters(false)
+            .max_depth(if recursive { None } else { Some(1) })
+            .build();
+        files.extend(
+            walker
+                .flatten()
+                .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+                .map(ignore::DirEntry::into_path),
+        );
+    }
+    files
+}
+
+fn analyze(
+    paths: &[PathBuf],
+    args: &WatchArgs,
+    filter: &PathFilter,
+    options: &AnalyzeOptions,
+    metrics: &Metrics,
+) -> Vec<FileAnalysis> {
+    let mut results: Vec<FileAnalysis> = paths
+        .par_iter()
+        // Deleted again, or a directory
+        .filter(|path| path.is_file())
+        .filter(|path| filter.includes_file(watch_root(path, &args.paths), path))
+        .filter_map(|path| match analyze_file(path, options) {
+            Ok(analysis) => {
+                metrics.record(&analysis);
+                Some(analysis)
+            }
+            Err(e) => {
+                metrics.record_error();
+                eprintln!("Warning: {}: {:#}", path.display(), e);
+                None
+            }
+        })
+        .collect();
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+    results
+}
+
+// Globs with a `/` are matched relative to the watched path the file is under
+fn watch_root<'a>(path: &Path, roots: &'a [PathBuf]) -> &'a Path {
+    roots
+        .iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+        .map_or(Path::new(""), PathBuf::as_path)
+}
+
+fn report(results: &[FileAnalysis], args: &WatchArgs) {
+    let time = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    // --only narrows the report, not what metrics and alerts see
+    let shown = results
+        .iter()
+        .filter(|analysis| args.only.is_empty() || args.only.contains(&category(&analysis.file_type)));
+    for analysis in shown {
+        let path = display_path(&analysis.path);
+        if args.json {
+            let line = serde_json::json!({
+                "time": time,
+                "path": path,
+                "type": analysis.file_type.display_compact(),
+                "entropy": analysis.entropy,
+                "size": analysis.size,
+                "notes": analysis.notes,
+            });
+            println!("{}", line);
+        } else if args.simple {
+            println!(
+                "{},{},{},{:.2},{}",
+                time,
+                escape_csv(&path),
+                escape_csv(&analysis.type_label(analysis.file_type.display_compact())),
+                analysis.entropy,
+                analysis.size
+            );
+        } else {
+            let entropy_str = format!("{:.2}/8.0", analysis.entropy);
+            let entropy_colored = if analysis.entropy > 7.5 {
+                entropy_str.red()
+            } else if analysis.entropy > 6.0 {
+                entropy_str.yellow()
+            } else {
+                entropy_str.green()
+            };
+            println!(
+                "{}  {}  {}  {}  {}",
+                time.dimmed(),
+                path,
+                analysis.type_label(analysis.file_type.display_plain()),
+                entropy_colored,
+                format_size(analysis.size)
+            );
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use enro::{analyze_reader, AnalyzeOptions, FileAnalysis};
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// macOS exposes the resource fork both as this xattr and as a path; it is analyzed via the path
+#[cfg(target_os = "macos")]
+const RESOURCE_FORK_XATTR: &str = "com.apple.ResourceFork";
+
+/// Analyze the extended attributes (and on macOS the resource fork) of every file,
+/// each reported as its own entry next to the file
+pub fn analyze_all(files: &[PathBuf], options: &AnalyzeOptions) -> Vec<FileAnalysis> {
+    if !xattr::SUPPORTED_PLATFORM {
+        return Vec::new();
+    }
+    files.par_iter().flat_map_iter(|path| analyze(path, options)).collect()
+}
+
+fn analyze(path: &Path, options: &AnalyzeOptions) -> Vec<FileAnalysis> {
+    let mut results = Vec::new();
+    if let Some(analysis) = resource_fork(path, options) {
+        results.push(analysis);
+    }
+
+    let Ok(names) = xattr::list(path) else {
+        return results;
+    };
+    for name in names {
+        #[cfg(target_os = "macos")]
+        if name == RESOURCE_FORK_XATTR {
+            continue;
+        }
+        let Ok(Some(value)) = xattr::get(path, &name) else {
+            continue;
+        };
+        // Not a real path: `file#xattr-name` keeps the attribute next to its file in reports
+        let label = PathBuf::from(format!("{}#{}", path.display(), name.to_string_lossy()));
+        if let Ok(mut analysis) = analyze_reader(label, &mut value.as_slice(), options) {
+            analysis.notes.push("extended attribute".to_string());
+            results.push(analysis);
+        }
+    }
+    results
+}
+
+// Legacy Mac data (and payloads) can hide here; empty forks are the norm and not reported
+#[cfg(target_os = "macos")]
+fn resource_fork(path: &Path, options: &AnalyzeOptions) -> Option<FileAnalysis> {
+    let fork = path.join("..namedfork/rsrc");
+    if std::fs::metadata(&fork).ok()?.len() == 0 {
+        return None;
+    }
+    let mut analysis = enro::analyze_file(&fork, options).ok()?;
+    analysis.notes.push("resource fork".to_string());
+    Some(analysis)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn resource_fork(_path: &Path, _options: &AnalyzeOptions) -> Option<FileAnalysis> {
+    None
+}
@@ -0,0 +1,306 @@
+use anyhow::{bail, Context, Result};
+use flate2::bufread::DeflateDecoder;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Read, Seek, SeekFrom};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4B50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4B50;
+const EOCD_SIGNATURE: u32 = 0x0605_4B50;
+const ZIP64_EOCD_LOCATOR_SIGNATURE: u32 = 0x0706_4B50;
+const ZIP64_EOCD_SIGNATURE: u32 = 0x0606_4B50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4B50;
+
+const EOCD_MIN_SIZE: u64 = 22;
+// EOCD record plus the largest possible archive comment
+const EOCD_SEARCH_WINDOW: u64 = EOCD_MIN_SIZE + 0xFFFF;
+// Refuse absurd central directories instead of allocating them
+const MAX_CENTRAL_DIRECTORY: u64 = 256 * 1024 * 1024;
+
+const ZIP64_EXTRA_FIELD: u16 = 0x0001;
+const AES_EXTRA_FIELD: u16 = 0x9901;
+const METHOD_DEFLATE: u16 = 8;
+const METHOD_AES: u16 = 99;
+const FLAG_ENCRYPTED: u16 = 0x0001;
+const FLAG_DATA_DESCRIPTOR: u16 = 0x0008;
+const FLAG_STRONG_ENCRYPTION: u16 = 0x0040;
+
+/// How an encrypted ZIP entry is protected
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ZipEncryption {
+    /// Legacy PKWARE stream cipher, trivially crackable
+    ZipCrypto,
+    /// WinZip AE-1/AE-2 with the given key size in bits
+    Aes(u16),
+    /// PKWARE Strong Encryption (SES)
+    Strong,
+}
+
+impl ZipEncryption {
+    pub fn label(&self) -> String {
+        match self {
+            ZipEncryption::ZipCrypto => "ZipCrypto".to_string(),
+            ZipEncryption::Aes(0) => "AES".to_string(),
+            ZipEncryption::Aes(bits) => format!("AES-{}", bits),
+            ZipEncryption::Strong => "PKWARE strong encryption".to_string(),
+        }
+    }
+
+    /// Analysis note, flagging the weak legacy scheme
+    pub fn note(&self) -> String {
+        match self {
+            ZipEncryption::ZipCrypto => "encrypted: ZipCrypto (weak)".to_string(),
+            other => format!("encrypted: {}", other.label()),
+        }
+    }
+}
+
+/// An entry from the ZIP central directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZipEntry {
+    pub name: String,
+    /// Uncompressed size
+    pub size: u64,
+    pub encryption: Option<ZipEncryption>,
+}
+
+fn u16_at(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+}
+
+fn u64_at(buf: &[u8], offset: usize) -> u64 {
+    u64::from(u32_at(buf, offset)) | (u64::from(u32_at(buf, offset + 4)) << 32)
+}
+
+/// Read the central directory without touching any member data
+pub fn read_central_directory<R: Read + Seek>(reader: &mut R) -> Result<Vec<ZipEntry>> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    if file_len < EOCD_MIN_SIZE {
+        bail!("File too small to be a ZIP archive");
+    }
+
+    // Locate the end-of-central-directory record by scanning backwards
+    let window = file_len.min(EOCD_SEARCH_WINDOW);
+    let window_start = file_len - window;
+    let mut tail = vec![0u8; window as usize];
+    reader.seek(SeekFrom::Start(window_start))?;
+    reader.read_exact(&mut tail).context("Failed to read ZIP trailer")?;
+
+    let eocd_pos = (0..=tail.len() - EOCD_MIN_SIZE as usize)
+        .rev()
+        .find(|&i| u32_at(&tail, i) == EOCD_SIGNATURE)
+        .context("ZIP end of central directory not found")?;
+    let eocd = &tail[eocd_pos..];
+
+    let mut entry_count = u64::from(u16_at(eocd, 10));
+    let mut cd_size = u64::from(u32_at(eocd, 12));
+    let mut cd_offset = u64::from(u32_at(eocd, 16));
+
+    // ZIP64 archives keep the real values in a separate record
+    if eocd_pos >= 20 && u32_at(&tail, eocd_pos - 20) == ZIP64_EOCD_LOCATOR_SIGNATURE {
+        let zip64_offset = u64_at(&tail, eocd_pos - 20 + 8);
+        let mut record = [0u8; 56];
+        reader.seek(SeekFrom::Start(zip64_offset))?;
+        reader.read_exact(&mut record).context("Failed to read ZIP64 record")?;
+        if u32_at(&record, 0) == ZIP64_EOCD_SIGNATURE {
+            entry_count = u64_at(&record, 32);
+            cd_size = u64_at(&record, 40);
+            cd_offset = u64_at(&record, 48);
+        }
+    }
+
+    if cd_size > MAX_CENTRAL_DIRECTORY || cd_offset.saturating_add(cd_size) > file_len {
+        bail!("Invalid ZIP central directory bounds");
+    }
+
+    let mut cd = vec![0u8; cd_size as usize];
+    reader.seek(SeekFrom::Start(cd_offset))?;
+    reader.read_exact(&mut cd).context("Failed to read ZIP central directory")?;
+
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while entries.len() as u64 != entry_count && pos + 46 <= cd.len() {
+        if u32_at(&cd, pos) != CENTRAL_HEADER_SIGNATURE {
+            bail!("Corrupt ZIP central directory");
+        }
+        let flags = u16_at(&cd, pos + 8);
+        let method = u16_at(&cd, pos + 10);
+        let mut size = u64::from(u32_at(&cd, pos + 24));
+        let name_len = u16_at(&cd, pos + 28) as usize;
+        let extra_len = u16_at(&cd, pos + 30) as usize;
+        let comment_len = u16_at(&cd, pos + 32) as usize;
+
+        let name_start = pos + 46;
+        let extra_start = name_start + name_len;
+        let next = extra_start + extra_len + comment_len;
+        if next > cd.len() {
+            bail!("Truncated ZIP central directory entry");
+        }
+
+        let extra = &cd[extra_start..extra_start + extra_len];
+        let encryption = entry_encryption(flags, method, extra);
+
+        // The ZIP64 field lists only the values saturated in the header, uncompressed size first
+        if size == u64::from(u32::MAX) {
+            if let Some((_, data)) = extra_fields(extra).find(|&(id, data)| id == ZIP64_EXTRA_FIELD && data.len() >= 8) {
+                size = u64_at(data, 0);
+            }
+        }
+
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(&cd[name_start..extra_start]).into_owned(),
+            size,
+            encryption,
+        });
+        pos = next;
+    }
+
+    Ok(entries)
+}
+
+fn entry_encryption(flags: u16, method: u16, extra: &[u8]) -> Option<ZipEncryption> {
+    if flags & FLAG_ENCRYPTED == 0 {
+        None
+    } else if method == METHOD_AES {
+        let strength = extra_fields(extra)
+            .find(|&(id, _)| id == AES_EXTRA_FIELD)
+            .and_then(|(_, data)| data.get(4).copied());
+        Some(ZipEncryption::Aes(match strength {
+            Some(1) => 128,
+            Some(2) => 192,
+            Some(3) => 256,
+            _ => 0,
+        }))
+    } else if flags & FLAG_STRONG_ENCRYPTION != 0 {
+        Some(ZipEncryption::Strong)
+    } else {
+        Some(ZipEncryption::ZipCrypto)
+    }
+}
+
+/// A member as described by its local file header
+#[derive(Debug, Clone)]
+pub struct LocalEntry {
+    pub name: String,
+    pub encryption: Option<ZipEncryption>,
+}
+
+impl LocalEntry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+/// Walk a ZIP archive front to back without seeking, handing each member's contents to
+/// `visit`. Deflated members are decompressed; stored, encrypted or otherwise compressed
+/// members are passed through as-is. Stops at the central directory.
+pub fn for_each_local_entry<R: BufRead>(
+    reader: &mut R,
+    mut visit: impl FnMut(&LocalEntry, &mut dyn Read) -> Result<()>,
+) -> Result<()> {
+    loop {
+        let mut signature = [0u8; 4];
+        if reader.read_exact(&mut signature).is_err() || u32_at(&signature, 0) != LOCAL_HEADER_SIGNATURE {
+            return Ok(());
+        }
+
+        let mut header = [0u8; 26];
+        reader.read_exact(&mut header).context("Truncated ZIP local header")?;
+        let flags = u16_at(&header, 2);
+        let method = u16_at(&header, 4);
+        let mut compressed_size = u64::from(u32_at(&header, 14));
+        let name_len = u16_at(&header, 22) as usize;
+        let extra_len = u16_at(&header, 24) as usize;
+
+        let mut name = vec![0u8; name_len];
+        let mut extra = vec![0u8; extra_len];
+        reader.read_exact(&mut name).context("Truncated ZIP entry name")?;
+        reader.read_exact(&mut extra).context("Truncated ZIP extra field")?;
+
+        // ZIP64 local headers carry uncompressed then compressed size
+        let zip64 = extra_fields(&extra).find(|&(id, _)| id == ZIP64_EXTRA_FIELD);
+        if let Some((_, data)) = zip64.filter(|(_, data)| data.len() >= 16) {
+            if compressed_size == u64::from(u32::MAX) {
+                compressed_size = u64_at(data, 8);
+            }
+        }
+
+        let entry = LocalEntry {
+            name: String::from_utf8_lossy(&name).into_owned(),
+            encryption: entry_encryption(flags, method, &extra),
+        };
+        let streamed = flags & FLAG_DATA_DESCRIPTOR != 0;
+
+        if method == METHOD_DEFLATE && entry.encryption.is_none() {
+            if streamed {
+                // Deflate streams are self-terminating, so the size can stay unknown
+                let mut decoder = DeflateDecoder::new(&mut *reader);
+                visit(&entry, &mut decoder)?;
+                std::io::copy(&mut decoder, &mut std::io::sink())?;
+                skip_data_descriptor(reader, zip64.is_some())?;
+            } else {
+                let mut decoder = DeflateDecoder::new((&mut *reader).take(compressed_size));
+                visit(&entry, &mut decoder)?;
+                std::io::copy(decoder.get_mut(), &mut std::io::sink())?;
+            }
+        } else {
+            if streamed {
+                bail!("Cannot stream ZIP entry {} with unknown size", entry.name);
+            }
+            let mut data = (&mut *reader).take(compressed_size);
+            visit(&entry, &mut data)?;
+            std::io::copy(&mut data, &mut std::io::sink())?;
+        }
+    }
+}
+
+// Sizes and CRC written after the data, optionally preceded by a signature
+fn skip_data_descriptor<R: Read>(reader: &mut R, zip64: bool) -> Result<()> {
+    let mut first = [0u8; 4];
+    reader.read_exact(&mut first).context("Truncated ZIP data descriptor")?;
+    let remaining = match (u32_at(&first, 0) == DATA_DESCRIPTOR_SIGNATURE, zip64) {
+        (true, false) => 12,
+        (true, true) => 20,
+        (false, false) => 8,
+        (false, true) => 16,
+    };
+    let mut rest = vec![0u8; remaining];
+    reader.read_exact(&mut rest).context("Truncated ZIP data descriptor")?;
+    Ok(())
+}
+
+// Iterate (header id, payload) pairs of an extra field block
+fn extra_fields(extra: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        if pos + 4 > extra.len() {
+            return None;
+        }
+        let id = u16_at(extra, pos);
+        let len = u16_at(extra, pos + 2) as usize;
+        let start = pos + 4;
+        pos = start + len;
+        Some((id, &extra[start..pos.min(extra.len())]))
+    })
+}
+
+/// Distinct encryption schemes used across the archive, in first-seen order
+pub fn encryption_schemes(entries: &[ZipEntry]) -> Vec<ZipEncryption> {
+    let mut schemes = Vec::new();
+    for scheme in entries.iter().filter_map(|e| e.encryption) {
+        if !schemes.contains(&scheme) {
+            schemes.push(scheme);
+        }
+    }
+    schemes
+}
+
+/// OOXML documents store VBA projects as `vbaProject.bin` parts
+pub fn has_macros(entries: &[ZipEntry]) -> bool {
+    entries
+        .iter()
+        .any(|e| e.name.to_ascii_lowercase().ends_with("vbaproject.bin"))
+}